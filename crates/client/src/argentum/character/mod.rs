@@ -49,6 +49,12 @@ pub struct BodyFrameMetadata {
     pub right_foot: Offset,
 }
 
+impl BodyFrameMetadata {
+    fn marks_feet(&self) -> bool {
+        self.left_foot != Offset::ZERO || self.right_foot != Offset::ZERO
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct AnimatedCharacter {
     pub body: Body,
@@ -98,6 +104,44 @@ impl AnimatedCharacter {
         self.animator.current_frame == 0
     }
 
+    /// Foot that touched the floor since the `previous` state of the animator, `Some(true)` for
+    /// the left one. Frames mark a foot on the floor with its offset, bodies without them step
+    /// at the start and at the middle of the walk
+    pub fn footstep(&self, previous: &Animator) -> Option<bool> {
+        let Animator {
+            animation,
+            direction,
+            current_frame,
+            ..
+        } = self.animator;
+        if animation != CharacterAnimation::Walk
+            || previous.animation != animation
+            || previous.direction != direction
+            || previous.current_frame == current_frame
+        {
+            return None;
+        }
+
+        let frames = &self.body[animation][direction].frames;
+        if !frames.iter().any(BodyFrameMetadata::marks_feet) {
+            return match current_frame {
+                0 => Some(true),
+                frame if frame == frames.len() / 2 => Some(false),
+                _ => None,
+            };
+        }
+
+        let frame = &frames[current_frame];
+        let previous_frame = &frames[previous.current_frame];
+        if frame.left_foot != Offset::ZERO && previous_frame.left_foot == Offset::ZERO {
+            Some(true)
+        } else if frame.right_foot != Offset::ZERO && previous_frame.right_foot == Offset::ZERO {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
     pub fn get_body_frame(&self) -> &BodyFrameMetadata {
         let Animator {
             animation,
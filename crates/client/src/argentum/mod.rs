@@ -1,5 +1,6 @@
 pub mod animations;
 pub mod character;
+pub mod particles;
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct Image {
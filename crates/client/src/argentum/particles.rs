@@ -0,0 +1,48 @@
+use serde_with::serde_as;
+use serde_with::DurationMilliSeconds;
+use std::{collections::HashMap, time::Duration};
+
+use engine::draw::Color;
+
+/// Emitters of the tile particles and of the spell fxs, by their ids
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct Emitters {
+    #[serde(default)]
+    pub tiles: HashMap<u32, Emitter>,
+    #[serde(default)]
+    pub fxs: HashMap<u32, Emitter>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Emitter {
+    /// Particles spawned per second
+    pub rate: f32,
+    pub max_particles: usize,
+    #[serde_as(as = "DurationMilliSeconds")]
+    pub lifetime: Duration,
+    /// Time spawning particles for each loop of a fx, tile emitters spawn while they are visible
+    #[serde_as(as = "DurationMilliSeconds")]
+    #[serde(default)]
+    pub duration: Duration,
+
+    /// Pixels from the center of the tile
+    #[serde(default)]
+    pub offset: (f32, f32),
+    /// Width and height of the area around the offset where particles spawn
+    #[serde(default)]
+    pub spread: (f32, f32),
+    /// Pixels per second, picked between both velocities on each axis
+    pub velocity: [(f32, f32); 2],
+    /// Pixels per second squared, positive pulls the particles down
+    #[serde(default)]
+    pub gravity: f32,
+
+    /// Tint over the life of the particles, the colors are evenly spread
+    pub colors: Vec<Color>,
+    /// Images from `images.ron` played over the life of the particles
+    pub frames: Vec<u32>,
+    /// Glowing particles, like sparks or fire
+    #[serde(default)]
+    pub additive: bool,
+}
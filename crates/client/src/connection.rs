@@ -12,7 +12,12 @@ use engine::{
     },
     engine::GameEngine,
 };
-use shared::protocol::{client::ClientPacket, server::ServerPacket, ProtocolMessage};
+use shared::protocol::{
+    client::ClientPacket,
+    logging::{self, log_packet, Direction},
+    server::ServerPacket,
+    ProtocolMessage,
+};
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 use wtransport::{ClientConfig, Endpoint};
@@ -232,7 +237,17 @@ impl Connection {
                     match connection_receiver.read(&mut buffer).await {
                         Ok(Some(bytes_read)) => {
                             if let Some(message) = ServerPacket::decode(&buffer[..bytes_read]) {
-                                info!("server <= \n {message:?}");
+                                if let Some(level) = logging::sample(message.name()) {
+                                    log_packet(
+                                        level,
+                                        Direction::Incoming,
+                                        None,
+                                        None,
+                                        message.name(),
+                                        bytes_read,
+                                        &message,
+                                    );
+                                }
 
                                 if incoming_messages_sender.send(message).is_err() {
                                     error!("poisoned");
@@ -267,8 +282,22 @@ impl Connection {
                     last_send = now;
 
                     if let Ok(message) = outgoing_messages_receiver.recv() {
-                        info!("server => \n {message:?}");
+                        let name = message.name();
+                        // encoding consumes the packet, it's formatted before only if it's logged
+                        let logged = logging::sample(name)
+                            .map(|level| (level, format!("{:?}", message.redacted())));
                         if let Some(bytes) = message.encode() {
+                            if let Some((level, payload)) = logged {
+                                log_packet(
+                                    level,
+                                    Direction::Outgoing,
+                                    None,
+                                    None,
+                                    name,
+                                    bytes.len(),
+                                    &format_args!("{payload}"),
+                                );
+                            }
                             if connection_sender.write_all(&bytes).await.is_err() {
                                 error!("failed to send message to server");
                             }
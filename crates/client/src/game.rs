@@ -7,6 +7,7 @@ use crate::{
     maps::Maps,
     resources::Resources,
     screens::{home::HomeScreen, GameScreen, Screen},
+    sounds::Sounds,
     ui::fonts::Fonts,
 };
 
@@ -33,6 +34,7 @@ impl Game for Finisterra {
         let resources = Resources::load(engine);
         let mut maps = Maps::initialize("assets/finisterra/maps/");
         Fonts::load(engine);
+        Sounds::load(engine);
         let screen_transition = channel();
 
         let args: Vec<String> = std::env::args().collect();
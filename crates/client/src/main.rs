@@ -1,4 +1,4 @@
-use engine::game::run_game;
+use engine::{game::run_game, sound::Hearing};
 use game::Finisterra;
 use roma::Roma;
 
@@ -8,6 +8,7 @@ mod game;
 pub mod maps;
 pub mod resources;
 pub mod screens;
+pub mod sounds;
 pub mod texture;
 pub mod ui;
 
@@ -18,6 +19,11 @@ async fn main() {
         height: 540,
         title: "finisterra".to_string(),
         vsync: true,
+        hearing: Hearing {
+            tile_size: 32.,
+            range: 16.,
+            pan_width: 8.,
+        },
     };
 
     run_game::<Finisterra, Roma>(settings).await;
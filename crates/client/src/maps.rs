@@ -1,25 +1,91 @@
-use std::num::NonZeroUsize;
+use std::{
+    num::NonZeroUsize,
+    sync::mpsc::{channel, Receiver, Sender},
+};
 
 use lru::LruCache;
+use nohash_hasher::IntSet;
 use shared::world::Map;
 
 pub struct Maps {
     folder: String,
     cache: LruCache<u16, Map>,
+
+    // maps being loaded in background
+    prefetching: IntSet<u16>,
+    prefetched_sender: Sender<(u16, Option<Map>)>,
+    prefetched: Receiver<(u16, Option<Map>)>,
+    /// neighbours loaded since the last `ready_neighbours`
+    ready: Vec<u16>,
 }
 
 impl Maps {
     pub fn initialize(folder: &str) -> Self {
+        let (prefetched_sender, prefetched) = channel();
         Self {
             folder: folder.to_string(),
             cache: LruCache::new(NonZeroUsize::new(20).unwrap()),
+            prefetching: IntSet::default(),
+            prefetched_sender,
+            prefetched,
+            ready: vec![],
         }
     }
 
     pub fn get(&mut self, map_number: &u16) -> &mut Map {
+        self.receive_prefetched();
+        let folder = &self.folder;
         self.cache.get_or_insert_mut(*map_number, || {
-            let path = format!("{}map_{map_number}", self.folder);
+            let path = format!("{folder}map_{map_number}");
             Map::from_path(&path).expect("Map not found or invalid")
         })
     }
+
+    /// Loads in background the maps linked by the exits of `map_number`
+    pub fn prefetch_neighbours(&mut self, map_number: u16) {
+        let neighbours = self
+            .get(&map_number)
+            .tiles
+            .iter()
+            .flatten()
+            .filter_map(|tile| tile.exit)
+            .map(|exit| exit.map)
+            .filter(|map| *map != map_number)
+            .collect::<IntSet<u16>>();
+
+        for neighbour in neighbours {
+            if self.cache.contains(&neighbour) {
+                self.ready.push(neighbour);
+                continue;
+            }
+            if !self.prefetching.insert(neighbour) {
+                continue;
+            }
+            let path = format!("{}map_{neighbour}", self.folder);
+            let sender = self.prefetched_sender.clone();
+            std::thread::spawn(move || {
+                let _ = sender.send((neighbour, Map::from_path(&path)));
+            });
+        }
+    }
+
+    /// Neighbours prefetched since the last call that are already loaded
+    pub fn ready_neighbours(&mut self) -> Vec<u16> {
+        self.receive_prefetched();
+        std::mem::take(&mut self.ready)
+    }
+
+    fn receive_prefetched(&mut self) {
+        while let Ok((map_number, map)) = self.prefetched.try_recv() {
+            self.prefetching.remove(&map_number);
+            match map {
+                Some(map) if !self.cache.contains(&map_number) => {
+                    self.cache.put(map_number, map);
+                    self.ready.push(map_number);
+                }
+                Some(_) => {}
+                None => tracing::warn!("neighbour map {map_number} not found or invalid"),
+            }
+        }
+    }
 }
@@ -4,7 +4,11 @@ use std::{
 };
 
 use engine::engine::GameEngine;
-use shared::world::Direction;
+use nohash_hasher::IntMap;
+use shared::{
+    argentum::object::Object,
+    world::{Direction, MapInfo},
+};
 
 use crate::{
     argentum::{
@@ -13,6 +17,7 @@ use crate::{
             animation::{BodyAnimation, CharacterAnimation, CharacterAnimations},
             *,
         },
+        particles::Emitters,
         Image,
     },
     ui::textures::Textures,
@@ -21,6 +26,9 @@ use crate::{
 #[derive(Debug, Default)]
 pub struct Resources {
     pub images: Vec<Image>,
+    pub objects: IntMap<usize, Object>,
+    pub map_infos: IntMap<u16, MapInfo>,
+    pub emitters: Emitters,
     pub animations: Vec<Animation<ImageFrameMetadata>>,
 
     pub bodies: Vec<(Body, Vec<Skin>)>,
@@ -40,6 +48,9 @@ impl Resources {
         let mut resources = Resources::default();
 
         resources.load_images(engine, "assets/finisterra/init/images.ron");
+        resources.load_objects("assets/finisterra/init/objects.ron");
+        resources.load_map_infos("assets/finisterra/init/maps.ron");
+        resources.load_emitters("assets/finisterra/init/particles.ron");
         resources.load_body(engine, "assets/finisterra/bodies/human/");
         resources.load_body(engine, "assets/finisterra/bodies/elf/");
         resources.load_body(engine, "assets/finisterra/bodies/drow/");
@@ -78,6 +89,53 @@ impl Resources {
         }
     }
 
+    fn load_objects(&mut self, path: &str) {
+        let Ok(file) = File::open(path) else {
+            tracing::warn!("objects file {path} not present");
+            return;
+        };
+        let reader = std::io::BufReader::new(file);
+
+        let objects: HashMap<usize, Object> =
+            ron::de::from_reader(reader).expect("objects to be correct");
+        self.objects = objects.into_iter().collect();
+    }
+
+    /// Maps without info use the default properties, like on the server
+    fn load_map_infos(&mut self, path: &str) {
+        let Ok(file) = File::open(path) else {
+            tracing::warn!("maps info file {path} not present");
+            return;
+        };
+        let reader = std::io::BufReader::new(file);
+
+        let infos: HashMap<u16, MapInfo> =
+            ron::de::from_reader(reader).expect("maps info to be correct");
+        self.map_infos = infos.into_iter().collect();
+    }
+
+    fn load_emitters(&mut self, path: &str) {
+        let Ok(file) = File::open(path) else {
+            tracing::warn!("particles file {path} not present");
+            return;
+        };
+        let reader = std::io::BufReader::new(file);
+
+        self.emitters = ron::de::from_reader(reader).expect("particles to be correct");
+    }
+
+    pub fn object_name(&self, item_id: u32) -> String {
+        self.objects
+            .get(&(item_id as usize))
+            .map(|object| object.name.clone())
+            .unwrap_or_else(|| format!("#{item_id}"))
+    }
+
+    pub fn object_image(&self, item_id: u32) -> Option<&Image> {
+        let object = self.objects.get(&(item_id as usize))?;
+        self.images.get(object.grh)
+    }
+
     fn load_body<E: GameEngine>(&mut self, engine: &mut E, folder: &str) {
         let body_ron_path = format!("{folder}body.ron");
         let file = File::open(body_ron_path).expect("body.ron not found");
@@ -94,9 +152,9 @@ impl Resources {
         for skin in skins {
             let skin = skin.expect("should be an entry");
             let skin_path = skin.path();
-            let img = image::open(skin_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            // only the header is read, the texture is decoded when it is drawn
+            let (width, height) =
+                image::image_dimensions(&skin_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(skin_path.to_str().expect("is a file"));
             let skin = process_and_build_metadata_from_image(
@@ -125,9 +183,8 @@ impl Resources {
         for cloth in clothing {
             let cloth = cloth.expect("should be an entry");
             let cloth_path = cloth.path();
-            let img = image::open(cloth_path.clone()).expect("cloth file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&cloth_path).expect("cloth file is not an image");
 
             let file_num = engine.add_texture(cloth_path.to_str().expect("is a file"));
             let cloth = process_and_build_metadata_from_image(
@@ -153,9 +210,8 @@ impl Resources {
         for file in faces {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_head(
@@ -175,9 +231,8 @@ impl Resources {
         for file in eyes {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_head(
@@ -196,9 +251,8 @@ impl Resources {
         for file in hairs {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_head(
@@ -223,9 +277,8 @@ impl Resources {
         for file in shields {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_image(
@@ -249,9 +302,8 @@ impl Resources {
         for file in weapons {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_image(
@@ -275,9 +327,8 @@ impl Resources {
         for file in helmets {
             let file = file.expect("should be an entry");
             let file_path = file.path();
-            let img = image::open(file_path.clone()).expect("skin file is not an image");
-            let width = img.width();
-            let height = img.height();
+            let (width, height) =
+                image::image_dimensions(&file_path).expect("skin file is not an image");
 
             let file_num = engine.add_texture(file_path.to_str().expect("is a file"));
             let metadata = process_and_build_metadata_from_image(
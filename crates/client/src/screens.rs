@@ -18,6 +18,9 @@ pub mod demo;
 pub mod home;
 pub mod world;
 
+#[cfg(test)]
+mod tests;
+
 pub enum Screen {
     Home(Box<HomeScreen>),
     Demo,
@@ -226,6 +226,7 @@ impl UI for AccountUI {
                 color: WHITE,
                 index: context.resources.textures.dv_background,
                 source: [0, 0, 0, 0],
+                ..Default::default()
             },
             Target::World,
         );
@@ -92,6 +92,10 @@ impl GameScreen for HomeScreen {
                             .expect("poisoned");
                     }
                     ServerPacket::Account(server::Account::LoginFailed) => self.connecting = false,
+                    ServerPacket::Account(server::Account::Banned { reason }) => {
+                        tracing::warn!("login rejected: {reason}");
+                        self.connecting = false
+                    }
                     ServerPacket::Account(server::Account::CreateFailed { .. }) => {
                         self.connecting = false
                     }
@@ -219,6 +223,7 @@ impl UI for HomeUI {
                 color: [255, 255, 255, 255],
                 index: context.resources.textures.dv_background,
                 source: [0, 0, 0, 0],
+                ..Default::default()
             },
             Target::UI,
         );
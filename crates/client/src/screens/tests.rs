@@ -0,0 +1,114 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use engine::{
+    draw::Target, engine::GameEngine, game::Game, input::keyboard::KeyCode, settings::Settings,
+};
+use headless::{Headless, Input};
+
+use crate::{
+    connection::ConnectionState,
+    game::Context,
+    maps::Maps,
+    resources::Resources,
+    screens::{account::AccountScreen, home::HomeScreen, GameScreen, Screen},
+    ui::{fonts::Fonts, textures::Textures},
+};
+
+/// Runs a screen like `Finisterra` does, without the asset files nor a server
+struct Harness {
+    resources: Resources,
+    maps: Maps,
+    connection: ConnectionState,
+    screen: Option<Screen>,
+    screen_transition: (Sender<Screen>, Receiver<Screen>),
+}
+
+impl Game for Harness {
+    fn initialize<E: GameEngine>(engine: &mut E) -> Self {
+        Fonts::load(engine);
+        let resources = Resources {
+            textures: Textures::load(engine),
+            ..Default::default()
+        };
+        Self {
+            resources,
+            maps: Maps::initialize("assets/finisterra/maps/"),
+            connection: ConnectionState::new("https://127.0.0.1:7666", engine),
+            screen: None,
+            screen_transition: channel(),
+        }
+    }
+
+    fn tick<E: GameEngine>(&mut self, engine: &mut E) {
+        let mut context = Context {
+            screen_transition_sender: &self.screen_transition.0,
+            connection: &mut self.connection,
+            resources: &self.resources,
+            maps: &mut self.maps,
+            engine,
+        };
+        let screen = self.screen.as_mut().expect("a screen to run");
+        screen.update(&mut context);
+        screen.draw(&mut context);
+    }
+}
+
+impl Harness {
+    fn open(engine: &mut Headless, screen: impl FnOnce(&mut Context<Headless>) -> Screen) -> Self {
+        let mut harness = Self::initialize(engine);
+        let mut context = Context {
+            screen_transition_sender: &harness.screen_transition.0,
+            connection: &mut harness.connection,
+            resources: &harness.resources,
+            maps: &mut harness.maps,
+            engine,
+        };
+        harness.screen = Some(screen(&mut context));
+        harness
+    }
+}
+
+#[test]
+fn home_screen_types_the_user_and_hides_the_password() {
+    let mut engine = Headless::new(&Settings::default());
+    let mut harness = Harness::open(&mut engine, |context| {
+        Screen::Home(Box::new(HomeScreen::new(context)))
+    });
+
+    engine.script([
+        vec![Input::Type("admin".to_string())],
+        vec![Input::Press(KeyCode::Tab)],
+        vec![
+            Input::Release(KeyCode::Tab),
+            Input::Type("hunter2".to_string()),
+        ],
+    ]);
+    engine.run(&mut harness, 3);
+
+    let frame = engine.frame();
+    assert!(frame.contains_text("User"));
+    assert!(frame.contains_text("Log in"));
+    assert!(frame.contains_text("admin"));
+    assert!(frame.contains_text("*******"));
+    assert!(!frame.contains_text("hunter2"));
+    assert_eq!(frame.images_of(harness.resources.textures.input).count(), 2);
+}
+
+#[test]
+fn account_screen_offers_to_create_characters_in_empty_slots() {
+    let mut engine = Headless::new(&Settings::default());
+    let mut harness = Harness::open(&mut engine, |context| {
+        Screen::Account(Box::new(AccountScreen::new(context, vec![])))
+    });
+
+    engine.run(&mut harness, 1);
+
+    let frame = engine.frame();
+    let textures = &harness.resources.textures;
+    assert_eq!(frame.images_of(textures.char_create_slot).count(), 6);
+    assert_eq!(frame.images_of(textures.char_slot).count(), 0);
+    assert!(frame.contains_text("Enter"));
+    assert!(frame
+        .images_in(Target::World)
+        .any(|image| image.index == textures.dv_background));
+}
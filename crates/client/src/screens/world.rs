@@ -7,13 +7,21 @@ use engine::{
 };
 use nohash_hasher::IntMap;
 use shared::{
-    protocol::server::{CharacterUpdate, DialogKind, ServerPacket},
+    protocol::{
+        chat::Channel,
+        crafting::CraftingKind,
+        server::{
+            Bank, CharacterUpdate, Chat, Commerce, Crafting, DialogKind, Environment, Event,
+            Message, Pet, ServerPacket, Trade, UserUpdate,
+        },
+    },
     world::{Direction, WorldPosition},
 };
 
 use crate::{
     game::Context,
     screens::world::map::WorldMap,
+    sounds,
     ui::{colors::*, fonts::*},
 };
 
@@ -21,6 +29,9 @@ use self::{
     entity::{Character, Entity},
     fps::Fps,
     hud::HUD,
+    lights::Lights,
+    particles::Particles,
+    weather::Weather,
 };
 
 use super::GameScreen;
@@ -31,9 +42,12 @@ pub mod fps;
 pub mod hud;
 pub mod input;
 pub mod interpolation;
+mod lights;
 mod map;
+mod particles;
 pub mod prediction;
 pub mod reconciliation;
+mod weather;
 
 const TILE_SIZE: u16 = 32;
 const TILE_SIZE_F: f32 = TILE_SIZE as f32;
@@ -44,6 +58,14 @@ const WORLD_RENDER_WIDTH: u16 = 549; // It's around 17 tiles
 const WORLD_RENDER_HEIGHT: u16 = 521; // It's around 16 tiles
 const HORIZONTAL_TILES: u16 = 17;
 const VERTICAL_TILES: u16 = 16;
+/// Tiles around the view whose lights and particles are drawn
+const EXTRA_TILES: u16 = 5;
+const HORIZONTAL_EXTRA_TILES: u16 = ((HORIZONTAL_TILES + 1) / 2) + EXTRA_TILES;
+const VERTICAL_EXTRA_TILES: u16 = (VERTICAL_TILES / 2) + EXTRA_TILES;
+
+/// Texture loading priorities, the current map loads before its neighbours
+const CURRENT_MAP_PRIORITY: u8 = 200;
+const NEIGHBOUR_MAP_PRIORITY: u8 = 100;
 
 pub struct WorldScreen {
     hud: HUD,
@@ -58,7 +80,13 @@ pub struct WorldScreen {
     movement_sequence: u8,
     predictions: Vec<(u8, WorldPosition)>,
     last_move: Instant,
+    last_click: Option<(Instant, WorldPosition)>,
+    /// click to walk destination, the path is searched again on every step
+    walk_target: Option<WorldPosition>,
     map: WorldMap,
+    lights: Lights,
+    particles: Particles,
+    weather: Weather,
     fps: Fps,
 }
 
@@ -73,12 +101,18 @@ impl GameScreen for WorldScreen {
         self.update_fps(context);
         self.update_ping(context);
         self.update_character(context);
+        self.update_particles(context);
+        self.update_weather(context);
         self.update_message_input(context);
+        self.prefetch_neighbour_textures(context);
     }
 
     fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
         self.draw_hud(context);
         self.draw_world_2(context);
+        self.draw_particles(context);
+        self.draw_weather(context);
+        self.draw_lights(context);
     }
 }
 
@@ -93,6 +127,8 @@ impl WorldScreen {
         let map = context.maps.get(&character.position.map);
         map.tile_mut(character.position.x, character.position.y)
             .user = Some(entity_id);
+        context.maps.prefetch_neighbours(character.position.map);
+        map::prefetch_textures(context, character.position.map, CURRENT_MAP_PRIORITY);
         entities.insert(entity_id, Entity::Character(character));
 
         context.engine.set_mouse_cursor(CursorIcon::Default);
@@ -105,8 +141,13 @@ impl WorldScreen {
             predictions: vec![],
             input: VecDeque::new(),
             last_move: Instant::now(),
+            last_click: None,
+            walk_target: None,
             fps: Fps::default(),
             map: WorldMap::initialize(context),
+            lights: Lights::default(),
+            particles: Particles::default(),
+            weather: Weather::default(),
             // map: WorldMap::default(),
         }
     }
@@ -136,13 +177,11 @@ impl WorldScreen {
                     map.tile_mut(character.position.x, character.position.y)
                         .user = Some(entity_id);
 
-                    let entity = Entity::Character(Character::from(context, character));
+                    let entity = Entity::Character(Character::from(context, *character));
 
                     self.entities.insert(entity_id, entity);
                 }
-                CharacterUpdate::Remove { entity_id } => {
-                    self.entities.remove(&entity_id);
-                }
+                CharacterUpdate::Remove { entity_id } => self.remove_entity(context, entity_id),
                 CharacterUpdate::Move {
                     entity_id,
                     position,
@@ -166,7 +205,34 @@ impl WorldScreen {
                     else {
                         return;
                     };
+                    let old_position = character.position;
                     character.translate(position);
+                    context
+                        .maps
+                        .get(&old_position.map)
+                        .tile_mut(old_position.x, old_position.y)
+                        .user = None;
+                    context
+                        .maps
+                        .get(&position.map)
+                        .tile_mut(position.x, position.y)
+                        .user = Some(self.entity_id);
+
+                    if old_position.map != position.map {
+                        // the server sends the entities of the new map
+                        let others = self
+                            .entities
+                            .keys()
+                            .filter(|id| **id != self.entity_id)
+                            .copied()
+                            .collect::<Vec<_>>();
+                        for id in others {
+                            self.remove_entity(context, id);
+                        }
+                        self.map_changed();
+                        context.maps.prefetch_neighbours(position.map);
+                        map::prefetch_textures(context, position.map, CURRENT_MAP_PRIORITY);
+                    }
                 }
                 CharacterUpdate::MoveResponse {
                     request_id,
@@ -203,10 +269,266 @@ impl WorldScreen {
                 }
                 _ => {}
             },
-            ServerPacket::UserUpdate(_) => todo!(),
-            ServerPacket::Event(_) => todo!(),
+            ServerPacket::UserUpdate(update) => match update {
+                UserUpdate::InventorySlot { slot, item } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.inventory.set(slot as usize, item);
+                        if self.hud.commerce.is_visible() {
+                            self.hud.commerce.set_inventory(context, character);
+                        }
+                        if self.hud.bank.is_visible() {
+                            self.hud.bank.set_inventory(context, &character.inventory);
+                        }
+                        if self.hud.trade.is_visible() {
+                            self.hud.trade.set_inventory(context, &character.inventory);
+                        }
+                    }
+                    self.hud.inventory.set_slot(context, slot as usize, item);
+                }
+                UserUpdate::Gold { gold } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.gold = gold;
+                    }
+                }
+                UserUpdate::BankSlot { slot, item } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.vault.items.set(slot as usize, item);
+                        self.hud.bank.set_vault(context, &character.vault);
+                    }
+                }
+                UserUpdate::BankGold { gold } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.vault.gold = gold;
+                        self.hud.bank.set_vault(context, &character.vault);
+                    }
+                }
+                UserUpdate::Mana { mana } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.stats.mana.current = mana;
+                    }
+                }
+                _ => {}
+            },
+            ServerPacket::Event(event) => {
+                match event {
+                    Event::LevelUp => self.flash_level_up(),
+                    Event::FX {
+                        position,
+                        fx,
+                        loops,
+                    } => self
+                        .particles
+                        .spawn_fx(context.resources, position, fx, loops),
+                    _ => {}
+                }
+                self.play_event_sound(context, event);
+            }
             ServerPacket::Object(_) => todo!(),
-            ServerPacket::Message(_) => todo!(),
+            ServerPacket::Message(message) => match message {
+                Message::Console { text } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &text, GRAY_4, TAHOMA_REGULAR_8_ID);
+                }
+                Message::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+                Message::See { .. } | Message::SeeNothing => {}
+            },
+            ServerPacket::Crafting(crafting) => match crafting {
+                Crafting::Recipes { kind, recipes } => {
+                    let Some(Entity::Character(character)) = self.entities.get(&self.entity_id)
+                    else {
+                        return;
+                    };
+                    let skill = match kind {
+                        CraftingKind::Smithy => character.skills.smithy,
+                        CraftingKind::Carpentry => character.skills.carpentry,
+                    };
+                    self.hud.crafting.open(context, kind, recipes, skill);
+                }
+                Crafting::Crafted { item_id, amount } => {
+                    let name = context.resources.object_name(item_id);
+                    self.hud.console.push(
+                        context.engine,
+                        &format!("You crafted {amount} {name}"),
+                        GRAY_4,
+                        TAHOMA_REGULAR_8_ID,
+                    );
+                }
+                Crafting::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+            },
+            ServerPacket::Commerce(commerce) => match commerce {
+                Commerce::Open { name, items, .. } => {
+                    if let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) {
+                        self.hud.commerce.open(context, &name, items, character);
+                    }
+                }
+                Commerce::Stock { items } => self.hud.commerce.set_stock(context, items),
+                Commerce::Close => self.hud.commerce.close(),
+                Commerce::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+            },
+            ServerPacket::Bank(bank) => match bank {
+                Bank::Open { vault } => {
+                    if let Some(Entity::Character(character)) =
+                        self.entities.get_mut(&self.entity_id)
+                    {
+                        character.vault = vault;
+                        self.hud
+                            .bank
+                            .open(context, &character.vault, &character.inventory);
+                    }
+                }
+                Bank::Close => self.hud.bank.close(),
+                Bank::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+            },
+            ServerPacket::Trade(trade) => match trade {
+                Trade::Requested { name, .. } => {
+                    self.hud.console.push(
+                        context.engine,
+                        &format!("{name} wants to trade, double click them to accept"),
+                        YELLOW,
+                        TAHOMA_REGULAR_8_ID,
+                    );
+                }
+                Trade::Started { name, .. } => {
+                    if let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) {
+                        self.hud.trade.open(context, &name, &character.inventory);
+                    }
+                }
+                Trade::Update {
+                    own,
+                    other,
+                    own_stage,
+                    other_stage,
+                } => {
+                    self.hud
+                        .trade
+                        .set_offers(context, own, other, own_stage, other_stage);
+                }
+                Trade::Completed => {
+                    self.hud.trade.close();
+                    self.hud.console.push(
+                        context.engine,
+                        "Trade completed",
+                        GRAY_4,
+                        TAHOMA_REGULAR_8_ID,
+                    );
+                }
+                Trade::Cancelled { reason } => {
+                    self.hud.trade.close();
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+                Trade::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+            },
+            ServerPacket::Pet(pet) => {
+                let (text, color) = match pet {
+                    Pet::Tamed { name, .. } => (format!("You tamed {name}"), GRAY_4),
+                    Pet::Summoned { name, .. } => (format!("You summoned {name}"), GRAY_4),
+                    Pet::Gone { name, .. } => (format!("{name} is no longer your pet"), GRAY_4),
+                    Pet::Failed { reason } => (reason, RED),
+                };
+                self.hud
+                    .console
+                    .push(context.engine, &text, color, TAHOMA_REGULAR_8_ID);
+            }
+            ServerPacket::Environment(environment) => match environment {
+                Environment::Clock { time, day } => self.weather.set_clock(time, day),
+                Environment::Weather { intensity } => self.weather.set_intensity(intensity),
+            },
+            ServerPacket::Chat(chat) => match chat {
+                Chat::Message {
+                    channel,
+                    from,
+                    text,
+                } => {
+                    let line = match channel {
+                        Channel::System => text,
+                        Channel::Say => format!("{from}: {text}"),
+                        Channel::Whisper => format!("{from} whispers: {text}"),
+                        Channel::Party => format!("[Party] {from}: {text}"),
+                        Channel::Guild => format!("[Guild] {from}: {text}"),
+                        Channel::Global => format!("[Global] {from}: {text}"),
+                        Channel::Trade => format!("[Trade] {from}: {text}"),
+                    };
+                    self.hud.console.push_chat(context.engine, channel, &line);
+                }
+                Chat::Whispered { to, text } => {
+                    self.hud.console.push_chat(
+                        context.engine,
+                        Channel::Whisper,
+                        &format!("To {to}: {text}"),
+                    );
+                }
+                Chat::Failed { reason } => {
+                    self.hud
+                        .console
+                        .push(context.engine, &reason, RED, TAHOMA_REGULAR_8_ID);
+                }
+            },
+        }
+    }
+
+    /// Combat sounds are heard from the entity, even when it's off screen
+    fn play_event_sound<E: GameEngine>(&self, context: &mut Context<E>, event: Event) {
+        let (sound, entity_id) = match event {
+            Event::LevelUp => {
+                sounds::play(context.engine, sounds::LEVEL_UP);
+                return;
+            }
+            Event::Attack { entity_id } => (sounds::SWING, entity_id),
+            Event::ShieldBlock { entity_id } => (sounds::SHIELD_BLOCK, entity_id),
+            Event::Hit { entity_id } => (sounds::HIT, entity_id),
+            Event::Kill { entity_id } => (sounds::DEATH, entity_id),
+            Event::FX { .. } => return,
+        };
+        let Some(Entity::Character(character)) = self.entities.get(&entity_id) else {
+            return;
+        };
+        sounds::play_at(context.engine, sound, character.render_position);
+    }
+
+    fn remove_entity<E: GameEngine>(&mut self, context: &mut Context<E>, entity_id: u32) {
+        let Some(Entity::Character(character)) = self.entities.remove(&entity_id) else {
+            return;
+        };
+        let position = character.position;
+        let tile = context
+            .maps
+            .get(&position.map)
+            .tile_mut(position.x, position.y);
+        if tile.user == Some(entity_id) {
+            tile.user = None;
         }
     }
 
@@ -268,13 +590,26 @@ impl WorldScreen {
                 .set_world_camera_position(camera::Position { x, y });
 
             self.hud.update_character(context, character);
-
-            if !self.input.is_empty() && (!character.moving() || character.just_finished_moving()) {
+            let position = character.position;
+            let trigger = context
+                .maps
+                .get(&position.map)
+                .trigger(position.x, position.y);
+            self.hud.set_zone(context, trigger);
+
+            let wants_to_move = !self.input.is_empty() || self.walk_target.is_some();
+            if wants_to_move && (!character.moving() || character.just_finished_moving()) {
                 self.start_move(context);
             }
         }
     }
 
+    fn prefetch_neighbour_textures<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        for neighbour in context.maps.ready_neighbours() {
+            map::prefetch_textures(context, neighbour, NEIGHBOUR_MAP_PRIORITY);
+        }
+    }
+
     fn update_fps<E: GameEngine>(&mut self, context: &mut Context<E>) {
         self.fps.update(context.engine.get_delta());
         let fps = format!("{:.0} FPS", self.fps.get());
@@ -13,7 +13,7 @@ use crate::{
 };
 use engine::{
     draw::{
-        image::DrawImage,
+        image::{Blend, DrawImage},
         text::{DrawText, ParsedText},
         Color, Dimensions, Position, Target,
     },
@@ -29,6 +29,7 @@ use shared::{
 use crate::{
     game::Context,
     resources::Resources,
+    sounds,
     ui::{colors::*, fonts::TAHOMA_BOLD_8_SHADOW_ID},
 };
 
@@ -260,7 +261,11 @@ impl Character {
             }
         }
 
+        let previous = self.animation.animator;
         self.animation.update_animation(delta);
+        if let Some(left) = self.animation.footstep(&previous) {
+            sounds::footstep(engine, left, self.render_position);
+        }
         if let Some(dialog) = self.dialog.as_mut() {
             dialog.update(delta);
             if dialog.finished() {
@@ -376,6 +381,9 @@ impl Character {
                             color: transparent(GRAY_6, transparency),
                             index: *texture,
                             source: [offset_x as u16, offset_y as u16, 64, 64],
+                            // the shimmer lightens what is behind the character
+                            blend: Blend::Additive,
+                            ..Default::default()
                         },
                         Target::World,
                     );
@@ -431,6 +439,7 @@ impl Character {
                         source: [image.x, image.y, image.width, image.height],
                         color,
                         index: image.file,
+                        ..Default::default()
                     },
                     target,
                 );
@@ -0,0 +1,227 @@
+use engine::{draw::Target, engine::GameEngine};
+use shared::{
+    character::{Inventory, Vault},
+    protocol::client::{Bank, ClientPacket},
+};
+
+use crate::{
+    game::Context,
+    ui::{
+        button::{Button, ButtonBuilder},
+        colors::*,
+        fonts::{TAHOMA_BOLD_8_SHADOW_ID, WIZARD_16_ID},
+        input_field::InputField,
+        label::Label,
+        list::List,
+        texture::Texture,
+        Alignment, Widget,
+    },
+};
+
+/// Vault on the left and inventory on the right, without a selection the amount is used as gold.
+/// The item selected on the other side is where the moved items stack
+pub struct BankWindow {
+    pub position: (u16, u16), // center
+    visible: bool,
+
+    background: Texture,
+    title: Label,
+    gold: Label,
+    vault: List,
+    inventory: List,
+    amount: InputField,
+    withdraw_button: Button,
+    deposit_button: Button,
+    close_button: Button,
+
+    vault_slots: Vec<usize>,
+    inventory_slots: Vec<usize>,
+}
+
+impl BankWindow {
+    pub fn initialize<E: GameEngine>(context: &mut Context<E>) -> Self {
+        let background = Texture::new(context.resources.textures.window, WHITE, (0, 0));
+        let title = Label::from("Bank", WIZARD_16_ID, GRAY_6, context.engine);
+        let gold = Label::from("", TAHOMA_BOLD_8_SHADOW_ID, YELLOW, context.engine);
+        let vault = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 90));
+        let inventory = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 90));
+        let amount = InputField::new(
+            GRAY_6,
+            GRAY_1,
+            (0, 0),
+            (200, 30),
+            TAHOMA_BOLD_8_SHADOW_ID,
+            context.resources.textures.input,
+            context,
+        );
+
+        let button = |context: &mut Context<E>, text: &str, color| {
+            let label = Label::from(text, TAHOMA_BOLD_8_SHADOW_ID, GRAY_6, context.engine);
+            ButtonBuilder::new()
+                .texture_id(context.resources.textures.button)
+                .label(label)
+                .color(color)
+                .alignment(Alignment::Center)
+                .z(1.)
+                .target(Target::UI)
+                .build()
+        };
+        let withdraw_button = button(context, "Withdraw", BLUE);
+        let deposit_button = button(context, "Deposit", BLUE);
+        let close_button = button(context, "Close", GRAY_2);
+
+        Self {
+            position: (0, 0),
+            visible: false,
+            background,
+            title,
+            gold,
+            vault,
+            inventory,
+            amount,
+            withdraw_button,
+            deposit_button,
+            close_button,
+            vault_slots: vec![],
+            inventory_slots: vec![],
+        }
+    }
+
+    pub fn open<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        vault: &Vault,
+        inventory: &Inventory,
+    ) {
+        self.vault.clear_selection();
+        self.inventory.clear_selection();
+        self.set_vault(context, vault);
+        self.set_inventory(context, inventory);
+        self.visible = true;
+    }
+
+    pub fn set_vault<E: GameEngine>(&mut self, context: &mut Context<E>, vault: &Vault) {
+        self.gold
+            .set_text(&format!("Gold: {}", vault.gold), context.engine);
+        self.vault_slots = set_items(context, &mut self.vault, &vault.items);
+    }
+
+    pub fn set_inventory<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        inventory: &Inventory,
+    ) {
+        self.inventory_slots = set_items(context, &mut self.inventory, inventory);
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn amount(&self) -> u64 {
+        self.amount.text().trim().parse().unwrap_or(1)
+    }
+}
+
+/// Fills the list with the items and returns the slot of each line
+fn set_items<E: GameEngine>(
+    context: &mut Context<E>,
+    list: &mut List,
+    items: &Inventory,
+) -> Vec<usize> {
+    let mut lines = vec![];
+    let mut slots = vec![];
+    for (slot, item) in items.slots() {
+        let name = context.resources.object_name(item.item_id);
+        lines.push((format!("{name} x{}", item.amount), GRAY_5));
+        slots.push(slot);
+    }
+    list.set_lines(context.engine, lines);
+    slots
+}
+
+/// Slot of the selected line, items moved to the other side stack on it
+fn selected_slot(list: &List, slots: &[usize]) -> Option<u8> {
+    list.selected()
+        .and_then(|selected| slots.get(selected))
+        .map(|slot| *slot as u8)
+}
+
+impl Widget for BankWindow {
+    fn update<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        let (x, y) = self.position;
+        self.background.update(context);
+        self.background.position = (x, y);
+        self.title.position = (x, y + 85);
+        self.gold.position = (x, y + 68);
+        self.vault.position = (x - 148, y + 60);
+        self.inventory.position = (x + 8, y + 60);
+        self.amount.position = (x, y - 68);
+        self.withdraw_button.position = (x - 95, y - 85);
+        self.close_button.position = (x, y - 85);
+        self.deposit_button.position = (x + 95, y - 85);
+
+        self.vault.update(context);
+        self.inventory.update(context);
+        self.amount.update(context);
+        self.withdraw_button.update(context);
+        self.deposit_button.update(context);
+        self.close_button.update(context);
+
+        let amount = self.amount();
+        if self.withdraw_button.clicked() {
+            let packet = match self
+                .vault
+                .selected()
+                .and_then(|selected| self.vault_slots.get(selected))
+            {
+                Some(slot) => Bank::ExtractItem {
+                    slot: *slot as u8,
+                    amount: std::cmp::min(amount, u16::MAX as u64) as u16,
+                    to: selected_slot(&self.inventory, &self.inventory_slots),
+                },
+                None => Bank::Extract { amount },
+            };
+            context.connection.send(ClientPacket::Bank(packet));
+        } else if self.deposit_button.clicked() {
+            let packet = match self
+                .inventory
+                .selected()
+                .and_then(|selected| self.inventory_slots.get(selected))
+            {
+                Some(slot) => Bank::DepositItem {
+                    slot: *slot as u8,
+                    amount: std::cmp::min(amount, u16::MAX as u64) as u16,
+                    to: selected_slot(&self.vault, &self.vault_slots),
+                },
+                None => Bank::Deposit { amount },
+            };
+            context.connection.send(ClientPacket::Bank(packet));
+        } else if self.close_button.clicked() {
+            context.connection.send(ClientPacket::Bank(Bank::Close));
+            self.close();
+        }
+    }
+
+    fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        self.background.draw(context);
+        self.title.draw(context);
+        self.gold.draw(context);
+        self.vault.draw(context);
+        self.inventory.draw(context);
+        self.amount.draw(context);
+        self.withdraw_button.draw(context);
+        self.deposit_button.draw(context);
+        self.close_button.draw(context);
+    }
+}
@@ -0,0 +1,199 @@
+use engine::{draw::Target, engine::GameEngine};
+use shared::{
+    character::Character,
+    protocol::{
+        client::{ClientPacket, Commerce},
+        commerce::{sell_price, ShopItem},
+    },
+};
+
+use crate::{
+    game::Context,
+    ui::{
+        button::{Button, ButtonBuilder},
+        colors::*,
+        fonts::{TAHOMA_BOLD_8_SHADOW_ID, WIZARD_16_ID},
+        label::Label,
+        list::List,
+        texture::Texture,
+        Alignment, Widget,
+    },
+};
+
+/// Two pane window, merchant stock on the left and user inventory on the right
+pub struct CommerceWindow {
+    pub position: (u16, u16), // center
+    visible: bool,
+
+    background: Texture,
+    title: Label,
+    shop: List,
+    inventory: List,
+    buy_button: Button,
+    sell_button: Button,
+    close_button: Button,
+
+    inventory_slots: Vec<usize>,
+}
+
+impl CommerceWindow {
+    pub fn initialize<E: GameEngine>(context: &mut Context<E>) -> Self {
+        let background = Texture::new(context.resources.textures.window, WHITE, (0, 0));
+        let title = Label::from("", WIZARD_16_ID, GRAY_6, context.engine);
+        let shop = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 130));
+        let inventory = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 130));
+
+        let button = |context: &mut Context<E>, text: &str, color| {
+            let label = Label::from(text, TAHOMA_BOLD_8_SHADOW_ID, GRAY_6, context.engine);
+            ButtonBuilder::new()
+                .texture_id(context.resources.textures.button)
+                .label(label)
+                .color(color)
+                .alignment(Alignment::Center)
+                .z(1.)
+                .target(Target::UI)
+                .build()
+        };
+        let buy_button = button(context, "Buy", BLUE);
+        let sell_button = button(context, "Sell", BLUE);
+        let close_button = button(context, "Close", GRAY_2);
+
+        Self {
+            position: (0, 0),
+            visible: false,
+            background,
+            title,
+            shop,
+            inventory,
+            buy_button,
+            sell_button,
+            close_button,
+            inventory_slots: vec![],
+        }
+    }
+
+    pub fn open<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        name: &str,
+        items: Vec<ShopItem>,
+        character: &Character,
+    ) {
+        self.title.set_text(name, context.engine);
+        self.shop.clear_selection();
+        self.inventory.clear_selection();
+        self.set_stock(context, items);
+        self.set_inventory(context, character);
+        self.visible = true;
+    }
+
+    pub fn set_stock<E: GameEngine>(&mut self, context: &mut Context<E>, items: Vec<ShopItem>) {
+        let lines = items
+            .iter()
+            .map(|item| {
+                let name = context.resources.object_name(item.item_id);
+                let color = if item.amount > 0 { GRAY_5 } else { GRAY_3 };
+                (format!("{name} x{} - {}g", item.amount, item.price), color)
+            })
+            .collect();
+        self.shop.set_lines(context.engine, lines);
+    }
+
+    /// Lists the inventory with the price the merchant pays for each item
+    pub fn set_inventory<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        character: &Character,
+    ) {
+        let mut lines = vec![];
+        self.inventory_slots.clear();
+        for (slot, item) in character.inventory.slots() {
+            let (name, value) = match context.resources.objects.get(&(item.item_id as usize)) {
+                Some(object) => (object.name.clone(), object.value),
+                None => (context.resources.object_name(item.item_id), 0),
+            };
+            let price = sell_price(
+                value,
+                character.skills.trading,
+                character.attributes.charisma,
+            );
+            let color = if price > 0 { GRAY_5 } else { GRAY_3 };
+            lines.push((format!("{name} x{} - {price}g", item.amount), color));
+            self.inventory_slots.push(slot);
+        }
+        self.inventory.set_lines(context.engine, lines);
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Widget for CommerceWindow {
+    fn update<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        let (x, y) = self.position;
+        self.background.update(context);
+        self.background.position = (x, y);
+        self.title.position = (x, y + 85);
+        self.shop.position = (x - 148, y + 70);
+        self.inventory.position = (x + 8, y + 70);
+        self.buy_button.position = (x - 95, y - 85);
+        self.close_button.position = (x, y - 85);
+        self.sell_button.position = (x + 95, y - 85);
+
+        self.shop.update(context);
+        self.inventory.update(context);
+        self.buy_button.update(context);
+        self.sell_button.update(context);
+        self.close_button.update(context);
+
+        if self.buy_button.clicked() {
+            if let Some(slot) = self.shop.selected() {
+                context
+                    .connection
+                    .send(ClientPacket::Commerce(Commerce::Buy {
+                        slot: slot as u8,
+                        amount: 1,
+                    }));
+            }
+        } else if self.sell_button.clicked() {
+            if let Some(slot) = self
+                .inventory
+                .selected()
+                .and_then(|selected| self.inventory_slots.get(selected))
+            {
+                context
+                    .connection
+                    .send(ClientPacket::Commerce(Commerce::Sell {
+                        slot: *slot as u8,
+                        amount: 1,
+                    }));
+            }
+        } else if self.close_button.clicked() {
+            context
+                .connection
+                .send(ClientPacket::Commerce(Commerce::Close));
+            self.close();
+        }
+    }
+
+    fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        self.background.draw(context);
+        self.title.draw(context);
+        self.shop.draw(context);
+        self.inventory.draw(context);
+        self.buy_button.draw(context);
+        self.sell_button.draw(context);
+        self.close_button.draw(context);
+    }
+}
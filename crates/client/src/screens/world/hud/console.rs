@@ -1,5 +1,7 @@
 use std::collections::VecDeque;
 
+use shared::protocol::chat::Channel;
+
 use engine::{
     draw::{
         text::{DrawText, ParsedText},
@@ -11,24 +13,68 @@ use engine::{
 use crate::ui::{
     colors::*,
     fonts::{TAHOMA_BOLD_8_ID, TAHOMA_BOLD_8_SHADOW_ID, TAHOMA_REGULAR_8_ID},
+    list::mouse_position,
     Widget,
 };
 
 const MAX_LINES: usize = 300;
 const LINES: usize = 6;
 const LINE_HEIGHT: u16 = 14;
+const TAB_WIDTH: u16 = 40;
+const TABS_OFFSET: u16 = 400;
+const TABS: [ConsoleTab; 4] = [
+    ConsoleTab::All,
+    ConsoleTab::Chat,
+    ConsoleTab::Global,
+    ConsoleTab::System,
+];
+
+/// Filter of the lines shown in the console
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ConsoleTab {
+    All,
+    Chat,
+    Global,
+    System,
+}
+
+impl ConsoleTab {
+    fn name(&self) -> &'static str {
+        match self {
+            ConsoleTab::All => "All",
+            ConsoleTab::Chat => "Chat",
+            ConsoleTab::Global => "Global",
+            ConsoleTab::System => "System",
+        }
+    }
+
+    fn shows(&self, channel: Channel) -> bool {
+        match self {
+            ConsoleTab::All => true,
+            ConsoleTab::Chat => matches!(
+                channel,
+                Channel::Say | Channel::Whisper | Channel::Party | Channel::Guild
+            ),
+            ConsoleTab::Global => matches!(channel, Channel::Global | Channel::Trade),
+            ConsoleTab::System => channel == Channel::System,
+        }
+    }
+}
 
 pub struct Console {
     pub position: (u16, u16),
     pub z: f32,
 
     pub lines: VecDeque<Line>,
+    pub tab: ConsoleTab,
+    tab_names: Vec<ParsedText>,
 }
 
 pub struct Line {
     text: ParsedText,
     color: Color,
     font_id: FontID,
+    channel: Channel,
 }
 
 impl Line {
@@ -37,11 +83,13 @@ impl Line {
         text: &str,
         color: Color,
         font_id: FontID,
+        channel: Channel,
     ) -> Self {
         Self {
             text: engine.parse_text(font_id, text).expect("can parse"),
             color,
             font_id,
+            channel,
         }
     }
 }
@@ -52,6 +100,15 @@ impl Console {
             position: (0, 0),
             z: 1.,
             lines: VecDeque::new(),
+            tab: ConsoleTab::All,
+            tab_names: TABS
+                .iter()
+                .map(|tab| {
+                    engine
+                        .parse_text(TAHOMA_BOLD_8_SHADOW_ID, tab.name())
+                        .expect("can parse")
+                })
+                .collect(),
         };
 
         console.push(engine, "Test line 1", GRAY_2, TAHOMA_REGULAR_8_ID);
@@ -61,6 +118,7 @@ impl Console {
         console
     }
 
+    /// Pushes a line to the system channel
     pub fn push<E: engine::engine::GameEngine>(
         &mut self,
         engine: &mut E,
@@ -68,7 +126,28 @@ impl Console {
         color: Color,
         font_id: FontID,
     ) {
-        let line = Line::new(engine, text, color, font_id);
+        self.push_line(Line::new(engine, text, color, font_id, Channel::System));
+    }
+
+    pub fn push_chat<E: engine::engine::GameEngine>(
+        &mut self,
+        engine: &mut E,
+        channel: Channel,
+        text: &str,
+    ) {
+        let color = match channel {
+            Channel::Say => GRAY_6,
+            Channel::Whisper => PURPLE_2,
+            Channel::Party => GREEN_3,
+            Channel::Guild => BLUE_3,
+            Channel::Global => ORANGE,
+            Channel::Trade => YELLOW,
+            Channel::System => GRAY_4,
+        };
+        self.push_line(Line::new(engine, text, color, TAHOMA_REGULAR_8_ID, channel));
+    }
+
+    fn push_line(&mut self, line: Line) {
         if self.lines.len() >= MAX_LINES {
             self.lines.pop_back();
         }
@@ -77,12 +156,30 @@ impl Console {
 }
 
 impl Widget for Console {
-    fn update<E: engine::engine::GameEngine>(&mut self, _context: &mut crate::game::Context<E>) {}
+    fn update<E: engine::engine::GameEngine>(&mut self, context: &mut crate::game::Context<E>) {
+        if !context.engine.mouse_clicked() {
+            return;
+        }
+        let (x, y) = mouse_position(context.engine);
+        let (left, top) = (self.position.0 + TABS_OFFSET, self.position.1);
+        if x < left || y < top || y > top + LINE_HEIGHT {
+            return;
+        }
+        if let Some(tab) = TABS.get(((x - left) / TAB_WIDTH) as usize) {
+            self.tab = *tab;
+        }
+    }
 
     fn draw<E: engine::engine::GameEngine>(&mut self, context: &mut crate::game::Context<E>) {
         let mut y = self.position.1 - LINE_HEIGHT;
         let x = self.position.0;
-        for line in self.lines.iter().take(LINES).rev() {
+        let lines = self
+            .lines
+            .iter()
+            .filter(|line| self.tab.shows(line.channel))
+            .take(LINES)
+            .collect::<Vec<_>>();
+        for line in lines.into_iter().rev() {
             context.engine.draw_text(
                 line.font_id,
                 DrawText {
@@ -95,5 +192,22 @@ impl Widget for Console {
 
             y -= line.text.height;
         }
+
+        for (i, (tab, name)) in TABS.iter().zip(&self.tab_names).enumerate() {
+            let color = if *tab == self.tab { YELLOW } else { GRAY_3 };
+            context.engine.draw_text(
+                TAHOMA_BOLD_8_SHADOW_ID,
+                DrawText {
+                    text: name,
+                    position: Position::new(
+                        self.position.0 + TABS_OFFSET + i as u16 * TAB_WIDTH + name.total_width / 2,
+                        self.position.1,
+                        self.z,
+                    ),
+                    color,
+                },
+                Target::UI,
+            );
+        }
     }
 }
@@ -0,0 +1,165 @@
+use engine::{draw::Target, engine::GameEngine};
+use shared::protocol::{
+    client::{ClientPacket, Craft},
+    crafting::{CraftingKind, Recipe},
+};
+
+use crate::{
+    game::Context,
+    ui::{
+        button::{Button, ButtonBuilder},
+        colors::*,
+        fonts::{TAHOMA_BOLD_8_SHADOW_ID, WIZARD_16_ID},
+        label::Label,
+        list::List,
+        texture::Texture,
+        Alignment, Widget,
+    },
+};
+
+pub struct CraftingWindow {
+    pub position: (u16, u16), // center
+    visible: bool,
+
+    background: Texture,
+    title: Label,
+    list: List,
+    craft_button: Button,
+    close_button: Button,
+
+    recipes: Vec<Recipe>,
+}
+
+impl CraftingWindow {
+    pub fn initialize<E: GameEngine>(context: &mut Context<E>) -> Self {
+        let background = Texture::new(context.resources.textures.window, WHITE, (0, 0));
+        let title = Label::from("", WIZARD_16_ID, GRAY_6, context.engine);
+        let list = List::new(TAHOMA_BOLD_8_SHADOW_ID, (280, 130));
+
+        let craft_label = Label::from("Craft", TAHOMA_BOLD_8_SHADOW_ID, GRAY_6, context.engine);
+        let craft_button = ButtonBuilder::new()
+            .texture_id(context.resources.textures.button)
+            .label(craft_label)
+            .color(BLUE)
+            .alignment(Alignment::Left)
+            .z(1.)
+            .target(Target::UI)
+            .build();
+
+        let close_label = Label::from("Close", TAHOMA_BOLD_8_SHADOW_ID, GRAY_6, context.engine);
+        let close_button = ButtonBuilder::new()
+            .texture_id(context.resources.textures.button)
+            .label(close_label)
+            .color(GRAY_2)
+            .alignment(Alignment::Right)
+            .z(1.)
+            .target(Target::UI)
+            .build();
+
+        Self {
+            position: (0, 0),
+            visible: false,
+            background,
+            title,
+            list,
+            craft_button,
+            close_button,
+            recipes: vec![],
+        }
+    }
+
+    pub fn open<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        kind: CraftingKind,
+        recipes: Vec<Recipe>,
+        skill: u8,
+    ) {
+        let title = match kind {
+            CraftingKind::Smithy => "Smithy",
+            CraftingKind::Carpentry => "Carpentry",
+        };
+        self.title.set_text(title, context.engine);
+
+        let lines = recipes
+            .iter()
+            .map(|recipe| {
+                let ingredients = recipe
+                    .ingredients
+                    .iter()
+                    .map(|ingredient| {
+                        format!(
+                            "{} {}",
+                            ingredient.amount,
+                            context.resources.object_name(ingredient.item_id)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let color = if skill >= recipe.skill {
+                    GRAY_5
+                } else {
+                    GRAY_3
+                };
+                (format!("{} ({ingredients})", recipe.name), color)
+            })
+            .collect();
+        self.list.set_lines(context.engine, lines);
+        self.list.clear_selection();
+        self.recipes = recipes;
+        self.visible = true;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+}
+
+impl Widget for CraftingWindow {
+    fn update<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        let (x, y) = self.position;
+        self.background.update(context);
+        self.background.position = (x, y);
+        self.title.position = (x, y + 85);
+        self.list.position = (x - 140, y + 70);
+        self.craft_button.position = (x + 10, y - 95);
+        self.close_button.position = (x - 10, y - 95);
+
+        self.list.update(context);
+        self.craft_button.update(context);
+        self.close_button.update(context);
+
+        if self.craft_button.clicked() {
+            if let Some(recipe) = self
+                .list
+                .selected()
+                .and_then(|selected| self.recipes.get(selected))
+            {
+                context.connection.send(ClientPacket::Craft(Craft::Item {
+                    item_id: recipe.item_id,
+                    amount: 1,
+                }));
+            }
+        } else if self.close_button.clicked() {
+            self.close();
+        }
+    }
+
+    fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        self.background.draw(context);
+        self.title.draw(context);
+        self.list.draw(context);
+        self.craft_button.draw(context);
+        self.close_button.draw(context);
+    }
+}
@@ -1,6 +1,10 @@
-use engine::engine::GameEngine;
+use engine::{
+    draw::{image::DrawImage, Position, Target},
+    engine::GameEngine,
+};
+use shared::character::Item;
 
-use crate::game::Context;
+use crate::{argentum::Image, game::Context};
 
 use crate::ui::fonts::TAHOMA_REGULAR_8_ID;
 use crate::ui::{
@@ -16,7 +20,7 @@ pub struct Inventory {
 
 pub struct InventorySlot {
     background: Texture,
-    item: Option<Texture>,
+    item: Option<(Item, Image)>,
     amount: Label,
     equipped: Label,
     position: (u16, u16),
@@ -51,6 +55,17 @@ impl Inventory {
     pub fn hide(&mut self) {
         self.visible = false;
     }
+
+    pub fn set_slot<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        slot: usize,
+        item: Option<Item>,
+    ) {
+        if let Some(inventory_slot) = self.slots.get_mut(slot) {
+            inventory_slot.set_item(context, item);
+        }
+    }
 }
 
 impl Widget for Inventory {
@@ -111,6 +126,23 @@ impl InventorySlot {
     }
 }
 
+impl InventorySlot {
+    pub fn set_item<E: GameEngine>(&mut self, context: &mut Context<E>, item: Option<Item>) {
+        self.item = item.map(|item| {
+            let image = context
+                .resources
+                .object_image(item.item_id)
+                .cloned()
+                .unwrap_or_default();
+            (item, image)
+        });
+        if let Some((item, _)) = self.item.as_ref() {
+            self.amount
+                .set_text(&item.amount.to_string(), context.engine);
+        }
+    }
+}
+
 impl Widget for InventorySlot {
     fn update<E: engine::engine::GameEngine>(&mut self, context: &mut crate::game::Context<E>) {
         self.background.update(context);
@@ -141,8 +173,21 @@ impl Widget for InventorySlot {
             self.background.color = color;
         }
         self.background.draw(context);
-        if let Some(item) = self.item.as_mut() {
-            item.draw(context);
+        if let Some((_, image)) = self.item.as_ref() {
+            context.engine.draw_image(
+                DrawImage {
+                    position: Position::new(
+                        self.position.0 + SLOT_SIZE / 2 - image.width / 2,
+                        self.position.1 + SLOT_SIZE / 2 - image.height / 2,
+                        0.995,
+                    ),
+                    color: WHITE,
+                    source: [image.x, image.y, image.width, image.height],
+                    index: image.file,
+                    ..Default::default()
+                },
+                Target::UI,
+            );
             self.amount.draw(context);
         }
         if self.is_equipped {
@@ -4,7 +4,13 @@ use engine::{
     draw::{image::DrawImage, Target},
     engine::GameEngine,
 };
-use shared::protocol::client::{Action, ClientPacket};
+use shared::{
+    protocol::{
+        chat::Channel,
+        client::{Action, Chat, ClientPacket},
+    },
+    world::TileTrigger,
+};
 
 use crate::{
     game::Context,
@@ -19,16 +25,23 @@ use crate::{
     },
 };
 
-use self::{console::Console, inventory::Inventory, spellbook::Spellbook};
+use self::{
+    bank::BankWindow, commerce::CommerceWindow, console::Console, crafting::CraftingWindow,
+    inventory::Inventory, spellbook::Spellbook, trade::TradeWindow,
+};
 
 use super::{
     entity::Character, WorldScreen, SCREEN_HEIGHT, SCREEN_WIDTH, WORLD_RENDER_HEIGHT,
     WORLD_RENDER_WIDTH,
 };
 
+pub mod bank;
+pub mod commerce;
 pub mod console;
+pub mod crafting;
 pub mod inventory;
 pub mod spellbook;
+pub mod trade;
 
 pub struct HUD {
     pub x: u16,
@@ -58,9 +71,17 @@ pub struct HUD {
     // info
     pub fps: Label,
     pub ping: Label,
+    zone: Label,
+    zone_trigger: TileTrigger,
 
     pub console: Console,
     pub message_input: Option<InputField>,
+
+    // windows
+    pub crafting: CraftingWindow,
+    pub commerce: CommerceWindow,
+    pub bank: BankWindow,
+    pub trade: TradeWindow,
 }
 
 impl HUD {
@@ -117,6 +138,9 @@ impl HUD {
         );
         ping.alignment = Alignment::Right;
 
+        let mut zone = Label::from("", TAHOMA_BOLD_8_SHADOW_ID, GREEN, context.engine);
+        zone.alignment = Alignment::Left;
+
         let mut inventory_button = ButtonBuilder::new()
             .texture_id(context.resources.textures.inventory_button_disabled)
             .selected_texture(context.resources.textures.inventory_button)
@@ -126,7 +150,10 @@ impl HUD {
             .target(Target::UI)
             .build();
         inventory_button.select();
-        let inventory = Inventory::initialize(context);
+        let mut inventory = Inventory::initialize(context);
+        for (slot, item) in character.inventory.slots() {
+            inventory.set_slot(context, slot, Some(*item));
+        }
 
         let spells_button = ButtonBuilder::new()
             .texture_id(context.resources.textures.spells_button_disabled)
@@ -139,6 +166,10 @@ impl HUD {
         let spellbook = Spellbook::initialize(context);
 
         let console = Console::initialize(context.engine);
+        let crafting = CraftingWindow::initialize(context);
+        let commerce = CommerceWindow::initialize(context);
+        let bank = BankWindow::initialize(context);
+        let trade = TradeWindow::initialize(context);
 
         Self {
             x: 0,
@@ -158,6 +189,8 @@ impl HUD {
 
             fps,
             ping,
+            zone,
+            zone_trigger: TileTrigger::None,
 
             inventory_button,
             inventory,
@@ -167,6 +200,11 @@ impl HUD {
 
             console,
             message_input: None,
+
+            crafting,
+            commerce,
+            bank,
+            trade,
         }
     }
 
@@ -201,6 +239,20 @@ impl HUD {
         self.gold.set_text(&gold, context.engine);
     }
 
+    /// Shows a hint while the user stands on a tile where combat is not allowed
+    pub fn set_zone<E: GameEngine>(&mut self, context: &mut Context<E>, trigger: TileTrigger) {
+        if trigger == self.zone_trigger {
+            return;
+        }
+        self.zone_trigger = trigger;
+        let text = match trigger {
+            TileTrigger::SafeZone => "Safe zone",
+            TileTrigger::NoCombat => "No combat zone",
+            _ => "",
+        };
+        self.zone.set_text(text, context.engine);
+    }
+
     fn recalculate_positions(&mut self) {
         let right_panel_x_start = self.x + 12 + WORLD_RENDER_WIDTH;
         self.name.position = (self.x + SCREEN_WIDTH - 120, self.y + 35);
@@ -230,6 +282,8 @@ impl HUD {
             self.y + WORLD_RENDER_HEIGHT - 10,
         );
 
+        self.zone.position = (self.x + 20, self.y + WORLD_RENDER_HEIGHT - 10);
+
         self.console.position = (self.x + 20, self.y + 10 + WORLD_RENDER_HEIGHT);
 
         if let Some(input) = self.message_input.as_mut() {
@@ -237,6 +291,23 @@ impl HUD {
             let y = self.y + 20;
             input.position = (x, y);
         }
+
+        let windows_position = (
+            self.x + 14 + WORLD_RENDER_WIDTH / 2,
+            self.y + 10 + WORLD_RENDER_HEIGHT / 2,
+        );
+        self.crafting.position = windows_position;
+        self.commerce.position = windows_position;
+        self.bank.position = windows_position;
+        self.trade.position = windows_position;
+    }
+
+    /// Checks if any window is covering the world
+    pub fn window_open(&self) -> bool {
+        self.crafting.is_visible()
+            || self.commerce.is_visible()
+            || self.bank.is_visible()
+            || self.trade.is_visible()
     }
 }
 
@@ -275,6 +346,11 @@ impl UI for HUD {
         if let Some(input) = self.message_input.as_mut() {
             input.update(context);
         }
+
+        self.crafting.update(context);
+        self.commerce.update(context);
+        self.bank.update(context);
+        self.trade.update(context);
     }
 
     fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
@@ -307,6 +383,7 @@ impl UI for HUD {
 
         self.fps.draw(context);
         self.ping.draw(context);
+        self.zone.draw(context);
 
         self.console.draw(context);
 
@@ -314,6 +391,11 @@ impl UI for HUD {
             input.draw(context)
         }
 
+        self.crafting.draw(context);
+        self.commerce.draw(context);
+        self.bank.draw(context);
+        self.trade.draw(context);
+
         context.engine.draw_image(
             DrawImage {
                 position: Position::new(self.x + 565, self.y, 1.),
@@ -342,11 +424,7 @@ impl WorldScreen {
                 if let Some(input) = self.hud.message_input.as_mut() {
                     let message = input.text();
                     if !message.is_empty() {
-                        context
-                            .connection
-                            .send(ClientPacket::UserAction(Action::Talk {
-                                text: message.to_string(),
-                            }));
+                        context.connection.send(chat_packet(message));
                     }
                 }
                 self.hud.message_input = None;
@@ -368,3 +446,33 @@ impl WorldScreen {
         }
     }
 }
+
+/// Chat prefixes: `\name text` whispers, `#` party, `%` guild, `!` global and `$` trade,
+/// `/` sends a command, anything else is said out loud
+fn chat_packet(message: &str) -> ClientPacket {
+    if message.starts_with('/') {
+        return ClientPacket::Command(message.to_string());
+    }
+    if let Some(whisper) = message.strip_prefix('\\') {
+        let (to, text) = whisper.split_once(' ').unwrap_or((whisper, ""));
+        return ClientPacket::Chat(Chat::Whisper {
+            to: to.to_string(),
+            text: text.to_string(),
+        });
+    }
+    let channel = match message.chars().next() {
+        Some('#') => Channel::Party,
+        Some('%') => Channel::Guild,
+        Some('!') => Channel::Global,
+        Some('$') => Channel::Trade,
+        _ => {
+            return ClientPacket::UserAction(Action::Talk {
+                text: message.to_string(),
+            })
+        }
+    };
+    ClientPacket::Chat(Chat::Send {
+        channel,
+        text: message[1..].to_string(),
+    })
+}
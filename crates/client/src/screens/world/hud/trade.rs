@@ -0,0 +1,279 @@
+use engine::{draw::Target, engine::GameEngine};
+use shared::{
+    character::Inventory,
+    protocol::{
+        client::{ClientPacket, Trade},
+        trade::{TradeOffer, TradeStage},
+    },
+};
+
+use crate::{
+    game::Context,
+    ui::{
+        button::{Button, ButtonBuilder},
+        colors::*,
+        fonts::{TAHOMA_BOLD_8_SHADOW_ID, WIZARD_16_ID},
+        input_field::InputField,
+        label::Label,
+        list::List,
+        texture::Texture,
+        Alignment, Widget,
+    },
+};
+
+/// Own inventory on the left and the other user offer on the right,
+/// without a selection the amount is offered as gold
+pub struct TradeWindow {
+    pub position: (u16, u16), // center
+    visible: bool,
+
+    background: Texture,
+    title: Label,
+    status: Label,
+    inventory: List,
+    other: List,
+    amount: InputField,
+    offer_button: Button,
+    ready_button: Button,
+    confirm_button: Button,
+    cancel_button: Button,
+
+    items: Inventory,
+    inventory_slots: Vec<usize>,
+    own: TradeOffer,
+    own_stage: TradeStage,
+}
+
+impl TradeWindow {
+    pub fn initialize<E: GameEngine>(context: &mut Context<E>) -> Self {
+        let background = Texture::new(context.resources.textures.window, WHITE, (0, 0));
+        let title = Label::from("", WIZARD_16_ID, GRAY_6, context.engine);
+        let status = Label::from("", TAHOMA_BOLD_8_SHADOW_ID, GRAY_4, context.engine);
+        let inventory = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 90));
+        let other = List::new(TAHOMA_BOLD_8_SHADOW_ID, (140, 90));
+        let amount = InputField::new(
+            GRAY_6,
+            GRAY_1,
+            (0, 0),
+            (200, 30),
+            TAHOMA_BOLD_8_SHADOW_ID,
+            context.resources.textures.input,
+            context,
+        );
+
+        let button = |context: &mut Context<E>, text: &str, color| {
+            let label = Label::from(text, TAHOMA_BOLD_8_SHADOW_ID, GRAY_6, context.engine);
+            ButtonBuilder::new()
+                .texture_id(context.resources.textures.button)
+                .label(label)
+                .color(color)
+                .alignment(Alignment::Center)
+                .z(1.)
+                .target(Target::UI)
+                .build()
+        };
+        let offer_button = button(context, "Offer", BLUE);
+        let ready_button = button(context, "Ready", GREEN);
+        let confirm_button = button(context, "Confirm", GREEN);
+        let cancel_button = button(context, "Cancel", GRAY_2);
+
+        Self {
+            position: (0, 0),
+            visible: false,
+            background,
+            title,
+            status,
+            inventory,
+            other,
+            amount,
+            offer_button,
+            ready_button,
+            confirm_button,
+            cancel_button,
+            items: Inventory::default(),
+            inventory_slots: vec![],
+            own: TradeOffer::default(),
+            own_stage: TradeStage::Offering,
+        }
+    }
+
+    pub fn open<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        name: &str,
+        inventory: &Inventory,
+    ) {
+        self.title
+            .set_text(&format!("Trade with {name}"), context.engine);
+        self.inventory.clear_selection();
+        self.other.clear_selection();
+        self.own = TradeOffer::default();
+        self.own_stage = TradeStage::Offering;
+        self.set_offers(
+            context,
+            TradeOffer::default(),
+            TradeOffer::default(),
+            TradeStage::Offering,
+            TradeStage::Offering,
+        );
+        self.set_inventory(context, inventory);
+        self.visible = true;
+    }
+
+    pub fn set_inventory<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        inventory: &Inventory,
+    ) {
+        self.items = inventory.clone();
+
+        let mut lines = vec![];
+        self.inventory_slots.clear();
+        for (slot, item) in self.items.slots() {
+            let name = context.resources.object_name(item.item_id);
+            let offered = self
+                .own
+                .items
+                .iter()
+                .find(|offered| offered.slot as usize == slot);
+            let line = match offered {
+                Some(offered) => (
+                    format!("{name} x{} ({} offered)", item.amount, offered.amount),
+                    YELLOW,
+                ),
+                None => (format!("{name} x{}", item.amount), GRAY_5),
+            };
+            lines.push(line);
+            self.inventory_slots.push(slot);
+        }
+        self.inventory.set_lines(context.engine, lines);
+    }
+
+    pub fn set_offers<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        own: TradeOffer,
+        other: TradeOffer,
+        own_stage: TradeStage,
+        other_stage: TradeStage,
+    ) {
+        let mut lines = vec![];
+        if other.gold > 0 {
+            lines.push((format!("{} gold", other.gold), YELLOW));
+        }
+        for item in other.items.iter() {
+            let name = context.resources.object_name(item.item_id);
+            lines.push((format!("{name} x{}", item.amount), GRAY_5));
+        }
+        self.other.set_lines(context.engine, lines);
+
+        let other_status = match other_stage {
+            TradeStage::Offering => "offering",
+            TradeStage::Ready => "ready",
+            TradeStage::Confirmed => "confirmed",
+        };
+        self.status.set_text(
+            &format!("You offer {} gold - They are {other_status}", own.gold),
+            context.engine,
+        );
+
+        self.own = own;
+        self.own_stage = own_stage;
+        let items = std::mem::take(&mut self.items);
+        self.set_inventory(context, &items);
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn amount(&self) -> u64 {
+        self.amount.text().trim().parse().unwrap_or(1)
+    }
+}
+
+impl Widget for TradeWindow {
+    fn update<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        let (x, y) = self.position;
+        self.background.update(context);
+        self.background.position = (x, y);
+        self.title.position = (x, y + 85);
+        self.status.position = (x, y + 68);
+        self.inventory.position = (x - 148, y + 60);
+        self.other.position = (x + 8, y + 60);
+        self.amount.position = (x, y - 68);
+        self.offer_button.position = (x - 95, y - 85);
+        self.ready_button.position = (x, y - 85);
+        self.confirm_button.position = (x, y - 85);
+        self.cancel_button.position = (x + 95, y - 85);
+
+        self.inventory.update(context);
+        self.other.update(context);
+        self.amount.update(context);
+        self.offer_button.update(context);
+        self.cancel_button.update(context);
+        let ready = match self.own_stage {
+            TradeStage::Offering => {
+                self.ready_button.update(context);
+                self.ready_button.clicked()
+            }
+            _ => false,
+        };
+        let confirm = match self.own_stage {
+            TradeStage::Ready => {
+                self.confirm_button.update(context);
+                self.confirm_button.clicked()
+            }
+            _ => false,
+        };
+
+        if self.offer_button.clicked() {
+            let amount = self.amount();
+            let packet = match self
+                .inventory
+                .selected()
+                .and_then(|selected| self.inventory_slots.get(selected))
+            {
+                Some(slot) => Trade::Offer {
+                    slot: *slot as u8,
+                    amount: std::cmp::min(amount, u32::MAX as u64) as u32,
+                },
+                None => Trade::Gold { amount },
+            };
+            context.connection.send(ClientPacket::Trade(packet));
+        } else if ready {
+            context.connection.send(ClientPacket::Trade(Trade::Ready));
+        } else if confirm {
+            context.connection.send(ClientPacket::Trade(Trade::Confirm));
+        } else if self.cancel_button.clicked() {
+            context.connection.send(ClientPacket::Trade(Trade::Cancel));
+            self.close();
+        }
+    }
+
+    fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !self.visible {
+            return;
+        }
+        self.background.draw(context);
+        self.title.draw(context);
+        self.status.draw(context);
+        self.inventory.draw(context);
+        self.other.draw(context);
+        self.amount.draw(context);
+        self.offer_button.draw(context);
+        match self.own_stage {
+            TradeStage::Offering => self.ready_button.draw(context),
+            TradeStage::Ready => self.confirm_button.draw(context),
+            TradeStage::Confirmed => {}
+        }
+        self.cancel_button.draw(context);
+    }
+}
@@ -1,7 +1,13 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use engine::{engine::GameEngine, input::keyboard::KeyCode};
-use shared::world::Direction;
+use engine::{camera::Zoom, engine::GameEngine, input::keyboard::KeyCode};
+use shared::{
+    protocol::client::{Action, ClientPacket},
+    world::{
+        pathfinding::{find_path, Walker},
+        Direction, WorldPosition,
+    },
+};
 
 use crate::{
     game::Context,
@@ -13,15 +19,19 @@ use crate::{
 
 use super::{
     entity::{Character, Entity},
-    WorldScreen,
+    WorldScreen, TILE_SIZE_F,
 };
 
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(300);
+
 impl WorldScreen {
     pub fn process_input<E: GameEngine>(&mut self, context: &mut Context<E>) {
         let mut push = |direction| {
             if !self.input.contains(&direction) {
                 self.input.push_front(direction);
             }
+            // arrow keys take over click to walk
+            self.walk_target = None;
         };
 
         if context.engine.key_pressed(KeyCode::ArrowUp) {
@@ -49,6 +59,10 @@ impl WorldScreen {
             self.input.retain(|dir| dir != &Direction::West);
         }
 
+        if context.engine.mouse_clicked() && !self.hud.window_open() {
+            self.process_click(context);
+        }
+
         // TODO: remove
         if context.engine.key_pressed(KeyCode::KeyH) {
             if let Some(Entity::Character(character)) = self.entities.get_mut(&self.entity_id) {
@@ -80,4 +94,76 @@ impl WorldScreen {
             }
         }
     }
+
+    fn process_click<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        let Some(position) = self.clicked_tile(context.engine) else {
+            return;
+        };
+
+        let now = Instant::now();
+        match self.last_click {
+            Some((time, last)) if last == position && now - time < DOUBLE_CLICK_TIME => {
+                context
+                    .connection
+                    .send(ClientPacket::UserAction(Action::DoubleClick { position }));
+                self.last_click = None;
+            }
+            _ => {
+                context
+                    .connection
+                    .send(ClientPacket::UserAction(Action::LeftClick { position }));
+                self.walk_to(context, position);
+                self.last_click = Some((now, position));
+            }
+        }
+    }
+
+    /// Starts click to walk when the tile can be reached, clicks on entities only select them
+    fn walk_to<E: GameEngine>(&mut self, context: &mut Context<E>, position: WorldPosition) {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return;
+        };
+        let map = context.maps.get(&position.map);
+        let reachable = map
+            .get_tile(position.x, position.y)
+            .is_some_and(|tile| tile.user.is_none())
+            && find_path(map, &character.position, &position, Walker::User).is_some();
+        if reachable && position != character.position {
+            self.walk_target = Some(position);
+        }
+    }
+
+    /// Tile under the mouse cursor, if it's inside the world viewport
+    fn clicked_tile<E: GameEngine>(&self, engine: &E) -> Option<WorldPosition> {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return None;
+        };
+        let zoom = match engine.get_camera_zoom() {
+            Zoom::None => 1.,
+            Zoom::Double => 2.,
+        };
+
+        let mouse = engine.mouse_position();
+        let viewport = engine.get_world_camera_viewport();
+        // viewports are top-left based while the mouse is bottom-left based
+        let bottom = engine.get_window_size().height as f32 - viewport.y - viewport.height;
+        let x = mouse.x - viewport.x;
+        let y = mouse.y - bottom;
+        if x < 0. || y < 0. || x >= viewport.width || y >= viewport.height {
+            return None;
+        }
+
+        let camera = engine.get_world_camera_position();
+        let world_x = camera.x - viewport.width / zoom / 2. + x / zoom;
+        let world_y = camera.y - viewport.height / zoom / 2. + y / zoom;
+        if world_x < 0. || world_y < 0. {
+            return None;
+        }
+
+        Some(WorldPosition {
+            map: character.position.map,
+            x: (world_x / TILE_SIZE_F) as u16,
+            y: (world_y / TILE_SIZE_F) as u16,
+        })
+    }
 }
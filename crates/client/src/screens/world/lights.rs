@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use engine::{
+    draw::{light::DrawLight, Color},
+    engine::GameEngine,
+};
+use itertools::iproduct;
+use shared::{
+    argentum::object::ObjectData,
+    world::{Light, MapInfo},
+};
+
+use crate::{
+    game::Context,
+    screens::world::{
+        entity::Entity, get_range, WorldScreen, HORIZONTAL_EXTRA_TILES, TILE_SIZE, TILE_SIZE_F,
+        VERTICAL_EXTRA_TILES,
+    },
+};
+
+/// Bonfires on the ground light up like torches
+const BONFIRE_COLOR: Color = [255, 150, 70, 255];
+const BONFIRE_RADIUS: f32 = TILE_SIZE_F * 4.;
+
+/// Dungeons without a base light are dark, only torches and spells light them up
+const DUNGEON_LIGHT: Color = [45, 45, 60, 255];
+
+const LEVEL_UP_COLOR: Color = [255, 220, 130, 255];
+const LEVEL_UP_RADIUS: f32 = TILE_SIZE_F * 3.;
+const LEVEL_UP_DURATION: Duration = Duration::from_millis(1200);
+
+/// Light that fades out, for spells and other effects
+struct Flash {
+    light: DrawLight,
+    start: Instant,
+    duration: Duration,
+}
+
+pub struct Lights {
+    flashes: Vec<Flash>,
+    start: Instant,
+}
+
+impl Default for Lights {
+    fn default() -> Self {
+        Self {
+            flashes: vec![],
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Lights {
+    pub fn flash(&mut self, light: DrawLight, duration: Duration) {
+        self.flashes.push(Flash {
+            light,
+            start: Instant::now(),
+            duration,
+        });
+    }
+}
+
+/// Light of the map where no light reaches, maps without a base light are lit by the sun
+pub fn ambient_light(info: Option<&MapInfo>, daylight: Color) -> Color {
+    let dungeon = info.is_some_and(|info| info.zone.eq_ignore_ascii_case("dungeon"));
+    match info.map(|info| info.base_light) {
+        None | Some(0) if dungeon => DUNGEON_LIGHT,
+        None | Some(0) => daylight,
+        // packed like the colors of the tile lights, blue in the lowest byte
+        Some(base_light) => {
+            let [blue, green, red, _] = base_light.to_le_bytes();
+            [red, green, blue, 255]
+        }
+    }
+}
+
+fn tile_light(light: &Light, (x, y): (f32, f32)) -> DrawLight {
+    DrawLight {
+        position: [x, y],
+        radius: light.range as f32 * TILE_SIZE_F,
+        color: [light.color.r, light.color.g, light.color.b, 255],
+    }
+}
+
+impl WorldScreen {
+    pub fn draw_lights<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return;
+        };
+        let position = character.position;
+        let info = context.resources.map_infos.get(&position.map);
+        let daylight = self.weather.daylight();
+        context
+            .engine
+            .set_ambient_light(ambient_light(info, daylight));
+
+        let map = context.maps.get(&position.map);
+        // lights of the tiles around the view reach into it
+        let (x_start, x_end, y_start, y_end) =
+            get_range(&position, HORIZONTAL_EXTRA_TILES, VERTICAL_EXTRA_TILES);
+        let elapsed = self.lights.start.elapsed().as_secs_f32();
+        for (y, x) in iproduct!(y_start..y_end, x_start..x_end) {
+            let tile = &map.tiles[x][y];
+            let center = (
+                (x as u16 * TILE_SIZE + TILE_SIZE) as f32 + TILE_SIZE_F / 2.,
+                (y as u16 * TILE_SIZE + TILE_SIZE) as f32 + TILE_SIZE_F / 2.,
+            );
+
+            if let Some(light) = &tile.light {
+                context.engine.draw_light(tile_light(light, center));
+            }
+
+            let bonfire = tile
+                .obj
+                .as_ref()
+                .and_then(|obj| context.resources.objects.get(&(obj.index as usize)))
+                .is_some_and(|object| matches!(object.data, ObjectData::Bonfire));
+            if bonfire {
+                // every bonfire flickers on its own
+                let flicker = (elapsed * 9. + (x * 7 + y) as f32).sin() * 0.08;
+                context.engine.draw_light(DrawLight {
+                    position: [center.0, center.1],
+                    radius: BONFIRE_RADIUS * (1. + flicker),
+                    color: BONFIRE_COLOR,
+                });
+            }
+        }
+
+        self.lights
+            .flashes
+            .retain(|flash| flash.start.elapsed() < flash.duration);
+        for flash in &self.lights.flashes {
+            let left = 1. - flash.start.elapsed().as_secs_f32() / flash.duration.as_secs_f32();
+            let mut light = flash.light;
+            light.color[3] = (light.color[3] as f32 * left) as u8;
+            context.engine.draw_light(light);
+        }
+    }
+
+    /// The own character glows when it levels up
+    pub fn flash_level_up(&mut self) {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return;
+        };
+        let (x, y) = character.render_position;
+        self.lights.flash(
+            DrawLight {
+                position: [x + TILE_SIZE_F / 2., y + TILE_SIZE_F],
+                radius: LEVEL_UP_RADIUS,
+                color: LEVEL_UP_COLOR,
+            },
+            LEVEL_UP_DURATION,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::world::MapInfo;
+
+    use super::{ambient_light, DUNGEON_LIGHT};
+
+    const DAYLIGHT: [u8; 4] = [200, 200, 180, 255];
+
+    fn map(zone: &str, base_light: u32) -> MapInfo {
+        MapInfo {
+            zone: zone.to_string(),
+            base_light,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn maps_without_base_light_follow_the_sun() {
+        assert_eq!(ambient_light(None, DAYLIGHT), DAYLIGHT);
+        assert_eq!(ambient_light(Some(&map("CAMPO", 0)), DAYLIGHT), DAYLIGHT);
+    }
+
+    #[test]
+    fn dungeons_without_base_light_are_dark() {
+        assert_eq!(
+            ambient_light(Some(&map("DUNGEON", 0)), DAYLIGHT),
+            DUNGEON_LIGHT
+        );
+    }
+
+    #[test]
+    fn base_light_is_unpacked_like_tile_lights() {
+        let ambient = ambient_light(Some(&map("dungeon", 0x00102030)), DAYLIGHT);
+        assert_eq!(ambient, [0x10, 0x20, 0x30, 255]);
+    }
+}
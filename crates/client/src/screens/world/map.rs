@@ -3,12 +3,13 @@ use engine::{
     engine::{GameEngine, TextureID},
 };
 use itertools::iproduct;
+use nohash_hasher::IntSet;
 
 use crate::{
     game::Context,
     screens::world::{
-        depth::Z, entity::Entity, get_range, WorldScreen, HORIZONTAL_TILES, TILE_SIZE,
-        VERTICAL_TILES, WHITE, WORLD_RENDER_HEIGHT, WORLD_RENDER_WIDTH,
+        depth::Z, entity::Entity, get_range, WorldScreen, HORIZONTAL_EXTRA_TILES, TILE_SIZE,
+        VERTICAL_EXTRA_TILES, VERTICAL_TILES, WHITE, WORLD_RENDER_HEIGHT, WORLD_RENDER_WIDTH,
     },
     texture::TextureState,
 };
@@ -39,33 +40,43 @@ impl WorldMap {
 
 impl WorldScreen {
     pub fn draw_world_2<E: GameEngine>(&mut self, context: &mut Context<E>) {
-        if matches!(self.map.ground.1, TextureState::Dirty) {
-            let target = Target::Texture {
-                id: self.map.ground.0,
-            };
-            self.draw_map_layer_to(context, 0, target);
-            self.map.ground.1 = TextureState::JustDraw;
-        }
-
-        if matches!(self.map.roof.1, TextureState::Dirty) {
-            let target = Target::Texture {
-                id: self.map.roof.0,
-            };
-            self.draw_map_layer_to(context, 3, target);
-            self.map.roof.1 = TextureState::JustDraw;
-        }
+        self.map.ground.1 = self.bake_layer(context, 0, self.map.ground);
+        self.map.roof.1 = self.bake_layer(context, 3, self.map.roof);
 
-        if matches!(self.map.ground.1, TextureState::JustDraw) {
-            self.map.ground.1 = TextureState::Ready;
-        } else {
+        // until a layer is baked it's drawn tile by tile
+        if matches!(self.map.ground.1, TextureState::Ready) {
             self.draw_world_prerendered_layer_character_vision(context, self.map.ground.0, 0.);
+        } else {
+            self.draw_world_character_vision(context, 0);
         }
         self.draw_world_character_vision(context, 1);
         self.draw_world_character_vision(context, 2);
-        if matches!(self.map.roof.1, TextureState::JustDraw) {
-            self.map.roof.1 = TextureState::Ready;
-        } else {
+        if matches!(self.map.roof.1, TextureState::Ready) {
             self.draw_world_prerendered_layer_character_vision(context, self.map.roof.0, 0.99);
+        } else {
+            self.draw_world_character_vision(context, 3);
+        }
+    }
+
+    /// Layers are baked once their textures are loaded, so placeholders don't end up baked
+    fn bake_layer<E: GameEngine>(
+        &mut self,
+        context: &mut Context<E>,
+        layer: usize,
+        (texture_id, state): (TextureID, TextureState),
+    ) -> TextureState {
+        match state {
+            TextureState::Dirty if context.engine.textures_loading() == 0 => {
+                self.draw_map_layer_to(context, layer, Target::Texture { id: texture_id });
+                // evicted textures started loading again, the layer is baked once they are back
+                if context.engine.textures_loading() > 0 {
+                    TextureState::Dirty
+                } else {
+                    TextureState::JustDraw
+                }
+            }
+            TextureState::JustDraw => TextureState::Ready,
+            state => state,
         }
     }
 
@@ -97,6 +108,7 @@ impl WorldScreen {
                         color: WHITE,
                         source: [image.x, image.y, image.width, image.height],
                         index: image.file,
+                        ..Default::default()
                     },
                     target,
                 );
@@ -129,6 +141,7 @@ impl WorldScreen {
                     WORLD_RENDER_HEIGHT + TILE_SIZE,
                 ],
                 index: texture_id,
+                ..Default::default()
             },
             Target::World,
         );
@@ -144,9 +157,6 @@ impl WorldScreen {
         };
         let position = &character.position;
         let map = context.maps.get(&position.map);
-        const EXTRA_TILES: u16 = 5;
-        const HORIZONTAL_EXTRA_TILES: u16 = ((HORIZONTAL_TILES + 1) / 2) + EXTRA_TILES;
-        const VERTICAL_EXTRA_TILES: u16 = (VERTICAL_TILES / 2) + EXTRA_TILES;
         let (x_start, x_end, y_start, y_end) =
             get_range(position, HORIZONTAL_EXTRA_TILES, VERTICAL_EXTRA_TILES);
 
@@ -168,6 +178,7 @@ impl WorldScreen {
                         color,
                         source: [image.x, image.y, image.width, image.height],
                         index: image.file,
+                        ..Default::default()
                     },
                     Target::World,
                 );
@@ -188,3 +199,22 @@ impl WorldScreen {
         self.map.roof.1 = TextureState::Dirty;
     }
 }
+
+/// Loads the textures of a map in the background, ahead of its tiles being drawn
+pub fn prefetch_textures<E: GameEngine>(context: &mut Context<E>, map_number: u16, priority: u8) {
+    let images = &context.resources.images;
+    let textures = context
+        .maps
+        .get(&map_number)
+        .tiles
+        .iter()
+        .flatten()
+        .flat_map(|tile| tile.graphics)
+        .filter(|graphic| *graphic != 0)
+        .filter_map(|graphic| images.get(graphic))
+        .map(|image| image.file)
+        .collect::<IntSet<TextureID>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    context.engine.prefetch_textures(&textures, priority);
+}
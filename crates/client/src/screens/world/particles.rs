@@ -0,0 +1,463 @@
+use std::time::Duration;
+
+use engine::{
+    draw::{
+        image::{Blend, DrawImage},
+        Color, Position, Target,
+    },
+    engine::GameEngine,
+};
+use itertools::iproduct;
+use nohash_hasher::IntMap;
+use rand::Rng;
+use shared::world::WorldPosition;
+
+use crate::{
+    argentum::particles::Emitter,
+    game::Context,
+    resources::Resources,
+    screens::world::{
+        depth::Z, entity::Entity, get_range, WorldScreen, HORIZONTAL_EXTRA_TILES, TILE_SIZE_F,
+        VERTICAL_EXTRA_TILES,
+    },
+};
+
+/// Particles go over the characters of their tile and under the ones of the next row
+const DEPTH_BIAS: f32 = 0.0009;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Tile(u32),
+    Fx(u32),
+}
+
+struct EmitterState {
+    kind: Kind,
+    /// Center of the tile, in world pixels
+    position: (f32, f32),
+    z: f32,
+    /// Time left spawning particles, tile emitters spawn until they are stopped
+    remaining: Option<Duration>,
+    /// Particles owed by the spawn rate, spawned once they add up to one
+    pending: f32,
+    alive: usize,
+}
+
+impl EmitterState {
+    fn spawning(&self) -> bool {
+        self.remaining.is_none_or(|remaining| !remaining.is_zero())
+    }
+}
+
+struct Particle {
+    emitter: usize,
+    position: (f32, f32),
+    velocity: (f32, f32),
+    age: f32,
+    lifetime: f32,
+}
+
+/// Emitters of the visible tiles and of the fxs, particles and emitters are kept in pools that
+/// are reused, so the allocations stay flat once the busiest scene was shown
+#[derive(Default)]
+pub struct Particles {
+    particles: Vec<Particle>,
+    emitters: Vec<Option<EmitterState>>,
+    free_emitters: Vec<usize>,
+
+    map: u16,
+    /// Emitters of the tiles, by tile, with the frame the tile was last seen
+    tiles: IntMap<u32, (usize, u64)>,
+    frame: u64,
+}
+
+impl Particles {
+    /// Plays the emitter of a fx on a tile, `loops` times in a row
+    pub fn spawn_fx(
+        &mut self,
+        resources: &Resources,
+        position: WorldPosition,
+        fx: u16,
+        loops: u16,
+    ) {
+        if position.map != self.map {
+            return;
+        }
+        let Some(emitter) = resources.emitters.fxs.get(&(fx as u32)) else {
+            return;
+        };
+        let (x, y) = (position.x as usize - 1, position.y as usize - 1);
+        self.add_emitter(
+            Kind::Fx(fx as u32),
+            x,
+            y,
+            Some(emitter.duration * loops.max(1) as u32),
+        );
+    }
+
+    fn add_emitter(
+        &mut self,
+        kind: Kind,
+        x: usize,
+        y: usize,
+        remaining: Option<Duration>,
+    ) -> usize {
+        let state = EmitterState {
+            kind,
+            position: tile_center(x, y),
+            z: Z[2][x][y] + DEPTH_BIAS,
+            remaining,
+            pending: 0.,
+            alive: 0,
+        };
+        match self.free_emitters.pop() {
+            Some(index) => {
+                self.emitters[index] = Some(state);
+                index
+            }
+            None => {
+                self.emitters.push(Some(state));
+                self.emitters.len() - 1
+            }
+        }
+    }
+
+    /// Tile emitters stop spawning, their particles live until their lifetime ends
+    fn stop_tiles(&mut self) {
+        for (index, _) in self.tiles.drain().map(|(_, entry)| entry) {
+            if let Some(emitter) = &mut self.emitters[index] {
+                emitter.remaining = Some(Duration::ZERO);
+            }
+        }
+    }
+
+    fn update(&mut self, resources: &Resources, delta: Duration) {
+        let seconds = delta.as_secs_f32();
+        let rng = &mut rand::thread_rng();
+
+        for (index, slot) in self.emitters.iter_mut().enumerate() {
+            let Some(state) = slot else {
+                continue;
+            };
+            let Some(emitter) = definition(resources, state.kind) else {
+                state.remaining = Some(Duration::ZERO);
+                continue;
+            };
+            if state.spawning() {
+                state.pending += emitter.rate * seconds;
+                while state.pending >= 1. && state.alive < emitter.max_particles {
+                    state.pending -= 1.;
+                    state.alive += 1;
+                    self.particles
+                        .push(spawn(rng, emitter, index, state.position));
+                }
+                // the rate doesn't pile up while the emitter is full
+                state.pending = state.pending.min(1.);
+                state.remaining = state
+                    .remaining
+                    .map(|remaining| remaining.saturating_sub(delta));
+            }
+        }
+
+        let mut index = 0;
+        while index < self.particles.len() {
+            let particle = &mut self.particles[index];
+            particle.age += seconds;
+            if particle.age >= particle.lifetime {
+                if let Some(state) = &mut self.emitters[particle.emitter] {
+                    state.alive -= 1;
+                }
+                self.particles.swap_remove(index);
+                continue;
+            }
+            let gravity = self.emitters[particle.emitter]
+                .as_ref()
+                .and_then(|state| definition(resources, state.kind))
+                .map_or(0., |emitter| emitter.gravity);
+            particle.velocity.1 -= gravity * seconds;
+            particle.position.0 += particle.velocity.0 * seconds;
+            particle.position.1 += particle.velocity.1 * seconds;
+            index += 1;
+        }
+
+        for (index, slot) in self.emitters.iter_mut().enumerate() {
+            if slot
+                .as_ref()
+                .is_some_and(|state| !state.spawning() && state.alive == 0)
+            {
+                *slot = None;
+                self.free_emitters.push(index);
+            }
+        }
+    }
+
+    fn draw<E: GameEngine>(&self, engine: &mut E, resources: &Resources) {
+        for particle in &self.particles {
+            let Some(state) = &self.emitters[particle.emitter] else {
+                continue;
+            };
+            let Some(emitter) = definition(resources, state.kind) else {
+                continue;
+            };
+            let life = particle.age / particle.lifetime;
+            let Some(image) = frame(emitter, life).and_then(|id| resources.images.get(id)) else {
+                continue;
+            };
+
+            let x = particle.position.0 - image.width as f32 / 2.;
+            let y = particle.position.1 - image.height as f32 / 2.;
+            engine.draw_image(
+                DrawImage {
+                    position: Position::new(x.max(0.) as u16, y.max(0.) as u16, state.z),
+                    color: color(&emitter.colors, life),
+                    source: [image.x, image.y, image.width, image.height],
+                    index: image.file,
+                    blend: if emitter.additive {
+                        Blend::Additive
+                    } else {
+                        Blend::Alpha
+                    },
+                    ..Default::default()
+                },
+                Target::World,
+            );
+        }
+    }
+}
+
+fn definition(resources: &Resources, kind: Kind) -> Option<&Emitter> {
+    match kind {
+        Kind::Tile(id) => resources.emitters.tiles.get(&id),
+        Kind::Fx(id) => resources.emitters.fxs.get(&id),
+    }
+}
+
+fn spawn(rng: &mut impl Rng, emitter: &Emitter, index: usize, origin: (f32, f32)) -> Particle {
+    let mut between = |(min, max): (f32, f32)| min + (max - min) * rng.gen::<f32>();
+    let [first, second] = emitter.velocity;
+    let spread = (emitter.spread.0 / 2., emitter.spread.1 / 2.);
+    Particle {
+        emitter: index,
+        position: (
+            origin.0 + emitter.offset.0 + between((-spread.0, spread.0)),
+            origin.1 + emitter.offset.1 + between((-spread.1, spread.1)),
+        ),
+        velocity: (between((first.0, second.0)), between((first.1, second.1))),
+        age: 0.,
+        lifetime: emitter.lifetime.as_secs_f32().max(f32::EPSILON),
+    }
+}
+
+/// Image of `images.ron` shown at a point of the particle life, from 0 to 1
+fn frame(emitter: &Emitter, life: f32) -> Option<usize> {
+    let last = emitter.frames.len().checked_sub(1)?;
+    let index = ((life * emitter.frames.len() as f32) as usize).min(last);
+    Some(emitter.frames[index] as usize)
+}
+
+/// Tint at a point of the particle life, interpolated between the closest colors
+fn color(colors: &[Color], life: f32) -> Color {
+    let Some(last) = colors.len().checked_sub(1) else {
+        return [255, 255, 255, 255];
+    };
+    let position = life.clamp(0., 1.) * last as f32;
+    let index = (position as usize).min(last);
+    let next = (index + 1).min(last);
+    let amount = position - index as f32;
+    std::array::from_fn(|channel| {
+        let from = colors[index][channel] as f32;
+        let to = colors[next][channel] as f32;
+        (from + (to - from) * amount).round() as u8
+    })
+}
+
+/// Center of a tile of the map arrays, in world pixels
+fn tile_center(x: usize, y: usize) -> (f32, f32) {
+    (
+        (x + 1) as f32 * TILE_SIZE_F + TILE_SIZE_F / 2.,
+        (y + 1) as f32 * TILE_SIZE_F + TILE_SIZE_F / 2.,
+    )
+}
+
+impl WorldScreen {
+    /// Starts the emitters of the tiles that came into view and stops the ones that left it
+    pub fn update_particles<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return;
+        };
+        let position = character.position;
+        let particles = &mut self.particles;
+        if particles.map != position.map {
+            particles.stop_tiles();
+            particles.map = position.map;
+        }
+
+        particles.frame += 1;
+        let frame = particles.frame;
+        let map = context.maps.get(&position.map);
+        let (x_start, x_end, y_start, y_end) =
+            get_range(&position, HORIZONTAL_EXTRA_TILES, VERTICAL_EXTRA_TILES);
+        for (y, x) in iproduct!(y_start..y_end, x_start..x_end) {
+            let Some(particle) = map.tiles[x][y].particle else {
+                continue;
+            };
+            let key = ((x as u32) << 16) | y as u32;
+            if let Some((_, seen)) = particles.tiles.get_mut(&key) {
+                *seen = frame;
+            } else if context.resources.emitters.tiles.contains_key(&particle) {
+                let index = particles.add_emitter(Kind::Tile(particle), x, y, None);
+                particles.tiles.insert(key, (index, frame));
+            }
+        }
+        let emitters = &mut particles.emitters;
+        particles.tiles.retain(|_, (index, seen)| {
+            if *seen != frame {
+                if let Some(emitter) = &mut emitters[*index] {
+                    emitter.remaining = Some(Duration::ZERO);
+                }
+            }
+            *seen == frame
+        });
+
+        particles.update(context.resources, context.engine.get_delta());
+    }
+
+    pub fn draw_particles<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        self.particles.draw(context.engine, context.resources);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use shared::world::WorldPosition;
+
+    use super::{color, frame, Particles};
+    use crate::{
+        argentum::particles::{Emitter, Emitters},
+        resources::Resources,
+    };
+
+    fn emitter(frames: Vec<u32>) -> Emitter {
+        Emitter {
+            rate: 1000.,
+            max_particles: 4,
+            lifetime: Duration::from_millis(100),
+            duration: Duration::from_millis(50),
+            offset: (0., 0.),
+            spread: (0., 0.),
+            velocity: [(0., 10.), (0., 10.)],
+            gravity: 0.,
+            colors: vec![],
+            frames,
+            additive: false,
+        }
+    }
+
+    #[test]
+    fn frames_follow_the_particle_life() {
+        let emitter = emitter(vec![10, 20, 30]);
+        assert_eq!(frame(&emitter, 0.), Some(10));
+        assert_eq!(frame(&emitter, 0.5), Some(20));
+        assert_eq!(frame(&emitter, 0.99), Some(30));
+        assert_eq!(frame(&emitter, 1.), Some(30));
+        assert_eq!(frame(&super::tests::emitter(vec![]), 0.5), None);
+    }
+
+    #[test]
+    fn colors_interpolate_between_the_closest_ones() {
+        let colors = [[0, 0, 0, 0], [200, 100, 50, 255], [200, 100, 50, 55]];
+        assert_eq!(color(&colors, 0.), [0, 0, 0, 0]);
+        assert_eq!(color(&colors, 0.25), [100, 50, 25, 128]);
+        assert_eq!(color(&colors, 0.5), [200, 100, 50, 255]);
+        assert_eq!(color(&colors, 0.75), [200, 100, 50, 155]);
+        assert_eq!(color(&colors, 2.), [200, 100, 50, 55]);
+        assert_eq!(color(&[[10, 20, 30, 40]], 0.7), [10, 20, 30, 40]);
+        assert_eq!(color(&[], 0.7), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn finished_fxs_are_reused() {
+        let resources = Resources {
+            emitters: Emitters {
+                fxs: HashMap::from([(1, emitter(vec![1]))]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let position = WorldPosition {
+            map: 0,
+            x: 50,
+            y: 50,
+        };
+        let mut particles = Particles::default();
+
+        particles.spawn_fx(&resources, position, 1, 1);
+        particles.update(&resources, Duration::from_millis(50));
+        assert_eq!(particles.particles.len(), 4);
+        let capacity = particles.particles.capacity();
+
+        particles.update(&resources, Duration::from_millis(100));
+        assert!(particles.particles.is_empty());
+        assert!(particles.emitters[0].is_none());
+        assert_eq!(particles.free_emitters, [0]);
+
+        particles.spawn_fx(&resources, position, 1, 1);
+        particles.update(&resources, Duration::from_millis(50));
+        assert_eq!(particles.emitters.len(), 1);
+        assert!(particles.free_emitters.is_empty());
+        assert_eq!(particles.particles.len(), 4);
+        assert_eq!(particles.particles.capacity(), capacity);
+    }
+
+    #[test]
+    fn fxs_of_other_maps_are_ignored() {
+        let resources = Resources {
+            emitters: Emitters {
+                fxs: HashMap::from([(1, emitter(vec![1]))]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut particles = Particles::default();
+
+        particles.spawn_fx(
+            &resources,
+            WorldPosition {
+                map: 2,
+                x: 50,
+                y: 50,
+            },
+            1,
+            1,
+        );
+        particles.spawn_fx(
+            &resources,
+            WorldPosition {
+                map: 0,
+                x: 50,
+                y: 50,
+            },
+            7,
+            1,
+        );
+        assert!(particles.emitters.is_empty());
+    }
+
+    #[test]
+    fn shipped_emitters_are_valid() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../assets/finisterra/init/particles.ron"
+        );
+        let file = std::fs::File::open(path).expect("particles file to exist");
+        let emitters: Emitters = ron::de::from_reader(file).expect("particles to be correct");
+        assert!(!emitters.tiles.is_empty());
+        assert!(!emitters.fxs.is_empty());
+        for emitter in emitters.tiles.values().chain(emitters.fxs.values()) {
+            assert!(!emitter.frames.is_empty());
+            assert!(!emitter.colors.is_empty());
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use std::time::{Duration, Instant};
 
 use engine::engine::GameEngine;
-use shared::protocol::{
-    client::{Action, ClientPacket},
-    movement::MoveRequest,
+use shared::{
+    protocol::{
+        client::{Action, ClientPacket},
+        movement::MoveRequest,
+    },
+    world::pathfinding::{find_path, Walker},
 };
 
 use crate::game::Context;
@@ -16,16 +19,32 @@ impl WorldScreen {
         if let Some(Entity::Character(character)) = self.entities.get_mut(&self.entity_id) {
             let elapsed_since_last_move = Instant::now() - self.last_move;
             let latency = context.connection.ping();
-            let was_idle_but_wants_to_move = !character.moving() && !self.input.is_empty();
+            let wants_to_move = !self.input.is_empty() || self.walk_target.is_some();
+            let was_idle_but_wants_to_move = !character.moving() && wants_to_move;
 
             if (was_idle_but_wants_to_move || character.just_finished_moving())
                 && elapsed_since_last_move >= Duration::from_millis(200 - latency as u64)
             {
-                if let Some(direction) = self.input.front() {
-                    let map = context.maps.get(&character.position.map);
-                    let position = map.next_position(&character.position, *direction);
-                    character.change_direction(*direction);
+                let map = context.maps.get(&character.position.map);
+                let direction = match (self.input.front(), self.walk_target) {
+                    (Some(direction), _) => Some(*direction),
+                    (None, Some(target)) => {
+                        let step = find_path(map, &character.position, &target, Walker::User)
+                            .and_then(|path| path.first().copied());
+                        if step.is_none() {
+                            self.walk_target = None;
+                        }
+                        step
+                    }
+                    (None, None) => None,
+                };
+                if let Some(direction) = direction {
+                    let position = map.next_position(&character.position, direction);
+                    character.change_direction(direction);
                     character.move_to(position);
+                    if self.walk_target == Some(position) {
+                        self.walk_target = None;
+                    }
 
                     self.last_move = Instant::now();
                     self.predictions.push((self.movement_sequence, position));
@@ -33,7 +52,7 @@ impl WorldScreen {
                         .connection
                         .send(ClientPacket::UserAction(Action::Move(MoveRequest {
                             id: self.movement_sequence,
-                            direction: *direction,
+                            direction,
                         })));
                     self.movement_sequence += 1;
                 }
@@ -0,0 +1,376 @@
+use std::time::Instant;
+
+use engine::{
+    camera,
+    draw::{
+        image::{DrawImage, Transform},
+        Color, Position, Target,
+    },
+    engine::{GameEngine, SoundID, TextureID},
+    sound::Bus,
+};
+use rand::Rng;
+use shared::world::{MapInfo, TileTrigger};
+
+use crate::{
+    game::Context,
+    screens::world::{entity::Entity, WorldScreen, WORLD_RENDER_HEIGHT, WORLD_RENDER_WIDTH},
+    sounds,
+    ui::colors::WHITE,
+};
+
+/// Drops and flakes on screen at full intensity
+const MAX_DROPS: usize = 400;
+const MAX_FLAKES: usize = 250;
+
+/// Weather goes over the roofs, the fog over everything
+const WEATHER_Z: f32 = 0.995;
+const FOG_Z: f32 = 0.999;
+
+/// Intensity units per second, the weather eases toward the one sent by the server
+const INTENSITY_SPEED: f32 = 64.;
+/// Horizontal pixels per second of the rain, it falls slanted
+const RAIN_WIND: f32 = -80.;
+
+const RAIN_COLOR: Color = [170, 180, 210, 150];
+const SNOW_COLOR: Color = [255, 255, 255, 220];
+const FOG_COLOR: Color = [190, 195, 205, 0];
+/// Fog maps are foggy even in clear weather
+const FOG_ALPHA: (f32, f32) = (60., 150.);
+
+/// Tint of the sun through the day, by hour
+const NIGHT: Color = [90, 100, 150, 255];
+const DAYLIGHT: [(f32, Color); 8] = [
+    (0., NIGHT),
+    (5., NIGHT),
+    (7., [255, 190, 160, 255]),
+    (9., WHITE),
+    (17., WHITE),
+    (19., [255, 160, 120, 255]),
+    (21., NIGHT),
+    (24., NIGHT),
+];
+/// Heavy weather darkens the day this much
+const OVERCAST: f32 = 0.25;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Precipitation {
+    Rain,
+    Snow,
+}
+
+/// Snow falls on snowy terrain even on maps flagged with rain
+fn precipitation(info: &MapInfo) -> Option<Precipitation> {
+    if info.snow || (info.rain && info.terrain.eq_ignore_ascii_case("nieve")) {
+        Some(Precipitation::Snow)
+    } else if info.rain {
+        Some(Precipitation::Rain)
+    } else {
+        None
+    }
+}
+
+/// Drop or flake, relative to the bottom left corner of the view
+struct Drop {
+    x: f32,
+    y: f32,
+    /// Pixels per second downwards
+    speed: f32,
+    /// Length of the rain streaks, phase of the sway of the flakes
+    variation: f32,
+}
+
+struct Clock {
+    time: u32,
+    day: u32,
+    received: Instant,
+}
+
+#[derive(Default)]
+pub struct Weather {
+    clock: Option<Clock>,
+    target: u8,
+    intensity: f32,
+    precipitation: Option<Precipitation>,
+    drops: Vec<Drop>,
+    /// Ambient loop playing and its volume
+    sound: Option<(SoundID, u8)>,
+}
+
+impl Weather {
+    pub fn set_clock(&mut self, time: u32, day: u32) {
+        self.clock = Some(Clock {
+            time,
+            day,
+            received: Instant::now(),
+        });
+    }
+
+    pub fn set_intensity(&mut self, intensity: u8) {
+        self.target = intensity;
+    }
+
+    /// Hour of the world clock, from 0 to 24
+    fn hour(&self) -> Option<f32> {
+        let clock = self.clock.as_ref()?;
+        let day = clock.day.max(1) as u128;
+        let time = (clock.time as u128 + clock.received.elapsed().as_millis()) % day;
+        Some(time as f32 / day as f32 * 24.)
+    }
+
+    /// Tint of the sun at the time of the world clock, the world is fully lit until it is synced
+    pub fn daylight(&self) -> Color {
+        let Some(hour) = self.hour() else {
+            return WHITE;
+        };
+        let next = DAYLIGHT
+            .iter()
+            .position(|(start, _)| *start > hour)
+            .unwrap_or(DAYLIGHT.len() - 1);
+        let (from_hour, from) = DAYLIGHT[next.saturating_sub(1)];
+        let (to_hour, to) = DAYLIGHT[next];
+        let amount = ((hour - from_hour) / (to_hour - from_hour).max(f32::EPSILON)).clamp(0., 1.);
+        let overcast = 1. - OVERCAST * self.intensity / 255.;
+        std::array::from_fn(|channel| {
+            let (from, to) = (from[channel] as f32, to[channel] as f32);
+            match channel {
+                3 => 255,
+                _ => ((from + (to - from) * amount) * overcast).round() as u8,
+            }
+        })
+    }
+
+    fn update<E: GameEngine>(&mut self, engine: &mut E, info: Option<&MapInfo>, indoors: bool) {
+        let delta = engine.get_delta().as_secs_f32();
+        let target = self.target as f32;
+        let step = INTENSITY_SPEED * delta;
+        self.intensity = if self.intensity < target {
+            (self.intensity + step).min(target)
+        } else {
+            (self.intensity - step).max(target)
+        };
+
+        let precipitation = info.and_then(precipitation);
+        if precipitation != self.precipitation {
+            self.precipitation = precipitation;
+            self.drops.clear();
+        }
+        let max = match precipitation {
+            Some(Precipitation::Rain) => MAX_DROPS,
+            Some(Precipitation::Snow) => MAX_FLAKES,
+            None => 0,
+        };
+        let count = (max as f32 * self.intensity / 255.) as usize;
+        let rng = &mut rand::thread_rng();
+        self.drops.truncate(count);
+        while self.drops.len() < count {
+            self.drops.push(spawn(rng, precipitation));
+        }
+
+        let (width, height) = (WORLD_RENDER_WIDTH as f32, WORLD_RENDER_HEIGHT as f32);
+        for drop in &mut self.drops {
+            let sway = match precipitation {
+                Some(Precipitation::Snow) => {
+                    drop.variation += delta;
+                    drop.variation.sin() * 20.
+                }
+                _ => RAIN_WIND,
+            };
+            drop.x = (drop.x + sway * delta).rem_euclid(width);
+            drop.y = (drop.y - drop.speed * delta).rem_euclid(height);
+        }
+
+        // the rain is heard muffled under a roof, the wind is not heard at all
+        let sound = match (precipitation, indoors) {
+            _ if self.target == 0 => None,
+            (Some(Precipitation::Rain), false) => Some((sounds::RAIN, self.target)),
+            (Some(Precipitation::Rain), true) => Some((sounds::RAIN_INDOORS, self.target)),
+            (Some(Precipitation::Snow), false) => Some((sounds::WIND, self.target)),
+            _ => None,
+        };
+        if sound != self.sound {
+            self.sound = sound;
+            engine.stop_sounds(Bus::Ambient);
+            if let Some((id, volume)) = sound {
+                sounds::play_ambient(engine, id, volume);
+            }
+        }
+    }
+
+    fn draw<E: GameEngine>(
+        &self,
+        engine: &mut E,
+        pixel: TextureID,
+        info: Option<&MapInfo>,
+        camera: camera::Position,
+    ) {
+        let (width, height) = (WORLD_RENDER_WIDTH as f32, WORLD_RENDER_HEIGHT as f32);
+        let origin = (camera.x - width / 2., camera.y - height / 2.);
+        let quad = |x: f32, y: f32, z: f32, color: Color, transform: Transform| DrawImage {
+            position: Position::new(
+                (origin.0 + x).max(0.) as u16,
+                (origin.1 + y).max(0.) as u16,
+                z,
+            ),
+            color,
+            source: [0, 0, 1, 1],
+            index: pixel,
+            transform,
+            ..Default::default()
+        };
+
+        for drop in &self.drops {
+            let (color, transform) = match self.precipitation {
+                Some(Precipitation::Rain) => (
+                    RAIN_COLOR,
+                    Transform {
+                        scale: [1., drop.variation],
+                        pivot: [0.5, 0.],
+                        // leaning the way the drop falls
+                        rotation: (RAIN_WIND / drop.speed).atan(),
+                        ..Default::default()
+                    },
+                ),
+                _ => (
+                    SNOW_COLOR,
+                    Transform {
+                        scale: [2., 2.],
+                        ..Default::default()
+                    },
+                ),
+            };
+            engine.draw_image(
+                quad(drop.x, drop.y, WEATHER_Z, color, transform),
+                Target::World,
+            );
+        }
+
+        if info.is_some_and(|info| info.fog) {
+            let (clear, heavy) = FOG_ALPHA;
+            let mut color = FOG_COLOR;
+            color[3] = (clear + (heavy - clear) * self.intensity / 255.) as u8;
+            let transform = Transform {
+                scale: [width, height],
+                pivot: [0., 0.],
+                ..Default::default()
+            };
+            engine.draw_image(quad(0., 0., FOG_Z, color, transform), Target::World);
+        }
+    }
+}
+
+fn spawn(rng: &mut impl Rng, precipitation: Option<Precipitation>) -> Drop {
+    let (speed, variation) = match precipitation {
+        Some(Precipitation::Snow) => (rng.gen_range(30.0..60.), rng.gen_range(0.0..6.3)),
+        _ => (rng.gen_range(450.0..650.), rng.gen_range(8.0..16.)),
+    };
+    Drop {
+        x: rng.gen_range(0.0..WORLD_RENDER_WIDTH as f32),
+        y: rng.gen_range(0.0..WORLD_RENDER_HEIGHT as f32),
+        speed,
+        variation,
+    }
+}
+
+impl WorldScreen {
+    /// Under a roof the weather is not seen, only heard
+    fn indoors<E: GameEngine>(&self, context: &mut Context<E>) -> Option<bool> {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return None;
+        };
+        let position = character.position;
+        let trigger = context
+            .maps
+            .get(&position.map)
+            .trigger(position.x, position.y);
+        Some(trigger == TileTrigger::UnderRoof)
+    }
+
+    fn current_map(&self) -> Option<u16> {
+        let Some(Entity::Character(character)) = self.entities.get(&self.entity_id) else {
+            return None;
+        };
+        Some(character.position.map)
+    }
+
+    pub fn update_weather<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        let Some(indoors) = self.indoors(context) else {
+            return;
+        };
+        let info = self
+            .current_map()
+            .and_then(|map| context.resources.map_infos.get(&map));
+        self.weather.update(context.engine, info, indoors);
+    }
+
+    pub fn draw_weather<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if self.indoors(context) != Some(false) {
+            return;
+        }
+        let info = self
+            .current_map()
+            .and_then(|map| context.resources.map_infos.get(&map));
+        let camera = context.engine.get_world_camera_position();
+        self.weather.draw(
+            context.engine,
+            context.resources.textures.pixel,
+            info,
+            camera,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::world::MapInfo;
+
+    use super::{precipitation, Precipitation, Weather, NIGHT};
+    use crate::ui::colors::WHITE;
+
+    /// A real day long, the time the test takes doesn't move the clock
+    const DAY: u32 = 24 * 60 * 60 * 1000;
+
+    fn at(hour: f32) -> Weather {
+        let mut weather = Weather::default();
+        weather.set_clock((hour / 24. * DAY as f32) as u32, DAY);
+        weather
+    }
+
+    #[test]
+    fn world_is_lit_until_the_clock_is_synced() {
+        assert_eq!(Weather::default().daylight(), WHITE);
+    }
+
+    #[test]
+    fn daylight_follows_the_hour() {
+        assert_eq!(at(0.).daylight(), NIGHT);
+        assert_eq!(at(3.).daylight(), NIGHT);
+        assert_eq!(at(12.).daylight(), WHITE);
+        assert_eq!(at(23.).daylight(), NIGHT);
+        // halfway between the night and the dawn
+        assert_eq!(at(6.).daylight(), [173, 145, 155, 255]);
+    }
+
+    #[test]
+    fn heavy_weather_darkens_the_day() {
+        let mut weather = at(12.);
+        weather.intensity = 255.;
+        assert_eq!(weather.daylight(), [191, 191, 191, 255]);
+        weather.intensity = 0.;
+        assert_eq!(weather.daylight(), WHITE);
+    }
+
+    #[test]
+    fn snowy_terrain_turns_the_rain_into_snow() {
+        let info = |rain, snow, terrain: &str| MapInfo {
+            rain,
+            snow,
+            terrain: terrain.to_string(),
+            ..Default::default()
+        };
+        assert!(precipitation(&info(false, false, "bosque")).is_none());
+        assert!(precipitation(&info(true, false, "bosque")) == Some(Precipitation::Rain));
+        assert!(precipitation(&info(true, false, "NIEVE")) == Some(Precipitation::Snow));
+        assert!(precipitation(&info(false, true, "bosque")) == Some(Precipitation::Snow));
+    }
+}
@@ -0,0 +1,111 @@
+use engine::{
+    camera,
+    engine::{GameEngine, SoundID},
+    sound::{Bus, PlaySound},
+};
+
+/// Ids are the numbers of the Argentum wav files, copied to `assets/sounds/`
+pub const SWING: SoundID = 2;
+pub const LEVEL_UP: SoundID = 6;
+pub const HIT: SoundID = 10;
+pub const DEATH: SoundID = 11;
+pub const FOOTSTEP_LEFT: SoundID = 23;
+pub const FOOTSTEP_RIGHT: SoundID = 24;
+pub const SHIELD_BLOCK: SoundID = 37;
+/// Weather loops, Argentum names these files instead of numbering them, see `file`
+pub const RAIN: SoundID = 1000;
+pub const RAIN_INDOORS: SoundID = 1001;
+pub const WIND: SoundID = 1002;
+
+const SOUNDS: [SoundID; 10] = [
+    SWING,
+    LEVEL_UP,
+    HIT,
+    DEATH,
+    FOOTSTEP_LEFT,
+    FOOTSTEP_RIGHT,
+    SHIELD_BLOCK,
+    RAIN,
+    RAIN_INDOORS,
+    WIND,
+];
+
+const FOOTSTEP_VOLUME: u8 = 160;
+
+pub struct Sounds;
+
+impl Sounds {
+    pub fn load<E: GameEngine>(engine: &mut E) {
+        for id in SOUNDS {
+            engine.set_sound(&format!("./assets/sounds/{}.wav", file(id)), id);
+        }
+    }
+}
+
+/// Name of the wav file of a sound
+fn file(id: SoundID) -> String {
+    match id {
+        RAIN => "lluviaout".to_string(),
+        RAIN_INDOORS => "lluviain".to_string(),
+        WIND => "viento".to_string(),
+        id => id.to_string(),
+    }
+}
+
+/// Plays an effect centered, for the sounds of the own character
+pub fn play<E: GameEngine>(engine: &mut E, id: SoundID) {
+    engine.play_sound(
+        id,
+        PlaySound {
+            times: 1,
+            volume: 255,
+            bus: Bus::Effects,
+            position: None,
+        },
+    );
+}
+
+/// Plays an effect heard from the world position of its source
+pub fn play_at<E: GameEngine>(engine: &mut E, id: SoundID, position: (f32, f32)) {
+    engine.play_sound(
+        id,
+        PlaySound {
+            times: 1,
+            volume: 255,
+            bus: Bus::Effects,
+            position: Some(camera::Position {
+                x: position.0,
+                y: position.1,
+            }),
+        },
+    );
+}
+
+pub fn footstep<E: GameEngine>(engine: &mut E, left: bool, position: (f32, f32)) {
+    let id = if left { FOOTSTEP_LEFT } else { FOOTSTEP_RIGHT };
+    engine.play_sound(
+        id,
+        PlaySound {
+            times: 1,
+            volume: FOOTSTEP_VOLUME,
+            bus: Bus::Effects,
+            position: Some(camera::Position {
+                x: position.0,
+                y: position.1,
+            }),
+        },
+    );
+}
+
+/// Loops an ambient sound until the ambient bus is stopped
+pub fn play_ambient<E: GameEngine>(engine: &mut E, id: SoundID, volume: u8) {
+    engine.play_sound(
+        id,
+        PlaySound {
+            times: 0,
+            volume,
+            bus: Bus::Ambient,
+            position: None,
+        },
+    );
+}
@@ -0,0 +1,138 @@
+use engine::{
+    camera::Zoom,
+    draw::{
+        text::{DrawText, ParsedText},
+        Color, Position, Target,
+    },
+    engine::{FontID, GameEngine},
+};
+
+use crate::game::Context;
+
+use super::{colors::*, Widget};
+
+const LINE_HEIGHT: u16 = 13;
+
+/// Selectable list of text lines, clicking the first or last visible line scrolls the list
+pub struct List {
+    pub position: (u16, u16), // top left
+    pub size: (u16, u16),
+    pub z: f32,
+
+    font_id: FontID,
+    lines: Vec<(ParsedText, Color)>,
+    offset: usize,
+    selection: Option<usize>,
+}
+
+impl List {
+    pub fn new(font_id: FontID, size: (u16, u16)) -> Self {
+        Self {
+            position: (0, 0),
+            size,
+            z: 1.,
+            font_id,
+            lines: vec![],
+            offset: 0,
+            selection: None,
+        }
+    }
+
+    pub fn set_lines<E: GameEngine>(&mut self, engine: &mut E, lines: Vec<(String, Color)>) {
+        self.lines = lines
+            .into_iter()
+            .map(|(text, color)| {
+                let text = engine.parse_text(self.font_id, &text).expect("can parse");
+                (text, color)
+            })
+            .collect();
+        if self
+            .selection
+            .is_some_and(|selection| selection >= self.lines.len())
+        {
+            self.selection = None;
+        }
+        self.offset = std::cmp::min(self.offset, self.max_offset());
+    }
+
+    pub fn selected(&self) -> Option<usize> {
+        self.selection
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    pub fn contains(&self, (x, y): (u16, u16)) -> bool {
+        x > self.position.0
+            && x < self.position.0 + self.size.0
+            && y < self.position.1
+            && y > self.position.1 - self.size.1
+    }
+
+    fn visible_lines(&self) -> usize {
+        (self.size.1 / LINE_HEIGHT) as usize
+    }
+
+    fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.visible_lines())
+    }
+}
+
+impl Widget for List {
+    fn update<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        if !context.engine.mouse_clicked() {
+            return;
+        }
+        let mouse = mouse_position(context.engine);
+        if !self.contains(mouse) {
+            return;
+        }
+
+        let line = ((self.position.1 - mouse.1) / LINE_HEIGHT) as usize;
+        let selection = self.offset + line;
+        if selection >= self.lines.len() {
+            return;
+        }
+        self.selection = Some(selection);
+
+        if line == 0 {
+            self.offset = self.offset.saturating_sub(1);
+        } else if line + 1 >= self.visible_lines() {
+            self.offset = std::cmp::min(self.max_offset(), self.offset + 1);
+        }
+    }
+
+    fn draw<E: GameEngine>(&mut self, context: &mut Context<E>) {
+        let (x, y) = self.position;
+        let end = std::cmp::min(self.lines.len(), self.offset + self.visible_lines());
+        for i in self.offset..end {
+            let (text, color) = &self.lines[i];
+            let color = if self.selection == Some(i) {
+                tint(*color, 0.6)
+            } else {
+                *color
+            };
+            let line_y = y - LINE_HEIGHT * (i - self.offset + 1) as u16;
+            context.engine.draw_text(
+                self.font_id,
+                DrawText {
+                    text,
+                    position: Position::new(x + 4 + text.total_width / 2, line_y, self.z),
+                    color,
+                },
+                Target::UI,
+            );
+        }
+    }
+}
+
+/// Mouse position in UI coordinates
+pub fn mouse_position<E: GameEngine>(engine: &E) -> (u16, u16) {
+    let position = engine.mouse_position();
+    let zoom = match engine.get_camera_zoom() {
+        Zoom::None => 1.,
+        Zoom::Double => 2.,
+    };
+    ((position.x / zoom) as u16, (position.y / zoom) as u16)
+}
@@ -8,6 +8,7 @@ pub mod colors;
 pub mod fonts;
 pub mod input_field;
 pub mod label;
+pub mod list;
 pub mod texture;
 pub mod textures;
 
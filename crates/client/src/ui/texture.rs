@@ -55,6 +55,7 @@ impl Widget for Texture {
                 color: self.color,
                 index: self.texture_id,
                 source: [0, 0, width, self.size.1],
+                ..Default::default()
             },
             Target::UI,
         )
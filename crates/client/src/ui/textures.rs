@@ -22,6 +22,8 @@ pub struct Textures {
     pub spell_selection: TextureID,
     pub inventory_slot: TextureID,
     pub character_shadow: TextureID,
+    /// White pixel, tinted and scaled for flat shapes
+    pub pixel: TextureID,
 }
 
 impl Textures {
@@ -49,6 +51,7 @@ impl Textures {
             spells_list: engine.add_texture("./assets/interface/main/spells_list.png"),
             inventory_slot: engine.add_texture("./assets/interface/main/inventory_slot.png"),
             character_shadow: engine.add_texture("./assets/finisterra/images/shadow-2.png"),
+            pixel: engine.add_texture("./assets/interface/pixel.png"),
         }
     }
 }
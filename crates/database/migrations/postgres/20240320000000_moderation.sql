@@ -0,0 +1,37 @@
+CREATE TABLE IF NOT EXISTS sanctions
+(
+    id              bigserial primary key,
+    kind            text not null,
+    account_name    text,
+    ip              text,
+    reason          text not null,
+    moderator       text not null,
+    created_at      timestamptz not null default current_timestamp,
+    expires_at      timestamptz,
+    revoked_at      timestamptz
+);
+
+CREATE INDEX IF NOT EXISTS sanctions_account_name ON sanctions (account_name);
+CREATE INDEX IF NOT EXISTS sanctions_ip ON sanctions (ip);
+
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id          bigserial primary key,
+    moderator   text not null,
+    action      text not null,
+    target      text not null,
+    reason      text not null,
+    created_at  timestamptz not null default current_timestamp
+);
+
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION 'the audit log is append only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
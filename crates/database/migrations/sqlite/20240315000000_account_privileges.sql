@@ -0,0 +1 @@
+ALTER TABLE accounts ADD COLUMN privileges integer not null default 0;
@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS sanctions
+(
+    id              integer primary key autoincrement,
+    kind            text not null,
+    account_name    text,
+    ip              text,
+    reason          text not null,
+    moderator       text not null,
+    created_at      timestamp not null default current_timestamp,
+    expires_at      timestamp,
+    revoked_at      timestamp
+);
+
+CREATE INDEX IF NOT EXISTS sanctions_account_name ON sanctions (account_name);
+CREATE INDEX IF NOT EXISTS sanctions_ip ON sanctions (ip);
+
+CREATE TABLE IF NOT EXISTS audit_log
+(
+    id          integer primary key autoincrement,
+    moderator   text not null,
+    action      text not null,
+    target      text not null,
+    reason      text not null,
+    created_at  timestamp not null default current_timestamp
+);
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append only');
+END;
@@ -0,0 +1,45 @@
+-- the byte columns were declared as bytea, which sqlite gives numeric affinity,
+-- they are rebuilt as blob so the declared type matches what is stored
+CREATE TABLE character_skills_blob
+(
+    name  text primary key,
+    value blob not null,
+
+    CONSTRAINT fk_character_name_4 FOREIGN KEY (name) REFERENCES characters(name)
+);
+INSERT INTO character_skills_blob SELECT name, value FROM character_skills;
+DROP TABLE character_skills;
+ALTER TABLE character_skills_blob RENAME TO character_skills;
+
+CREATE TABLE character_inventories_blob
+(
+    name  text primary key,
+    value blob not null,
+
+    CONSTRAINT fk_character_name_5 FOREIGN KEY (name) REFERENCES characters(name)
+);
+INSERT INTO character_inventories_blob SELECT name, value FROM character_inventories;
+DROP TABLE character_inventories;
+ALTER TABLE character_inventories_blob RENAME TO character_inventories;
+
+CREATE TABLE character_vaults_blob
+(
+    name  text primary key,
+    value blob not null,
+
+    CONSTRAINT fk_character_name_6 FOREIGN KEY (name) REFERENCES characters(name)
+);
+INSERT INTO character_vaults_blob SELECT name, value FROM character_vaults;
+DROP TABLE character_vaults;
+ALTER TABLE character_vaults_blob RENAME TO character_vaults;
+
+CREATE TABLE character_spellbooks_blob
+(
+    name  text primary key,
+    value blob not null,
+
+    CONSTRAINT fk_character_name_7 FOREIGN KEY (name) REFERENCES characters(name)
+);
+INSERT INTO character_spellbooks_blob SELECT name, value FROM character_spellbooks;
+DROP TABLE character_spellbooks;
+ALTER TABLE character_spellbooks_blob RENAME TO character_spellbooks;
@@ -1,236 +1,465 @@
 use std::env;
 
-use anyhow::Result;
-use model::{Account, Character, CharacterPreview, CreateAccount, CreateCharacter};
+use anyhow::{bail, Context, Result};
+use model::{
+    Account, AuditEntry, Character, CharacterItems, CharacterPreview, CreateAccount,
+    CreateCharacter, CreateSanction, Sanction, SanctionKind,
+};
 use sqlx::{
     migrate::MigrateDatabase,
     types::chrono::{DateTime, Utc},
-    Acquire, SqlitePool,
+    Acquire, PgPool, Postgres, Sqlite, SqlitePool,
 };
 
 pub mod model;
 pub mod types;
 
+/// Runs the body with the pool of the backend in use, the body is compiled once per backend
+/// so the queries have to be valid in both dialects
+macro_rules! with_pool {
+    ($database:expr, |$pool:ident| $body:block) => {
+        match &$database.pool {
+            Pool::Sqlite($pool) => $body,
+            Pool::Postgres($pool) => $body,
+        }
+    };
+}
+
 pub struct Database {
-    pool: SqlitePool,
+    pool: Pool,
+}
+
+/// The backend is picked by the scheme of the url, each one has its own migrations
+enum Pool {
+    Sqlite(SqlitePool),
+    Postgres(PgPool),
 }
 
 impl Database {
     pub async fn initialize() -> Result<Self> {
         let db_url = env::var("DATABASE_URL").unwrap_or("sqlite:argentum.db".to_string());
-        if !sqlx::Sqlite::database_exists(&db_url).await? {
-            sqlx::Sqlite::create_database(&db_url).await?;
-        }
+        Self::connect(&db_url).await
+    }
 
-        let pool = SqlitePool::connect(&db_url).await?;
-        sqlx::migrate!().run(&pool).await?;
+    /// Connects to `sqlite:` or `postgres://` urls, the database is created and migrated
+    pub async fn connect(db_url: &str) -> Result<Self> {
+        let pool = if db_url.starts_with("sqlite:") {
+            if !Sqlite::database_exists(db_url).await? {
+                Sqlite::create_database(db_url).await?;
+            }
+            let pool = SqlitePool::connect(db_url).await?;
+            sqlx::migrate!("./migrations/sqlite").run(&pool).await?;
+            Pool::Sqlite(pool)
+        } else if db_url.starts_with("postgres://") || db_url.starts_with("postgresql://") {
+            if !Postgres::database_exists(db_url).await? {
+                Postgres::create_database(db_url).await?;
+            }
+            let pool = PgPool::connect(db_url).await?;
+            sqlx::migrate!("./migrations/postgres").run(&pool).await?;
+            Pool::Postgres(pool)
+        } else {
+            bail!("unsupported database url {db_url}, use sqlite: or postgres://");
+        };
 
         Ok(Self { pool })
     }
 
     pub async fn create_account(&self, create_account: &CreateAccount<'_>) -> Result<Account> {
-        let mut conn = self.pool.acquire().await?;
-
-        let account = sqlx::query_as::<_, Account>(r#"INSERT INTO "accounts" ("name", "email", "password", "pin") VALUES ($1, $2, $3, $4) RETURNING *;"#)
-            .bind(create_account.name)
-            .bind(create_account.email)
-            .bind(create_account.password)
-            .bind(create_account.pin)
-            .fetch_one(&mut *conn)
-            .await?;
-
-        Ok(account)
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            // sqlite only commits the insert once the statement runs to the end, `fetch_one` stops
+            // at the returned row and the account is not visible to the other connections
+            let account = sqlx::query_as::<_, Account>(r#"INSERT INTO "accounts" ("name", "email", "password", "pin") VALUES ($1, $2, $3, $4) RETURNING *;"#)
+                .bind(create_account.name)
+                .bind(create_account.email)
+                .bind(create_account.password)
+                .bind(create_account.pin)
+                .fetch_all(&mut *conn)
+                .await?
+                .pop()
+                .context("the insert returned no account")?;
+
+            Ok(account)
+        })
     }
 
     pub async fn account(&self, mail: &str) -> Result<Account> {
-        let mut conn = self.pool.acquire().await?;
-        let account = sqlx::query_as::<_, Account>("SELECT * FROM accounts as a WHERE a.name = $1")
-            .bind(mail)
-            .fetch_one(&mut *conn)
-            .await?;
-
-        Ok(account)
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            let account =
+                sqlx::query_as::<_, Account>("SELECT * FROM accounts as a WHERE a.name = $1")
+                    .bind(mail)
+                    .fetch_one(&mut *conn)
+                    .await?;
+
+            Ok(account)
+        })
     }
 
     pub async fn account_characters(&self, account_name: &str) -> Result<Vec<CharacterPreview>> {
-        let mut conn = self.pool.acquire().await?;
-        let characters = sqlx::query_as::<_, CharacterPreview>(
-            r#"              
-            SELECT char.*,
-                look.body, look.face, look.skin, look.hair,
-                equipment.weapon, equipment.shield, equipment.headgear, equipment.clothing
-            FROM characters AS char 
-
-            JOIN character_equipment as equipment ON char.name = equipment.name
-            JOIN character_look as look ON char.name = look.name
-
-            WHERE char.name IN (SELECT character_name FROM account_characters WHERE account_name = $1);
-            "#,
-        )
-        .bind(account_name)
-        .fetch_all(&mut *conn)
-        .await;
-
-        characters.map_err(|e| {
-            println!("error while querying characters {e}");
-            anyhow::anyhow!("{e}")
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            let characters = sqlx::query_as::<_, CharacterPreview>(
+                r#"              
+                SELECT c.*,
+                    look.body, look.face, look.skin, look.hair,
+                    equipment.weapon, equipment.shield, equipment.headgear, equipment.clothing
+                FROM characters AS c
+
+                JOIN character_equipment as equipment ON c.name = equipment.name
+                JOIN character_look as look ON c.name = look.name
+
+                WHERE c.name IN (SELECT character_name FROM account_characters WHERE account_name = $1);
+                "#,
+            )
+            .bind(account_name)
+            .fetch_all(&mut *conn)
+            .await;
+
+            characters.map_err(|e| {
+                println!("error while querying characters {e}");
+                anyhow::anyhow!("{e}")
+            })
         })
     }
 
     pub async fn character(&self, character_name: &str) -> Result<Character> {
-        let mut conn = self.pool.acquire().await?;
-        let character = sqlx::query_as::<_, Character>(
-            r#"              
-            SELECT 
-                char.*,
-                stats.health, stats.mana, stats.stamina, stats.max_health, stats.max_mana, stats.max_stamina,
-                attributes.strength, attributes.agility, attributes.intelligence, attributes.charisma, attributes.constitution,
-                look.body, look.face, look.skin, look.hair,
-                equipment.weapon, equipment.shield, equipment.headgear, equipment.clothing,
-                character_skills.value as skills,
-                character_inventory.value as inventory,
-                character_vault.value as vault,
-                character_spellbook.value as spellbook 
-            FROM characters AS char 
-
-            JOIN character_statistics as stats ON char.name = stats.name
-            JOIN character_attributes as attributes ON char.name = attributes.name
-            JOIN character_equipment as equipment ON char.name = equipment.name
-            JOIN character_skills ON char.name = character_skills.name
-            JOIN character_inventories as character_inventory ON char.name = character_inventory.name
-            JOIN character_vaults as character_vault ON char.name = character_vault.name
-            JOIN character_spellbooks as character_spellbook ON char.name = character_spellbook.name
-            JOIN character_look as look ON char.name = look.name
-
-            WHERE char.name = $1
-            "#,
-        )
-        .bind(character_name)
-        .fetch_one(&mut *conn)
-        .await?;
-
-        Ok(character)
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            let character = sqlx::query_as::<_, Character>(
+                r#"              
+                SELECT 
+                    c.*,
+                    stats.health, stats.mana, stats.stamina, stats.max_health, stats.max_mana, stats.max_stamina,
+                    attributes.strength, attributes.agility, attributes.intelligence, attributes.charisma, attributes.constitution,
+                    look.body, look.face, look.skin, look.hair,
+                    equipment.weapon, equipment.shield, equipment.headgear, equipment.clothing,
+                    character_skills.value as skills,
+                    character_inventory.value as inventory,
+                    character_vault.value as vault,
+                    character_spellbook.value as spellbook 
+                FROM characters AS c
+
+                JOIN character_statistics as stats ON c.name = stats.name
+                JOIN character_attributes as attributes ON c.name = attributes.name
+                JOIN character_equipment as equipment ON c.name = equipment.name
+                JOIN character_skills ON c.name = character_skills.name
+                JOIN character_inventories as character_inventory ON c.name = character_inventory.name
+                JOIN character_vaults as character_vault ON c.name = character_vault.name
+                JOIN character_spellbooks as character_spellbook ON c.name = character_spellbook.name
+                JOIN character_look as look ON c.name = look.name
+
+                WHERE c.name = $1
+                "#,
+            )
+            .bind(character_name)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            Ok(character)
+        })
     }
     pub async fn insert_character(
         &self,
         account_name: &str,
         character: CreateCharacter,
     ) -> Result<Character> {
-        let mut conn = self.pool.acquire().await?;
-
-        let mut transaction = conn.begin().await?;
-
-        let CreateCharacter {
-            attributes,
-            statistics,
-            look,
-            equipment,
-            ..
-        } = &character;
-
-        sqlx::query(r#"INSERT INTO "characters" ("name", "class_id", "race_id", "gender_id", "map", "x", "y") VALUES ($1, $2, $3, $4, $5, $6, $7)"#)
-            .bind(&character.name)
-            .bind(character.class_id)
-            .bind(character.race_id)
-            .bind(character.gender_id)
-            .bind(character.map)
-            .bind(character.x)
-            .bind(character.y)
-            .execute(&mut *transaction)
-            .await?;
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+
+            let mut transaction = conn.begin().await?;
+
+            let CreateCharacter {
+                attributes,
+                statistics,
+                look,
+                equipment,
+                ..
+            } = &character;
+
+            sqlx::query(r#"INSERT INTO "characters" ("name", "class_id", "race_id", "gender_id", "map", "x", "y") VALUES ($1, $2, $3, $4, $5, $6, $7)"#)
+                .bind(&character.name)
+                .bind(character.class_id)
+                .bind(character.race_id)
+                .bind(character.gender_id)
+                .bind(character.map)
+                .bind(character.x)
+                .bind(character.y)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_inventories" ("name", "value") VALUES ($1, $2)"#)
+                .bind(&character.name)
+                .bind(Vec::<u8>::new())
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_attributes" ("name", "strength", "agility", "intelligence", "charisma", "constitution") VALUES ($1, $2, $3, $4, $5, $6)"#)
+                .bind(&character.name)
+                .bind(attributes.strength)
+                .bind(attributes.agility)
+                .bind(attributes.intelligence)
+                .bind(attributes.charisma)
+                .bind(attributes.constitution)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_statistics" ("name", "health", "mana", "stamina", "max_health", "max_mana", "max_stamina") VALUES ($1, $2, $3, $4, $5, $6, $7)"#)
+                .bind(&character.name)
+                .bind(statistics.health)
+                .bind(statistics.mana)
+                .bind(statistics.stamina)
+                .bind(statistics.max_health)
+                .bind(statistics.max_mana)
+                .bind(statistics.max_stamina)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_look" ("name", "body", "face", "skin", "hair") VALUES ($1, $2, $3, $4, $5)"#)
+                .bind(&character.name)
+                .bind(look.body)
+                .bind(look.face)
+                .bind(look.skin)
+                .bind(look.hair)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_equipment" ("name", "weapon", "shield", "clothing", "headgear") VALUES ($1, $2, $3, $4, $5)"#)
+                .bind(&character.name)
+                .bind(equipment.weapon)
+                .bind(equipment.shield)
+                .bind(equipment.clothing)
+                .bind(equipment.headgear)
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_vaults" ("name", "value") VALUES ($1, $2)"#)
+                .bind(&character.name)
+                .bind(Vec::<u8>::new())
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_skills" ("name", "value") VALUES ($1, $2)"#)
+                .bind(&character.name)
+                .bind(Vec::<u8>::new())
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "character_spellbooks" ("name", "value") VALUES ($1, $2)"#)
+                .bind(&character.name)
+                .bind(Vec::<u8>::new())
+                .execute(&mut *transaction)
+                .await?;
+
+            sqlx::query(r#"INSERT INTO "account_characters" ("account_name", "character_name") VALUES ($1, $2)"#)
+                .bind(account_name)
+                .bind(&character.name)
+                .execute(&mut *transaction)
+                .await?;
+
+            transaction.commit().await?;
+
+            Ok(Character {
+                name: character.name.to_string(),
+                description: String::new(),
+                level: 1,
+                exp: 0,
+                class_id: character.class_id,
+                race_id: character.race_id,
+                gender_id: character.gender_id,
+                created_at: DateTime::<Utc>::MIN_UTC,
+                inventory: vec![],
+                spellbook: vec![],
+                vault: vec![],
+                skills: vec![],
+                attributes: attributes.clone(),
+                equipment: equipment.clone(),
+                look: look.clone(),
+                gold: 0,
+                map: character.map,
+                x: character.x,
+                y: character.y,
+                guild: None,
+                stats: statistics.clone(),
+            })
+        })
+    }
 
-        sqlx::query(r#"INSERT INTO "character_inventories" ("name", "value") VALUES ($1, $2)"#)
-            .bind(&character.name)
-            .bind(vec![])
-            .execute(&mut *transaction)
-            .await?;
+    /// Updates gold, inventory and vault of every character in a single transaction
+    pub async fn update_characters_items(&self, characters: &[CharacterItems]) -> Result<()> {
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
 
-        sqlx::query(r#"INSERT INTO "character_attributes" ("name", "strength", "agility", "intelligence", "charisma", "constitution") VALUES ($1, $2, $3, $4, $5, $6)"#)
-            .bind(&character.name)
-            .bind(attributes.strength)
-            .bind(attributes.agility)
-            .bind(attributes.intelligence)
-            .bind(attributes.charisma)
-            .bind(attributes.constitution)
-            .execute(&mut *transaction)
-            .await?;
+            let mut transaction = conn.begin().await?;
 
-        sqlx::query(r#"INSERT INTO "character_statistics" ("name", "health", "mana", "stamina", "max_health", "max_mana", "max_stamina") VALUES ($1, $2, $3, $4, $5, $6, $7)"#)
-            .bind(&character.name)
-            .bind(statistics.health)
-            .bind(statistics.mana)
-            .bind(statistics.stamina)
-            .bind(statistics.max_health)
-            .bind(statistics.max_mana)
-            .bind(statistics.max_stamina)
-            .execute(&mut *transaction)
-            .await?;
+            for items in characters {
+                sqlx::query(r#"UPDATE "characters" SET "gold" = $1 WHERE "name" = $2"#)
+                    .bind(items.gold)
+                    .bind(&items.name)
+                    .execute(&mut *transaction)
+                    .await?;
 
-        sqlx::query(r#"INSERT INTO "character_look" ("name", "body", "face", "skin", "hair") VALUES ($1, $2, $3, $4, $5)"#)
-            .bind(&character.name)
-            .bind(look.body)
-            .bind(look.face)
-            .bind(look.skin)
-            .bind(look.hair)
-            .execute(&mut *transaction)
-            .await?;
+                sqlx::query(r#"UPDATE "character_inventories" SET "value" = $1 WHERE "name" = $2"#)
+                    .bind(&items.inventory)
+                    .bind(&items.name)
+                    .execute(&mut *transaction)
+                    .await?;
 
-        sqlx::query(r#"INSERT INTO "character_equipment" ("name", "weapon", "shield", "clothing", "headgear") VALUES ($1, $2, $3, $4, $5)"#)
-            .bind(&character.name)
-            .bind(equipment.weapon)
-            .bind(equipment.shield)
-            .bind(equipment.clothing)
-            .bind(equipment.headgear)
-            .execute(&mut *transaction)
-            .await?;
+                sqlx::query(r#"UPDATE "character_vaults" SET "value" = $1 WHERE "name" = $2"#)
+                    .bind(&items.vault)
+                    .bind(&items.name)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
 
-        sqlx::query(r#"INSERT INTO "character_vaults" ("name", "value") VALUES ($1, $2)"#)
-            .bind(&character.name)
-            .bind(vec![])
-            .execute(&mut *transaction)
-            .await?;
+            transaction.commit().await?;
 
-        sqlx::query(r#"INSERT INTO "character_skills" ("name", "value") VALUES ($1, $2)"#)
-            .bind(&character.name)
-            .bind(vec![])
-            .execute(&mut *transaction)
-            .await?;
+            Ok(())
+        })
+    }
 
-        sqlx::query(r#"INSERT INTO "character_spellbooks" ("name", "value") VALUES ($1, $2)"#)
-            .bind(&character.name)
-            .bind(vec![])
-            .execute(&mut *transaction)
-            .await?;
+    /// Stores the sanction and its audit log entry in a single transaction
+    pub async fn add_sanction(&self, sanction: &CreateSanction) -> Result<Sanction> {
+        with_pool!(self, |pool| {
+            let expires_at = match sanction.duration.map(expiration) {
+                Some(None) => bail!("sanction duration too long"),
+                expires_at => expires_at.flatten(),
+            };
+
+            let mut conn = pool.acquire().await?;
+
+            let mut transaction = conn.begin().await?;
+            let stored = sqlx::query_as::<_, Sanction>(r#"INSERT INTO "sanctions" ("kind", "account_name", "ip", "reason", "moderator", "expires_at") VALUES ($1, $2, $3, $4, $5, $6) RETURNING *;"#)
+                .bind(sanction.kind)
+                .bind(&sanction.account_name)
+                .bind(&sanction.ip)
+                .bind(&sanction.reason)
+                .bind(&sanction.moderator)
+                .bind(expires_at)
+                .fetch_one(&mut *transaction)
+                .await?;
+
+            let target = sanction
+                .account_name
+                .as_deref()
+                .or(sanction.ip.as_deref())
+                .unwrap_or_default();
+            sqlx::query(r#"INSERT INTO "audit_log" ("moderator", "action", "target", "reason") VALUES ($1, $2, $3, $4);"#)
+                .bind(&sanction.moderator)
+                .bind(sanction.kind.as_str())
+                .bind(target)
+                .bind(&sanction.reason)
+                .execute(&mut *transaction)
+                .await?;
+
+            transaction.commit().await?;
+
+            Ok(stored)
+        })
+    }
+
+    /// Revokes the active sanctions of that kind against the account, returns how many were
+    /// The account name is matched ignoring case, like the server matches the online users
+    pub async fn revoke_sanctions(
+        &self,
+        kind: SanctionKind,
+        account_name: &str,
+        moderator: &str,
+        reason: &str,
+    ) -> Result<u64> {
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+
+            let mut transaction = conn.begin().await?;
+
+            let revoked = sqlx::query(r#"UPDATE "sanctions" SET "revoked_at" = $1 WHERE "kind" = $2 AND LOWER("account_name") = LOWER($3) AND "revoked_at" IS NULL"#)
+                .bind(Utc::now())
+                .bind(kind)
+                .bind(account_name)
+                .execute(&mut *transaction)
+                .await?
+                .rows_affected();
+
+            sqlx::query(r#"INSERT INTO "audit_log" ("moderator", "action", "target", "reason") VALUES ($1, $2, $3, $4);"#)
+                .bind(moderator)
+                .bind(format!("revoke {}", kind.as_str()))
+                .bind(account_name)
+                .bind(reason)
+                .execute(&mut *transaction)
+                .await?;
+
+            transaction.commit().await?;
+
+            Ok(revoked)
+        })
+    }
 
-        sqlx::query(r#"INSERT INTO "account_characters" ("account_name", "character_name") VALUES ($1, $2)"#)
+    /// Sanctions in effect for the account or the ip
+    pub async fn active_sanctions(
+        &self,
+        account_name: &str,
+        ip: Option<&str>,
+    ) -> Result<Vec<Sanction>> {
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            let sanctions = sqlx::query_as::<_, Sanction>(
+                r#"
+                SELECT * FROM sanctions
+                WHERE (account_name = $1 OR (ip IS NOT NULL AND ip = $2))
+                    AND revoked_at IS NULL
+                    AND (expires_at IS NULL OR expires_at > $3);
+                "#,
+            )
             .bind(account_name)
-            .bind(&character.name)
-            .execute(&mut *transaction)
+            .bind(ip)
+            .bind(Utc::now())
+            .fetch_all(&mut *conn)
+            .await?;
+
+            Ok(sanctions)
+        })
+    }
+
+    /// Records a moderation action that is not a sanction, like a kick
+    pub async fn audit(
+        &self,
+        moderator: &str,
+        action: &str,
+        target: &str,
+        reason: &str,
+    ) -> Result<()> {
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            sqlx::query(r#"INSERT INTO "audit_log" ("moderator", "action", "target", "reason") VALUES ($1, $2, $3, $4);"#)
+                .bind(moderator)
+                .bind(action)
+                .bind(target)
+                .bind(reason)
+                .execute(&mut *conn)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    /// Latest entries of the audit log, newest first
+    pub async fn audit_log(&self, limit: i64) -> Result<Vec<AuditEntry>> {
+        with_pool!(self, |pool| {
+            let mut conn = pool.acquire().await?;
+            let entries = sqlx::query_as::<_, AuditEntry>(
+                r#"SELECT * FROM audit_log ORDER BY id DESC LIMIT $1"#,
+            )
+            .bind(limit)
+            .fetch_all(&mut *conn)
             .await?;
 
-        transaction.commit().await?;
-
-        Ok(Character {
-            name: character.name.to_string(),
-            description: String::new(),
-            level: 1,
-            exp: 0,
-            class_id: character.class_id,
-            race_id: character.race_id,
-            gender_id: character.gender_id,
-            created_at: DateTime::<Utc>::MIN_UTC,
-            inventory: vec![],
-            spellbook: vec![],
-            vault: vec![],
-            skills: vec![],
-            attributes: attributes.clone(),
-            equipment: equipment.clone(),
-            look: look.clone(),
-            gold: 0,
-            map: character.map,
-            x: character.x,
-            y: character.y,
-            stats: statistics.clone(),
+            Ok(entries)
         })
     }
 }
+
+/// When a sanction of that duration expires, `None` when it can't be represented
+fn expiration(duration: std::time::Duration) -> Option<DateTime<Utc>> {
+    let seconds = i64::try_from(duration.as_secs()).ok()?;
+    DateTime::from_timestamp(Utc::now().timestamp().checked_add(seconds)?, 0)
+}
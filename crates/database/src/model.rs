@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use sqlx::types::chrono::{DateTime, Utc};
 
 pub struct CreateAccount<'s> {
@@ -14,6 +16,8 @@ pub struct Account {
     pub password: String,
     pub pin: i32,
     pub created_at: DateTime<Utc>,
+    /// 0 player, 1 counsellor, 2 game master, 3 admin
+    pub privileges: i32,
 }
 
 #[derive(sqlx::FromRow, Debug)]
@@ -38,6 +42,15 @@ pub struct CreateCharacter {
     pub equipment: Equipment,
 }
 
+/// Everything that changes when items or gold move around, saved together
+#[derive(Clone, Debug)]
+pub struct CharacterItems {
+    pub name: String,
+    pub gold: i64,
+    pub inventory: Vec<u8>,
+    pub vault: Vec<u8>,
+}
+
 #[derive(sqlx::FromRow, Debug)]
 pub struct CharacterPreview {
     pub name: String,
@@ -47,10 +60,11 @@ pub struct CharacterPreview {
     pub class_id: i32,
     pub race_id: i32,
     pub gender_id: i32,
-    pub gold: i32,
+    pub gold: i64,
     pub map: i32,
     pub x: i32,
     pub y: i32,
+    pub guild: Option<String>,
 
     #[sqlx(flatten)]
     pub look: Look,
@@ -73,6 +87,7 @@ pub struct Character {
     pub map: i32,
     pub x: i32,
     pub y: i32,
+    pub guild: Option<String>,
 
     pub inventory: Vec<u8>,
     pub spellbook: Vec<u8>,
@@ -128,3 +143,66 @@ pub struct Statistics {
     pub max_mana: i32,
     pub max_stamina: i32,
 }
+
+#[derive(sqlx::Type, Debug, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "text", rename_all = "lowercase")]
+pub enum SanctionKind {
+    Ban,
+    Mute,
+    Jail,
+    Warning,
+}
+
+impl SanctionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SanctionKind::Ban => "ban",
+            SanctionKind::Mute => "mute",
+            SanctionKind::Jail => "jail",
+            SanctionKind::Warning => "warning",
+        }
+    }
+}
+
+/// Sanction against an account or an ip, revoked sanctions are kept
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Sanction {
+    pub id: i64,
+    pub kind: SanctionKind,
+    pub account_name: Option<String>,
+    pub ip: Option<String>,
+    pub reason: String,
+    pub moderator: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl Sanction {
+    /// Time left until the sanction expires, `None` if it's permanent
+    pub fn remaining(&self) -> Option<Duration> {
+        let expires_at = self.expires_at?;
+        Some((expires_at - Utc::now()).to_std().unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateSanction {
+    pub kind: SanctionKind,
+    pub account_name: Option<String>,
+    pub ip: Option<String>,
+    pub reason: String,
+    pub moderator: String,
+    /// permanent when not present
+    pub duration: Option<Duration>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub moderator: String,
+    pub action: String,
+    pub target: String,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -1,7 +1,7 @@
 use shared::character;
 use shared::protocol::ProtocolMessage;
 use shared::{
-    character::{Class, Inventory, Race, Skills, Stat, Stats},
+    character::{Class, Inventory, Race, Skills, Spellbook, Stat, Stats, Vault},
     world::WorldPosition,
 };
 
@@ -23,6 +23,7 @@ impl From<model::Character> for character::Character {
                 x: character.x as u16,
                 y: character.y as u16,
             },
+            guild: character.guild,
             class: Class::from(character.class_id as usize).unwrap(),
             race: Race::from(character.race_id as usize).unwrap(),
             look: character.look.into(),
@@ -44,6 +45,8 @@ impl From<model::Character> for character::Character {
                 },
             },
             inventory: Inventory::decode(&character.inventory).unwrap_or_default(),
+            vault: Vault::decode(&character.vault).unwrap_or_default(),
+            spellbook: Spellbook::decode(&character.spellbook).unwrap_or_default(),
         }
     }
 }
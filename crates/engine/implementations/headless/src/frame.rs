@@ -0,0 +1,71 @@
+use engine::{
+    draw::{image::DrawImage, light::DrawLight, Color, Position, Target},
+    engine::{FontID, SoundID, TextureID},
+    sound::{Bus, Spatial},
+};
+
+/// Everything the game asked to draw or play during a frame, in call order
+#[derive(Debug, Default, Clone)]
+pub struct Frame {
+    pub images: Vec<RecordedImage>,
+    pub texts: Vec<RecordedText>,
+    pub sounds: Vec<RecordedSound>,
+    pub lights: Vec<DrawLight>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordedImage {
+    pub target: Target,
+    pub image: DrawImage,
+}
+
+#[derive(Debug, Clone)]
+pub struct RecordedText {
+    pub target: Target,
+    pub font: FontID,
+    pub text: String,
+    pub position: Position,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordedSound {
+    pub id: SoundID,
+    pub bus: Bus,
+    pub volume: u8,
+    /// Gain and panning heard from the world camera
+    pub spatial: Spatial,
+}
+
+impl Frame {
+    pub fn images_in(&self, target: Target) -> impl Iterator<Item = &DrawImage> {
+        self.images
+            .iter()
+            .filter(move |image| image.target == target)
+            .map(|image| &image.image)
+    }
+
+    /// Draws of a texture in any target
+    pub fn images_of(&self, texture_id: TextureID) -> impl Iterator<Item = &RecordedImage> {
+        self.images
+            .iter()
+            .filter(move |image| image.image.index == texture_id)
+    }
+
+    pub fn texts_in(&self, target: Target) -> impl Iterator<Item = &RecordedText> {
+        self.texts.iter().filter(move |text| text.target == target)
+    }
+
+    /// First text drawn that contains `text`
+    pub fn text(&self, text: &str) -> Option<&RecordedText> {
+        self.texts.iter().find(|drawn| drawn.text.contains(text))
+    }
+
+    pub fn contains_text(&self, text: &str) -> bool {
+        self.text(text).is_some()
+    }
+
+    pub fn sounds_of(&self, id: SoundID) -> impl Iterator<Item = &RecordedSound> {
+        self.sounds.iter().filter(move |sound| sound.id == id)
+    }
+}
@@ -0,0 +1,156 @@
+use engine::input::keyboard::{Key, KeyCode, NamedKey};
+
+/// Input applied at the start of a frame
+#[derive(Debug, Clone)]
+pub enum Input {
+    /// The key goes down, it's held until released
+    Press(KeyCode),
+    Release(KeyCode),
+    /// Key repeat of a held key, like keeping a key down in a text field
+    Repeat(KeyCode),
+    /// Presses and releases the chars of the text in the same frame
+    Type(String),
+    /// Position with the origin at the bottom left corner, like `mouse_position`
+    MouseMove {
+        x: f32,
+        y: f32,
+    },
+    MouseDown,
+    MouseUp,
+    SecondaryClick,
+}
+
+/// Keyboard and mouse state, the actions only last one frame
+#[derive(Default)]
+pub struct InputState {
+    pub pressed: Vec<(Option<KeyCode>, Key)>,
+    pub released: Vec<(Option<KeyCode>, Key)>,
+    pub repeated: Vec<(KeyCode, Key)>,
+    pub held: Vec<(KeyCode, Key)>,
+
+    pub mouse_position: (f32, f32),
+    pub mouse_clicked: bool,
+    pub mouse_released: bool,
+    pub mouse_held: bool,
+    pub mouse_secondary_clicked: bool,
+}
+
+impl InputState {
+    pub fn apply(&mut self, input: Input) {
+        match input {
+            Input::Press(code) => {
+                let key = logical_key(code);
+                if !self.held.iter().any(|(held, _)| *held == code) {
+                    self.held.push((code, key.clone()));
+                }
+                self.pressed.push((Some(code), key));
+            }
+            Input::Release(code) => {
+                self.held.retain(|(held, _)| *held != code);
+                self.released.push((Some(code), logical_key(code)));
+            }
+            Input::Repeat(code) => self.repeated.push((code, logical_key(code))),
+            Input::Type(text) => {
+                for char in text.chars() {
+                    let key = Key::Character(char.to_string().into());
+                    self.pressed.push((None, key.clone()));
+                    self.released.push((None, key));
+                }
+            }
+            Input::MouseMove { x, y } => self.mouse_position = (x, y),
+            Input::MouseDown => {
+                self.mouse_clicked = true;
+                self.mouse_held = true;
+            }
+            Input::MouseUp => {
+                self.mouse_released = true;
+                self.mouse_held = false;
+            }
+            Input::SecondaryClick => self.mouse_secondary_clicked = true,
+        }
+    }
+
+    /// Forgets the actions of the frame, held keys and buttons stay
+    pub fn step(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.repeated.clear();
+        self.mouse_clicked = false;
+        self.mouse_released = false;
+        self.mouse_secondary_clicked = false;
+    }
+}
+
+/// Key seen by text inputs for a physical key, with a us layout and no modifiers
+fn logical_key(code: KeyCode) -> Key {
+    let named = match code {
+        KeyCode::Enter | KeyCode::NumpadEnter => NamedKey::Enter,
+        KeyCode::Tab => NamedKey::Tab,
+        KeyCode::Backspace => NamedKey::Backspace,
+        KeyCode::Delete => NamedKey::Delete,
+        KeyCode::Escape => NamedKey::Escape,
+        KeyCode::Space => NamedKey::Space,
+        KeyCode::ArrowUp => NamedKey::ArrowUp,
+        KeyCode::ArrowDown => NamedKey::ArrowDown,
+        KeyCode::ArrowLeft => NamedKey::ArrowLeft,
+        KeyCode::ArrowRight => NamedKey::ArrowRight,
+        KeyCode::ShiftLeft | KeyCode::ShiftRight => NamedKey::Shift,
+        KeyCode::ControlLeft | KeyCode::ControlRight => NamedKey::Control,
+        KeyCode::AltLeft | KeyCode::AltRight => NamedKey::Alt,
+        code => {
+            return match character(code) {
+                Some(char) => Key::Character(char.to_string().into()),
+                None => Key::Unidentified(winit::keyboard::NativeKey::Unidentified),
+            }
+        }
+    };
+    Key::Named(named)
+}
+
+fn character(code: KeyCode) -> Option<char> {
+    let char = match code {
+        KeyCode::KeyA => 'a',
+        KeyCode::KeyB => 'b',
+        KeyCode::KeyC => 'c',
+        KeyCode::KeyD => 'd',
+        KeyCode::KeyE => 'e',
+        KeyCode::KeyF => 'f',
+        KeyCode::KeyG => 'g',
+        KeyCode::KeyH => 'h',
+        KeyCode::KeyI => 'i',
+        KeyCode::KeyJ => 'j',
+        KeyCode::KeyK => 'k',
+        KeyCode::KeyL => 'l',
+        KeyCode::KeyM => 'm',
+        KeyCode::KeyN => 'n',
+        KeyCode::KeyO => 'o',
+        KeyCode::KeyP => 'p',
+        KeyCode::KeyQ => 'q',
+        KeyCode::KeyR => 'r',
+        KeyCode::KeyS => 's',
+        KeyCode::KeyT => 't',
+        KeyCode::KeyU => 'u',
+        KeyCode::KeyV => 'v',
+        KeyCode::KeyW => 'w',
+        KeyCode::KeyX => 'x',
+        KeyCode::KeyY => 'y',
+        KeyCode::KeyZ => 'z',
+        KeyCode::Digit0 | KeyCode::Numpad0 => '0',
+        KeyCode::Digit1 | KeyCode::Numpad1 => '1',
+        KeyCode::Digit2 | KeyCode::Numpad2 => '2',
+        KeyCode::Digit3 | KeyCode::Numpad3 => '3',
+        KeyCode::Digit4 | KeyCode::Numpad4 => '4',
+        KeyCode::Digit5 | KeyCode::Numpad5 => '5',
+        KeyCode::Digit6 | KeyCode::Numpad6 => '6',
+        KeyCode::Digit7 | KeyCode::Numpad7 => '7',
+        KeyCode::Digit8 | KeyCode::Numpad8 => '8',
+        KeyCode::Digit9 | KeyCode::Numpad9 => '9',
+        KeyCode::Minus => '-',
+        KeyCode::Equal => '=',
+        KeyCode::Comma => ',',
+        KeyCode::Period => '.',
+        KeyCode::Slash => '/',
+        _ => return None,
+    };
+    Some(char)
+}
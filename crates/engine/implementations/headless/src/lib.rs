@@ -0,0 +1,450 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use engine::{
+    camera::{Position, Viewport, Zoom},
+    draw::{
+        light::DrawLight,
+        text::{DrawText, ParsedText},
+        Color, Dimensions, Target,
+    },
+    engine::{FontID, GameEngine, SoundID, TextureID},
+    game::Game,
+    input::keyboard::{Key, KeyCode},
+    settings::Settings,
+    sound::{Bus, Hearing, Spatial},
+    window::Size,
+};
+use nohash_hasher::{IntMap, IntSet};
+use winit::window::CursorIcon;
+
+pub use frame::{Frame, RecordedImage, RecordedSound, RecordedText};
+pub use input::Input;
+
+mod frame;
+mod input;
+
+/// Width of every glyph, text is laid out without reading the font files
+pub const GLYPH_WIDTH: u16 = 7;
+pub const GLYPH_HEIGHT: u16 = 13;
+
+/// Delta of each frame unless `set_delta` is called
+const FRAME_DELTA: Duration = Duration::from_millis(16);
+
+/// Engine without window nor GPU, it records what is drawn and takes scripted input
+pub struct Headless {
+    size: Size,
+    delta: Duration,
+
+    world_viewport: Viewport,
+    ui_viewport: Viewport,
+    zoom: Zoom,
+    world_position: Position,
+
+    textures: IntMap<TextureID, Option<(u16, u16)>>,
+    next_texture_id: TextureID,
+    /// Highest priority each texture was prefetched with
+    prefetched: IntMap<TextureID, u8>,
+    fonts: IntSet<FontID>,
+    next_sound_id: SoundID,
+    music: Option<SoundID>,
+    hearing: Hearing,
+    volumes: HashMap<Bus, u8>,
+    cursor: CursorIcon,
+    ambient_light: Color,
+
+    input: input::InputState,
+    script: VecDeque<Vec<Input>>,
+
+    drawing: Frame,
+    frame: Frame,
+    frames_rendered: usize,
+}
+
+impl Headless {
+    pub fn new(settings: &Settings) -> Self {
+        let size = Size {
+            width: settings.width as u16,
+            height: settings.height as u16,
+        };
+        let viewport = Viewport {
+            x: 0.,
+            y: 0.,
+            width: size.width as f32,
+            height: size.height as f32,
+        };
+        Self {
+            size,
+            delta: FRAME_DELTA,
+
+            world_viewport: viewport,
+            ui_viewport: viewport,
+            zoom: Zoom::None,
+            world_position: Position { x: 0., y: 0. },
+
+            textures: IntMap::default(),
+            // ids set by the game usually start low, the ones added start after them
+            next_texture_id: 1_000_000,
+            prefetched: IntMap::default(),
+            fonts: IntSet::default(),
+            next_sound_id: 1_000_000,
+            music: None,
+            hearing: settings.hearing,
+            volumes: HashMap::new(),
+            cursor: CursorIcon::Default,
+            ambient_light: [255, 255, 255, 255],
+
+            input: input::InputState::default(),
+            script: VecDeque::new(),
+
+            drawing: Frame::default(),
+            frame: Frame::default(),
+            frames_rendered: 0,
+        }
+    }
+
+    /// Queues input for the next frames, one entry per frame
+    pub fn script(&mut self, frames: impl IntoIterator<Item = Vec<Input>>) {
+        self.script.extend(frames);
+    }
+
+    /// Applies input to the next frame, together with the scripted one
+    pub fn apply(&mut self, input: Input) {
+        self.input.apply(input);
+    }
+
+    /// Runs a frame like the window event loop does
+    pub fn tick<G: Game>(&mut self, game: &mut G) {
+        if let Some(inputs) = self.script.pop_front() {
+            for input in inputs {
+                self.input.apply(input);
+            }
+        }
+        game.tick(self);
+        self.render();
+        self.finish();
+        self.input.step();
+    }
+
+    pub fn run<G: Game>(&mut self, game: &mut G, frames: usize) {
+        for _ in 0..frames {
+            self.tick(game);
+        }
+    }
+
+    /// Last rendered frame
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    pub fn frames_rendered(&self) -> usize {
+        self.frames_rendered
+    }
+
+    pub fn cursor(&self) -> CursorIcon {
+        self.cursor
+    }
+
+    pub fn music(&self) -> Option<SoundID> {
+        self.music
+    }
+
+    pub fn ambient_light(&self) -> Color {
+        self.ambient_light
+    }
+
+    pub fn volume(&self, bus: Bus) -> u8 {
+        self.volumes.get(&bus).copied().unwrap_or(u8::MAX)
+    }
+
+    /// Priority a texture was prefetched with, textures load right away
+    pub fn prefetch_priority(&self, texture_id: TextureID) -> Option<u8> {
+        self.prefetched.get(&texture_id).copied()
+    }
+
+    /// Dimensions reported by `texture_dimensions`, for textures whose file is not available
+    pub fn set_texture_dimensions(&mut self, texture_id: TextureID, dimensions: (u16, u16)) {
+        self.textures.insert(texture_id, Some(dimensions));
+    }
+}
+
+impl GameEngine for Headless {
+    /// The window is not used, `Headless::new` doesn't need one
+    fn initialize(_window: winit::window::Window, settings: &Settings) -> Self {
+        Self::new(settings)
+    }
+
+    fn handle_event(&mut self, _event: &winit::event::Event<()>) {}
+
+    fn key_pressed(&self, key: KeyCode) -> bool {
+        self.input
+            .pressed
+            .iter()
+            .any(|(code, _)| *code == Some(key))
+    }
+
+    fn key_released(&self, key: KeyCode) -> bool {
+        self.input
+            .released
+            .iter()
+            .any(|(code, _)| *code == Some(key))
+    }
+
+    fn key_held(&self, key: KeyCode) -> bool {
+        self.input.held.iter().any(|(code, _)| *code == key)
+    }
+
+    fn held_keys(&self) -> Vec<Key> {
+        self.input
+            .repeated
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    fn pressed_keys(&self) -> Vec<Key> {
+        self.input
+            .pressed
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    fn released_keys(&self) -> Vec<Key> {
+        self.input
+            .released
+            .iter()
+            .map(|(_, key)| key.clone())
+            .collect()
+    }
+
+    fn mouse_position(&self) -> engine::input::mouse::Position {
+        let (x, y) = self.input.mouse_position;
+        engine::input::mouse::Position { x, y }
+    }
+
+    fn mouse_clicked(&self) -> bool {
+        self.input.mouse_clicked
+    }
+
+    fn mouse_held(&self) -> bool {
+        self.input.mouse_held
+    }
+
+    fn mouse_released(&self) -> bool {
+        self.input.mouse_released
+    }
+
+    fn mouse_secondary_clicked(&self) -> bool {
+        self.input.mouse_secondary_clicked
+    }
+
+    fn set_mouse_cursor(&mut self, cursor: CursorIcon) {
+        self.cursor = cursor;
+    }
+
+    fn add_texture(&mut self, path: &str) -> TextureID {
+        let id = self.next_texture_id;
+        self.next_texture_id += 1;
+        self.set_texture(path, id);
+        id
+    }
+
+    fn set_texture(&mut self, path: &str, id: TextureID) {
+        // only the header is read, missing files are fine
+        let dimensions = image::image_dimensions(path)
+            .ok()
+            .map(|(width, height)| (width as u16, height as u16));
+        self.textures.insert(id, dimensions);
+    }
+
+    fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)> {
+        self.textures.get(&texture_id).copied().flatten()
+    }
+
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8) {
+        for id in ids {
+            let prefetched = self.prefetched.entry(*id).or_default();
+            *prefetched = (*prefetched).max(priority);
+        }
+    }
+
+    fn textures_loading(&self) -> usize {
+        0
+    }
+
+    fn create_texture(&mut self, dimensions: Dimensions) -> TextureID {
+        let id = self.next_texture_id;
+        self.next_texture_id += 1;
+        self.set_texture_dimensions(id, (dimensions.width, dimensions.height));
+        id
+    }
+
+    fn draw_image(&mut self, parameters: engine::draw::image::DrawImage, target: Target) {
+        self.drawing.images.push(RecordedImage {
+            target,
+            image: parameters,
+        });
+    }
+
+    fn draw_light(&mut self, parameters: DrawLight) {
+        self.drawing.lights.push(parameters);
+    }
+
+    fn set_ambient_light(&mut self, color: Color) {
+        self.ambient_light = color;
+    }
+
+    fn add_font(&mut self, id: FontID, _path: &str, _texture_id: TextureID) {
+        self.fonts.insert(id);
+    }
+
+    fn draw_text(&mut self, id: FontID, parameters: DrawText, target: Target) {
+        // `parse_text` keeps each char in the page x of its glyph
+        let text = parameters
+            .text
+            .chars
+            .iter()
+            .filter_map(|char| char::from_u32(char.page_rect.x as u32))
+            .collect();
+        self.drawing.texts.push(RecordedText {
+            target,
+            font: id,
+            text,
+            position: parameters.position,
+            color: parameters.color,
+        });
+    }
+
+    fn parse_text(&mut self, id: FontID, text: &str) -> Option<ParsedText> {
+        if !self.fonts.contains(&id) {
+            return None;
+        }
+        let chars = text
+            .chars()
+            .enumerate()
+            .map(|(index, char)| bmfont::CharPosition {
+                page_rect: bmfont::Rect {
+                    x: char as i32,
+                    y: 0,
+                    width: GLYPH_WIDTH as u32,
+                    height: GLYPH_HEIGHT as u32,
+                },
+                screen_rect: bmfont::Rect {
+                    x: (index as u16 * GLYPH_WIDTH) as i32,
+                    y: 0,
+                    width: GLYPH_WIDTH as u32,
+                    height: GLYPH_HEIGHT as u32,
+                },
+                page_index: 0,
+            })
+            .collect::<Vec<_>>();
+        Some(ParsedText {
+            total_width: chars.len() as u16 * GLYPH_WIDTH,
+            height: if chars.is_empty() { 0 } else { GLYPH_HEIGHT },
+            chars,
+        })
+    }
+
+    fn add_sound(&mut self, path: &str) -> SoundID {
+        let id = self.next_sound_id;
+        self.next_sound_id += 1;
+        self.set_sound(path, id);
+        id
+    }
+
+    fn set_sound(&mut self, _path: &str, _id: SoundID) {}
+
+    /// Sounds out of the hearing range are not recorded
+    fn play_sound(&mut self, id: SoundID, parameters: engine::sound::PlaySound) {
+        let spatial = match parameters.position {
+            Some(position) => match self.hearing.spatialize(self.world_position, position) {
+                Some(spatial) => spatial,
+                None => return,
+            },
+            None => Spatial::CENTER,
+        };
+        self.drawing.sounds.push(RecordedSound {
+            id,
+            bus: parameters.bus,
+            volume: parameters.volume,
+            spatial,
+        });
+    }
+
+    fn play_music(&mut self, id: SoundID, _parameters: engine::sound::PlayMusic) {
+        self.music = Some(id);
+    }
+
+    fn stop_music(&mut self) {
+        self.music = None;
+    }
+
+    fn set_volume(&mut self, bus: Bus, volume: u8) {
+        self.volumes.insert(bus, volume);
+    }
+
+    fn stop_sounds(&mut self, bus: Bus) {
+        if bus == Bus::Music {
+            self.music = None;
+        }
+    }
+
+    fn get_world_camera_viewport(&self) -> Viewport {
+        self.world_viewport
+    }
+
+    fn set_world_camera_viewport(&mut self, viewport: Viewport) {
+        self.world_viewport = viewport;
+    }
+
+    fn get_camera_zoom(&self) -> Zoom {
+        self.zoom
+    }
+
+    fn set_camera_zoom(&mut self, zoom: Zoom) {
+        self.zoom = zoom;
+    }
+
+    fn get_world_camera_position(&self) -> Position {
+        self.world_position
+    }
+
+    fn set_world_camera_position(&mut self, position: Position) {
+        self.world_position = position;
+    }
+
+    fn get_ui_camera_viewport(&self) -> Viewport {
+        self.ui_viewport
+    }
+
+    fn set_ui_camera_viewport(&mut self, viewport: Viewport) {
+        self.ui_viewport = viewport;
+    }
+
+    fn set_delta(&mut self, delta: Duration) {
+        self.delta = delta;
+    }
+
+    fn get_delta(&self) -> Duration {
+        self.delta
+    }
+
+    fn get_window_size(&self) -> Size {
+        self.size
+    }
+
+    fn set_window_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    /// The frame drawn so far becomes the recorded one
+    fn render(&mut self) {
+        self.frame = std::mem::take(&mut self.drawing);
+        self.frames_rendered += 1;
+    }
+
+    fn finish(&self) {}
+}
@@ -0,0 +1,144 @@
+use engine::{
+    camera,
+    draw::{image::DrawImage, text::DrawText, Position, Target},
+    engine::GameEngine,
+    game::Game,
+    input::keyboard::{Key, KeyCode},
+    settings::Settings,
+    sound::{Bus, PlaySound},
+};
+use headless::{Headless, Input};
+
+const FONT: u32 = 1;
+const BUTTON: u32 = 2;
+
+/// Text field that is submitted with enter and a button that counts clicks
+#[derive(Default)]
+struct Form {
+    text: String,
+    submitted: Option<String>,
+    clicks: usize,
+}
+
+impl Game for Form {
+    fn initialize<E: GameEngine>(engine: &mut E) -> Self {
+        engine.add_font(FONT, "missing.fnt", 0);
+        Self::default()
+    }
+
+    fn tick<E: GameEngine>(&mut self, engine: &mut E) {
+        for key in engine.pressed_keys() {
+            if let Key::Character(char) = key {
+                self.text.push_str(&char);
+            }
+        }
+        if engine.key_pressed(KeyCode::Enter) {
+            self.submitted = Some(std::mem::take(&mut self.text));
+        }
+        let mouse = engine.mouse_position();
+        if engine.mouse_released() && mouse.x < 100. && mouse.y < 20. {
+            self.clicks += 1;
+        }
+
+        engine.draw_image(
+            DrawImage {
+                index: BUTTON,
+                ..Default::default()
+            },
+            Target::UI,
+        );
+        let label = self.submitted.as_deref().unwrap_or(&self.text).to_string();
+        let parsed = engine.parse_text(FONT, &label).unwrap();
+        engine.draw_text(
+            FONT,
+            DrawText {
+                text: &parsed,
+                position: Position::new(10, 10, 1.),
+                color: [255, 255, 255, 255],
+            },
+            Target::UI,
+        );
+    }
+}
+
+#[test]
+fn records_text_and_images_per_target() {
+    let mut engine = Headless::new(&Settings::default());
+    let mut form = Form::initialize(&mut engine);
+
+    engine.script([vec![Input::Type("admin".to_string())]]);
+    engine.tick(&mut form);
+
+    let frame = engine.frame();
+    assert!(frame.contains_text("admin"));
+    assert_eq!(frame.images_in(Target::UI).count(), 1);
+    assert_eq!(frame.images_in(Target::World).count(), 0);
+    assert_eq!(frame.images_of(BUTTON).count(), 1);
+    assert_eq!(frame.texts_in(Target::UI).count(), 1);
+}
+
+#[test]
+fn scripted_input_lasts_one_frame() {
+    let mut engine = Headless::new(&Settings::default());
+    let mut form = Form::initialize(&mut engine);
+
+    engine.script([
+        vec![Input::Type("hi".to_string())],
+        vec![Input::Press(KeyCode::Enter)],
+        vec![Input::Release(KeyCode::Enter)],
+        vec![Input::MouseMove { x: 50., y: 10. }, Input::MouseDown],
+        vec![Input::MouseUp],
+        vec![],
+    ]);
+    engine.run(&mut form, 6);
+
+    assert_eq!(engine.frames_rendered(), 6);
+    assert_eq!(form.submitted.as_deref(), Some("hi"));
+    assert_eq!(form.clicks, 1);
+    assert!(!engine.key_held(KeyCode::Enter));
+    assert!(!engine.mouse_held());
+}
+
+#[test]
+fn text_layout_is_monospaced() {
+    let mut engine = Headless::new(&Settings::default());
+    assert!(engine.parse_text(FONT, "hello").is_none());
+
+    engine.add_font(FONT, "missing.fnt", 0);
+    let parsed = engine.parse_text(FONT, "hello").unwrap();
+    assert_eq!(parsed.chars.len(), 5);
+    assert_eq!(parsed.total_width, 5 * headless::GLYPH_WIDTH);
+}
+
+#[test]
+fn positional_sounds_are_heard_from_the_world_camera() {
+    let mut engine = Headless::new(&Settings::default());
+    engine.set_world_camera_position(camera::Position { x: 320., y: 320. });
+    let play = |engine: &mut Headless, id, x| {
+        engine.play_sound(
+            id,
+            PlaySound {
+                times: 1,
+                volume: 255,
+                bus: Bus::Effects,
+                position: Some(camera::Position { x, y: 320. }),
+            },
+        );
+    };
+    play(&mut engine, 1, 320.);
+    // four tiles to the right
+    play(&mut engine, 2, 448.);
+    play(&mut engine, 3, 192.);
+    // out of the hearing range
+    play(&mut engine, 4, 320. + 17. * 32.);
+    engine.render();
+
+    let frame = engine.frame();
+    let sound = |id| frame.sounds_of(id).next().map(|sound| sound.spatial);
+    assert_eq!(sound(1).unwrap().gain, 1.);
+    assert_eq!(sound(1).unwrap().pan, 0.);
+    assert!(sound(2).unwrap().gain < 1.);
+    assert_eq!(sound(2).unwrap().pan, 0.5);
+    assert_eq!(sound(3).unwrap().pan, -0.5);
+    assert!(sound(4).is_none());
+}
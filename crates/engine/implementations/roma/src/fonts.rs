@@ -1,5 +1,8 @@
 use engine::{
-    draw::text::ParsedText,
+    draw::{
+        image::DrawImage,
+        text::{DrawText, ParsedText},
+    },
     engine::{FontID, TextureID},
 };
 use std::io::Cursor;
@@ -76,3 +79,36 @@ impl Fonts {
         self.fonts.get(&id).map(|font| font.texture_id)
     }
 }
+
+/// One draw per glyph, centered horizontally on the text position
+pub fn text_draws<'a>(
+    texture_id: TextureID,
+    parameters: &'a DrawText<'a>,
+) -> impl Iterator<Item = DrawImage> + 'a {
+    let offset_x = ((parameters.text.total_width as f32 / 2.).round() as u16).saturating_sub(1);
+
+    parameters.text.chars.iter().map(move |char| {
+        let mut position = parameters.position;
+
+        let x = char.screen_rect.x;
+        let y = char.screen_rect.y;
+        let source = [
+            char.page_rect.x as u16,
+            char.page_rect.y as u16,
+            char.screen_rect.width as u16,
+            char.screen_rect.height as u16,
+        ];
+
+        // glyphs can start before the pen position
+        position.x = (position.x as i32 + x - offset_x as i32) as u16;
+        position.y = (position.y as i32 + y) as u16;
+
+        DrawImage {
+            position,
+            source,
+            color: parameters.color,
+            index: texture_id,
+            ..Default::default()
+        }
+    })
+}
@@ -5,7 +5,7 @@ use std::{
 
 use camera::Camera;
 use engine::{
-    draw::{image::DrawImage, Target},
+    draw::Target,
     engine::{FontID, GameEngine, SoundID, TextureID},
 };
 use fonts::Fonts;
@@ -21,6 +21,7 @@ mod files;
 mod fonts;
 mod input;
 mod renderer;
+pub mod software;
 mod sounds;
 mod state;
 mod texture;
@@ -32,7 +33,7 @@ pub struct Roma {
     world_camera: Camera,
     ui_camera: Camera,
     fonts: Fonts,
-    _sounds: Sounds,
+    sounds: Sounds,
     renderer: Box<dyn Renderer>,
     input: WinitInputHelper,
 }
@@ -52,7 +53,7 @@ impl GameEngine for Roma {
         let world_camera = Camera::initialize(state.size, true);
         let ui_camera = Camera::initialize(state.size, false);
         let fonts = Fonts::initialize();
-        let _sounds = Sounds::initialize();
+        let sounds = Sounds::initialize(settings.hearing);
         let input = WinitInputHelper::new();
 
         Self {
@@ -62,7 +63,7 @@ impl GameEngine for Roma {
             world_camera,
             ui_camera,
             fonts,
-            _sounds,
+            sounds,
             renderer,
             input,
         }
@@ -142,13 +143,41 @@ impl GameEngine for Roma {
         self.renderer.texture_dimensions(texture_id)
     }
 
-    fn draw_image(&mut self, draw: engine::draw::image::DrawImage, target: engine::draw::Target) {
-        if self
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8) {
+        self.renderer.prefetch_textures(ids, priority);
+    }
+
+    fn textures_loading(&self) -> usize {
+        self.renderer.textures_loading()
+    }
+
+    fn draw_image(
+        &mut self,
+        mut draw: engine::draw::image::DrawImage,
+        target: engine::draw::Target,
+    ) {
+        let Some(index) = self
             .renderer
             .ensure_texture(&self.state, draw.index, target)
-        {
-            self.renderer.push_draw_image(draw, target);
+        else {
+            return;
+        };
+        if index != draw.index && draw.source == [0, 0, 0, 0] {
+            // the placeholder covers the size of the whole texture
+            if let Some((width, height)) = self.renderer.texture_dimensions(draw.index) {
+                draw.source = [0, 0, width, height];
+            }
         }
+        draw.index = index;
+        self.renderer.push_draw_image(draw, target);
+    }
+
+    fn draw_light(&mut self, parameters: engine::draw::light::DrawLight) {
+        self.renderer.push_light(parameters);
+    }
+
+    fn set_ambient_light(&mut self, color: engine::draw::Color) {
+        self.renderer.set_ambient_light(color);
     }
 
     fn add_font(&mut self, id: FontID, path: &str, texture_id: TextureID) {
@@ -164,61 +193,47 @@ impl GameEngine for Roma {
             log::error!("[draw_text] texture id for font {id} not found");
             return;
         };
-        if !self
+        // glyphs are not drawn with the placeholder
+        if self
             .renderer
             .ensure_texture(&self.state, texture_id, target)
+            != Some(texture_id)
         {
             return;
         }
 
-        let offset_x = (parameters.text.total_width as f32 / 2.).round() as u16 - 1;
-
-        for char in &parameters.text.chars {
-            let mut position = parameters.position;
-
-            let x = char.screen_rect.x;
-            let y = char.screen_rect.y;
-            let source = [
-                char.page_rect.x as u16,
-                char.page_rect.y as u16,
-                char.screen_rect.width as u16,
-                char.screen_rect.height as u16,
-            ];
-
-            position.x += x as u16;
-            position.x -= offset_x;
-            position.y += y as u16;
-
-            self.renderer.push_draw_image(
-                DrawImage {
-                    position,
-                    source,
-                    color: parameters.color,
-                    index: texture_id,
-                },
-                target,
-            );
+        for draw in fonts::text_draws(texture_id, &parameters) {
+            self.renderer.push_draw_image(draw, target);
         }
     }
 
-    fn add_sound(&mut self, _path: &str) -> SoundID {
-        todo!()
+    fn add_sound(&mut self, path: &str) -> SoundID {
+        self.sounds.add_sound(path)
     }
 
-    fn set_sound(&mut self, _path: &str, _id: SoundID) {
-        todo!()
+    fn set_sound(&mut self, path: &str, id: SoundID) {
+        self.sounds.set_sound(path, id);
     }
 
-    fn play_sound(&mut self, _id: SoundID, _parameters: engine::sound::PlaySound) {
-        todo!()
+    fn play_sound(&mut self, id: SoundID, parameters: engine::sound::PlaySound) {
+        let listener = self.world_camera.position;
+        self.sounds.play_sound(id, parameters, listener);
     }
 
-    fn play_music(&mut self, _id: SoundID, _parameters: engine::sound::PlayMusic) {
-        todo!()
+    fn play_music(&mut self, id: SoundID, parameters: engine::sound::PlayMusic) {
+        self.sounds.play_music(id, parameters);
     }
 
     fn stop_music(&mut self) {
-        todo!()
+        self.sounds.stop_music();
+    }
+
+    fn set_volume(&mut self, bus: engine::sound::Bus, volume: u8) {
+        self.sounds.set_volume(bus, volume);
+    }
+
+    fn stop_sounds(&mut self, bus: engine::sound::Bus) {
+        self.sounds.stop_sounds(bus);
     }
 
     fn get_world_camera_viewport(&self) -> engine::camera::Viewport {
@@ -285,6 +300,7 @@ impl GameEngine for Roma {
     }
 
     fn render(&mut self) {
+        self.sounds.update();
         self.renderer
             .render(&self.state, &self.world_camera, &self.ui_camera);
     }
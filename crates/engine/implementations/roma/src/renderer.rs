@@ -1,5 +1,9 @@
 use engine::{
-    draw::{image::DrawImage, Target},
+    draw::{
+        image::{Blend, DrawImage, Source},
+        light::DrawLight,
+        Color, Position, Target,
+    },
     engine::TextureID,
     window::Size,
 };
@@ -10,6 +14,9 @@ use self::{
     sprite_batch_renderer::SpriteBatchRenderer, texture_array_renderer::TextureArrayRenderer,
 };
 
+mod atlas;
+mod lighting;
+mod loader;
 mod sprite_batch_renderer;
 mod texture_array;
 mod texture_array_renderer;
@@ -24,12 +31,91 @@ pub trait Renderer {
     fn add_texture(&mut self, texture: Texture) -> TextureID;
     fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)>;
 
-    fn ensure_texture(&mut self, state: &State, id: TextureID, target: Target) -> bool;
+    /// Returns the texture to draw with, a placeholder while the texture is loading
+    fn ensure_texture(&mut self, state: &State, id: TextureID, target: Target)
+        -> Option<TextureID>;
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8);
+    fn textures_loading(&self) -> usize;
     fn push_draw_image(&mut self, draw: DrawImage, target: Target);
+    fn push_light(&mut self, light: DrawLight);
+    fn set_ambient_light(&mut self, color: Color);
 
     fn render(&mut self, state: &State, world_camera: &Camera, ui_camera: &Camera);
 }
 
+/// `DrawImage` as the shaders read it, the flip and the blend mode packed as integers
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, bytemuck::Zeroable, bytemuck::Pod)]
+struct Instance {
+    position: Position,
+    color: Color,
+    source: Source,
+    index: u32,
+    scale: [f32; 2],
+    pivot: [f32; 2],
+    rotation: f32,
+    /// Bit 0 mirrors horizontally, bit 1 vertically
+    flip: u32,
+    blend: u32,
+}
+
+impl From<&DrawImage> for Instance {
+    fn from(draw: &DrawImage) -> Self {
+        let transform = draw.transform;
+        Self {
+            position: draw.position,
+            color: draw.color,
+            source: draw.source,
+            index: draw.index,
+            scale: transform.scale,
+            pivot: transform.pivot,
+            rotation: transform.rotation,
+            flip: transform.flip.horizontal as u32 | (transform.flip.vertical as u32) << 1,
+            blend: draw.blend.index() as u32,
+        }
+    }
+}
+
+fn instances(draws: &[DrawImage]) -> Vec<Instance> {
+    draws.iter().map(Instance::from).collect()
+}
+
+/// `Instance` fields as instance attributes, the transform and blend mode go last
+const INSTANCE_ATTRIBUTES: [wgpu::VertexAttribute; 10] = wgpu::vertex_attr_array![
+    0 => Uint32, 1 => Float32, 2 => Unorm8x4, 3 => Uint32x2, 4 => Sint32,
+    5 => Float32x2, 6 => Float32x2, 7 => Float32, 8 => Uint32, 9 => Uint32
+];
+
+/// The alpha channels are added in every mode
+fn blend_state(blend: Blend) -> wgpu::BlendState {
+    let color = match blend {
+        Blend::Additive => wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        },
+        // the shader already faded the color towards white where it's transparent
+        Blend::Multiply => wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Dst,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        },
+        Blend::Alpha => wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::SrcAlpha,
+            dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+            operation: wgpu::BlendOperation::Add,
+        },
+    };
+    wgpu::BlendState {
+        color,
+        alpha: wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        },
+    }
+}
+
 pub fn initialize(state: &State) -> Box<dyn Renderer> {
     if std::env::var("SPRITE_BATCH").is_ok() || state.limits.max_samplers_per_shader_stage <= 1000 {
         Box::new(SpriteBatchRenderer::initialize(state))
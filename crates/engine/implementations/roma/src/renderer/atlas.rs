@@ -0,0 +1,410 @@
+use engine::{
+    draw::{image::DrawImage, Dimensions},
+    engine::TextureID,
+};
+use image::RgbaImage;
+use nohash_hasher::IntMap;
+use wgpu::{Device, Queue};
+
+use crate::texture;
+
+/// Width and height of every page, the minimum texture size every adapter supports
+const PAGE_SIZE: u32 = 2048;
+
+/// Transparent pixels around every texture so neighbours don't bleed into each other
+const PADDING: u32 = 1;
+
+/// Textures bigger than this are not packed, they would fill the pages too soon
+const MAX_PACKED_SIZE: u32 = 512;
+
+/// Pages are repacked when the textures evicted from them add up to this area
+const REPACK_AREA: u32 = PAGE_SIZE * PAGE_SIZE / 4;
+
+/// Texture placed at a new position of a page when it is repacked
+type Moved = (TextureID, Region, u32, u32);
+
+#[derive(Clone, Copy)]
+struct Region {
+    page: usize,
+    x: u16,
+    y: u16,
+    width: u16,
+    height: u16,
+}
+
+/// Row of textures, the height is set by the first one
+struct Shelf {
+    y: u32,
+    height: u32,
+    width: u32,
+}
+
+#[derive(Default)]
+struct Packer {
+    shelves: Vec<Shelf>,
+}
+
+impl Packer {
+    /// Places a rectangle on the shortest shelf it fits, or on a new shelf
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let width = width + PADDING * 2;
+        let height = height + PADDING * 2;
+
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= height && shelf.height <= height * 2)
+            .filter(|shelf| PAGE_SIZE - shelf.width >= width)
+            .min_by_key(|shelf| shelf.height)
+        {
+            let x = shelf.width;
+            shelf.width += width;
+            return Some((x + PADDING, shelf.y + PADDING));
+        }
+
+        let y = self
+            .shelves
+            .last()
+            .map(|shelf| shelf.y + shelf.height)
+            .unwrap_or(0);
+        if y + height > PAGE_SIZE || width > PAGE_SIZE {
+            return None;
+        }
+        self.shelves.push(Shelf { y, height, width });
+        Some((PADDING, y + PADDING))
+    }
+}
+
+struct Page {
+    id: TextureID,
+    texture: texture::Texture,
+    packer: Packer,
+    /// Area of the textures evicted since the page was packed
+    freed: u32,
+}
+
+/// Packs small textures in shared pages, so the sprite batch renderer draws them in one batch
+#[derive(Default)]
+pub struct Atlas {
+    pages: Vec<Page>,
+    regions: IntMap<TextureID, Region>,
+}
+
+impl Atlas {
+    pub fn fits(image: &RgbaImage) -> bool {
+        image.width() <= MAX_PACKED_SIZE && image.height() <= MAX_PACKED_SIZE
+    }
+
+    /// Copies the texture into a page, `next_id` is used when a new page is needed
+    /// Returns the pages that were created or replaced
+    pub fn insert(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        id: TextureID,
+        image: &RgbaImage,
+        next_id: TextureID,
+    ) -> Vec<TextureID> {
+        let (width, height) = image.dimensions();
+        let mut changed = vec![];
+
+        let mut allocation = self.pages.iter_mut().enumerate().find_map(|(index, page)| {
+            let (x, y) = page.packer.allocate(width, height)?;
+            Some((index, x, y))
+        });
+        if allocation.is_none() {
+            if let Some(index) = self.repack(device, queue) {
+                changed.push(self.pages[index].id);
+                allocation = self.pages[index]
+                    .packer
+                    .allocate(width, height)
+                    .map(|(x, y)| (index, x, y));
+            }
+        }
+        let (page, x, y) = match allocation {
+            Some(allocation) => allocation,
+            None => {
+                self.pages.push(Page {
+                    id: next_id,
+                    texture: create_page(device),
+                    packer: Packer::default(),
+                    freed: 0,
+                });
+                changed.push(next_id);
+                let index = self.pages.len() - 1;
+                let (x, y) = self.pages[index]
+                    .packer
+                    .allocate(width, height)
+                    .expect("packed textures fit in an empty page");
+                (index, x, y)
+            }
+        };
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &self.pages[page].texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+            },
+            image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.regions.insert(
+            id,
+            Region {
+                page,
+                x: x as u16,
+                y: y as u16,
+                width: width as u16,
+                height: height as u16,
+            },
+        );
+
+        changed
+    }
+
+    /// Frees the place of an evicted texture, it's reused once the page is repacked
+    pub fn remove(&mut self, id: TextureID) {
+        if let Some(region) = self.regions.remove(&id) {
+            self.pages[region.page].freed += region.width as u32 * region.height as u32;
+        }
+    }
+
+    pub fn page(&self, id: TextureID) -> Option<TextureID> {
+        self.regions
+            .get(&id)
+            .map(|region| self.pages[region.page].id)
+    }
+
+    pub fn page_texture(&self, page_id: TextureID) -> Option<&texture::Texture> {
+        self.pages
+            .iter()
+            .find(|page| page.id == page_id)
+            .map(|page| &page.texture)
+    }
+
+    /// Points the draw to the page and moves its source to the packed texture
+    pub fn remap(&self, draw: &mut DrawImage) {
+        let Some(region) = self.regions.get(&draw.index) else {
+            return;
+        };
+        if draw.source[2] == 0 && draw.source[3] == 0 {
+            draw.source = [region.x, region.y, region.width, region.height];
+        } else {
+            draw.source[0] += region.x;
+            draw.source[1] += region.y;
+        }
+        draw.index = self.pages[region.page].id;
+    }
+
+    /// Packs again the live textures of the page with the most evicted area, on a new texture
+    fn repack(&mut self, device: &Device, queue: &Queue) -> Option<usize> {
+        let (index, _) = self
+            .pages
+            .iter()
+            .enumerate()
+            .filter(|(_, page)| page.freed >= REPACK_AREA)
+            .max_by_key(|(_, page)| page.freed)?;
+
+        let regions = self
+            .regions
+            .iter()
+            .filter(|(_, region)| region.page == index)
+            .map(|(id, region)| (*id, *region))
+            .collect::<Vec<_>>();
+        // the old page is kept if the textures don't fit again
+        let (packer, moved) = pack(regions)?;
+
+        let texture = create_page(device);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Atlas Repack Encoder"),
+        });
+        for (id, region, x, y) in moved {
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &self.pages[index].texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: region.x as u32,
+                        y: region.y as u32,
+                        z: 0,
+                    },
+                },
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                },
+                wgpu::Extent3d {
+                    width: region.width as u32,
+                    height: region.height as u32,
+                    depth_or_array_layers: 1,
+                },
+            );
+            self.regions.insert(
+                id,
+                Region {
+                    x: x as u16,
+                    y: y as u16,
+                    ..region
+                },
+            );
+        }
+        queue.submit([encoder.finish()]);
+
+        let page = &mut self.pages[index];
+        log::info!("repacked atlas page {}, {} freed", page.id, page.freed);
+        page.texture = texture;
+        page.packer = packer;
+        page.freed = 0;
+        Some(index)
+    }
+}
+
+/// Places the regions on an empty packer, tallest first. Returns where each one goes
+fn pack(mut regions: Vec<(TextureID, Region)>) -> Option<(Packer, Vec<Moved>)> {
+    regions.sort_unstable_by_key(|(_, region)| std::cmp::Reverse(region.height));
+    let mut packer = Packer::default();
+    let mut moved = vec![];
+    for (id, region) in regions {
+        let (x, y) = packer.allocate(region.width as u32, region.height as u32)?;
+        moved.push((id, region, x, y));
+    }
+    Some((packer, moved))
+}
+
+fn create_page(device: &Device) -> texture::Texture {
+    texture::Texture::with_usage(
+        device,
+        Dimensions {
+            width: PAGE_SIZE as u16,
+            height: PAGE_SIZE as u16,
+        },
+        wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_DST
+            | wgpu::TextureUsages::COPY_SRC,
+        Some("atlas page"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::{pack, Atlas, Packer, Region, MAX_PACKED_SIZE, PADDING, PAGE_SIZE};
+
+    /// Shelves of the biggest packed textures that fit in a page, and textures in each of them
+    const MAX_PACKED_PER_ROW: u32 = PAGE_SIZE / (MAX_PACKED_SIZE + PADDING * 2);
+
+    fn region(width: u32, height: u32, (x, y): (u32, u32)) -> Region {
+        Region {
+            page: 0,
+            x: x as u16,
+            y: y as u16,
+            width: width as u16,
+            height: height as u16,
+        }
+    }
+
+    #[test]
+    fn rectangles_share_the_shelves_they_fit() {
+        let mut packer = Packer::default();
+        assert_eq!(packer.allocate(10, 10), Some((1, 1)));
+        assert_eq!(packer.allocate(10, 10), Some((13, 1)));
+        // up to half the height of the shelf
+        assert_eq!(packer.allocate(10, 8), Some((25, 1)));
+        assert_eq!(packer.allocate(4, 4), Some((37, 1)));
+        assert_eq!(packer.shelves.len(), 1);
+    }
+
+    #[test]
+    fn new_shelves_are_opened_below() {
+        let mut packer = Packer::default();
+        assert_eq!(packer.allocate(10, 10), Some((1, 1)));
+        // too short for the first shelf
+        assert_eq!(packer.allocate(2, 2), Some((1, 13)));
+        // too tall for both
+        assert_eq!(packer.allocate(10, 20), Some((1, 17)));
+        // the shortest shelf that fits is used
+        assert_eq!(packer.allocate(2, 2), Some((5, 13)));
+        assert_eq!(packer.shelves.len(), 3);
+    }
+
+    #[test]
+    fn full_pages_reject_rectangles() {
+        let mut packer = Packer::default();
+        let size = MAX_PACKED_SIZE + PADDING * 2;
+        for row in 0..MAX_PACKED_PER_ROW {
+            for column in 0..MAX_PACKED_PER_ROW {
+                assert_eq!(
+                    packer.allocate(MAX_PACKED_SIZE, MAX_PACKED_SIZE),
+                    Some((column * size + PADDING, row * size + PADDING))
+                );
+            }
+        }
+        assert_eq!(packer.allocate(MAX_PACKED_SIZE, MAX_PACKED_SIZE), None);
+
+        // what is left at the end of the shelves and below them is still used
+        let end = MAX_PACKED_PER_ROW * size;
+        assert_eq!(packer.allocate(100, 400), Some((end + PADDING, PADDING)));
+        assert_eq!(packer.allocate(100, 100), Some((PADDING, end + PADDING)));
+        assert_eq!(packer.allocate(PAGE_SIZE, 1), None);
+    }
+
+    #[test]
+    fn only_small_textures_are_packed() {
+        assert!(Atlas::fits(&RgbaImage::new(
+            MAX_PACKED_SIZE,
+            MAX_PACKED_SIZE
+        )));
+        assert!(!Atlas::fits(&RgbaImage::new(MAX_PACKED_SIZE + 1, 1)));
+        assert!(!Atlas::fits(&RgbaImage::new(1, MAX_PACKED_SIZE + 1)));
+    }
+
+    #[test]
+    fn repacking_frees_the_space_of_evicted_textures() {
+        let mut packer = Packer::default();
+        let mut regions = vec![];
+        for id in 0..MAX_PACKED_PER_ROW * MAX_PACKED_PER_ROW {
+            let position = packer.allocate(MAX_PACKED_SIZE, MAX_PACKED_SIZE).unwrap();
+            regions.push((id, region(MAX_PACKED_SIZE, MAX_PACKED_SIZE, position)));
+        }
+        assert_eq!(packer.allocate(MAX_PACKED_SIZE, MAX_PACKED_SIZE), None);
+
+        // every other texture is evicted, leaving holes that the packer can't reuse
+        regions.retain(|(id, _)| id % 2 == 0);
+        let live = regions.len();
+        let (mut packer, moved) = pack(regions).unwrap();
+        assert_eq!(moved.len(), live);
+        assert!(packer.allocate(MAX_PACKED_SIZE, MAX_PACKED_SIZE).is_some());
+
+        let mut positions = moved
+            .iter()
+            .map(|(_, _, x, y)| (*x, *y))
+            .collect::<Vec<_>>();
+        positions.sort_unstable();
+        positions.dedup();
+        assert_eq!(positions.len(), live);
+    }
+
+    #[test]
+    fn repacking_keeps_the_page_when_textures_dont_fit() {
+        let count = MAX_PACKED_PER_ROW * MAX_PACKED_PER_ROW + 1;
+        let regions = (0..count)
+            .map(|id| (id, region(MAX_PACKED_SIZE, MAX_PACKED_SIZE, (0, 0))))
+            .collect();
+        assert!(pack(regions).is_none());
+    }
+}
@@ -0,0 +1,314 @@
+use engine::{
+    draw::{light::DrawLight, Color},
+    window::Size,
+};
+use wgpu::{CommandEncoder, Device, PushConstantRange, ShaderStages};
+
+use crate::{camera::Camera, state::State};
+
+const LIGHT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+const LIGHT_ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+    wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32, 2 => Unorm8x4];
+
+/// Accumulates the lights of a frame in a texture that is multiplied over the world
+pub struct Lighting {
+    ambient: Color,
+    lights: Vec<DrawLight>,
+
+    light_pipeline: wgpu::RenderPipeline,
+    apply_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    vertex_buffer: wgpu::Buffer,
+
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Lighting {
+    pub fn initialize(state: &State) -> Self {
+        let shader = state
+            .device
+            .create_shader_module(wgpu::include_wgsl!("lighting_shader.wgsl"));
+        let bind_group_layout = create_bind_group_layout(&state.device);
+        let light_pipeline = create_light_pipeline(&state.device, &shader);
+        let apply_pipeline = create_apply_pipeline(state, &shader, &bind_group_layout);
+        let vertex_buffer = create_vertex_buffer(&state.device, 10);
+
+        let size = Size {
+            width: state.config.width as u16,
+            height: state.config.height as u16,
+        };
+        let view = create_light_texture(&state.device, size);
+        let bind_group = create_bind_group(&state.device, &bind_group_layout, &view);
+
+        Self {
+            ambient: [255, 255, 255, 255],
+            lights: vec![],
+
+            light_pipeline,
+            apply_pipeline,
+            bind_group_layout,
+            vertex_buffer,
+
+            view,
+            bind_group,
+        }
+    }
+
+    pub fn resize(&mut self, state: &State, size: Size) {
+        self.view = create_light_texture(&state.device, size);
+        self.bind_group = create_bind_group(&state.device, &self.bind_group_layout, &self.view);
+    }
+
+    pub fn set_ambient(&mut self, color: Color) {
+        self.ambient = color;
+    }
+
+    pub fn push(&mut self, light: DrawLight) {
+        self.lights.push(light);
+    }
+
+    /// The world is drawn as it is under a white ambient light
+    pub fn enabled(&self) -> bool {
+        self.ambient[..3] != [255, 255, 255]
+    }
+
+    /// Draws the lights of the frame over the ambient light, before the world is drawn
+    pub fn prepare(&mut self, state: &State, encoder: &mut CommandEncoder, camera: &Camera) {
+        let lights = std::mem::take(&mut self.lights);
+        if !self.enabled() {
+            return;
+        }
+
+        let required_size = std::mem::size_of_val(lights.as_slice()) as u64;
+        if self.vertex_buffer.size() < required_size {
+            self.vertex_buffer = create_vertex_buffer(&state.device, lights.len() + 10);
+        }
+        state
+            .queue
+            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&lights));
+
+        let [red, green, blue, _] = self.ambient.map(|channel| channel as f64 / 255.);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Lighting Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: red,
+                        g: green,
+                        b: blue,
+                        a: 1.,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if lights.is_empty() {
+            return;
+        }
+
+        let viewport = camera.viewport;
+        let projection = camera.build_view_projection_matrix();
+        render_pass.set_pipeline(&self.light_pipeline);
+        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&projection));
+        render_pass.set_viewport(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+            0.,
+            1.,
+        );
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..4, 0..lights.len() as u32);
+    }
+
+    /// Multiplies the lights over the world drawn so far, inside the world camera viewport
+    pub fn apply<'pass>(&'pass self, render_pass: &mut wgpu::RenderPass<'pass>, camera: &Camera) {
+        if !self.enabled() {
+            return;
+        }
+        let viewport = camera.viewport;
+        render_pass.set_pipeline(&self.apply_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_viewport(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+            0.,
+            1.,
+        );
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_light_texture(device: &Device, size: Size) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("light_texture"),
+        size: wgpu::Extent3d {
+            width: size.width.max(1) as u32,
+            height: size.height.max(1) as u32,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: LIGHT_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_vertex_buffer(device: &Device, lights: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Lights Vertex Buffer"),
+        size: (std::mem::size_of::<DrawLight>() * lights) as wgpu::BufferAddress,
+        mapped_at_creation: false,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+    })
+}
+
+fn create_bind_group_layout(device: &Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        }],
+        label: Some("light_bind_group_layout"),
+    })
+}
+
+fn create_bind_group(
+    device: &Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("light_bind_group"),
+        layout: bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(view),
+        }],
+    })
+}
+
+fn create_light_pipeline(device: &Device, shader: &wgpu::ShaderModule) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Light Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[PushConstantRange {
+            stages: ShaderStages::VERTEX,
+            range: 0..64,
+        }],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Light Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_light",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<DrawLight>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Instance,
+                attributes: &LIGHT_ATTRIBUTES,
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_light",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: LIGHT_FORMAT,
+                // overlapping lights add up
+                blend: Some(wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::REPLACE,
+                }),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+/// Drawn in the pass of the frame, so it has to match its depth attachment
+fn create_apply_pipeline(
+    state: &State,
+    shader: &wgpu::ShaderModule,
+    bind_group_layout: &wgpu::BindGroupLayout,
+) -> wgpu::RenderPipeline {
+    let layout = state
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Apply Light Pipeline Layout"),
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+    state
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Apply Light Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_apply",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_apply",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: state.config.format,
+                    blend: Some(wgpu::BlendState {
+                        color: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Dst,
+                            dst_factor: wgpu::BlendFactor::Zero,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                        alpha: wgpu::BlendComponent {
+                            src_factor: wgpu::BlendFactor::Zero,
+                            dst_factor: wgpu::BlendFactor::One,
+                            operation: wgpu::BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+}
@@ -0,0 +1,49 @@
+var<push_constant> camera_projection: mat4x4<f32>;
+
+@group(0) @binding(0) var light_texture: texture_2d<f32>;
+
+struct LightInput {
+    @builtin(vertex_index) vertex_index: u32,
+    @location(0) position: vec2<f32>,
+    @location(1) radius: f32,
+    @location(2) color: vec4<f32>,
+}
+
+struct LightOutput {
+    @builtin(position) position: vec4<f32>,
+    // from the center of the light, 1 at its radius
+    @location(0) offset: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_light(input: LightInput) -> LightOutput {
+    var output: LightOutput;
+
+    let corner = vec2<f32>(f32(input.vertex_index & 1u), f32(input.vertex_index >> 1u)) * 2.0 - 1.0;
+    output.position = camera_projection * vec4<f32>(input.position + corner * input.radius, 1.0, 1.0);
+    output.position.z = 0.0;
+    output.offset = corner;
+    output.color = input.color;
+
+    return output;
+}
+
+@fragment
+fn fs_light(in: LightOutput) -> @location(0) vec4<f32> {
+    let falloff = clamp(1.0 - length(in.offset), 0.0, 1.0);
+    return vec4<f32>(in.color.rgb * in.color.a * falloff * falloff, 1.0);
+}
+
+@vertex
+fn vs_apply(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // one triangle covering the viewport
+    let corner = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_apply(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // the light texture has the size of the frame
+    return textureLoad(light_texture, vec2<i32>(position.xy), 0);
+}
@@ -0,0 +1,181 @@
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use engine::engine::TextureID;
+use image::RgbaImage;
+
+use crate::texture;
+
+/// Most threads decoding textures at once
+const MAX_WORKERS: usize = 4;
+
+pub type Decoded = (TextureID, Result<RgbaImage, String>);
+
+struct Job {
+    id: TextureID,
+    path: String,
+    priority: u8,
+    /// Order of the request, older requests go first within a priority
+    sequence: u64,
+}
+
+impl PartialEq for Job {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Job {}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.sequence.cmp(&self.sequence))
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BinaryHeap<Job>,
+    sequence: u64,
+    closed: bool,
+}
+
+/// Decodes texture files on worker threads, higher priorities first
+pub struct Loader {
+    queue: Arc<(Mutex<Queue>, Condvar)>,
+    decoded: Receiver<Decoded>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl Loader {
+    pub fn initialize() -> Self {
+        let queue = Arc::new((Mutex::new(Queue::default()), Condvar::new()));
+        let (sender, decoded) = mpsc::channel();
+        let workers = thread::available_parallelism()
+            .map(|count| count.get())
+            .unwrap_or(1)
+            .clamp(1, MAX_WORKERS);
+
+        let workers = (0..workers)
+            .map(|index| {
+                let queue = queue.clone();
+                let sender = sender.clone();
+                thread::Builder::new()
+                    .name(format!("texture loader {index}"))
+                    .spawn(move || work(&queue, &sender))
+                    .expect("couldn't spawn texture loader")
+            })
+            .collect();
+
+        Self {
+            queue,
+            decoded,
+            workers,
+        }
+    }
+
+    pub fn request(&self, id: TextureID, path: &str, priority: u8) {
+        let (queue, available) = &*self.queue;
+        let Ok(mut queue) = queue.lock() else {
+            return;
+        };
+        queue.sequence += 1;
+        let sequence = queue.sequence;
+        queue.jobs.push(Job {
+            id,
+            path: path.to_string(),
+            priority,
+            sequence,
+        });
+        available.notify_one();
+    }
+
+    /// Raises the priority of a texture that is already queued
+    pub fn promote(&self, id: TextureID, priority: u8) {
+        let (queue, _) = &*self.queue;
+        let Ok(mut queue) = queue.lock() else {
+            return;
+        };
+        if !queue
+            .jobs
+            .iter()
+            .any(|job| job.id == id && job.priority < priority)
+        {
+            return;
+        }
+        let jobs = std::mem::take(&mut queue.jobs);
+        queue.jobs = jobs
+            .into_iter()
+            .map(|mut job| {
+                if job.id == id {
+                    job.priority = job.priority.max(priority);
+                }
+                job
+            })
+            .collect();
+    }
+
+    /// Textures decoded since the last call
+    pub fn decoded(&self) -> impl Iterator<Item = Decoded> + '_ {
+        self.decoded.try_iter()
+    }
+}
+
+impl Drop for Loader {
+    fn drop(&mut self) {
+        let (queue, available) = &*self.queue;
+        if let Ok(mut queue) = queue.lock() {
+            queue.closed = true;
+            queue.jobs.clear();
+        }
+        available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work(queue: &(Mutex<Queue>, Condvar), sender: &Sender<Decoded>) {
+    let (queue, available) = queue;
+    loop {
+        let job = {
+            let Ok(mut queue) = queue.lock() else {
+                return;
+            };
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop() {
+                    break job;
+                }
+                queue = match available.wait(queue) {
+                    Ok(queue) => queue,
+                    Err(_) => return,
+                };
+            }
+        };
+
+        let image = texture::read_image(&job.path)
+            .map(|image| image.to_rgba8())
+            .map_err(|error| format!("{error} on {}", job.path));
+        if sender.send((job.id, image)).is_err() {
+            return;
+        }
+    }
+}
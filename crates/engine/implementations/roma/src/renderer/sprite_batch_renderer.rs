@@ -4,7 +4,11 @@ use std::{
 };
 
 use engine::{
-    draw::{image::DrawImage, Target},
+    draw::{
+        image::{Blend, DrawImage},
+        light::DrawLight,
+        Color, Target,
+    },
     engine::TextureID,
     window::Size,
 };
@@ -18,22 +22,26 @@ use crate::{
     Renderer,
 };
 
-use super::textures::Textures;
+use super::{
+    blend_state, instances, lighting::Lighting, textures::Textures, Instance, INSTANCE_ATTRIBUTES,
+};
 
 pub struct SpriteBatchRenderer {
     textures: Textures,
+    lighting: Lighting,
 
     bind_groups: IntMap<TextureID, wgpu::BindGroup>,
     bind_group_layout: wgpu::BindGroupLayout,
-    pipeline: wgpu::RenderPipeline,
+    /// One per blend mode
+    pipelines: Vec<wgpu::RenderPipeline>,
     vertex_buffer: wgpu::Buffer,
 
     draws_counter: usize,
-    draws_to_textures: IntMap<TextureID, IntMap<TextureID, Vec<DrawImage>>>,
-    draws_to_zero_world: IntMap<TextureID, Vec<DrawImage>>,
-    transparent_draws_to_world: IntMap<TextureID, Vec<DrawImage>>,
-    draws_to_world: IntMap<TextureID, Vec<DrawImage>>,
-    draws_to_ui: IntMap<TextureID, Vec<DrawImage>>,
+    draws_to_textures: IntMap<TextureID, IntMap<BatchKey, Vec<DrawImage>>>,
+    draws_to_zero_world: IntMap<BatchKey, Vec<DrawImage>>,
+    transparent_draws_to_world: IntMap<BatchKey, Vec<DrawImage>>,
+    draws_to_world: IntMap<BatchKey, Vec<DrawImage>>,
+    draws_to_ui: IntMap<BatchKey, Vec<DrawImage>>,
 
     depth_texture_view: wgpu::TextureView,
     depth_textures: HashMap<engine::window::Size, wgpu::TextureView>,
@@ -42,54 +50,64 @@ pub struct SpriteBatchRenderer {
 impl Renderer for SpriteBatchRenderer {
     fn resize(&mut self, state: &State, size: Size) {
         self.depth_texture_view = create_depth_texture(state, size);
+        self.lighting.resize(state, size);
     }
 
-    fn ensure_texture(&mut self, state: &State, id: TextureID, target: Target) -> bool {
-        if self.textures.load_texture(&state.device, &state.queue, id) {
+    fn ensure_texture(
+        &mut self,
+        state: &State,
+        id: TextureID,
+        target: Target,
+    ) -> Option<TextureID> {
+        let id = self.textures.ensure(&state.device, &state.queue, id)?;
+        let bound = self.textures.page(id).unwrap_or(id);
+        if let Entry::Vacant(e) = self.bind_groups.entry(bound) {
+            if let Some(texture) = self.textures.get(bound).flatten() {
+                let bind_group = create_bind_group(state, &self.bind_group_layout, texture);
+                e.insert(bind_group);
+            }
+        }
+        if let Target::Texture { id } = target {
             if let Entry::Vacant(e) = self.bind_groups.entry(id) {
                 if let Some(texture) = self.textures.get(id).flatten() {
                     let bind_group = create_bind_group(state, &self.bind_group_layout, texture);
                     e.insert(bind_group);
                 }
             }
-            if let Target::Texture { id } = target {
-                if let Entry::Vacant(e) = self.bind_groups.entry(id) {
-                    if let Some(texture) = self.textures.get(id).flatten() {
-                        let bind_group = create_bind_group(state, &self.bind_group_layout, texture);
-                        e.insert(bind_group);
-                    }
-                }
-            }
+        }
 
-            true
-        } else {
-            false
+        Some(id)
+    }
+
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8) {
+        for id in ids {
+            self.textures.prefetch(*id, priority);
         }
     }
 
-    fn push_draw_image(&mut self, draw: DrawImage, target: Target) {
+    fn textures_loading(&self) -> usize {
+        self.textures.loading()
+    }
+
+    fn push_draw_image(&mut self, mut draw: DrawImage, target: Target) {
+        self.textures.remap(&mut draw);
         self.draws_counter += 1;
+        let key = batch_key(&draw);
         match target {
             Target::World => {
                 if draw.position.z == 0.0 {
-                    self.draws_to_zero_world
-                        .entry(draw.index)
-                        .or_default()
-                        .push(draw);
-                } else if draw.color[3] < 255 {
+                    self.draws_to_zero_world.entry(key).or_default().push(draw);
+                } else if draw.color[3] < 255 || draw.blend != Blend::Alpha {
                     self.transparent_draws_to_world
-                        .entry(draw.index)
+                        .entry(key)
                         .or_default()
                         .push(draw);
                 } else {
-                    self.draws_to_world
-                        .entry(draw.index)
-                        .or_default()
-                        .push(draw);
+                    self.draws_to_world.entry(key).or_default().push(draw);
                 }
             }
             Target::UI => {
-                self.draws_to_ui.entry(draw.index).or_default().push(draw);
+                self.draws_to_ui.entry(key).or_default().push(draw);
             }
             Target::Texture {
                 id: target_texture_id,
@@ -97,13 +115,21 @@ impl Renderer for SpriteBatchRenderer {
                 self.draws_to_textures
                     .entry(target_texture_id)
                     .or_default()
-                    .entry(draw.index)
+                    .entry(key)
                     .or_default()
                     .push(draw);
             }
         }
     }
 
+    fn push_light(&mut self, light: DrawLight) {
+        self.lighting.push(light);
+    }
+
+    fn set_ambient_light(&mut self, color: Color) {
+        self.lighting.set_ambient(color);
+    }
+
     fn render(&mut self, state: &State, world_camera: &Camera, ui_camera: &Camera) {
         let Ok(frame) = state.surface.get_current_texture() else {
             log::error!("");
@@ -161,17 +187,15 @@ impl Renderer for SpriteBatchRenderer {
                     width: size.width,
                     height: size.height,
                 };
-                render_pass.set_pipeline(&self.pipeline);
                 render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
                 let target_camera = Camera::initialize(dimensions, false);
-                Self::use_camera(&mut render_pass, &target_camera);
-
-                for (texture, range) in batches {
-                    if let Some(texture_bind_group) = self.bind_groups.get(&texture) {
-                        render_pass.set_bind_group(0, texture_bind_group, &[]);
-                        render_pass.draw(0..4, range.start as u32..range.end as u32);
-                    }
-                }
+                draw_batches(
+                    &mut render_pass,
+                    batches,
+                    &target_camera,
+                    &self.pipelines,
+                    &self.bind_groups,
+                );
             }
         }
         commands.push(encoder.finish());
@@ -185,6 +209,7 @@ impl Renderer for SpriteBatchRenderer {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        self.lighting.prepare(state, &mut encoder, world_camera);
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -202,29 +227,33 @@ impl Renderer for SpriteBatchRenderer {
                 occlusion_query_set: None,
             });
 
-            render_pass.set_pipeline(&self.pipeline);
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
 
-            Self::use_camera(&mut render_pass, world_camera);
-            for (texture_id, range) in world_ranges {
-                if let Some(texture_bind_group) = self.bind_groups.get(&texture_id) {
-                    render_pass.set_bind_group(0, texture_bind_group, &[]);
-                    render_pass.draw(0..4, range.start as u32..range.end as u32);
-                }
-            }
-
-            Self::use_camera(&mut render_pass, ui_camera);
-            for (texture_id, range) in ui_ranges {
-                if let Some(texture_bind_group) = self.bind_groups.get(&texture_id) {
-                    render_pass.set_bind_group(0, texture_bind_group, &[]);
-                    render_pass.draw(0..4, range.start as u32..range.end as u32);
-                }
-            }
+            draw_batches(
+                &mut render_pass,
+                world_ranges,
+                world_camera,
+                &self.pipelines,
+                &self.bind_groups,
+            );
+            self.lighting.apply(&mut render_pass, world_camera);
+            draw_batches(
+                &mut render_pass,
+                ui_ranges,
+                ui_camera,
+                &self.pipelines,
+                &self.bind_groups,
+            );
         }
         commands.push(encoder.finish());
 
         state.queue.submit(commands);
         frame.present();
+
+        // after the frame, packing may move the textures its draws were remapped to
+        for id in self.textures.update(&state.device, &state.queue) {
+            self.bind_groups.remove(&id);
+        }
     }
 
     fn add_texture_file(&mut self, path: &str) -> TextureID {
@@ -240,10 +269,7 @@ impl Renderer for SpriteBatchRenderer {
     }
 
     fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)> {
-        self.textures
-            .get(texture_id)
-            .flatten()
-            .map(|texture| (texture.width, texture.height))
+        self.textures.dimensions(texture_id)
     }
 }
 
@@ -255,8 +281,11 @@ impl SpriteBatchRenderer {
         };
         let depth_texture_view = create_depth_texture(state, size);
         let bind_group_layout = create_bind_group_layout(state);
-        let pipeline = create_pipeline(state, &bind_group_layout);
-        let sprites: Vec<DrawImage> = vec![];
+        let pipelines = Blend::ALL
+            .iter()
+            .map(|blend| create_pipeline(state, &bind_group_layout, *blend))
+            .collect();
+        let sprites: Vec<Instance> = vec![];
         let vertex_buffer = state
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
@@ -266,10 +295,11 @@ impl SpriteBatchRenderer {
             });
 
         Self {
-            textures: Textures::initialize(),
+            textures: Textures::initialize(std::env::var("NO_ATLAS").is_err()),
+            lighting: Lighting::initialize(state),
             bind_groups: IntMap::default(),
             bind_group_layout,
-            pipeline,
+            pipelines,
             vertex_buffer,
 
             draws_counter: 0,
@@ -289,21 +319,31 @@ impl SpriteBatchRenderer {
 
         let mut offset = 0;
         let write_buffer = |draws: &[DrawImage], offset: usize| {
-            let data = bytemuck::cast_slice(draws);
-            let buffer_offset = (std::mem::size_of::<DrawImage>() * offset) as u64;
-            queue.write_buffer(&self.vertex_buffer, buffer_offset, data);
+            let instances = instances(draws);
+            let buffer_offset = (std::mem::size_of::<Instance>() * offset) as u64;
+            queue.write_buffer(
+                &self.vertex_buffer,
+                buffer_offset,
+                bytemuck::cast_slice(&instances),
+            );
         };
 
-        let prepare_draws = |batches: &mut IntMap<TextureID, Vec<DrawImage>>,
-                             ranges: &mut Vec<(TextureID, Range<usize>)>,
+        let prepare_draws = |batches: &mut IntMap<BatchKey, Vec<DrawImage>>,
+                             ranges: &mut Batches,
                              offset: &mut usize| {
-            for (texture_id, draws) in batches.iter_mut() {
+            // alpha blended batches go first, the effects are drawn over them
+            let mut keys = batches.keys().copied().collect::<Vec<_>>();
+            keys.sort_unstable();
+            for key in keys {
+                let Some(draws) = batches.get_mut(&key) else {
+                    continue;
+                };
                 if draws.is_empty() {
                     continue;
                 }
                 write_buffer(draws, *offset);
 
-                ranges.push((*texture_id, *offset..(*offset + draws.len())));
+                ranges.push((key, *offset..(*offset + draws.len())));
                 *offset += draws.len();
                 draws.clear();
             }
@@ -343,11 +383,11 @@ impl SpriteBatchRenderer {
     }
 
     fn ensure_vertex_buffer_size(&mut self, device: &Device) {
-        let required_buffer_size = (std::mem::size_of::<DrawImage>() * self.draws_counter) as u64;
+        let required_buffer_size = (std::mem::size_of::<Instance>() * self.draws_counter) as u64;
         if self.vertex_buffer.size() < required_buffer_size {
             self.vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("Sprite Vertex Buffer"),
-                size: (std::mem::size_of::<DrawImage>() * (self.draws_counter + 10)) as u64
+                size: (std::mem::size_of::<Instance>() * (self.draws_counter + 10)) as u64
                     as wgpu::BufferAddress,
                 mapped_at_creation: false,
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
@@ -418,9 +458,44 @@ fn create_bind_group_layout(state: &State) -> wgpu::BindGroupLayout {
         })
 }
 
+fn draw_batches<'pass>(
+    render_pass: &mut wgpu::RenderPass<'pass>,
+    batches: Batches,
+    camera: &Camera,
+    pipelines: &'pass [wgpu::RenderPipeline],
+    bind_groups: &'pass IntMap<TextureID, wgpu::BindGroup>,
+) {
+    let mut current_blend = None;
+    for (key, range) in batches {
+        let (blend, texture_id) = split_batch_key(key);
+        if let Some(texture_bind_group) = bind_groups.get(&texture_id) {
+            if current_blend != Some(blend) {
+                // every pipeline has its own layout, the camera is set again
+                render_pass.set_pipeline(&pipelines[blend]);
+                SpriteBatchRenderer::use_camera(render_pass, camera);
+                current_blend = Some(blend);
+            }
+            render_pass.set_bind_group(0, texture_bind_group, &[]);
+            render_pass.draw(0..4, range.start as u32..range.end as u32);
+        }
+    }
+}
+
+/// Draws are batched by blend mode and texture, the blend mode goes in the high bits
+type BatchKey = u64;
+
+fn batch_key(draw: &DrawImage) -> BatchKey {
+    ((draw.blend.index() as u64) << 32) | draw.index as u64
+}
+
+fn split_batch_key(key: BatchKey) -> (usize, TextureID) {
+    ((key >> 32) as usize, key as TextureID)
+}
+
 fn create_pipeline(
     state: &State,
     bind_group_layout: &wgpu::BindGroupLayout,
+    blend: Blend,
 ) -> wgpu::RenderPipeline {
     let shader = state
         .device
@@ -437,58 +512,49 @@ fn create_pipeline(
                 }],
             });
 
-    state.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("Render Pipeline"),
-        layout: Some(&render_pipeline_layout),
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<DrawImage>() as wgpu::BufferAddress,
-                step_mode: wgpu::VertexStepMode::Instance,
-                attributes: &wgpu::vertex_attr_array![0 => Uint32, 1 => Float32, 2 => Unorm8x4, 3 => Uint32x2, 4 => Sint32],
-            }],
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(wgpu::ColorTargetState {
-                format: state.config.format,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent {
-                        src_factor: wgpu::BlendFactor::SrcAlpha,
-                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                    alpha: wgpu::BlendComponent {
-                        src_factor: wgpu::BlendFactor::One,
-                        dst_factor: wgpu::BlendFactor::One,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                }),
-
-                write_mask: wgpu::ColorWrites::ALL,
-            })],
-        }),
-        primitive: wgpu::PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleStrip,
-            strip_index_format: Some(wgpu::IndexFormat::Uint16),
-            front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
-            polygon_mode: wgpu::PolygonMode::Fill,
-            unclipped_depth: true,
-            conservative: false,
-        },
-        depth_stencil: Some(wgpu::DepthStencilState {
-            format: wgpu::TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: wgpu::CompareFunction::GreaterEqual,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
-        multisample: wgpu::MultisampleState::default(),
-        multiview: None,
-    })
+    state
+        .device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &INSTANCE_ATTRIBUTES,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: state.config.format,
+                    blend: Some(blend_state(blend)),
+
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: Some(wgpu::IndexFormat::Uint16),
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: true,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::GreaterEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
 }
 
 fn create_depth_texture(state: &State, size: engine::window::Size) -> wgpu::TextureView {
@@ -515,7 +581,7 @@ fn create_depth_texture(state: &State, size: engine::window::Size) -> wgpu::Text
     })
 }
 
-type Batches = Vec<(TextureID, Range<usize>)>;
+type Batches = Vec<(BatchKey, Range<usize>)>;
 
 #[derive(Debug)]
 pub struct Instructions {
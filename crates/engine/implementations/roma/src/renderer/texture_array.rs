@@ -60,6 +60,21 @@ impl TextureArray {
         self.bind_group = None;
     }
 
+    /// Removes an evicted texture, the indices after it move down
+    pub fn remove(&mut self, id: TextureID) {
+        let Some(index) = self.indices.get_mut(id as usize).and_then(Option::take) else {
+            return;
+        };
+        self.textures.remove(index as usize);
+        self.samplers.remove(index as usize);
+        for other in self.indices.iter_mut().flatten() {
+            if *other > index {
+                *other -= 1;
+            }
+        }
+        self.bind_group = None;
+    }
+
     pub fn prepare(&mut self, device: &wgpu::Device, bind_group_layout: &wgpu::BindGroupLayout) {
         if self.textures.is_empty() {
             return;
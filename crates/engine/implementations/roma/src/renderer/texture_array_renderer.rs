@@ -1,7 +1,11 @@
 use std::{collections::HashMap, num::NonZeroU32, ops::Range};
 
 use engine::{
-    draw::{image::DrawImage, Target},
+    draw::{
+        image::{Blend, DrawImage},
+        light::DrawLight,
+        Color, Target,
+    },
     engine::TextureID,
     window::Size,
 };
@@ -10,10 +14,14 @@ use wgpu::{util::DeviceExt, Device, PushConstantRange, Queue, ShaderStages, Surf
 
 use crate::{camera::Camera, state::State, texture::Texture, Renderer};
 
-use super::{texture_array::TextureArray, textures::Textures};
+use super::{
+    blend_state, instances, lighting::Lighting, texture_array::TextureArray, textures::Textures,
+    Instance, INSTANCE_ATTRIBUTES,
+};
 
 pub struct TextureArrayRenderer {
     textures: Textures,
+    lighting: Lighting,
     offscreen: Node,
     main: Node,
 
@@ -31,7 +39,8 @@ pub struct Node {
     vertex_buffer: wgpu::Buffer,
 
     bind_group_layout: wgpu::BindGroupLayout,
-    pipeline: wgpu::RenderPipeline,
+    /// One per blend mode
+    pipelines: Vec<wgpu::RenderPipeline>,
 
     textures_count: u32,
     pub texture_array: TextureArray,
@@ -40,24 +49,38 @@ pub struct Node {
 impl Renderer for TextureArrayRenderer {
     fn resize(&mut self, state: &State, size: Size) {
         self.depth_texture_view = create_depth_texture(state, size);
+        self.lighting.resize(state, size);
     }
 
-    fn ensure_texture(&mut self, state: &State, id: TextureID, target: Target) -> bool {
-        if self.textures.load_texture(&state.device, &state.queue, id) {
-            let texture_array = match target {
-                Target::World | Target::UI => &mut self.main.texture_array,
-                _ => &mut self.offscreen.texture_array,
-            };
-            if !texture_array.has_texture(id) {
-                let texture = self.textures.get(id).unwrap().unwrap();
-                let view = texture.view.clone();
-                let sampler = texture.sampler.clone();
-
-                texture_array.push(id, view, sampler);
-            }
-            return true;
+    fn ensure_texture(
+        &mut self,
+        state: &State,
+        id: TextureID,
+        target: Target,
+    ) -> Option<TextureID> {
+        let id = self.textures.ensure(&state.device, &state.queue, id)?;
+        let texture_array = match target {
+            Target::World | Target::UI => &mut self.main.texture_array,
+            _ => &mut self.offscreen.texture_array,
+        };
+        if !texture_array.has_texture(id) {
+            let texture = self.textures.get(id).unwrap().unwrap();
+            let view = texture.view.clone();
+            let sampler = texture.sampler.clone();
+
+            texture_array.push(id, view, sampler);
+        }
+        Some(id)
+    }
+
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8) {
+        for id in ids {
+            self.textures.prefetch(*id, priority);
         }
-        false
+    }
+
+    fn textures_loading(&self) -> usize {
+        self.textures.loading()
     }
 
     fn push_draw_image(&mut self, draw: DrawImage, target: Target) {
@@ -65,7 +88,7 @@ impl Renderer for TextureArrayRenderer {
             Target::World => {
                 if draw.position.z == 0.0 {
                     self.draws_to_zero_world.push(draw);
-                } else if draw.color[3] < 255 {
+                } else if draw.color[3] < 255 || draw.blend != Blend::Alpha {
                     self.transparent_draws_to_world.push(draw);
                 } else {
                     self.draws_to_world.push(draw);
@@ -85,15 +108,27 @@ impl Renderer for TextureArrayRenderer {
         }
     }
 
+    fn push_light(&mut self, light: DrawLight) {
+        self.lighting.push(light);
+    }
+
+    fn set_ambient_light(&mut self, color: Color) {
+        self.lighting.set_ambient(color);
+    }
+
     fn render(&mut self, state: &State, world_camera: &Camera, ui_camera: &Camera) {
         let Ok(frame) = state.surface.get_current_texture() else {
             log::error!("");
             return;
         };
+        for id in self.textures.update(&state.device, &state.queue) {
+            self.main.texture_array.remove(id);
+            self.offscreen.texture_array.remove(id);
+        }
         let Instructions {
             to_textures_ranges,
-            world_range,
-            ui_range,
+            world_ranges,
+            ui_ranges,
         } = self.prepare(&state.device, &state.queue, &state.config);
 
         let clear_store_ops = wgpu::Operations {
@@ -111,7 +146,7 @@ impl Renderer for TextureArrayRenderer {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        for (texture_id, range) in to_textures_ranges {
+        for (texture_id, ranges) in to_textures_ranges {
             if let Some(Some(texture)) = self.textures.get(texture_id) {
                 let size = engine::window::Size {
                     width: texture.width,
@@ -145,7 +180,8 @@ impl Renderer for TextureArrayRenderer {
                 };
                 let target_camera = Camera::initialize(dimensions, false);
                 self.offscreen.prepare_pass(&mut render_pass);
-                Self::render_range(&mut render_pass, range, &target_camera);
+                self.offscreen
+                    .render_ranges(&mut render_pass, ranges, &target_camera);
             }
         }
         commands.push(encoder.finish());
@@ -159,6 +195,7 @@ impl Renderer for TextureArrayRenderer {
             .create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: Some("Render Encoder"),
             });
+        self.lighting.prepare(state, &mut encoder, world_camera);
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
@@ -177,8 +214,15 @@ impl Renderer for TextureArrayRenderer {
             });
 
             self.main.prepare_pass(&mut render_pass);
-            Self::render_range(&mut render_pass, world_range, world_camera);
-            Self::render_range(&mut render_pass, ui_range, ui_camera);
+            self.main
+                .render_ranges(&mut render_pass, world_ranges, world_camera);
+            if self.lighting.enabled() {
+                // the lighting binds its own texture, the array is bound again for the ui
+                self.lighting.apply(&mut render_pass, world_camera);
+                self.main.prepare_pass(&mut render_pass);
+            }
+            self.main
+                .render_ranges(&mut render_pass, ui_ranges, ui_camera);
         }
         commands.push(encoder.finish());
 
@@ -199,10 +243,7 @@ impl Renderer for TextureArrayRenderer {
     }
 
     fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)> {
-        self.textures
-            .get(texture_id)
-            .flatten()
-            .map(|texture| (texture.width, texture.height))
+        self.textures.dimensions(texture_id)
     }
 }
 
@@ -215,7 +256,8 @@ impl TextureArrayRenderer {
         let depth_texture_view = create_depth_texture(state, size);
 
         Self {
-            textures: Textures::initialize(),
+            textures: Textures::initialize(false),
+            lighting: Lighting::initialize(state),
 
             draws_to_textures: IntMap::default(),
             draws_to_zero_world: vec![],
@@ -251,7 +293,7 @@ impl TextureArrayRenderer {
             self.offscreen.update_draws(draws);
             self.offscreen.write_buffer(queue, draws, offset);
 
-            to_textures_ranges.push((*texture_id, offset..(offset + draws.len())));
+            to_textures_ranges.push((*texture_id, blend_ranges(draws, offset)));
 
             offset += draws.len();
             draws.clear();
@@ -263,6 +305,15 @@ impl TextureArrayRenderer {
             + self.transparent_draws_to_world.len()
             + self.draws_to_ui.len();
         self.main.ensure_buffer_size(device, main_draws_len);
+        // each blend mode is drawn with its own pipeline, the world effects after the alpha
+        // blended draws. The ui and the textures keep the order they were drawn in
+        for draws in [
+            &mut self.draws_to_zero_world,
+            &mut self.draws_to_world,
+            &mut self.transparent_draws_to_world,
+        ] {
+            draws.sort_by_key(|draw| draw.blend.index());
+        }
         self.main.update_draws(&mut self.draws_to_zero_world);
         self.main.update_draws(&mut self.draws_to_world);
         self.main.update_draws(&mut self.transparent_draws_to_world);
@@ -271,8 +322,16 @@ impl TextureArrayRenderer {
         let world_draws = self.draws_to_zero_world.len()
             + self.draws_to_world.len()
             + self.transparent_draws_to_world.len();
-        let world_range = 0..world_draws;
-        let ui_range = world_draws..(world_draws + self.draws_to_ui.len());
+        let mut world_ranges = blend_ranges(&self.draws_to_zero_world, 0);
+        world_ranges.extend(blend_ranges(
+            &self.draws_to_world,
+            self.draws_to_zero_world.len(),
+        ));
+        world_ranges.extend(blend_ranges(
+            &self.transparent_draws_to_world,
+            self.draws_to_zero_world.len() + self.draws_to_world.len(),
+        ));
+        let ui_ranges = blend_ranges(&self.draws_to_ui, world_draws);
 
         self.main
             .write_buffer(queue, &self.draws_to_zero_world[..], 0);
@@ -303,35 +362,19 @@ impl TextureArrayRenderer {
         self.main.prepare(device, config);
 
         Instructions {
-            world_range,
-            ui_range,
+            world_ranges,
+            ui_ranges,
             to_textures_ranges,
         }
     }
-
-    fn render_range(render_pass: &mut wgpu::RenderPass, range: Range<usize>, camera: &Camera) {
-        let viewport = camera.viewport;
-        let projection = camera.build_view_projection_matrix();
-
-        render_pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::cast_slice(&projection));
-        render_pass.set_viewport(
-            viewport.x,
-            viewport.y,
-            viewport.width,
-            viewport.height,
-            0.,
-            1.,
-        );
-        render_pass.draw(0..4, range.start as u32..range.end as u32);
-    }
 }
 
 impl Node {
     fn initialize(device: &Device, config: &SurfaceConfiguration, textures_count: u32) -> Self {
         let bind_group_layout = create_bind_group_layout(device, textures_count);
-        let pipeline = create_pipeline(device, &bind_group_layout, config);
+        let pipelines = create_pipelines(device, &bind_group_layout, config);
 
-        let sprites = vec![DrawImage::default(); textures_count as usize];
+        let sprites = vec![Instance::default(); textures_count as usize];
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Texture Array Renderer Vertex Buffer"),
             contents: bytemuck::cast_slice(&sprites),
@@ -342,19 +385,18 @@ impl Node {
         Self {
             vertex_buffer,
             bind_group_layout,
-            pipeline,
+            pipelines,
             texture_array,
             textures_count,
         }
     }
 
     fn ensure_buffer_size(&mut self, device: &Device, size: usize) {
-        let queue_size_in_bytes = (std::mem::size_of::<DrawImage>() * size) as wgpu::BufferAddress;
+        let queue_size_in_bytes = (std::mem::size_of::<Instance>() * size) as wgpu::BufferAddress;
         if self.vertex_buffer.size() < queue_size_in_bytes {
             self.vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
                 label: Some("Sprite Vertex Buffer"),
-                size: (std::mem::size_of::<DrawImage>() * (size + 10)) as u64
-                    as wgpu::BufferAddress,
+                size: (std::mem::size_of::<Instance>() * (size + 10)) as u64 as wgpu::BufferAddress,
                 mapped_at_creation: false,
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             });
@@ -362,9 +404,13 @@ impl Node {
     }
 
     fn write_buffer(&mut self, queue: &Queue, draws: &[DrawImage], offset: usize) {
-        let data = bytemuck::cast_slice(draws);
-        let buffer_offset = (std::mem::size_of::<DrawImage>() * offset) as u64;
-        queue.write_buffer(&self.vertex_buffer, buffer_offset, data);
+        let instances = instances(draws);
+        let buffer_offset = (std::mem::size_of::<Instance>() * offset) as u64;
+        queue.write_buffer(
+            &self.vertex_buffer,
+            buffer_offset,
+            bytemuck::cast_slice(&instances),
+        );
     }
 
     fn update_draws(&self, draws: &mut [DrawImage]) {
@@ -377,9 +423,10 @@ impl Node {
 
     fn prepare(&mut self, device: &Device, config: &SurfaceConfiguration) {
         let size = self.texture_array.size();
-        if size > self.textures_count {
+        // evicted textures shrink the array, the layout has to match its length
+        if size > 0 && size != self.textures_count {
             self.bind_group_layout = create_bind_group_layout(device, size);
-            self.pipeline = create_pipeline(device, &self.bind_group_layout, config);
+            self.pipelines = create_pipelines(device, &self.bind_group_layout, config);
             self.textures_count = size;
         }
 
@@ -387,17 +434,70 @@ impl Node {
     }
 
     fn prepare_pass<'pass>(&'pass self, render_pass: &mut wgpu::RenderPass<'pass>) {
-        render_pass.set_pipeline(&self.pipeline);
         render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         if let Some(bind_group) = self.texture_array.get_bind_group() {
             render_pass.set_bind_group(0, bind_group, &[]);
         }
     }
+
+    fn render_ranges<'pass>(
+        &'pass self,
+        render_pass: &mut wgpu::RenderPass<'pass>,
+        ranges: BlendRanges,
+        camera: &Camera,
+    ) {
+        let viewport = camera.viewport;
+        let projection = camera.build_view_projection_matrix();
+
+        render_pass.set_viewport(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+            0.,
+            1.,
+        );
+        for (blend, range) in ranges {
+            // every pipeline has its own layout, the camera is set again
+            render_pass.set_pipeline(&self.pipelines[blend]);
+            render_pass.set_push_constants(
+                ShaderStages::VERTEX,
+                0,
+                bytemuck::cast_slice(&projection),
+            );
+            render_pass.draw(0..4, range.start as u32..range.end as u32);
+        }
+    }
+}
+
+/// Consecutive draws with the same blend mode, the world draws are sorted by it
+fn blend_ranges(draws: &[DrawImage], offset: usize) -> BlendRanges {
+    let mut ranges: BlendRanges = vec![];
+    for (index, draw) in draws.iter().enumerate() {
+        let blend = draw.blend.index();
+        match ranges.last_mut() {
+            Some((last, range)) if *last == blend => range.end = offset + index + 1,
+            _ => ranges.push((blend, offset + index..offset + index + 1)),
+        }
+    }
+    ranges
+}
+
+fn create_pipelines(
+    device: &Device,
+    bind_group_layout: &wgpu::BindGroupLayout,
+    config: &SurfaceConfiguration,
+) -> Vec<wgpu::RenderPipeline> {
+    Blend::ALL
+        .iter()
+        .map(|blend| create_pipeline(device, bind_group_layout, config, *blend))
+        .collect()
 }
 fn create_pipeline(
     device: &Device,
     bind_group_layout: &wgpu::BindGroupLayout,
     config: &SurfaceConfiguration,
+    blend: Blend,
 ) -> wgpu::RenderPipeline {
     let shader =
         device.create_shader_module(wgpu::include_wgsl!("texture_array_renderer_shader.wgsl"));
@@ -417,9 +517,9 @@ fn create_pipeline(
             module: &shader,
             entry_point: "vs_main",
             buffers: &[wgpu::VertexBufferLayout {
-                array_stride: std::mem::size_of::<DrawImage>() as wgpu::BufferAddress,
+                array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
                 step_mode: wgpu::VertexStepMode::Instance,
-                attributes: &wgpu::vertex_attr_array![0 => Uint32, 1 => Float32, 2 => Unorm8x4, 3 => Uint32x2, 4 => Sint32],
+                attributes: &INSTANCE_ATTRIBUTES,
             }],
         },
         fragment: Some(wgpu::FragmentState {
@@ -427,18 +527,7 @@ fn create_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.format,
-                blend: Some(wgpu::BlendState {
-                    color: wgpu::BlendComponent {
-                        src_factor: wgpu::BlendFactor::SrcAlpha,
-                        dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                    alpha: wgpu::BlendComponent {
-                        src_factor: wgpu::BlendFactor::One,
-                        dst_factor: wgpu::BlendFactor::One,
-                        operation: wgpu::BlendOperation::Add,
-                    },
-                }),
+                blend: Some(blend_state(blend)),
 
                 write_mask: wgpu::ColorWrites::COLOR,
             })],
@@ -509,9 +598,12 @@ fn create_depth_texture(state: &State, size: engine::window::Size) -> wgpu::Text
     texture.create_view(&wgpu::TextureViewDescriptor::default())
 }
 
+/// Ranges of draws and the blend mode they are drawn with
+type BlendRanges = Vec<(usize, Range<usize>)>;
+
 #[derive(Debug)]
 pub struct Instructions {
-    pub world_range: Range<usize>,
-    pub ui_range: Range<usize>,
-    pub to_textures_ranges: Vec<(TextureID, Range<usize>)>,
+    pub world_ranges: BlendRanges,
+    pub ui_ranges: BlendRanges,
+    pub to_textures_ranges: Vec<(TextureID, BlendRanges)>,
 }
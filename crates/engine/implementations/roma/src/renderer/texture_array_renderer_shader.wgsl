@@ -12,6 +12,11 @@ struct VertexInput {
     @location(2) color: vec4<f32>,
     @location(3) source: vec2<u32>,
     @location(4) index: i32,
+    @location(5) scale: vec2<f32>,
+    @location(6) pivot: vec2<f32>,
+    @location(7) rotation: f32,
+    @location(8) flip: u32,
+    @location(9) blend: u32,
 }
 
 fn map_source(source: vec4<f32>, texture_dimensions: vec2<u32>) -> vec4<f32> {
@@ -36,54 +41,31 @@ fn vs_main(input: VertexInput) -> VertexOutput {
     let y = f32(input.xy >> 16u);
     let x = f32(input.xy & 0xFFFFu);
 
-    switch (input.vertex_index) {
-        case 0u: {
-            let left = x;
-            let top = y;
-            position = vec2<f32>(left, top);
-
-            let tex_left = source.x / f32(texture_dimensions.x);
-            let tex_top = (source.y + source.w) / f32(texture_dimensions.y);
-            output.texture_position = vec2<f32>(tex_left, tex_top);
-            break;
-        }
-        case 1u: {
-            let top = y;
-            let right = x + source.z;
-            position = vec2<f32>(right, top);
-
-            let tex_top = (source.y + source.w) / f32(texture_dimensions.y);
-            let tex_right = (source.x + source.z) / f32(texture_dimensions.x);
-            output.texture_position = vec2<f32>(tex_right, tex_top);
-            break;
-        }
-        case 2u: {
-            let left = x;
-            let bottom = y + source.w;
-            position = vec2<f32>(left, bottom);
-
-            let tex_bottom = source.y / f32(texture_dimensions.y);
-            let tex_left = source.x / f32(texture_dimensions.x);
-            output.texture_position = vec2<f32>(tex_left, tex_bottom);
-            break;
-        }
-        case 3u: {
-            let right = x + source.z;
-            let bottom = y + source.w;
-            position = vec2<f32>(right, bottom);
-
-            let tex_bottom = source.y / f32(texture_dimensions.y);
-            let tex_right = (source.x + source.z) / f32(texture_dimensions.x);
-            output.texture_position = vec2<f32>(tex_right, tex_bottom);
-            break;
-        }
-        default: {}
+    // corners of the strip: bottom left, bottom right, top left, top right
+    let corner = vec2<f32>(f32(input.vertex_index & 1u), f32(input.vertex_index >> 1u));
+    let size = source.zw * input.scale;
+    let pivot = input.pivot * size;
+    let offset = corner * size - pivot;
+    let sine = sin(input.rotation);
+    let cosine = cos(input.rotation);
+    let rotated = vec2<f32>(offset.x * cosine - offset.y * sine, offset.x * sine + offset.y * cosine);
+    position = vec2<f32>(x, y) + pivot + rotated;
+
+    // the bottom of the quad samples the bottom of the source
+    var texture_corner = vec2<f32>(corner.x, 1.0 - corner.y);
+    if (input.flip & 1u) != 0u {
+        texture_corner.x = 1.0 - texture_corner.x;
     }
+    if (input.flip & 2u) != 0u {
+        texture_corner.y = 1.0 - texture_corner.y;
+    }
+    output.texture_position = (source.xy + texture_corner * source.zw) / vec2<f32>(texture_dimensions);
 
     output.position = camera_projection * vec4<f32>(position, 1.0, 1.0);
     output.position.z = input.z;
     output.color = input.color;
     output.index = input.index;
+    output.blend = input.blend;
 
     return output;
 }
@@ -93,6 +75,7 @@ struct VertexOutput {
     @location(0) texture_position: vec2<f32>,
     @location(1) color: vec4<f32>,
     @location(2) index: i32,
+    @location(3) @interpolate(flat) blend: u32,
 }
 
 @fragment
@@ -106,5 +89,11 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 
-    return color * in.color;
+    let output = color * in.color;
+    // the multiply pipeline keeps what is drawn where the image is transparent
+    if in.blend == 2u {
+        return vec4<f32>(mix(vec3<f32>(1.0), output.rgb, output.a), output.a);
+    }
+
+    return output;
 }
@@ -1,99 +1,317 @@
-use engine::engine::TextureID;
+use engine::{draw::image::DrawImage, engine::TextureID};
+use image::{Rgba, RgbaImage};
 use nohash_hasher::IntMap;
 use wgpu::{Device, Queue};
 
 use crate::texture;
 
+use super::{atlas::Atlas, loader::Loader};
+
+/// Bytes uploaded to the gpu on a frame, at least one texture is always uploaded
+const UPLOAD_BUDGET: usize = 8 * 1024 * 1024;
+
+/// Memory used by textures loaded from files when `TEXTURE_MEMORY_MB` is not set
+const DEFAULT_MEMORY_MB: usize = 512;
+
+/// Priority of the textures that are drawn before being prefetched
+pub const DRAW_PRIORITY: u8 = u8::MAX;
+
+/// Drawn instead of the textures that are still loading
+const PLACEHOLDER_COLOR: [u8; 4] = [0, 0, 0, 64];
+
 #[derive(Default)]
 enum Texture {
     #[default]
     Uninitialized,
+    Loading,
     NotFound,
     Present(texture::Texture),
+    /// Copied into an atlas page
+    Packed {
+        width: u16,
+        height: u16,
+    },
+    Page,
 }
 
 pub struct Textures {
     textures: Vec<Texture>,
     files: IntMap<u32, String>,
+    /// Frame each texture was last drawn, the least recently used ones are evicted first
+    last_used: Vec<u64>,
+    /// Dimensions read from the file headers, for the textures that are not loaded
+    dimensions: IntMap<TextureID, (u16, u16)>,
+
+    loader: Loader,
+    loading: usize,
+    placeholder: Option<TextureID>,
+    atlas: Option<Atlas>,
+
+    frame: u64,
+    memory: usize,
+    memory_limit: usize,
 
     next_texture_id: usize,
 }
 
 impl Textures {
-    pub fn initialize() -> Self {
+    /// Small textures are packed in atlas pages when `atlas` is set
+    pub fn initialize(atlas: bool) -> Self {
+        let memory_limit = std::env::var("TEXTURE_MEMORY_MB")
+            .ok()
+            .and_then(|megabytes| megabytes.parse().ok())
+            .unwrap_or(DEFAULT_MEMORY_MB)
+            * 1024
+            * 1024;
+
         Self {
             textures: vec![],
             files: IntMap::default(),
+            last_used: vec![],
+            dimensions: IntMap::default(),
+            loader: Loader::initialize(),
+            loading: 0,
+            placeholder: None,
+            atlas: atlas.then(Atlas::default),
+            frame: 0,
+            memory: 0,
+            memory_limit,
             next_texture_id: 0,
         }
     }
 
     pub fn add_file(&mut self, path: &str) -> TextureID {
         let id = self.next_texture_id as TextureID;
-        self.textures.push(Texture::Uninitialized);
-        self.files.insert(id, path.to_string());
-        self.next_texture_id += 1;
+        self.set_file(id, path);
         id
     }
 
     pub fn set_file(&mut self, id: TextureID, path: &str) {
-        let size = self.textures.len() as u32;
-        if id > size {
-            for _ in size..=id {
-                self.textures.push(Texture::Uninitialized);
-            }
-        }
-
+        self.reserve(id);
         self.files.insert(id, path.to_string());
-        if id as usize > self.next_texture_id {
-            self.next_texture_id = id as usize + 1;
-        }
+        self.dimensions.remove(&id);
     }
 
     pub fn add_texture(&mut self, texture: texture::Texture) -> TextureID {
-        let id = self.next_texture_id;
-        self.textures.push(Texture::Present(texture));
-        self.next_texture_id += 1;
-
-        id as TextureID
+        let id = self.next_texture_id as TextureID;
+        self.reserve(id);
+        self.textures[id as usize] = Texture::Present(texture);
+        id
     }
 
-    pub fn load_texture(&mut self, device: &Device, queue: &Queue, id: TextureID) -> bool {
+    /// Queues the textures that are not loaded yet, or raises their priority
+    pub fn prefetch(&mut self, id: TextureID, priority: u8) {
+        if id as usize >= self.textures.len() {
+            return;
+        }
         match self.textures[id as usize] {
             Texture::Uninitialized => {
-                if (id as usize) > self.next_texture_id {
-                    self.textures.push(Texture::Uninitialized);
-                    self.next_texture_id = 1 + id as usize;
-                }
                 let Some(path) = self.files.get(&id) else {
                     self.textures[id as usize] = Texture::NotFound;
                     log::error!("Path not found for texture {id}");
-                    return false;
+                    return;
                 };
+                self.loader.request(id, path, priority);
+                self.textures[id as usize] = Texture::Loading;
+                self.loading += 1;
+            }
+            Texture::Loading => self.loader.promote(id, priority),
+            Texture::NotFound | Texture::Present(_) | Texture::Packed { .. } | Texture::Page => {}
+        }
+    }
 
-                let Ok(texture) = texture::Texture::from_path(device, queue, path) else {
-                    log::error!("Texture not found on {path}");
-                    self.textures[id as usize] = Texture::NotFound;
-                    return false;
-                };
+    /// Returns the texture to draw with, the placeholder while the texture is loading
+    pub fn ensure(&mut self, device: &Device, queue: &Queue, id: TextureID) -> Option<TextureID> {
+        if id as usize >= self.textures.len() {
+            log::error!("Texture {id} was not added");
+            return None;
+        }
+        self.last_used[id as usize] = self.frame;
 
-                self.textures[id as usize] = Texture::Present(texture);
+        if let Texture::Uninitialized = self.textures[id as usize] {
+            self.prefetch(id, DRAW_PRIORITY);
+        }
+        match self.textures[id as usize] {
+            Texture::Present(_) | Texture::Packed { .. } | Texture::Page => Some(id),
+            Texture::Loading => Some(self.placeholder(device, queue)),
+            Texture::NotFound | Texture::Uninitialized => None,
+        }
+    }
 
-                true
+    /// Uploads the decoded textures within the frame budget and evicts the least recently used
+    /// ones over the memory limit, returns the textures that were evicted or replaced
+    pub fn update(&mut self, device: &Device, queue: &Queue) -> Vec<TextureID> {
+        let mut changed = vec![];
+        let mut uploaded = 0;
+        while uploaded < UPLOAD_BUDGET {
+            let Some((id, image)) = self.loader.decoded().next() else {
+                break;
+            };
+            if !matches!(self.textures[id as usize], Texture::Loading) {
+                continue;
             }
-            Texture::NotFound => {
-                log::debug!("Texture {id} not found");
-                false
+            self.loading -= 1;
+
+            match image {
+                Ok(image) if self.atlas.is_some() && Atlas::fits(&image) => {
+                    let bytes = image.as_raw().len();
+                    changed.extend(self.pack(device, queue, id, &image));
+                    self.dimensions.remove(&id);
+                    self.textures[id as usize] = Texture::Packed {
+                        width: image.width() as u16,
+                        height: image.height() as u16,
+                    };
+                    self.memory += bytes;
+                    uploaded += bytes;
+                }
+                Ok(image) => {
+                    let bytes = image.as_raw().len();
+                    let label = self.files.get(&id).map(String::as_str);
+                    let texture = texture::Texture::from_rgba(device, queue, &image, label);
+                    self.dimensions.remove(&id);
+                    self.textures[id as usize] = Texture::Present(texture);
+                    self.memory += bytes;
+                    uploaded += bytes;
+                }
+                Err(error) => {
+                    log::error!("Texture {id} not loaded: {error}");
+                    self.textures[id as usize] = Texture::NotFound;
+                }
             }
-            Texture::Present(_) => true,
         }
+
+        changed.extend(self.evict());
+        self.frame += 1;
+        changed
+    }
+
+    /// Textures requested that are not uploaded yet
+    pub fn loading(&self) -> usize {
+        self.loading
+    }
+
+    pub fn dimensions(&mut self, id: TextureID) -> Option<(u16, u16)> {
+        if let Some(Some(texture)) = self.get(id) {
+            return Some((texture.width, texture.height));
+        }
+        if let Some(Texture::Packed { width, height }) = self.textures.get(id as usize) {
+            return Some((*width, *height));
+        }
+        if let Some(dimensions) = self.dimensions.get(&id) {
+            return Some(*dimensions);
+        }
+
+        // only the header is read, the texture keeps loading on its own
+        let path = self.files.get(&id)?;
+        let (width, height) = image::image_dimensions(path).ok()?;
+        let dimensions = (width as u16, height as u16);
+        self.dimensions.insert(id, dimensions);
+        Some(dimensions)
     }
 
     pub fn get(&self, id: TextureID) -> Option<Option<&texture::Texture>> {
-        match &self.textures[id as usize] {
-            Texture::Uninitialized => None,
+        match self.textures.get(id as usize)? {
+            Texture::Uninitialized | Texture::Loading | Texture::Packed { .. } => None,
             Texture::NotFound => Some(None),
             Texture::Present(texture) => Some(Some(texture)),
+            Texture::Page => self.atlas.as_ref()?.page_texture(id).map(Some),
+        }
+    }
+
+    /// Atlas page a texture was packed in
+    pub fn page(&self, id: TextureID) -> Option<TextureID> {
+        self.atlas.as_ref()?.page(id)
+    }
+
+    /// Draws of packed textures are drawn from their page
+    pub fn remap(&self, draw: &mut DrawImage) {
+        if let Some(atlas) = &self.atlas {
+            atlas.remap(draw);
+        }
+    }
+
+    /// Copies the texture into the atlas, returns the pages that were created or replaced
+    fn pack(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        id: TextureID,
+        image: &RgbaImage,
+    ) -> Vec<TextureID> {
+        let next_id = self.next_texture_id as TextureID;
+        let Some(atlas) = &mut self.atlas else {
+            return vec![];
+        };
+        let pages = atlas.insert(device, queue, id, image, next_id);
+        if pages.contains(&next_id) {
+            self.reserve(next_id);
+            self.textures[next_id as usize] = Texture::Page;
+        }
+        pages
+    }
+
+    fn placeholder(&mut self, device: &Device, queue: &Queue) -> TextureID {
+        if let Some(id) = self.placeholder {
+            return id;
+        }
+        let image = RgbaImage::from_pixel(1, 1, Rgba(PLACEHOLDER_COLOR));
+        let texture = texture::Texture::from_rgba(device, queue, &image, Some("placeholder"));
+        let id = self.add_texture(texture);
+        self.placeholder = Some(id);
+        id
+    }
+
+    /// Unloads textures from files that were not drawn this frame, starting from the oldest
+    fn evict(&mut self) -> Vec<TextureID> {
+        if self.memory <= self.memory_limit {
+            return vec![];
+        }
+
+        let mut candidates = self
+            .files
+            .keys()
+            .filter(|id| self.last_used[**id as usize] < self.frame)
+            .filter(|id| {
+                matches!(
+                    self.textures[**id as usize],
+                    Texture::Present(_) | Texture::Packed { .. }
+                )
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|id| self.last_used[*id as usize]);
+
+        let mut evicted = vec![];
+        for id in candidates {
+            if self.memory <= self.memory_limit {
+                break;
+            }
+            match std::mem::take(&mut self.textures[id as usize]) {
+                Texture::Present(texture) => {
+                    self.memory -= texture.width as usize * texture.height as usize * 4;
+                    self.dimensions.insert(id, (texture.width, texture.height));
+                    evicted.push(id);
+                }
+                Texture::Packed { width, height } => {
+                    self.memory -= width as usize * height as usize * 4;
+                    self.dimensions.insert(id, (width, height));
+                    if let Some(atlas) = &mut self.atlas {
+                        atlas.remove(id);
+                    }
+                }
+                texture => self.textures[id as usize] = texture,
+            }
+        }
+        log::debug!("evicted {} textures", evicted.len());
+        evicted
+    }
+
+    fn reserve(&mut self, id: TextureID) {
+        let size = id as usize + 1;
+        if self.textures.len() < size {
+            self.textures.resize_with(size, Texture::default);
+            self.last_used.resize(size, 0);
         }
+        self.next_texture_id = self.next_texture_id.max(size);
     }
 }
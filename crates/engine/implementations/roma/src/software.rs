@@ -0,0 +1,508 @@
+use std::{collections::BTreeMap, env, path::Path};
+
+use engine::{
+    camera::{Position, Viewport, Zoom},
+    draw::{
+        image::{Blend, DrawImage},
+        light::DrawLight,
+        text::{DrawText, ParsedText},
+        Color, Dimensions, Target,
+    },
+    engine::{FontID, TextureID},
+    window::Size,
+};
+use image::{Rgba, RgbaImage};
+use nohash_hasher::IntMap;
+
+use crate::{
+    camera::Camera,
+    fonts::{self, Fonts},
+    texture::read_image,
+};
+
+/// Set it to write the golden images instead of comparing against them
+pub const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 255]);
+
+/// Pixel centers this close outside of a rotated quad are still drawn, like the rasterizer
+const EDGE: f32 = 1e-4;
+
+#[derive(Default)]
+enum Texture {
+    #[default]
+    Uninitialized,
+    NotFound,
+    Present(RgbaImage),
+}
+
+/// Reference renderer that runs on the cpu, it follows the gpu renderers: same cameras,
+/// depth test, discarded pixels and blending, so a frame can be saved and compared as a png
+pub struct SoftwareRenderer {
+    size: Size,
+    world_camera: Camera,
+    ui_camera: Camera,
+    fonts: Fonts,
+
+    textures: IntMap<TextureID, Texture>,
+    files: IntMap<TextureID, String>,
+    next_texture_id: TextureID,
+
+    draws_to_textures: BTreeMap<TextureID, Vec<DrawImage>>,
+    draws_to_zero_world: Vec<DrawImage>,
+    draws_to_world: Vec<DrawImage>,
+    transparent_draws_to_world: Vec<DrawImage>,
+    draws_to_ui: Vec<DrawImage>,
+
+    ambient_light: Color,
+    lights: Vec<DrawLight>,
+
+    frame: RgbaImage,
+}
+
+impl SoftwareRenderer {
+    pub fn new(size: Size) -> Self {
+        Self {
+            size,
+            world_camera: Camera::initialize(size, true),
+            ui_camera: Camera::initialize(size, false),
+            fonts: Fonts::initialize(),
+
+            textures: IntMap::default(),
+            files: IntMap::default(),
+            next_texture_id: 0,
+
+            draws_to_textures: BTreeMap::new(),
+            draws_to_zero_world: vec![],
+            draws_to_world: vec![],
+            transparent_draws_to_world: vec![],
+            draws_to_ui: vec![],
+
+            ambient_light: [255, 255, 255, 255],
+            lights: vec![],
+
+            frame: RgbaImage::from_pixel(size.width as u32, size.height as u32, CLEAR),
+        }
+    }
+
+    pub fn add_texture(&mut self, path: &str) -> TextureID {
+        let id = self.next_texture_id;
+        self.set_texture(path, id);
+        id
+    }
+
+    pub fn set_texture(&mut self, path: &str, id: TextureID) {
+        self.textures.insert(id, Texture::Uninitialized);
+        self.files.insert(id, path.to_string());
+        if id >= self.next_texture_id {
+            self.next_texture_id = id + 1;
+        }
+    }
+
+    pub fn create_texture(&mut self, dimensions: Dimensions) -> TextureID {
+        let id = self.next_texture_id;
+        let texture =
+            RgbaImage::from_pixel(dimensions.width as u32, dimensions.height as u32, CLEAR);
+        self.textures.insert(id, Texture::Present(texture));
+        self.next_texture_id += 1;
+        id
+    }
+
+    pub fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)> {
+        self.texture(texture_id)
+            .map(|texture| (texture.width() as u16, texture.height() as u16))
+    }
+
+    /// Pixels of a texture, loading it from its file the first time
+    pub fn texture(&mut self, id: TextureID) -> Option<&RgbaImage> {
+        if !self.load_texture(id) {
+            return None;
+        }
+        match self.textures.get(&id) {
+            Some(Texture::Present(texture)) => Some(texture),
+            _ => None,
+        }
+    }
+
+    pub fn draw_image(&mut self, draw: DrawImage, target: Target) {
+        if self.load_texture(draw.index) {
+            self.push_draw_image(draw, target);
+        }
+    }
+
+    pub fn add_font(&mut self, id: FontID, path: &str, texture_id: TextureID) {
+        self.fonts.add_font(id, texture_id, path);
+    }
+
+    pub fn parse_text(&mut self, id: FontID, text: &str) -> Option<ParsedText> {
+        self.fonts.parse_text(id, text)
+    }
+
+    pub fn draw_text(&mut self, id: FontID, parameters: DrawText, target: Target) {
+        let Some(texture_id) = self.fonts.get_texture_id(id) else {
+            log::error!("[draw_text] texture id for font {id} not found");
+            return;
+        };
+        if !self.load_texture(texture_id) {
+            return;
+        }
+
+        for draw in fonts::text_draws(texture_id, &parameters) {
+            self.push_draw_image(draw, target);
+        }
+    }
+
+    pub fn draw_light(&mut self, light: DrawLight) {
+        self.lights.push(light);
+    }
+
+    pub fn set_ambient_light(&mut self, color: Color) {
+        self.ambient_light = color;
+    }
+
+    pub fn set_world_camera_viewport(&mut self, viewport: Viewport) {
+        self.world_camera.viewport = viewport;
+    }
+
+    pub fn set_camera_zoom(&mut self, zoom: Zoom) {
+        self.world_camera.zoom = zoom;
+        self.ui_camera.zoom = zoom;
+    }
+
+    pub fn set_world_camera_position(&mut self, position: Position) {
+        self.world_camera.position = position;
+    }
+
+    pub fn set_ui_camera_viewport(&mut self, viewport: Viewport) {
+        self.ui_camera.viewport = viewport;
+    }
+
+    pub fn resize(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    /// Draws everything pushed since the last render, textures first like the gpu renderers
+    pub fn render(&mut self) {
+        for (target_id, draws) in std::mem::take(&mut self.draws_to_textures) {
+            // taken out so the draws can read the other textures
+            let Some(Texture::Present(mut target)) = self.textures.remove(&target_id) else {
+                continue;
+            };
+            let size = Size {
+                width: target.width() as u16,
+                height: target.height() as u16,
+            };
+            let camera = Camera::initialize(size, false);
+            clear(&mut target);
+            let mut depth = vec![0.; target.len() / 4];
+            for draw in &draws {
+                self.rasterize(&mut target, &mut depth, &camera, draw);
+            }
+            self.textures.insert(target_id, Texture::Present(target));
+        }
+
+        let mut frame =
+            RgbaImage::from_pixel(self.size.width as u32, self.size.height as u32, CLEAR);
+        let mut depth = vec![0.; frame.len() / 4];
+        let world = by_blend(std::mem::take(&mut self.draws_to_zero_world))
+            .into_iter()
+            .chain(by_blend(std::mem::take(&mut self.draws_to_world)))
+            .chain(by_blend(std::mem::take(
+                &mut self.transparent_draws_to_world,
+            )));
+        for draw in world {
+            self.rasterize(&mut frame, &mut depth, &self.world_camera, &draw);
+        }
+        self.apply_lights(&mut frame);
+        for draw in std::mem::take(&mut self.draws_to_ui) {
+            self.rasterize(&mut frame, &mut depth, &self.ui_camera, &draw);
+        }
+        self.frame = frame;
+    }
+
+    /// Last rendered frame
+    pub fn frame(&self) -> &RgbaImage {
+        &self.frame
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        self.frame
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(|error| error.to_string())
+    }
+
+    /// Compares the last frame against a png, with `UPDATE_GOLDEN` set the png is written instead.
+    /// On a mismatch the frame is saved next to it as `<name>.actual.png`
+    pub fn compare_golden(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        if env::var(UPDATE_GOLDEN).is_ok() {
+            return self.save_png(path);
+        }
+        let golden = read_image(&path.to_string_lossy())
+            .map_err(|error| format!("golden image {}: {error}", path.display()))?
+            .to_rgba8();
+
+        let different = if golden.dimensions() != self.frame.dimensions() {
+            Some(format!(
+                "size is {:?}, expected {:?}",
+                self.frame.dimensions(),
+                golden.dimensions()
+            ))
+        } else {
+            let pixels = golden
+                .pixels()
+                .zip(self.frame.pixels())
+                .filter(|(expected, actual)| expected != actual)
+                .count();
+            (pixels > 0).then(|| format!("{pixels} pixels are different"))
+        };
+
+        let Some(different) = different else {
+            return Ok(());
+        };
+        let actual = path.with_extension("actual.png");
+        self.save_png(&actual)?;
+        Err(format!(
+            "{} doesn't match: {different}, the frame was saved to {}",
+            path.display(),
+            actual.display()
+        ))
+    }
+
+    fn load_texture(&mut self, id: TextureID) -> bool {
+        let texture = self.textures.entry(id).or_default();
+        match texture {
+            Texture::Uninitialized => {
+                let Some(path) = self.files.get(&id) else {
+                    *texture = Texture::NotFound;
+                    log::error!("Path not found for texture {id}");
+                    return false;
+                };
+                let Ok(image) = read_image(path) else {
+                    log::error!("Texture not found on {path}");
+                    *texture = Texture::NotFound;
+                    return false;
+                };
+                *texture = Texture::Present(image.to_rgba8());
+                true
+            }
+            Texture::NotFound => false,
+            Texture::Present(_) => true,
+        }
+    }
+
+    fn push_draw_image(&mut self, draw: DrawImage, target: Target) {
+        match target {
+            Target::World => {
+                if draw.position.z == 0.0 {
+                    self.draws_to_zero_world.push(draw);
+                } else if draw.color[3] < 255 || draw.blend != Blend::Alpha {
+                    self.transparent_draws_to_world.push(draw);
+                } else {
+                    self.draws_to_world.push(draw);
+                }
+            }
+            Target::UI => self.draws_to_ui.push(draw),
+            Target::Texture { id } => self.draws_to_textures.entry(id).or_default().push(draw),
+        }
+    }
+
+    /// Multiplies the world by the ambient light plus the lights, like the lighting pass
+    fn apply_lights(&mut self, frame: &mut RgbaImage) {
+        let lights = std::mem::take(&mut self.lights);
+        if self.ambient_light[..3] == [255, 255, 255] {
+            return;
+        }
+
+        let projection = self.world_camera.build_view_projection_matrix();
+        let viewport = self.world_camera.viewport;
+        // pixels of the target per world pixel, the same on both axes
+        let scale = viewport.width * projection[0][0] / 2.;
+        let lights = lights
+            .iter()
+            .filter(|light| light.radius > 0.)
+            .map(|light| {
+                let center = to_screen(&projection, viewport, light.position[0], light.position[1]);
+                (center, light.radius * scale, light.color)
+            })
+            .collect::<Vec<_>>();
+
+        let first_column = viewport.x.max(0.);
+        let last_column = (viewport.x + viewport.width).min(frame.width() as f32);
+        let first_row = viewport.y.max(0.);
+        let last_row = (viewport.y + viewport.height).min(frame.height() as f32);
+        for row in pixel_centers(first_row, last_row) {
+            for column in pixel_centers(first_column, last_column) {
+                let mut levels = self.ambient_light.map(|channel| channel as f32 / 255.);
+                for ((x, y), radius, color) in &lights {
+                    let distance = (column + 0.5 - x).hypot(row + 0.5 - y) / radius;
+                    let falloff = (1. - distance).clamp(0., 1.);
+                    let intensity = color[3] as f32 / 255. * falloff * falloff;
+                    for (level, channel) in levels.iter_mut().zip(color).take(3) {
+                        *level += *channel as f32 / 255. * intensity;
+                    }
+                }
+
+                let pixel = frame.get_pixel_mut(column as u32, row as u32);
+                for (channel, level) in pixel.0.iter_mut().zip(levels).take(3) {
+                    // the light texture keeps 8 bits per channel
+                    let level = (level.min(1.) * 255.).round() / 255.;
+                    *channel = (*channel as f32 * level).round() as u8;
+                }
+            }
+        }
+    }
+
+    /// Draws a sprite the way the shader does: a quad of the source size projected by the
+    /// camera, sampled with nearest filtering and drawn if its depth is greater or equal
+    fn rasterize(
+        &self,
+        target: &mut RgbaImage,
+        depth: &mut [f32],
+        camera: &Camera,
+        draw: &DrawImage,
+    ) {
+        let Some(Texture::Present(texture)) = self.textures.get(&draw.index) else {
+            return;
+        };
+        let [mut source_x, mut source_y, mut source_width, mut source_height] =
+            draw.source.map(f32::from);
+        if source_width == 0. && source_height == 0. {
+            (source_x, source_y) = (0., 0.);
+            (source_width, source_height) = (texture.width() as f32, texture.height() as f32);
+        }
+
+        let projection = camera.build_view_projection_matrix();
+        let viewport = camera.viewport;
+        let to_screen = |x: f32, y: f32| to_screen(&projection, viewport, x, y);
+        let transform = draw.transform;
+        let width = source_width * transform.scale[0];
+        let height = source_height * transform.scale[1];
+        let pivot = (transform.pivot[0] * width, transform.pivot[1] * height);
+        let (sine, cosine) = transform.rotation.sin_cos();
+        // corners of the quad rotated around the pivot, in screen coordinates
+        let corner = |u: f32, v: f32| {
+            let (offset_x, offset_y) = (u * width - pivot.0, v * height - pivot.1);
+            to_screen(
+                draw.position.x as f32 + pivot.0 + offset_x * cosine - offset_y * sine,
+                draw.position.y as f32 + pivot.1 + offset_x * sine + offset_y * cosine,
+            )
+        };
+        let origin = corner(0., 0.);
+        let end_u = corner(1., 0.);
+        let end_v = corner(0., 1.);
+        let opposite = corner(1., 1.);
+        let axis_u = (end_u.0 - origin.0, end_u.1 - origin.1);
+        let axis_v = (end_v.0 - origin.0, end_v.1 - origin.1);
+        let determinant = axis_u.0 * axis_v.1 - axis_u.1 * axis_v.0;
+        if determinant == 0. {
+            return;
+        }
+
+        // pixels outside of the viewport are clipped
+        let clip_left = viewport.x.max(0.);
+        let clip_top = viewport.y.max(0.);
+        let clip_right = (viewport.x + viewport.width).min(target.width() as f32);
+        let clip_bottom = (viewport.y + viewport.height).min(target.height() as f32);
+
+        let z = draw.position.z.clamp(0., 1.);
+        let tint = draw.color.map(|channel| channel as f32 / 255.);
+
+        let xs = [origin.0, end_u.0, end_v.0, opposite.0];
+        let ys = [origin.1, end_u.1, end_v.1, opposite.1];
+        let first_column = xs.into_iter().fold(f32::MAX, f32::min).max(clip_left);
+        let last_column = xs.into_iter().fold(f32::MIN, f32::max).min(clip_right);
+        let first_row = ys.into_iter().fold(f32::MAX, f32::min).max(clip_top);
+        let last_row = ys.into_iter().fold(f32::MIN, f32::max).min(clip_bottom);
+
+        for row in pixel_centers(first_row, last_row) {
+            for column in pixel_centers(first_column, last_column) {
+                // position of the pixel center inside the quad, from its bottom left corner
+                let (delta_x, delta_y) = (column + 0.5 - origin.0, row + 0.5 - origin.1);
+                let u = (delta_x * axis_v.1 - delta_y * axis_v.0) / determinant;
+                let v = (axis_u.0 * delta_y - axis_u.1 * delta_x) / determinant;
+                if !(-EDGE..=1. + EDGE).contains(&u) || !(-EDGE..=1. + EDGE).contains(&v) {
+                    continue;
+                }
+
+                // the bottom of the quad samples the bottom of the source
+                let mut texture_u = u;
+                let mut texture_v = 1. - v;
+                if transform.flip.horizontal {
+                    texture_u = 1. - texture_u;
+                }
+                if transform.flip.vertical {
+                    texture_v = 1. - texture_v;
+                }
+                let texel_x = ((source_x + texture_u * source_width).floor() as i64)
+                    .clamp(0, texture.width() as i64 - 1) as u32;
+                let texel_y = ((source_y + texture_v * source_height).floor() as i64)
+                    .clamp(0, texture.height() as i64 - 1) as u32;
+
+                let texel = texture.get_pixel(texel_x, texel_y).0;
+                // opaque black is the transparent color of the sprites
+                if texel == [0, 0, 0, 255] || texel[3] == 0 {
+                    continue;
+                }
+
+                let index = row as usize * target.width() as usize + column as usize;
+                if z < depth[index] {
+                    continue;
+                }
+                depth[index] = z;
+
+                let color: [f32; 4] =
+                    std::array::from_fn(|channel| texel[channel] as f32 / 255. * tint[channel]);
+                blend(
+                    target.get_pixel_mut(column as u32, row as u32),
+                    color,
+                    draw.blend,
+                );
+            }
+        }
+    }
+}
+
+/// World position in pixels of the target, from its top left corner like the rasterizer
+fn to_screen(projection: &[[f32; 4]; 4], viewport: Viewport, x: f32, y: f32) -> (f32, f32) {
+    let ndc_x = projection[0][0] * x + projection[1][0] * y + projection[2][0] + projection[3][0];
+    let ndc_y = projection[0][1] * x + projection[1][1] * y + projection[2][1] + projection[3][1];
+    (
+        viewport.x + (ndc_x + 1.) / 2. * viewport.width,
+        viewport.y + (1. - ndc_y) / 2. * viewport.height,
+    )
+}
+
+/// Draws with the same blend mode keep their order, the world effects go after the alpha blended
+/// ones. The ui and the textures are drawn in order
+fn by_blend(mut draws: Vec<DrawImage>) -> Vec<DrawImage> {
+    draws.sort_by_key(|draw| draw.blend.index());
+    draws
+}
+
+/// Rows or columns whose center is in `start..end`
+fn pixel_centers(start: f32, end: f32) -> impl Iterator<Item = f32> {
+    let first = (start - 0.5).ceil().max(0.) as u32;
+    let last = (end - 0.5).ceil().max(0.) as u32;
+    (first..last).map(|pixel| pixel as f32)
+}
+
+/// Blending of the pipelines, the alpha channels are added
+fn blend(pixel: &mut Rgba<u8>, source: [f32; 4], mode: Blend) {
+    let destination = pixel.0.map(|channel| channel as f32 / 255.);
+    let alpha = source[3];
+    let color = |channel: usize| match mode {
+        Blend::Additive => source[channel] * alpha + destination[channel],
+        // faded towards white where the image is transparent, like the shader does
+        Blend::Multiply => (1. + (source[channel] - 1.) * alpha) * destination[channel],
+        Blend::Alpha => source[channel] * alpha + destination[channel] * (1. - alpha),
+    };
+    let blended = [color(0), color(1), color(2), source[3] + destination[3]];
+    pixel.0 = blended.map(|channel| (channel.clamp(0., 1.) * 255.).round() as u8);
+}
+
+fn clear(image: &mut RgbaImage) {
+    for pixel in image.pixels_mut() {
+        *pixel = CLEAR;
+    }
+}
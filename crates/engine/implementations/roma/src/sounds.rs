@@ -1,7 +1,166 @@
-pub struct Sounds {}
+use std::sync::{Arc, Mutex};
+
+use engine::{
+    camera::Position,
+    engine::SoundID,
+    sound::{Bus, Hearing, PlayMusic, PlaySound, Spatial},
+};
+use nohash_hasher::IntMap;
+
+use self::{clip::Clip, decoder::Decoder, mixer::Mixer, output::Output};
+
+mod clip;
+mod decoder;
+mod mixer;
+mod output;
+
+#[derive(Clone)]
+enum Decoding {
+    Loading,
+    Ready(Arc<Clip>),
+    Failed,
+}
+
+pub struct Sounds {
+    files: IntMap<SoundID, String>,
+    /// Decoded the first time they are played
+    clips: IntMap<SoundID, Decoding>,
+    /// Sounds played while their clip was being decoded, they start once it's ready
+    pending_sounds: Vec<(SoundID, PlaySound, Spatial)>,
+    pending_music: Option<(SoundID, PlayMusic)>,
+    next_sound_id: SoundID,
+    hearing: Hearing,
+
+    decoder: Decoder,
+    mixer: Arc<Mutex<Mixer>>,
+    _output: Output,
+}
 
 impl Sounds {
-    pub fn initialize() -> Self {
-        Self {}
+    pub fn initialize(hearing: Hearing) -> Self {
+        let (mixer, output) = output::open();
+        let sample_rate = mixer.lock().map(|mixer| mixer.sample_rate()).unwrap_or(0);
+        Self {
+            files: IntMap::default(),
+            clips: IntMap::default(),
+            pending_sounds: vec![],
+            pending_music: None,
+            next_sound_id: 0,
+            hearing,
+
+            decoder: Decoder::initialize(sample_rate),
+            mixer,
+            _output: output,
+        }
+    }
+
+    pub fn add_sound(&mut self, path: &str) -> SoundID {
+        let id = self.next_sound_id;
+        self.set_sound(path, id);
+        id
+    }
+
+    pub fn set_sound(&mut self, path: &str, id: SoundID) {
+        self.files.insert(id, path.to_string());
+        self.clips.remove(&id);
+        if id >= self.next_sound_id {
+            self.next_sound_id = id + 1;
+        }
+    }
+
+    /// Positional sounds are heard from `listener`, the ones out of range are not played
+    pub fn play_sound(&mut self, id: SoundID, parameters: PlaySound, listener: Position) {
+        let spatial = match parameters.position {
+            Some(position) => match self.hearing.spatialize(listener, position) {
+                Some(spatial) => spatial,
+                None => return,
+            },
+            None => Spatial::CENTER,
+        };
+        match self.clip(id) {
+            Decoding::Ready(clip) => self.mixer().play(id, clip, parameters, spatial),
+            Decoding::Loading => self.pending_sounds.push((id, parameters, spatial)),
+            Decoding::Failed => {}
+        }
+    }
+
+    pub fn play_music(&mut self, id: SoundID, parameters: PlayMusic) {
+        self.pending_music = None;
+        match self.clip(id) {
+            Decoding::Ready(clip) => self.mixer().play_music(id, clip, parameters.volume),
+            Decoding::Loading => self.pending_music = Some((id, parameters)),
+            Decoding::Failed => {}
+        }
+    }
+
+    pub fn stop_music(&mut self) {
+        self.pending_music = None;
+        self.mixer().fade_out(Bus::Music);
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: u8) {
+        self.mixer().set_volume(bus, volume);
+    }
+
+    pub fn stop_sounds(&mut self, bus: Bus) {
+        self.pending_sounds
+            .retain(|(_, parameters, _)| parameters.bus != bus);
+        if bus == Bus::Music {
+            self.pending_music = None;
+        }
+        self.mixer().stop(bus);
+    }
+
+    /// Stores the clips decoded since the last frame and starts the sounds waiting for them
+    pub fn update(&mut self) {
+        let decoded = self.decoder.decoded().collect::<Vec<_>>();
+        for (id, path, clip) in decoded {
+            // the sound was replaced by `set_sound` while it was being decoded
+            if self.files.get(&id) != Some(&path) {
+                continue;
+            }
+            let clip = match clip {
+                Ok(clip) => Decoding::Ready(Arc::new(clip)),
+                Err(error) => {
+                    log::error!("couldn't decode sound {path}: {error}");
+                    Decoding::Failed
+                }
+            };
+            self.clips.insert(id, clip);
+        }
+
+        let pending = std::mem::take(&mut self.pending_sounds);
+        for (id, parameters, spatial) in pending {
+            match self.clip(id) {
+                Decoding::Ready(clip) => self.mixer().play(id, clip, parameters, spatial),
+                Decoding::Loading => self.pending_sounds.push((id, parameters, spatial)),
+                Decoding::Failed => {}
+            }
+        }
+        if let Some((id, parameters)) = self.pending_music.take() {
+            self.play_music(id, parameters);
+        }
+    }
+
+    /// Requests the decoding the first time a sound is played
+    fn clip(&mut self, id: SoundID) -> Decoding {
+        if let Some(clip) = self.clips.get(&id) {
+            return clip.clone();
+        }
+        let Some(path) = self.files.get(&id) else {
+            log::error!("Path not found for sound {id}");
+            self.clips.insert(id, Decoding::Failed);
+            return Decoding::Failed;
+        };
+        self.decoder.request(id, path);
+        self.clips.insert(id, Decoding::Loading);
+        Decoding::Loading
+    }
+
+    fn mixer(&self) -> std::sync::MutexGuard<'_, Mixer> {
+        // the audio thread can't panic while mixing, a poisoned lock is still usable
+        self.mixer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
     }
 }
@@ -0,0 +1,103 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+/// Decoded sound, interleaved stereo samples at the sample rate of the output
+pub struct Clip {
+    pub samples: Vec<f32>,
+}
+
+impl Clip {
+    /// Decodes a WAV or OGG file
+    pub fn from_path(path: &str, sample_rate: u32) -> Result<Self, String> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        let (samples, channels, rate) = match extension.as_deref() {
+            Some("wav") => decode_wav(path)?,
+            Some("ogg") => decode_ogg(path)?,
+            _ => return Err(format!("unsupported sound format: {path}")),
+        };
+        let stereo = to_stereo(&samples, channels);
+        Ok(Self {
+            samples: resample(&stereo, rate, sample_rate),
+        })
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / 2
+    }
+}
+
+fn decode_wav(path: &str) -> Result<(Vec<f32>, u16, u32), String> {
+    let reader = hound::WavReader::open(path).map_err(|error| error.to_string())?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<Vec<_>, _>>(),
+        hound::SampleFormat::Int => {
+            let scale = (1_i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 / scale))
+                .collect()
+        }
+    }
+    .map_err(|error| error.to_string())?;
+    Ok((samples, spec.channels, spec.sample_rate))
+}
+
+fn decode_ogg(path: &str) -> Result<(Vec<f32>, u16, u32), String> {
+    let file = File::open(path).map_err(|error| error.to_string())?;
+    let mut reader = lewton::inside_ogg::OggStreamReader::new(BufReader::new(file))
+        .map_err(|error| error.to_string())?;
+    let channels = reader.ident_hdr.audio_channels as u16;
+    let rate = reader.ident_hdr.audio_sample_rate;
+
+    let mut samples = vec![];
+    while let Some(packet) = reader
+        .read_dec_packet_itl()
+        .map_err(|error| error.to_string())?
+    {
+        samples.extend(packet.into_iter().map(|sample| sample as f32 / 32768.));
+    }
+    Ok((samples, channels, rate))
+}
+
+/// Mono is played in both sides, only the first two channels of the rest are kept
+fn to_stereo(samples: &[f32], channels: u16) -> Vec<f32> {
+    match channels {
+        0 => vec![],
+        1 => samples
+            .iter()
+            .flat_map(|sample| [*sample, *sample])
+            .collect(),
+        2 => samples.to_vec(),
+        channels => samples
+            .chunks_exact(channels as usize)
+            .flat_map(|frame| [frame[0], frame[1]])
+            .collect(),
+    }
+}
+
+/// Linear interpolation, good enough for effects recorded at 22050 or 44100 hz
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || from == 0 || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let frames = samples.len() / 2;
+    let resampled_frames = (frames as u64 * to as u64 / from as u64) as usize;
+    let step = from as f64 / to as f64;
+
+    let mut resampled = Vec::with_capacity(resampled_frames * 2);
+    for frame in 0..resampled_frames {
+        let position = frame as f64 * step;
+        let index = position as usize;
+        let next = (index + 1).min(frames - 1);
+        let fraction = (position - index as f64) as f32;
+        for channel in 0..2 {
+            let current = samples[index * 2 + channel];
+            let following = samples[next * 2 + channel];
+            resampled.push(current + (following - current) * fraction);
+        }
+    }
+    resampled
+}
@@ -0,0 +1,47 @@
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use engine::engine::SoundID;
+
+use super::clip::Clip;
+
+/// Id and path of the request along with the result
+pub type Decoded = (SoundID, String, Result<Clip, String>);
+
+/// Decodes sound files on a worker thread, in the order they are requested. Once dropped the
+/// worker stops after the file it is decoding
+pub struct Decoder {
+    jobs: Sender<(SoundID, String)>,
+    decoded: Receiver<Decoded>,
+}
+
+impl Decoder {
+    pub fn initialize(sample_rate: u32) -> Self {
+        let (jobs, requests) = mpsc::channel::<(SoundID, String)>();
+        let (sender, decoded) = mpsc::channel();
+        thread::Builder::new()
+            .name("sound decoder".to_string())
+            .spawn(move || {
+                for (id, path) in requests {
+                    let clip = Clip::from_path(&path, sample_rate);
+                    if sender.send((id, path, clip)).is_err() {
+                        return;
+                    }
+                }
+            })
+            .expect("couldn't spawn sound decoder");
+
+        Self { jobs, decoded }
+    }
+
+    pub fn request(&self, id: SoundID, path: &str) {
+        let _ = self.jobs.send((id, path.to_string()));
+    }
+
+    /// Sounds decoded since the last call
+    pub fn decoded(&self) -> impl Iterator<Item = Decoded> + '_ {
+        self.decoded.try_iter()
+    }
+}
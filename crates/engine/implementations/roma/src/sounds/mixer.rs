@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use engine::{
+    engine::SoundID,
+    sound::{Bus, PlaySound, Spatial},
+};
+
+use super::clip::Clip;
+
+/// Seconds it takes to fade between two songs
+const CROSSFADE: f32 = 1.5;
+
+/// Instances of the same effect that can play at once, the oldest one is cut
+pub const MAX_INSTANCES: usize = 4;
+
+struct Voice {
+    id: SoundID,
+    clip: Arc<Clip>,
+    bus: Bus,
+    /// Order the voice started in, the frame goes back to 0 on every loop
+    start: u64,
+    frame: usize,
+    /// Plays left after the current one, ignored when looping
+    times: u16,
+    looping: bool,
+    volume: f32,
+    /// Gain of the left and right channels
+    balance: [f32; 2],
+    gain: f32,
+    /// Gain added every frame, the voice stops when it fades out
+    fade: f32,
+}
+
+/// Adds the voices playing in every bus, the output calls `mix` from its own thread
+pub struct Mixer {
+    sample_rate: u32,
+    voices: Vec<Voice>,
+    /// Voices started so far
+    started: u64,
+    volumes: [f32; 3],
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voices: vec![],
+            started: 0,
+            volumes: [1.; 3],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn play(&mut self, id: SoundID, clip: Arc<Clip>, parameters: PlaySound, spatial: Spatial) {
+        let instances = self
+            .voices
+            .iter()
+            .filter(|voice| voice.id == id && voice.bus == parameters.bus)
+            .count();
+        if instances >= MAX_INSTANCES {
+            if let Some(oldest) = self
+                .voices
+                .iter()
+                .enumerate()
+                .filter(|(_, voice)| voice.id == id && voice.bus == parameters.bus)
+                .min_by_key(|(_, voice)| voice.start)
+                .map(|(index, _)| index)
+            {
+                self.voices.remove(oldest);
+            }
+        }
+
+        let start = self.next_start();
+        self.voices.push(Voice {
+            id,
+            clip,
+            bus: parameters.bus,
+            start,
+            frame: 0,
+            times: parameters.times.saturating_sub(1),
+            looping: parameters.times == 0,
+            volume: parameters.volume as f32 / 255. * spatial.gain,
+            balance: balance(spatial.pan),
+            gain: 1.,
+            fade: 0.,
+        });
+    }
+
+    /// Fades out the current song while the new one fades in, the same song keeps playing
+    pub fn play_music(&mut self, id: SoundID, clip: Arc<Clip>, volume: u8) {
+        let volume = volume as f32 / 255.;
+        let fade = self.fade_step();
+        if let Some(current) = self
+            .voices
+            .iter_mut()
+            .find(|voice| voice.bus == Bus::Music && voice.id == id && voice.fade >= 0.)
+        {
+            current.volume = volume;
+            return;
+        }
+
+        let playing = self.fade_out(Bus::Music);
+        let start = self.next_start();
+        self.voices.push(Voice {
+            id,
+            clip,
+            bus: Bus::Music,
+            start,
+            frame: 0,
+            times: 0,
+            looping: true,
+            volume,
+            balance: [1., 1.],
+            gain: if playing { 0. } else { 1. },
+            fade,
+        });
+    }
+
+    /// Fades out the voices of a bus, returns if any was playing
+    pub fn fade_out(&mut self, bus: Bus) -> bool {
+        let fade = -self.fade_step();
+        let mut playing = false;
+        for voice in self.voices.iter_mut().filter(|voice| voice.bus == bus) {
+            voice.fade = fade;
+            playing = true;
+        }
+        playing
+    }
+
+    pub fn stop(&mut self, bus: Bus) {
+        self.voices.retain(|voice| voice.bus != bus);
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: u8) {
+        self.volumes[bus_index(bus)] = volume as f32 / 255.;
+    }
+
+    /// Fills interleaved stereo samples and advances the voices
+    pub fn mix(&mut self, output: &mut [f32]) {
+        output.fill(0.);
+        let volumes = self.volumes;
+        self.voices.retain_mut(|voice| {
+            let bus_volume = volumes[bus_index(voice.bus)];
+            for frame in output.chunks_exact_mut(2) {
+                if voice.frame >= voice.clip.frames() {
+                    if voice.clip.frames() == 0 || (!voice.looping && voice.times == 0) {
+                        return false;
+                    }
+                    voice.frame = 0;
+                    voice.times = voice.times.saturating_sub(1);
+                }
+
+                voice.gain = (voice.gain + voice.fade).clamp(0., 1.);
+                if voice.fade < 0. && voice.gain == 0. {
+                    return false;
+                }
+
+                let amplitude = voice.volume * voice.gain * bus_volume;
+                frame[0] += voice.clip.samples[voice.frame * 2] * amplitude * voice.balance[0];
+                frame[1] += voice.clip.samples[voice.frame * 2 + 1] * amplitude * voice.balance[1];
+                voice.frame += 1;
+            }
+            true
+        });
+
+        for sample in output {
+            *sample = sample.clamp(-1., 1.);
+        }
+    }
+
+    fn next_start(&mut self) -> u64 {
+        self.started += 1;
+        self.started
+    }
+
+    fn fade_step(&self) -> f32 {
+        1. / (CROSSFADE * self.sample_rate as f32)
+    }
+}
+
+/// The side the sound comes from keeps its volume while the other one fades
+fn balance(pan: f32) -> [f32; 2] {
+    [(1. - pan).min(1.), (1. + pan).min(1.)]
+}
+
+fn bus_index(bus: Bus) -> usize {
+    match bus {
+        Bus::Music => 0,
+        Bus::Effects => 1,
+        Bus::Ambient => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clip(frames: usize, sample: f32) -> Arc<Clip> {
+        Arc::new(Clip {
+            samples: vec![sample; frames * 2],
+        })
+    }
+
+    fn effect(times: u16) -> PlaySound {
+        PlaySound {
+            times,
+            volume: 255,
+            bus: Bus::Effects,
+            position: None,
+        }
+    }
+
+    /// Left channel of each mixed frame
+    fn mix(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut output = vec![0.; frames * 2];
+        mixer.mix(&mut output);
+        output.chunks_exact(2).map(|frame| frame[0]).collect()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-4, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn crossfades_between_songs() {
+        // a fade lasts 3 frames at this rate
+        let mut mixer = Mixer::new(2);
+        mixer.play_music(1, clip(100, 0.3), 255);
+        assert_close(mix(&mut mixer, 1)[0], 0.3);
+
+        mixer.play_music(2, clip(100, 0.6), 255);
+        let mixed = mix(&mut mixer, 6);
+        assert_close(mixed[0], 0.3 * 2. / 3. + 0.6 / 3.);
+        assert_close(mixed[1], 0.3 / 3. + 0.6 * 2. / 3.);
+        assert_close(mixed[5], 0.6);
+        assert_eq!(mixer.voices.len(), 1);
+        assert_eq!(mixer.voices[0].id, 2);
+
+        // the same song keeps playing
+        mixer.play_music(2, clip(100, 0.9), 255);
+        assert_eq!(mixer.voices.len(), 1);
+        assert_close(mix(&mut mixer, 1)[0], 0.6);
+    }
+
+    #[test]
+    fn culls_the_first_instance_started() {
+        let mut mixer = Mixer::new(44_100);
+        // the first one loops a short clip, its frame is always lower than the others
+        let first = clip(1, 0.1);
+        mixer.play(1, first.clone(), effect(0), Spatial::CENTER);
+        mix(&mut mixer, 1);
+        for _ in 1..MAX_INSTANCES {
+            mixer.play(1, clip(100, 0.1), effect(1), Spatial::CENTER);
+            mix(&mut mixer, 1);
+        }
+        mixer.play(2, clip(100, 0.1), effect(1), Spatial::CENTER);
+        assert_eq!(mixer.voices.len(), MAX_INSTANCES + 1);
+
+        mixer.play(1, clip(100, 0.1), effect(1), Spatial::CENTER);
+        assert_eq!(mixer.voices.len(), MAX_INSTANCES + 1);
+        assert!(!mixer
+            .voices
+            .iter()
+            .any(|voice| Arc::ptr_eq(&voice.clip, &first)));
+    }
+
+    #[test]
+    fn counts_the_loops() {
+        let mut mixer = Mixer::new(44_100);
+        mixer.play(1, clip(2, 0.5), effect(3), Spatial::CENTER);
+        let mixed = mix(&mut mixer, 8);
+        assert_eq!(mixed, [0.5, 0.5, 0.5, 0.5, 0.5, 0.5, 0., 0.]);
+        assert!(mixer.voices.is_empty());
+
+        mixer.play(1, clip(2, 0.5), effect(0), Spatial::CENTER);
+        assert!(mix(&mut mixer, 20).iter().all(|sample| *sample == 0.5));
+        assert_eq!(mixer.voices.len(), 1);
+    }
+
+    #[test]
+    fn applies_the_bus_volumes() {
+        let mut mixer = Mixer::new(44_100);
+        mixer.play(1, clip(10, 0.5), effect(1), Spatial::CENTER);
+        let ambient = PlaySound {
+            bus: Bus::Ambient,
+            ..effect(1)
+        };
+        mixer.play(2, clip(10, 0.25), ambient, Spatial::CENTER);
+        assert_close(mix(&mut mixer, 1)[0], 0.75);
+
+        mixer.set_volume(Bus::Effects, 0);
+        mixer.set_volume(Bus::Ambient, 51);
+        assert_close(mix(&mut mixer, 1)[0], 0.25 * 0.2);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    FromSample, SampleFormat, SizedSample, StreamConfig,
+};
+
+use super::mixer::Mixer;
+
+/// Sample rate of the null output
+const NULL_SAMPLE_RATE: u32 = 44100;
+
+/// Keeps the output playing until it's dropped
+pub enum Output {
+    Device { _stream: cpal::Stream },
+    Null { _null: NullOutput },
+}
+
+/// Consumes the mixer in real time without sound hardware, so sounds still end and loop
+pub struct NullOutput {
+    running: Arc<AtomicBool>,
+}
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Opens the default output device, or the null output when there is none or `NULL_AUDIO` is set
+pub fn open() -> (Arc<Mutex<Mixer>>, Output) {
+    if std::env::var("NULL_AUDIO").is_err() {
+        match open_device() {
+            Ok((mixer, stream)) => return (mixer, Output::Device { _stream: stream }),
+            Err(error) => log::warn!("couldn't open audio device, sounds are muted: {error}"),
+        }
+    }
+    let mixer = Arc::new(Mutex::new(Mixer::new(NULL_SAMPLE_RATE)));
+    let null = open_null(mixer.clone());
+    (mixer, Output::Null { _null: null })
+}
+
+fn open_device() -> Result<(Arc<Mutex<Mixer>>, cpal::Stream), String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("no output device")?;
+    let config = device
+        .default_output_config()
+        .map_err(|error| error.to_string())?;
+    let mixer = Arc::new(Mutex::new(Mixer::new(config.sample_rate().0)));
+
+    let sample_format = config.sample_format();
+    let config = config.into();
+    let stream = match sample_format {
+        SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer.clone()),
+        SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer.clone()),
+        SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer.clone()),
+        format => Err(format!("unsupported sample format {format:?}")),
+    }?;
+    stream.play().map_err(|error| error.to_string())?;
+
+    Ok((mixer, stream))
+}
+
+fn build_stream<T: SizedSample + FromSample<f32>>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mixer: Arc<Mutex<Mixer>>,
+) -> Result<cpal::Stream, String> {
+    let channels = config.channels as usize;
+    let mut stereo = vec![];
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                stereo.resize(data.len() / channels * 2, 0.);
+                match mixer.lock() {
+                    Ok(mut mixer) => mixer.mix(&mut stereo),
+                    Err(_) => stereo.fill(0.),
+                }
+                for (frame, sample) in data.chunks_exact_mut(channels).zip(stereo.chunks_exact(2)) {
+                    match frame {
+                        [mono] => *mono = T::from_sample((sample[0] + sample[1]) / 2.),
+                        [left, right, rest @ ..] => {
+                            *left = T::from_sample(sample[0]);
+                            *right = T::from_sample(sample[1]);
+                            for channel in rest {
+                                *channel = T::from_sample(0.);
+                            }
+                        }
+                        [] => {}
+                    }
+                }
+            },
+            |error| log::error!("audio output error: {error}"),
+            None,
+        )
+        .map_err(|error| error.to_string())
+}
+
+fn open_null(mixer: Arc<Mutex<Mixer>>) -> NullOutput {
+    let running = Arc::new(AtomicBool::new(true));
+    let thread_running = running.clone();
+    thread::spawn(move || {
+        let start = Instant::now();
+        let mut mixed_frames = 0;
+        let mut buffer = vec![];
+        while thread_running.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(10));
+            let frames = (start.elapsed().as_secs_f64() * NULL_SAMPLE_RATE as f64) as usize;
+            buffer.resize((frames - mixed_frames) * 2, 0.);
+            mixed_frames = frames;
+            if let Ok(mut mixer) = mixer.lock() {
+                mixer.mix(&mut buffer);
+            }
+        }
+    });
+    NullOutput { running }
+}
@@ -1,12 +1,10 @@
 use std::rc::Rc;
 
-use engine::draw::Dimensions;
-use image::GenericImageView;
-
 use crate::files::read_file;
+use engine::draw::Dimensions;
 
 pub struct Texture {
-    pub _texture: wgpu::Texture,
+    pub texture: wgpu::Texture,
     pub view: Rc<wgpu::TextureView>,
     pub sampler: Rc<wgpu::Sampler>,
     pub height: u16,
@@ -15,6 +13,21 @@ pub struct Texture {
 
 impl Texture {
     pub fn new(device: &wgpu::Device, dimensions: Dimensions) -> Self {
+        Self::with_usage(
+            device,
+            dimensions,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            None,
+        )
+    }
+
+    /// Empty texture, the atlas pages are written and copied instead of rendered to
+    pub fn with_usage(
+        device: &wgpu::Device,
+        dimensions: Dimensions,
+        usage: wgpu::TextureUsages,
+        label: Option<&str>,
+    ) -> Self {
         let size = wgpu::Extent3d {
             width: dimensions.width as u32,
             height: dimensions.height as u32,
@@ -22,13 +35,13 @@ impl Texture {
         };
         let format = wgpu::TextureFormat::Rgba8Unorm;
         let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: None,
+            label,
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             view_formats: &[format],
         });
         let view = Rc::new(texture.create_view(&wgpu::TextureViewDescriptor::default()));
@@ -43,7 +56,7 @@ impl Texture {
         }));
 
         Self {
-            _texture: texture,
+            texture,
             view,
             sampler,
             width: size.width as u16,
@@ -51,38 +64,13 @@ impl Texture {
         }
     }
 
-    pub fn from_path(
+    pub fn from_rgba(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
-        file_path: &str,
-    ) -> Result<Self, String> {
-        let read_file = read_file(file_path);
-        if let Ok(bytes) = read_file {
-            return Texture::from_bytes(device, queue, &bytes, file_path);
-        }
-
-        Err("couldn't read texture from path".to_string())
-    }
-
-    pub fn from_bytes(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        bytes: &[u8],
-        label: &str,
-    ) -> Result<Self, String> {
-        let img =
-            image::load_from_memory(bytes).map_err(|_| "Couldn't load from memory".to_string())?;
-        Ok(Self::from_image(device, queue, &img, Some(label)))
-    }
-
-    pub fn from_image(
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        img: &image::DynamicImage,
+        rgba: &image::RgbaImage,
         label: Option<&str>,
     ) -> Self {
-        let rgba = img.to_rgba8();
-        let dimensions = img.dimensions();
+        let dimensions = rgba.dimensions();
 
         let size = wgpu::Extent3d {
             width: dimensions.0,
@@ -108,7 +96,7 @@ impl Texture {
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
             },
-            &rgba,
+            rgba,
             wgpu::ImageDataLayout {
                 offset: 0,
                 bytes_per_row: Some(4 * dimensions.0),
@@ -129,7 +117,7 @@ impl Texture {
         }));
 
         Self {
-            _texture: texture,
+            texture,
             view,
             sampler,
             width: dimensions.0 as u16,
@@ -137,3 +125,9 @@ impl Texture {
         }
     }
 }
+
+/// Decodes an image file, the software renderer loads textures the same way
+pub fn read_image(file_path: &str) -> Result<image::DynamicImage, String> {
+    let bytes = read_file(file_path).map_err(|_| "couldn't read texture from path".to_string())?;
+    image::load_from_memory(&bytes).map_err(|_| "Couldn't load from memory".to_string())
+}
@@ -0,0 +1,339 @@
+use std::{
+    cell::Cell,
+    env, fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use engine::{
+    camera::{Position as CameraPosition, Zoom},
+    draw::{
+        image::{Blend, DrawImage, Flip, Transform},
+        light::DrawLight,
+        text::DrawText,
+        Dimensions, Position, Target,
+    },
+    engine::TextureID,
+    window::Size,
+};
+use image::{Rgba, RgbaImage};
+use roma::software::SoftwareRenderer;
+
+const SIZE: Size = Size {
+    width: 16,
+    height: 16,
+};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const GREEN: [u8; 4] = [0, 255, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+const GOLDEN_SCENE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/scene.png");
+
+/// Directory for the texture files of a test, removed when dropped
+struct TempDir {
+    path: PathBuf,
+    files: Cell<u32>,
+}
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = env::temp_dir().join(format!("roma_{name}_{nanos}"));
+        fs::create_dir_all(&path).unwrap();
+        Self {
+            path,
+            files: Cell::new(0),
+        }
+    }
+
+    /// Saves the image in the directory, textures are read when they are first drawn
+    fn save(&self, image: &RgbaImage) -> String {
+        self.files.set(self.files.get() + 1);
+        let path = self
+            .path
+            .join(format!("{}.png", self.files.get()))
+            .display()
+            .to_string();
+        image.save(&path).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Texture file filled with a color
+fn solid_texture(
+    renderer: &mut SoftwareRenderer,
+    dir: &TempDir,
+    color: [u8; 4],
+    size: u32,
+) -> TextureID {
+    let path = dir.save(&RgbaImage::from_pixel(size, size, Rgba(color)));
+    renderer.add_texture(&path)
+}
+
+fn draw(x: u16, y: u16, z: f32, index: TextureID) -> DrawImage {
+    DrawImage {
+        position: Position::new(x, y, z),
+        index,
+        ..Default::default()
+    }
+}
+
+/// Color at a screen position, with the origin at the bottom left corner like the cameras
+fn pixel(renderer: &SoftwareRenderer, x: u32, y: u32) -> [u8; 4] {
+    renderer.frame().get_pixel(x, SIZE.height as u32 - 1 - y).0
+}
+
+#[test]
+fn depth_tint_and_blending() {
+    let dir = TempDir::new("depth_tint_and_blending");
+    let mut renderer = SoftwareRenderer::new(SIZE);
+    let red = solid_texture(&mut renderer, &dir, RED, 4);
+    let green = solid_texture(&mut renderer, &dir, GREEN, 4);
+    let white = solid_texture(&mut renderer, &dir, WHITE, 2);
+
+    renderer.draw_image(draw(2, 2, 0.5, red), Target::UI);
+    // drawn later but behind the red one
+    renderer.draw_image(draw(4, 4, 0.3, green), Target::UI);
+    renderer.draw_image(
+        DrawImage {
+            color: [255, 0, 0, 128],
+            ..draw(10, 10, 0.5, white)
+        },
+        Target::UI,
+    );
+    renderer.render();
+
+    assert_eq!(pixel(&renderer, 2, 2), RED);
+    assert_eq!(pixel(&renderer, 5, 5), RED);
+    assert_eq!(pixel(&renderer, 7, 7), GREEN);
+    assert_eq!(pixel(&renderer, 8, 8), BLACK);
+    assert_eq!(pixel(&renderer, 10, 10), [128, 0, 0, 255]);
+    assert_eq!(pixel(&renderer, 12, 12), BLACK);
+}
+
+#[test]
+fn world_camera_and_zoom() {
+    let dir = TempDir::new("world_camera_and_zoom");
+    let mut renderer = SoftwareRenderer::new(SIZE);
+    let red = solid_texture(&mut renderer, &dir, RED, 2);
+
+    // the world camera is centered on its position
+    renderer.set_world_camera_position(CameraPosition { x: 100., y: 100. });
+    renderer.draw_image(draw(100, 100, 0.5, red), Target::World);
+    renderer.render();
+    assert_eq!(pixel(&renderer, 8, 8), RED);
+    assert_eq!(pixel(&renderer, 9, 9), RED);
+    assert_eq!(pixel(&renderer, 10, 10), BLACK);
+
+    renderer.set_camera_zoom(Zoom::Double);
+    renderer.draw_image(draw(100, 100, 0.5, red), Target::World);
+    renderer.render();
+    assert_eq!(pixel(&renderer, 11, 11), RED);
+    assert_eq!(pixel(&renderer, 12, 12), BLACK);
+}
+
+#[test]
+fn ambient_and_point_lights() {
+    let dir = TempDir::new("ambient_and_point_lights");
+    let mut renderer = SoftwareRenderer::new(SIZE);
+    let red = solid_texture(&mut renderer, &dir, RED, 8);
+
+    renderer.set_world_camera_position(CameraPosition { x: 100., y: 100. });
+    renderer.set_ambient_light([128, 128, 128, 255]);
+    renderer.draw_light(DrawLight {
+        position: [100.5, 100.5],
+        radius: 4.,
+        color: WHITE,
+    });
+    renderer.draw_image(draw(96, 96, 0.5, red), Target::World);
+    // the ui is drawn after the lights
+    renderer.draw_image(draw(0, 0, 0.5, red), Target::UI);
+    renderer.render();
+
+    assert_eq!(pixel(&renderer, 8, 8), RED);
+    assert_eq!(pixel(&renderer, 4, 11), [128, 0, 0, 255]);
+    assert_eq!(pixel(&renderer, 0, 0), RED);
+
+    // lights last one frame
+    renderer.draw_image(draw(96, 96, 0.5, red), Target::World);
+    renderer.render();
+    assert_eq!(pixel(&renderer, 8, 8), [128, 0, 0, 255]);
+}
+
+#[test]
+fn render_to_texture() {
+    let dir = TempDir::new("render_to_texture");
+    let mut renderer = SoftwareRenderer::new(SIZE);
+    let green = solid_texture(&mut renderer, &dir, GREEN, 2);
+    let target = renderer.create_texture(Dimensions {
+        width: 4,
+        height: 4,
+    });
+
+    renderer.draw_image(draw(0, 0, 0.5, green), Target::Texture { id: target });
+    renderer.render();
+    renderer.draw_image(draw(4, 4, 0.5, target), Target::UI);
+    renderer.render();
+
+    assert_eq!(pixel(&renderer, 4, 4), GREEN);
+    assert_eq!(pixel(&renderer, 5, 5), GREEN);
+    // the rest of the texture is cleared to black, which is not drawn
+    assert_eq!(pixel(&renderer, 7, 7), BLACK);
+    assert_eq!(renderer.texture_dimensions(target), Some((4, 4)));
+}
+
+#[test]
+fn transforms_and_blend_modes() {
+    let dir = TempDir::new("transforms_and_blend_modes");
+    let mut renderer = SoftwareRenderer::new(SIZE);
+    let path = dir.save(&RgbaImage::from_fn(2, 2, |x, _| {
+        Rgba(if x == 0 { RED } else { GREEN })
+    }));
+    let halves = renderer.add_texture(&path);
+    let red = solid_texture(&mut renderer, &dir, RED, 2);
+    let wide = solid_texture(&mut renderer, &dir, RED, 4);
+    let green = solid_texture(&mut renderer, &dir, GREEN, 2);
+
+    renderer.draw_image(
+        DrawImage {
+            transform: Transform {
+                flip: Flip {
+                    horizontal: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..draw(0, 0, 0.5, halves)
+        },
+        Target::UI,
+    );
+    renderer.draw_image(
+        DrawImage {
+            transform: Transform {
+                scale: [2., 2.],
+                ..Default::default()
+            },
+            ..draw(4, 0, 0.5, red)
+        },
+        Target::UI,
+    );
+    // a 4x4 quad of a 4x2 source, rotated a quarter turn around its center
+    renderer.draw_image(
+        DrawImage {
+            source: [0, 0, 4, 2],
+            transform: Transform {
+                rotation: std::f32::consts::FRAC_PI_2,
+                ..Default::default()
+            },
+            ..draw(8, 8, 0.5, wide)
+        },
+        Target::UI,
+    );
+    renderer.draw_image(draw(0, 12, 0.5, red), Target::UI);
+    renderer.draw_image(
+        DrawImage {
+            blend: Blend::Additive,
+            ..draw(0, 12, 0.5, green)
+        },
+        Target::UI,
+    );
+    renderer.render();
+
+    assert_eq!(pixel(&renderer, 0, 0), GREEN);
+    assert_eq!(pixel(&renderer, 1, 1), RED);
+    assert_eq!(pixel(&renderer, 7, 3), RED);
+    assert_eq!(pixel(&renderer, 8, 4), BLACK);
+    assert_eq!(pixel(&renderer, 9, 7), RED);
+    assert_eq!(pixel(&renderer, 10, 10), RED);
+    assert_eq!(pixel(&renderer, 8, 8), BLACK);
+    assert_eq!(pixel(&renderer, 11, 8), BLACK);
+    assert_eq!(pixel(&renderer, 1, 13), [255, 255, 0, 255]);
+}
+
+#[test]
+fn text() {
+    let mut renderer = SoftwareRenderer::new(Size {
+        width: 64,
+        height: 16,
+    });
+    let texture = renderer.add_texture("../../../../assets/fonts/tahoma_regular_8.png");
+    renderer.add_font(0, "../../../../assets/fonts/tahoma_regular_8.fnt", texture);
+
+    let text = renderer.parse_text(0, "Roma").expect("font");
+    renderer.draw_text(
+        0,
+        DrawText {
+            text: &text,
+            position: Position::new(32, 2, 1.),
+            color: WHITE,
+        },
+        Target::UI,
+    );
+    renderer.render();
+    assert!(renderer.frame().pixels().any(|pixel| pixel.0 != BLACK));
+}
+
+#[test]
+fn golden_scene() {
+    let dir = TempDir::new("golden_scene");
+    let mut renderer = SoftwareRenderer::new(Size {
+        width: 32,
+        height: 32,
+    });
+    let gradient = dir.save(&RgbaImage::from_fn(8, 8, |x, y| {
+        Rgba([x as u8 * 32, y as u8 * 32, 255 - x as u8 * 16, 255])
+    }));
+    let gradient = renderer.add_texture(&gradient);
+    let red = solid_texture(&mut renderer, &dir, RED, 4);
+    let green = solid_texture(&mut renderer, &dir, GREEN, 4);
+
+    renderer.set_world_camera_position(CameraPosition { x: 100., y: 100. });
+    renderer.set_ambient_light([100, 100, 140, 255]);
+    renderer.draw_light(DrawLight {
+        position: [96., 104.],
+        radius: 10.,
+        color: [255, 200, 120, 255],
+    });
+    renderer.draw_image(draw(84, 84, 0.2, gradient), Target::World);
+    renderer.draw_image(
+        DrawImage {
+            transform: Transform {
+                scale: [3., 3.],
+                rotation: std::f32::consts::FRAC_PI_4,
+                ..Default::default()
+            },
+            ..draw(96, 96, 0.5, gradient)
+        },
+        Target::World,
+    );
+    renderer.draw_image(
+        DrawImage {
+            color: [255, 255, 255, 128],
+            ..draw(2, 20, 0.5, red)
+        },
+        Target::UI,
+    );
+    renderer.draw_image(draw(4, 22, 0.4, green), Target::UI);
+    renderer.draw_image(
+        DrawImage {
+            blend: Blend::Additive,
+            ..draw(24, 2, 0.5, green)
+        },
+        Target::UI,
+    );
+    renderer.draw_image(draw(26, 4, 0.6, red), Target::UI);
+    renderer.render();
+
+    renderer.compare_golden(GOLDEN_SCENE).unwrap();
+}
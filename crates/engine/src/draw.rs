@@ -21,13 +21,14 @@ pub mod image {
 
     pub type Source = [u16; 4];
 
-    #[repr(C)]
-    #[derive(Debug, Copy, Clone, bytemuck::Zeroable, bytemuck::Pod)]
+    #[derive(Debug, Copy, Clone)]
     pub struct DrawImage {
         pub position: Position,
         pub color: Color,
         pub source: Source,
         pub index: u32,
+        pub transform: Transform,
+        pub blend: Blend,
     }
 
     impl Default for DrawImage {
@@ -37,9 +38,60 @@ pub mod image {
                 color: [255, 255, 255, 255],
                 source: [0, 0, 0, 0],
                 index: Default::default(),
+                transform: Default::default(),
+                blend: Default::default(),
+            }
+        }
+    }
+
+    /// Scale and rotation are applied around the pivot, flipping mirrors the source
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    pub struct Transform {
+        /// Positive, use `flip` to mirror the image
+        pub scale: [f32; 2],
+        /// Fraction of the image size, from its bottom left corner
+        pub pivot: [f32; 2],
+        /// Radians, counterclockwise
+        pub rotation: f32,
+        pub flip: Flip,
+    }
+
+    impl Default for Transform {
+        fn default() -> Self {
+            Self {
+                scale: [1., 1.],
+                pivot: [0.5, 0.5],
+                rotation: 0.,
+                flip: Flip::default(),
             }
         }
     }
+
+    #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+    pub struct Flip {
+        pub horizontal: bool,
+        pub vertical: bool,
+    }
+
+    /// How the image is combined with what is already drawn, draws are batched by it
+    #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+    pub enum Blend {
+        #[default]
+        Alpha,
+        /// Adds the color, for glows and light
+        Additive,
+        /// Darkens by the color, for shadows
+        Multiply,
+    }
+
+    impl Blend {
+        pub const ALL: [Blend; 3] = [Blend::Alpha, Blend::Additive, Blend::Multiply];
+
+        /// Position in `ALL`
+        pub fn index(self) -> usize {
+            self as usize
+        }
+    }
 }
 
 pub mod text {
@@ -65,13 +117,27 @@ pub mod text {
     }
 }
 
+pub mod light {
+    use super::Color;
+
+    /// Point light in world coordinates, fading out from its center to `radius`
+    #[repr(C)]
+    #[derive(Debug, Copy, Clone, Default, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+    pub struct DrawLight {
+        pub position: [f32; 2],
+        pub radius: f32,
+        /// The alpha is the intensity
+        pub color: Color,
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Dimensions {
     pub width: u16,
     pub height: u16,
 }
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
 pub enum Target {
     #[default]
     World,
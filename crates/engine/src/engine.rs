@@ -52,6 +52,13 @@ pub trait GameEngine {
 
     fn texture_dimensions(&mut self, texture_id: TextureID) -> Option<(u16, u16)>;
 
+    /// Loads textures in the background before they are drawn, higher priorities load first
+    /// Textures drawn before they are loaded use the highest priority
+    fn prefetch_textures(&mut self, ids: &[TextureID], priority: u8);
+
+    /// Textures requested that are still loading
+    fn textures_loading(&self) -> usize;
+
     /// Creates a texture that can be used to draw images or text
     /// The texture of this target can be used as any other texture
     fn create_texture(&mut self, dimensions: Dimensions) -> TextureID;
@@ -63,6 +70,12 @@ pub trait GameEngine {
     /// it should be possible to render images from atlases
     fn draw_image(&mut self, parameters: crate::draw::image::DrawImage, target: Target);
 
+    /// Lights the world during this frame, lights are added over the ambient light
+    fn draw_light(&mut self, parameters: crate::draw::light::DrawLight);
+
+    /// Light of the world where no light reaches, white draws the world without lighting
+    fn set_ambient_light(&mut self, color: crate::draw::Color);
+
     /// Adds a font and receives an integer to be used later on draw_text
     fn add_font(&mut self, id: FontID, path: &str, texture_id: TextureID);
 
@@ -90,6 +103,12 @@ pub trait GameEngine {
     /// Stops music
     fn stop_music(&mut self);
 
+    /// Sets the volume of a bus, 255 is the volume of the sound files
+    fn set_volume(&mut self, bus: crate::sound::Bus, volume: u8);
+
+    /// Stops every sound playing in a bus
+    fn stop_sounds(&mut self, bus: crate::sound::Bus);
+
     /// Get camera viewport
     fn get_world_camera_viewport(&self) -> crate::camera::Viewport;
 
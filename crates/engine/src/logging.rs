@@ -1,13 +1,21 @@
+use std::env;
+
 use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 
+/// `LOG_FORMAT=json` writes one json object per event
 pub fn initialize_logging() {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_target(true)
         .with_level(true)
-        .with_env_filter(env_filter)
-        .init();
+        .with_env_filter(env_filter);
+
+    if env::var("LOG_FORMAT").is_ok_and(|format| format == "json") {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
 }
@@ -1,8 +1,11 @@
+use crate::sound::Hearing;
+
 pub struct Settings {
     pub width: usize,
     pub height: usize,
     pub title: String,
     pub vsync: bool,
+    pub hearing: Hearing,
 }
 
 impl Default for Settings {
@@ -12,6 +15,7 @@ impl Default for Settings {
             height: 600,
             title: "Game".to_string(),
             vsync: false,
+            hearing: Hearing::default(),
         }
     }
 }
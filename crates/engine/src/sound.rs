@@ -1,8 +1,120 @@
+use crate::camera::Position;
+
+/// Mixer bus a sound is played in, each one has its own volume
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Bus {
+    Music,
+    #[default]
+    Effects,
+    Ambient,
+}
+
 pub struct PlaySound {
+    /// Times the sound is played, 0 loops it until `stop_sounds` is called
     pub times: u16,
     pub volume: u8,
+    pub bus: Bus,
+    /// World position of the source, the listener is the world camera. `None` plays it centered
+    pub position: Option<Position>,
 }
 
 pub struct PlayMusic {
     pub volume: u8,
 }
+
+/// How positional sounds are heard, distances are measured in tiles
+#[derive(Clone, Copy, Debug)]
+pub struct Hearing {
+    /// World units of a tile
+    pub tile_size: f32,
+    /// Sounds further than this are not played
+    pub range: f32,
+    /// Horizontal distance at which a sound is heard only on one side
+    pub pan_width: f32,
+}
+
+impl Default for Hearing {
+    fn default() -> Self {
+        Self {
+            tile_size: 32.,
+            range: 16.,
+            pan_width: 8.,
+        }
+    }
+}
+
+/// Gain and panning of a positional sound
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Spatial {
+    pub gain: f32,
+    /// From -1, only the left side, to 1, only the right side
+    pub pan: f32,
+}
+
+impl Spatial {
+    pub const CENTER: Spatial = Spatial { gain: 1., pan: 0. };
+}
+
+impl Hearing {
+    /// `None` when the source is out of the hearing range
+    pub fn spatialize(&self, listener: Position, source: Position) -> Option<Spatial> {
+        let x = (source.x - listener.x) / self.tile_size;
+        let y = (source.y - listener.y) / self.tile_size;
+        let distance = (x * x + y * y).sqrt();
+        if distance > self.range {
+            return None;
+        }
+        let closeness = 1. - distance / self.range;
+        Some(Spatial {
+            gain: closeness * closeness,
+            pan: (x / self.pan_width).clamp(-1., 1.),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Hearing, Position, Spatial};
+
+    const LISTENER: Position = Position { x: 320., y: 320. };
+
+    /// Source placed the given tiles away from the listener
+    fn spatialize(x: f32, y: f32) -> Option<Spatial> {
+        let hearing = Hearing::default();
+        let source = Position {
+            x: LISTENER.x + x * hearing.tile_size,
+            y: LISTENER.y + y * hearing.tile_size,
+        };
+        hearing.spatialize(LISTENER, source)
+    }
+
+    #[test]
+    fn sources_on_the_listener_are_centered() {
+        assert_eq!(spatialize(0., 0.), Some(Spatial::CENTER));
+    }
+
+    #[test]
+    fn gain_fades_out_to_the_edge_of_the_range() {
+        let half = spatialize(0., 8.).unwrap();
+        assert_eq!(half.gain, 0.25);
+        assert_eq!(half.pan, 0.);
+
+        let edge = spatialize(0., -16.).unwrap();
+        assert_eq!(edge.gain, 0.);
+    }
+
+    #[test]
+    fn sources_beyond_the_range_are_not_heard() {
+        assert_eq!(spatialize(0., 16.1), None);
+        assert_eq!(spatialize(12., 12.), None);
+    }
+
+    #[test]
+    fn pan_follows_the_side_up_to_the_pan_width() {
+        assert_eq!(spatialize(-4., 0.).unwrap().pan, -0.5);
+        assert_eq!(spatialize(4., 3.).unwrap().pan, 0.5);
+        assert_eq!(spatialize(-8., 0.).unwrap().pan, -1.);
+        assert_eq!(spatialize(12., 0.).unwrap().pan, 1.);
+        assert_eq!(spatialize(-15., 0.).unwrap().pan, -1.);
+    }
+}
@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc};
 
 use database::{
-    model::{Account, Character, CharacterPreview, CreateAccount, CreateCharacter},
+    model::{
+        Account, Character, CharacterPreview, CreateAccount, CreateCharacter, Sanction,
+        SanctionKind,
+    },
     Database,
 };
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
+
+use crate::metrics;
 
 pub struct Accounts {
     database: Arc<Database>,
@@ -14,6 +19,35 @@ pub struct Accounts {
     account_events_receiver: Receiver<AccountEvent>,
 }
 
+/// Permission level of an account, each level includes the ones below
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Privilege {
+    #[default]
+    Player,
+    Counsellor,
+    GameMaster,
+    Admin,
+}
+
+impl From<i32> for Privilege {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Privilege::Counsellor,
+            2 => Privilege::GameMaster,
+            3.. => Privilege::Admin,
+            _ => Privilege::Player,
+        }
+    }
+}
+
+/// Account the user logged in with, kept while the user is in the world
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub account_name: String,
+    pub privilege: Privilege,
+    pub ip: Option<IpAddr>,
+}
+
 #[derive(Debug)]
 pub enum AccountEvent {
     Created {
@@ -27,14 +61,20 @@ pub enum AccountEvent {
     LoginAccountOk {
         connection_id: u32,
         account_name: String,
+        privilege: Privilege,
         characters: Vec<CharacterPreview>,
     },
     LoginAccountFailed {
         connection_id: u32,
     },
+    LoginAccountBanned {
+        connection_id: u32,
+        reason: String,
+    },
     CreateCharacterOk {
         connection_id: u32,
         character: Character,
+        sanctions: Vec<Sanction>,
     },
     CreateCharacterFailed {
         connection_id: u32,
@@ -43,6 +83,7 @@ pub enum AccountEvent {
     LoginCharacterOk {
         connection_id: u32,
         character: Character,
+        sanctions: Vec<Sanction>,
     },
     LoginCharacterFailed {
         connection_id: u32,
@@ -83,7 +124,12 @@ impl Accounts {
                     password: &password,
                     pin: pin as i32,
                 };
-                let result = match database.create_account(&create_account).await {
+                let result = match metrics::query(
+                    "create_account",
+                    database.create_account(&create_account),
+                )
+                .await
+                {
                     Ok(account) => AccountEvent::Created {
                         connection_id,
                         account_name: account.name,
@@ -100,7 +146,7 @@ impl Accounts {
         });
     }
 
-    pub async fn login(&self, connection_id: u32, name: &str, password: &str) {
+    pub async fn login(&self, connection_id: u32, name: &str, password: &str, ip: Option<IpAddr>) {
         tokio::spawn({
             let database = self.database.clone();
             let name = name.to_string();
@@ -109,11 +155,32 @@ impl Accounts {
             let account_events_sender = self.account_events_sender.clone();
 
             async move {
-                let account = database.account(&name).await;
+                let account = metrics::query("account", database.account(&name)).await;
 
                 let result = match account {
-                    Ok(Account { name, password, .. }) if password == login_password => {
-                        let characters = match database.account_characters(&name).await {
+                    Ok(Account {
+                        name,
+                        password,
+                        privileges,
+                        ..
+                    }) if password == login_password => 'login: {
+                        let sanctions = account_sanctions(&database, &name, ip).await;
+                        if let Some(ban) = sanctions
+                            .iter()
+                            .find(|sanction| sanction.kind == SanctionKind::Ban)
+                        {
+                            break 'login AccountEvent::LoginAccountBanned {
+                                connection_id,
+                                reason: ban_reason(ban),
+                            };
+                        }
+
+                        let characters = match metrics::query(
+                            "account_characters",
+                            database.account_characters(&name),
+                        )
+                        .await
+                        {
                             Ok(characters) => characters,
                             Err(e) => {
                                 debug!(
@@ -126,6 +193,7 @@ impl Accounts {
                         AccountEvent::LoginAccountOk {
                             connection_id,
                             account_name: name,
+                            privilege: privileges.into(),
                             characters,
                         }
                     }
@@ -140,22 +208,30 @@ impl Accounts {
     pub async fn create_character(
         &self,
         connection_id: u32,
-        account_name: &str,
+        session: &Session,
         character: CreateCharacter,
     ) {
         tokio::spawn({
             let database = self.database.clone();
             let character = character.clone();
-            let account_name = account_name.to_string();
+            let account_name = session.account_name.clone();
+            let ip = session.ip;
 
             let account_events_sender = self.account_events_sender.clone();
 
             async move {
-                if let Ok(character) = database.insert_character(&account_name, character).await {
+                if let Ok(character) = metrics::query(
+                    "insert_character",
+                    database.insert_character(&account_name, character),
+                )
+                .await
+                {
+                    let sanctions = account_sanctions(&database, &account_name, ip).await;
                     account_events_sender
                         .send(AccountEvent::CreateCharacterOk {
                             connection_id,
                             character,
+                            sanctions,
                         })
                         .await
                         .expect("poisoned");
@@ -172,19 +248,25 @@ impl Accounts {
         });
     }
 
-    pub async fn enter(&self, connection_id: u32, character: &str) {
+    pub async fn enter(&self, connection_id: u32, session: &Session, character: &str) {
         tokio::spawn({
             let database = self.database.clone();
             let character = character.to_string();
+            let account_name = session.account_name.clone();
+            let ip = session.ip;
 
             let account_events_sender = self.account_events_sender.clone();
 
             async move {
-                if let Ok(character) = database.character(&character).await {
+                if let Ok(character) =
+                    metrics::query("character", database.character(&character)).await
+                {
+                    let sanctions = account_sanctions(&database, &account_name, ip).await;
                     account_events_sender
                         .send(AccountEvent::LoginCharacterOk {
                             connection_id,
                             character,
+                            sanctions,
                         })
                         .await
                         .expect("poisoned");
@@ -206,3 +288,35 @@ impl Accounts {
         events
     }
 }
+
+/// Sanctions in effect for the account or the ip, logins go on if they can't be read
+async fn account_sanctions(
+    database: &Database,
+    account_name: &str,
+    ip: Option<IpAddr>,
+) -> Vec<Sanction> {
+    let ip = ip.map(|ip| ip.to_string());
+    match metrics::query(
+        "active_sanctions",
+        database.active_sanctions(account_name, ip.as_deref()),
+    )
+    .await
+    {
+        Ok(sanctions) => sanctions,
+        Err(e) => {
+            error!("couldn't read the sanctions of {account_name}: {e}");
+            vec![]
+        }
+    }
+}
+
+fn ban_reason(ban: &Sanction) -> String {
+    match ban.expires_at {
+        Some(expires_at) => format!(
+            "Banned until {}: {}",
+            expires_at.format("%Y-%m-%d %H:%M UTC"),
+            ban.reason
+        ),
+        None => format!("Banned permanently: {}", ban.reason),
+    }
+}
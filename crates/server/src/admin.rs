@@ -0,0 +1,270 @@
+use std::{env, net::SocketAddr, time::Duration};
+
+use anyhow::{bail, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        oneshot,
+    },
+};
+use tracing::{error, info};
+
+/// Only reachable from the host running the server, override with `ADMIN_ADDRESS`, the interface
+/// has no authentication so it refuses to listen on addresses other than loopback ones
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7667";
+
+pub const HELP: &str = "\
+connections                     list connections and their state
+entities                        list the entities in the world
+inspect <name>                  show a character
+broadcast <text>                system message for every user
+kick <name> [reason]            disconnect a user
+ban <name> <hours|perm> [reason]
+                                ban the account of a user, perm bans it for good
+save                            save every character now
+reload                          reload objects, npcs, spells and map infos
+shutdown                        save every character and stop the server";
+
+/// Line based admin interface, each request line gets a response ended by an empty line
+pub struct Admin {
+    requests_receiver: Receiver<AdminRequest>,
+}
+
+pub struct AdminRequest {
+    pub command: AdminCommand,
+    reply: oneshot::Sender<String>,
+}
+
+#[derive(Debug)]
+pub enum AdminCommand {
+    Help,
+    Connections,
+    Entities,
+    Inspect {
+        name: String,
+    },
+    Broadcast {
+        text: String,
+    },
+    Kick {
+        name: String,
+        reason: String,
+    },
+    Ban {
+        name: String,
+        duration: Option<Duration>,
+        reason: String,
+    },
+    Save,
+    Reload,
+    Shutdown,
+}
+
+impl AdminCommand {
+    pub fn parse(line: &str) -> Result<Self, &'static str> {
+        let line = line.trim();
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+
+        let command = match (name, &args[..]) {
+            ("help", _) => AdminCommand::Help,
+            ("connections", _) => AdminCommand::Connections,
+            ("entities", _) => AdminCommand::Entities,
+            ("inspect", [name]) => AdminCommand::Inspect {
+                name: name.to_string(),
+            },
+            ("broadcast", [_, ..]) => AdminCommand::Broadcast {
+                text: rest.trim().to_string(),
+            },
+            ("kick", [name, reason @ ..]) => AdminCommand::Kick {
+                name: name.to_string(),
+                reason: reason.join(" "),
+            },
+            ("ban", [name, hours, reason @ ..]) => AdminCommand::Ban {
+                name: name.to_string(),
+                duration: ban_duration(hours)?,
+                reason: reason.join(" "),
+            },
+            ("save", _) => AdminCommand::Save,
+            ("reload", _) => AdminCommand::Reload,
+            ("shutdown", _) => AdminCommand::Shutdown,
+            _ => return Err("unknown command or wrong arguments, try help"),
+        };
+        Ok(command)
+    }
+}
+
+/// Hours of a ban, `perm` for a permanent one
+fn ban_duration(hours: &str) -> Result<Option<Duration>, &'static str> {
+    if hours == "perm" {
+        return Ok(None);
+    }
+    let hours: u64 = hours
+        .parse()
+        .map_err(|_| "hours must be a number or perm")?;
+    if hours == 0 {
+        return Err("hours must be positive, use perm for a permanent ban");
+    }
+    let seconds = hours.checked_mul(60 * 60).ok_or("ban too long")?;
+    Ok(Some(Duration::from_secs(seconds)))
+}
+
+impl AdminRequest {
+    pub fn reply(self, text: String) {
+        // the admin could have disconnected already
+        let _ = self.reply.send(text);
+    }
+}
+
+impl Admin {
+    pub async fn initialize() -> Result<Self> {
+        let address = env::var("ADMIN_ADDRESS").unwrap_or(DEFAULT_ADDRESS.to_string());
+        let address: SocketAddr = address.parse()?;
+        if !address.ip().is_loopback() {
+            bail!("admin address {address} is not a loopback address");
+        }
+        let listener = TcpListener::bind(address).await?;
+        info!("Admin interface listening on {address}");
+
+        let (requests_sender, requests_receiver) = channel(100);
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, address)) => {
+                        info!("admin connected from {address}");
+                        tokio::spawn(serve(stream, requests_sender.clone()));
+                    }
+                    Err(e) => error!("couldn't accept admin connection: {e}"),
+                }
+            }
+        });
+
+        Ok(Self { requests_receiver })
+    }
+
+    pub fn poll_requests(&mut self) -> Vec<AdminRequest> {
+        let mut requests = vec![];
+        while let Ok(request) = self.requests_receiver.try_recv() {
+            requests.push(request);
+        }
+        requests
+    }
+}
+
+async fn serve(stream: TcpStream, requests_sender: Sender<AdminRequest>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match AdminCommand::parse(&line) {
+            Ok(command) => {
+                let (reply, response) = oneshot::channel();
+                let request = AdminRequest { command, reply };
+                if requests_sender.send(request).await.is_err() {
+                    break;
+                }
+                response.await.unwrap_or_default()
+            }
+            Err(reason) => format!("error: {reason}"),
+        };
+        let response = format!("{}\n\n", response.trim_end());
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::AdminCommand;
+
+    fn parse(line: &str) -> AdminCommand {
+        AdminCommand::parse(line).expect("the command to parse")
+    }
+
+    #[test]
+    fn commands_without_arguments() {
+        assert!(matches!(parse("help"), AdminCommand::Help));
+        assert!(matches!(
+            parse("  connections  "),
+            AdminCommand::Connections
+        ));
+        assert!(matches!(parse("entities"), AdminCommand::Entities));
+        assert!(matches!(parse("save"), AdminCommand::Save));
+        assert!(matches!(parse("reload"), AdminCommand::Reload));
+        assert!(matches!(parse("shutdown"), AdminCommand::Shutdown));
+    }
+
+    #[test]
+    fn commands_with_arguments() {
+        assert!(
+            matches!(parse("inspect Alice"), AdminCommand::Inspect { name } if name == "Alice")
+        );
+        assert!(matches!(
+            parse("broadcast  the server  restarts soon "),
+            AdminCommand::Broadcast { text } if text == "the server  restarts soon"
+        ));
+        assert!(matches!(
+            parse("kick Alice"),
+            AdminCommand::Kick { name, reason } if name == "Alice" && reason.is_empty()
+        ));
+        assert!(matches!(
+            parse("kick Alice too  loud"),
+            AdminCommand::Kick { name, reason } if name == "Alice" && reason == "too loud"
+        ));
+    }
+
+    #[test]
+    fn bans_last_hours_or_for_good() {
+        assert!(matches!(
+            parse("ban Alice 2 cheating"),
+            AdminCommand::Ban { name, duration: Some(duration), reason }
+                if name == "Alice"
+                    && duration == Duration::from_secs(2 * 60 * 60)
+                    && reason == "cheating"
+        ));
+        assert!(matches!(
+            parse("ban Alice perm"),
+            AdminCommand::Ban { duration: None, reason, .. } if reason.is_empty()
+        ));
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        for line in [
+            "",
+            "unknown",
+            "inspect",
+            "inspect Alice Bob",
+            "broadcast",
+            "kick",
+            "ban",
+            "ban Alice",
+        ] {
+            assert_eq!(
+                AdminCommand::parse(line).err(),
+                Some("unknown command or wrong arguments, try help"),
+                "{line}"
+            );
+        }
+        assert_eq!(
+            AdminCommand::parse("ban Alice soon").err(),
+            Some("hours must be a number or perm")
+        );
+        assert_eq!(
+            AdminCommand::parse("ban Alice 0").err(),
+            Some("hours must be positive, use perm for a permanent ban")
+        );
+        assert_eq!(
+            AdminCommand::parse(&format!("ban Alice {}", u64::MAX)).err(),
+            Some("ban too long")
+        );
+    }
+}
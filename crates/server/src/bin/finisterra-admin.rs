@@ -0,0 +1,65 @@
+//! Talks to the admin interface of a running server.
+//!
+//! `finisterra-admin <command>` runs a single command, without arguments it reads commands
+//! from stdin. The address defaults to the server default and can be changed with
+//! `ADMIN_ADDRESS`.
+
+use std::{
+    env,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpStream,
+};
+
+// only the address is used, the rest of the module runs in the server
+#[allow(dead_code)]
+#[path = "../admin.rs"]
+mod admin;
+
+use admin::DEFAULT_ADDRESS;
+
+fn main() -> io::Result<()> {
+    let address = env::var("ADMIN_ADDRESS").unwrap_or(DEFAULT_ADDRESS.to_string());
+    let stream = TcpStream::connect(&address)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+
+    let command = env::args().skip(1).collect::<Vec<_>>().join(" ");
+    if !command.is_empty() {
+        return run(&command, &mut writer, &mut reader);
+    }
+
+    let stdin = io::stdin();
+    print!("> ");
+    io::stdout().flush()?;
+    for line in stdin.lock().lines() {
+        let line = line?;
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+        if !line.trim().is_empty() {
+            run(&line, &mut writer, &mut reader)?;
+        }
+        print!("> ");
+        io::stdout().flush()?;
+    }
+    Ok(())
+}
+
+/// Sends the command and prints the response, responses end with an empty line
+fn run(command: &str, writer: &mut TcpStream, reader: &mut impl BufRead) -> io::Result<()> {
+    writeln!(writer, "{command}")?;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the server closed the connection",
+            ));
+        }
+        if line.trim_end().is_empty() {
+            return Ok(());
+        }
+        print!("{line}");
+    }
+}
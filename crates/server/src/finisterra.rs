@@ -4,24 +4,37 @@ use std::{
 };
 
 use anyhow::Result;
-use database::{model::CreateCharacter, Database};
+use database::{
+    model::{CreateCharacter, SanctionKind},
+    Database,
+};
 use nohash_hasher::IntMap;
 use shared::protocol::{
     client::{self, ClientPacket},
     server::{self, ServerPacket},
 };
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tracing::info;
 
 use crate::{
-    accounts::{AccountEvent, Accounts},
+    accounts::{AccountEvent, Accounts, Privilege, Session},
+    admin::{self, Admin, AdminCommand},
+    metrics::{self, metrics, Metrics},
     server::Server,
     world::World,
 };
 
+/// Name recorded in the audit log for actions taken from the admin interface
+const ADMIN_MODERATOR: &str = "admin";
+
+/// How often the gauges of the world are refreshed
+const METRICS_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct Finisterra {
     server: Server,
     world: World,
     accounts: Accounts,
+    admin: Admin,
 
     /// connected users
     users: IntMap<u32, User>,
@@ -31,12 +44,13 @@ pub struct Finisterra {
     outcoming_messages_receiver: UnboundedReceiver<(u32, ServerPacket)>,
 
     last_tick: Instant,
+    last_metrics: Instant,
 }
 
 pub enum User {
     Connected,
     InAccount {
-        account_name: String,
+        session: Session,
         character_names: Vec<String>,
     },
     InWorld {
@@ -54,16 +68,19 @@ impl Finisterra {
         let (outcoming_messages_sender, receiver) = unbounded_channel();
 
         let server = Server::initialize(receiver).await?;
-        let accounts = Accounts::initialize(database);
+        let accounts = Accounts::initialize(database.clone());
+        let admin = Admin::initialize().await?;
+        Metrics::listen().await?;
 
         let (sender, outcoming_messages_receiver) = unbounded_channel();
-        let world = World::initialize(sender.clone());
+        let world = World::initialize(sender.clone(), database);
 
         let users = IntMap::default();
         let connection_ids = IntMap::default();
 
         Ok(Finisterra {
             accounts,
+            admin,
             server,
             world,
             users,
@@ -73,6 +90,7 @@ impl Finisterra {
             outcoming_messages_receiver,
 
             last_tick: Instant::now(),
+            last_metrics: Instant::now(),
         })
     }
 
@@ -81,11 +99,39 @@ impl Finisterra {
             let now = Instant::now();
             let delta = now - self.last_tick;
             if delta >= Duration::from_millis(16) {
-                self.update_connections().await;
-                self.process_incoming_messages().await;
-                self.update_world().await;
-                self.send_outcoming_messages().await;
+                {
+                    let _timer = phase_timer("connections");
+                    self.update_connections().await;
+                }
+                {
+                    let _timer = phase_timer("incoming");
+                    self.process_incoming_messages().await;
+                }
+                {
+                    let _timer = phase_timer("world");
+                    self.update_world().await;
+                }
+                let shutdown = {
+                    let _timer = phase_timer("admin");
+                    self.process_admin_requests().await
+                };
+                {
+                    let _timer = phase_timer("outcoming");
+                    self.send_outcoming_messages().await;
+                }
                 self.last_tick = now;
+
+                if now - self.last_metrics >= METRICS_INTERVAL {
+                    self.world.record_metrics();
+                    self.last_metrics = now;
+                }
+
+                if shutdown {
+                    self.world.save_all();
+                    self.world.flush().await;
+                    info!("server stopped by an admin");
+                    return Ok(());
+                }
             }
         }
     }
@@ -110,6 +156,11 @@ impl Finisterra {
         }
 
         let authentication_events = self.accounts.poll_account_events().await;
+        metrics().set(
+            metrics::QUEUE_DEPTH,
+            &[("queue", "account_events")],
+            authentication_events.len() as f64,
+        );
         for event in authentication_events {
             match event {
                 AccountEvent::Created {
@@ -119,7 +170,11 @@ impl Finisterra {
                     self.users.insert(
                         connection_id,
                         User::InAccount {
-                            account_name: account_name.clone(),
+                            session: Session {
+                                account_name: account_name.clone(),
+                                privilege: Privilege::Player,
+                                ip: self.server.address(connection_id),
+                            },
                             character_names: vec![],
                         },
                     );
@@ -140,6 +195,7 @@ impl Finisterra {
                 AccountEvent::LoginAccountOk {
                     connection_id,
                     account_name,
+                    privilege,
                     characters,
                 } => {
                     let character_names = characters
@@ -149,7 +205,11 @@ impl Finisterra {
                     self.users.insert(
                         connection_id,
                         User::InAccount {
-                            account_name,
+                            session: Session {
+                                account_name,
+                                privilege,
+                                ip: self.server.address(connection_id),
+                            },
                             character_names,
                         },
                     );
@@ -173,14 +233,29 @@ impl Finisterra {
                     )
                     .await;
                 }
+                AccountEvent::LoginAccountBanned {
+                    connection_id,
+                    reason,
+                } => {
+                    self.send(
+                        connection_id,
+                        ServerPacket::Account(server::Account::Banned { reason }),
+                    )
+                    .await;
+                }
                 AccountEvent::LoginCharacterOk {
                     connection_id,
                     character,
+                    sanctions,
                 } => {
+                    let Some(session) = self.session(connection_id) else {
+                        continue;
+                    };
                     let character = character.into();
-                    let entity_id = self.world.create_character(&character);
+                    let entity_id = self.world.create_character(&character, session);
                     self.users
                         .insert(connection_id, User::InWorld { entity_id });
+                    self.server.bind_entity(connection_id, entity_id);
                     self.connection_ids.insert(entity_id, connection_id);
                     self.send(
                         connection_id,
@@ -190,7 +265,8 @@ impl Finisterra {
                         }),
                     )
                     .await;
-                    self.world.notify_new_character(entity_id, &character).await;
+                    self.world.notify_new_character(entity_id, &character);
+                    self.world.apply_sanctions(entity_id, &sanctions);
                 }
                 AccountEvent::LoginCharacterFailed { connection_id } => {
                     self.send(
@@ -204,11 +280,16 @@ impl Finisterra {
                 AccountEvent::CreateCharacterOk {
                     connection_id,
                     character,
+                    sanctions,
                 } => {
+                    let Some(session) = self.session(connection_id) else {
+                        continue;
+                    };
                     let character = character.into();
-                    let entity_id = self.world.create_character(&character);
+                    let entity_id = self.world.create_character(&character, session);
                     self.users
                         .insert(connection_id, User::InWorld { entity_id });
+                    self.server.bind_entity(connection_id, entity_id);
 
                     self.connection_ids.insert(entity_id, connection_id);
                     self.send(
@@ -219,7 +300,8 @@ impl Finisterra {
                         }),
                     )
                     .await;
-                    self.world.notify_new_character(entity_id, &character).await;
+                    self.world.notify_new_character(entity_id, &character);
+                    self.world.apply_sanctions(entity_id, &sanctions);
                 }
                 AccountEvent::CreateCharacterFailed {
                     connection_id,
@@ -262,15 +344,21 @@ impl Finisterra {
                     .await
             }
             client::Account::LoginAccount { name, password } => {
-                self.accounts.login(connection_id, &name, &password).await
+                let ip = self.server.address(connection_id);
+                self.accounts
+                    .login(connection_id, &name, &password, ip)
+                    .await
             }
             client::Account::LoginCharacter { character } => {
                 if let Some(User::InAccount {
-                    character_names, ..
+                    session,
+                    character_names,
                 }) = self.users.get(&connection_id)
                 {
                     if character_names.contains(&character) {
-                        self.accounts.enter(connection_id, &character).await
+                        self.accounts
+                            .enter(connection_id, session, &character)
+                            .await
                     }
                 }
             }
@@ -280,7 +368,7 @@ impl Finisterra {
                 race,
                 gender,
             } => {
-                if let Some(User::InAccount { account_name, .. }) = self.users.get(&connection_id) {
+                if let Some(User::InAccount { session, .. }) = self.users.get(&connection_id) {
                     let create_character = CreateCharacter {
                         name,
                         class_id: class.id() as i32,
@@ -309,7 +397,7 @@ impl Finisterra {
                         equipment: database::model::Equipment::default(),
                     };
                     self.accounts
-                        .create_character(connection_id, account_name, create_character)
+                        .create_character(connection_id, session, create_character)
                         .await
                 }
             }
@@ -319,6 +407,103 @@ impl Finisterra {
 
     async fn update_world(&mut self) {
         self.world.tick().await;
+
+        for entity_id in self.world.take_kicked() {
+            if let Some(connection_id) = self.connection_ids.get(&entity_id) {
+                self.server.disconnect(*connection_id);
+            }
+        }
+    }
+
+    /// Returns true when an admin asked to shut down the server
+    async fn process_admin_requests(&mut self) -> bool {
+        let mut shutdown = false;
+        for request in self.admin.poll_requests() {
+            let response = match &request.command {
+                AdminCommand::Help => admin::HELP.to_string(),
+                AdminCommand::Connections => self.connections_report(),
+                AdminCommand::Entities => self.world.entities_report(),
+                AdminCommand::Inspect { name } => self
+                    .world
+                    .inspect(name)
+                    .unwrap_or(format!("{name} is not online")),
+                AdminCommand::Broadcast { text } => {
+                    self.world.broadcast(text);
+                    "broadcasted".to_string()
+                }
+                AdminCommand::Kick { name, reason } => match self.world.user_by_name(name) {
+                    Some(entity_id) => {
+                        self.world.kick(ADMIN_MODERATOR, entity_id, reason);
+                        format!("{name} kicked")
+                    }
+                    None => format!("{name} is not online"),
+                },
+                AdminCommand::Ban {
+                    name,
+                    duration,
+                    reason,
+                } => match self.world.user_by_name(name) {
+                    Some(entity_id) => match self.world.sanction(
+                        ADMIN_MODERATOR,
+                        entity_id,
+                        SanctionKind::Ban,
+                        *duration,
+                        reason,
+                        false,
+                    ) {
+                        Ok(()) => format!("{name} banned"),
+                        Err(reason) => format!("error: {reason}"),
+                    },
+                    None => format!("{name} is not online"),
+                },
+                AdminCommand::Save => {
+                    self.world.save_all();
+                    self.world.flush().await;
+                    "saved".to_string()
+                }
+                AdminCommand::Reload => match self.world.reload_catalogues() {
+                    Ok(report) => report,
+                    Err(e) => format!("error: {e}"),
+                },
+                AdminCommand::Shutdown => {
+                    shutdown = true;
+                    "shutting down".to_string()
+                }
+            };
+            request.reply(response);
+        }
+        shutdown
+    }
+
+    fn connections_report(&self) -> String {
+        let mut connection_ids = self.users.keys().copied().collect::<Vec<_>>();
+        connection_ids.sort();
+        connection_ids
+            .into_iter()
+            .map(|connection_id| {
+                let address = self
+                    .server
+                    .address(connection_id)
+                    .map(|ip| ip.to_string())
+                    .unwrap_or("unknown".to_string());
+                let state = match &self.users[&connection_id] {
+                    User::Connected => "connected".to_string(),
+                    User::InAccount { session, .. } => {
+                        format!("in account {}", session.account_name)
+                    }
+                    User::InWorld { entity_id } => format!("in world as entity {entity_id}"),
+                };
+                format!("{connection_id} {address} {state}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn session(&self, connection_id: u32) -> Option<Session> {
+        match self.users.get(&connection_id) {
+            Some(User::InAccount { session, .. }) => Some(session.clone()),
+            _ => None,
+        }
     }
 
     async fn send_outcoming_messages(&mut self) {
@@ -330,3 +515,7 @@ impl Finisterra {
         self.server.send_outcoming_messages().await;
     }
 }
+
+fn phase_timer(phase: &str) -> metrics::Timer {
+    metrics().timer(metrics::TICK_PHASE_SECONDS, &[("phase", phase)])
+}
@@ -1,10 +1,14 @@
+use std::env;
+
 use anyhow::Result;
 use finisterra::Finisterra;
 use tracing_subscriber::filter::EnvFilter;
 use tracing_subscriber::filter::LevelFilter;
 
 mod accounts;
+mod admin;
 mod finisterra;
+mod metrics;
 mod server;
 mod world;
 
@@ -17,14 +21,21 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// `LOG_FORMAT=json` writes one json object per event, packet logs are configured with
+/// `RUST_LOG=packets=debug` and `PACKET_LOG`
 pub fn init_logging() {
     let env_filter = EnvFilter::builder()
         .with_default_directive(LevelFilter::INFO.into())
         .from_env_lossy();
 
-    tracing_subscriber::fmt()
+    let subscriber = tracing_subscriber::fmt()
         .with_target(true)
         .with_level(true)
-        .with_env_filter(env_filter)
-        .init();
+        .with_env_filter(env_filter);
+
+    if env::var("LOG_FORMAT").is_ok_and(|format| format == "json") {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
+    }
 }
@@ -0,0 +1,436 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Write,
+    future::Future,
+    sync::{Mutex, OnceLock},
+    time::Instant,
+};
+
+use anyhow::Result;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{error, info};
+
+/// Only reachable from the host running the server, override with `METRICS_ADDRESS`
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9184";
+
+/// Upper bounds in seconds of the buckets of every histogram
+const BUCKETS: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.016, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0,
+];
+
+pub const PLAYERS_ONLINE: &str = "finisterra_players_online";
+pub const ENTITIES: &str = "finisterra_entities";
+pub const TICK_PHASE_SECONDS: &str = "finisterra_tick_phase_seconds";
+pub const PACKETS_RECEIVED: &str = "finisterra_packets_received_total";
+pub const BYTES_RECEIVED: &str = "finisterra_received_bytes_total";
+pub const PACKETS_SENT: &str = "finisterra_packets_sent_total";
+pub const BYTES_SENT: &str = "finisterra_sent_bytes_total";
+pub const QUEUE_DEPTH: &str = "finisterra_queue_depth";
+pub const DATABASE_QUERY_SECONDS: &str = "finisterra_database_query_seconds";
+pub const CONNECTIONS: &str = "finisterra_connections_total";
+
+/// Name, type and help of every exported family, in the order they are rendered
+const FAMILIES: [(&str, Kind, &str); 10] = [
+    (PLAYERS_ONLINE, Kind::Gauge, "Users in the world"),
+    (
+        ENTITIES,
+        Kind::Gauge,
+        "Entities in the world by map and kind",
+    ),
+    (
+        TICK_PHASE_SECONDS,
+        Kind::Histogram,
+        "Duration of each phase of the server tick",
+    ),
+    (
+        PACKETS_RECEIVED,
+        Kind::Counter,
+        "Packets received from clients by variant",
+    ),
+    (
+        BYTES_RECEIVED,
+        Kind::Counter,
+        "Bytes received from clients by variant",
+    ),
+    (
+        PACKETS_SENT,
+        Kind::Counter,
+        "Packets sent to clients by variant",
+    ),
+    (
+        BYTES_SENT,
+        Kind::Counter,
+        "Bytes sent to clients by variant",
+    ),
+    (
+        QUEUE_DEPTH,
+        Kind::Gauge,
+        "Messages waiting in the internal queues",
+    ),
+    (
+        DATABASE_QUERY_SECONDS,
+        Kind::Histogram,
+        "Duration of the database queries",
+    ),
+    (
+        CONNECTIONS,
+        Kind::Counter,
+        "Connections accepted and dropped",
+    ),
+];
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Counter,
+    Gauge,
+    Histogram,
+}
+
+impl Kind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+            Kind::Histogram => "histogram",
+        }
+    }
+}
+
+type Key = (&'static str, Vec<(&'static str, String)>);
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+/// Process wide registry, it's shared by the tick loop and the spawned tasks
+#[derive(Default)]
+pub struct Metrics {
+    values: Mutex<BTreeMap<Key, f64>>,
+    histograms: Mutex<BTreeMap<Key, Histogram>>,
+}
+
+/// Observes the time elapsed until it's dropped
+pub struct Timer {
+    name: &'static str,
+    labels: Vec<(&'static str, String)>,
+    start: Instant,
+}
+
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::default)
+}
+
+impl Metrics {
+    /// Serves the metrics over http, any path returns them
+    pub async fn listen() -> Result<()> {
+        let address = env::var("METRICS_ADDRESS").unwrap_or(DEFAULT_ADDRESS.to_string());
+        let listener = TcpListener::bind(&address).await?;
+        info!("Metrics listening on {address}");
+
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream));
+                    }
+                    Err(e) => error!("couldn't accept metrics connection: {e}"),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Adds to a counter
+    pub fn increment(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64) {
+        self.add(name, labels, value as f64);
+    }
+
+    /// Adds to a gauge, negative values decrease it
+    pub fn add(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+        let mut values = self.values.lock().expect("poisoned");
+        *values.entry(key(name, labels)).or_default() += value;
+    }
+
+    pub fn set(&self, name: &'static str, labels: &[(&'static str, &str)], value: f64) {
+        let mut values = self.values.lock().expect("poisoned");
+        values.insert(key(name, labels), value);
+    }
+
+    /// Replaces every series of a family, for gauges whose labels come and go
+    pub fn replace(
+        &self,
+        name: &'static str,
+        series: impl IntoIterator<Item = (Vec<(&'static str, String)>, f64)>,
+    ) {
+        let mut values = self.values.lock().expect("poisoned");
+        values.retain(|(family, _), _| *family != name);
+        values.extend(
+            series
+                .into_iter()
+                .map(|(labels, value)| ((name, labels), value)),
+        );
+    }
+
+    pub fn observe(&self, name: &'static str, labels: &[(&'static str, &str)], seconds: f64) {
+        let mut histograms = self.histograms.lock().expect("poisoned");
+        let histogram = histograms.entry(key(name, labels)).or_default();
+        for (bucket, bound) in histogram.buckets.iter_mut().zip(BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        histogram.count += 1;
+        histogram.sum += seconds;
+    }
+
+    pub fn timer(&self, name: &'static str, labels: &[(&'static str, &str)]) -> Timer {
+        Timer {
+            name,
+            labels: key(name, labels).1,
+            start: Instant::now(),
+        }
+    }
+
+    /// Prometheus text format
+    pub fn render(&self) -> String {
+        let values = self.values.lock().expect("poisoned");
+        let histograms = self.histograms.lock().expect("poisoned");
+
+        let mut text = String::new();
+        for (name, kind, help) in FAMILIES {
+            let _ = writeln!(text, "# HELP {name} {help}");
+            let _ = writeln!(text, "# TYPE {name} {}", kind.as_str());
+            for ((_, labels), value) in values.range(family(name)) {
+                let _ = writeln!(text, "{name}{} {value}", format_labels(labels, None));
+            }
+            for ((_, labels), histogram) in histograms.range(family(name)) {
+                for (bucket, bound) in histogram.buckets.iter().zip(BUCKETS) {
+                    let labels = format_labels(labels, Some(&bound.to_string()));
+                    let _ = writeln!(text, "{name}_bucket{labels} {bucket}");
+                }
+                let _ = writeln!(
+                    text,
+                    "{name}_bucket{} {}",
+                    format_labels(labels, Some("+Inf")),
+                    histogram.count
+                );
+                let labels = format_labels(labels, None);
+                let _ = writeln!(text, "{name}_sum{labels} {}", histogram.sum);
+                let _ = writeln!(text, "{name}_count{labels} {}", histogram.count);
+            }
+        }
+        text
+    }
+}
+
+/// Awaits a database query recording how long it took
+pub async fn query<T>(query: &str, future: impl Future<Output = T>) -> T {
+    let _timer = metrics().timer(DATABASE_QUERY_SECONDS, &[("query", query)]);
+    future.await
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        let labels = self
+            .labels
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect::<Vec<_>>();
+        metrics().observe(self.name, &labels, self.start.elapsed().as_secs_f64());
+    }
+}
+
+fn key(name: &'static str, labels: &[(&'static str, &str)]) -> Key {
+    let labels = labels
+        .iter()
+        .map(|(label, value)| (*label, value.to_string()))
+        .collect();
+    (name, labels)
+}
+
+/// Range of keys covering every series of the family
+fn family(name: &'static str) -> std::ops::RangeInclusive<Key> {
+    (name, vec![])..=(name, vec![("\u{10ffff}", String::new())])
+}
+
+fn format_labels(labels: &[(&'static str, String)], le: Option<&str>) -> String {
+    let mut pairs = labels
+        .iter()
+        .map(|(label, value)| format!("{label}=\"{}\"", escape(value)))
+        .collect::<Vec<_>>();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{le}\""));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+async fn serve(mut stream: TcpStream) {
+    // the request is not parsed, every scrape gets the same answer
+    let mut buffer = [0; 1024];
+    if stream.read(&mut buffer).await.is_err() {
+        return;
+    }
+    let body = metrics().render();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Content-Type: text/plain; version=0.0.4\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Metrics, DATABASE_QUERY_SECONDS, ENTITIES, FAMILIES, PACKETS_SENT, PLAYERS_ONLINE,
+        QUEUE_DEPTH, TICK_PHASE_SECONDS,
+    };
+
+    /// Series rendered after the HELP and TYPE lines of the family
+    fn series(text: &str, name: &str) -> Vec<String> {
+        text.lines()
+            .skip_while(|line| !line.starts_with(&format!("# TYPE {name} ")))
+            .skip(1)
+            .take_while(|line| !line.starts_with("# "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn every_family_is_described() {
+        let text = Metrics::default().render();
+        for (name, _, _) in FAMILIES {
+            assert!(series(&text, name).is_empty());
+        }
+        assert!(text.starts_with(
+            "# HELP finisterra_players_online Users in the world\n\
+             # TYPE finisterra_players_online gauge\n\
+             # HELP finisterra_entities Entities in the world by map and kind\n\
+             # TYPE finisterra_entities gauge\n"
+        ));
+        assert!(text.contains(
+            "# HELP finisterra_tick_phase_seconds Duration of each phase of the server tick\n\
+             # TYPE finisterra_tick_phase_seconds histogram\n"
+        ));
+        assert!(text.contains("# TYPE finisterra_packets_sent_total counter\n"));
+    }
+
+    #[test]
+    fn series_are_rendered_in_their_family() {
+        let metrics = Metrics::default();
+        metrics.set(PLAYERS_ONLINE, &[], 3.);
+        metrics.set(ENTITIES, &[("map", "1"), ("kind", "npc")], 20.);
+        metrics.set(ENTITIES, &[("map", "1"), ("kind", "user")], 2.);
+        metrics.increment(PACKETS_SENT, &[("variant", "Chat")], 4);
+        metrics.increment(PACKETS_SENT, &[("variant", "Chat")], 1);
+        metrics.add(QUEUE_DEPTH, &[("queue", "database")], 5.);
+        metrics.add(QUEUE_DEPTH, &[("queue", "database")], -2.);
+
+        let text = metrics.render();
+        assert_eq!(
+            series(&text, PLAYERS_ONLINE),
+            ["finisterra_players_online 3"]
+        );
+        assert_eq!(
+            series(&text, ENTITIES),
+            [
+                "finisterra_entities{map=\"1\",kind=\"npc\"} 20",
+                "finisterra_entities{map=\"1\",kind=\"user\"} 2",
+            ]
+        );
+        assert_eq!(
+            series(&text, PACKETS_SENT),
+            ["finisterra_packets_sent_total{variant=\"Chat\"} 5"]
+        );
+        assert_eq!(
+            series(&text, QUEUE_DEPTH),
+            ["finisterra_queue_depth{queue=\"database\"} 3"]
+        );
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let metrics = Metrics::default();
+        metrics.set(ENTITIES, &[("map", "a\"b\\c\nd")], 1.);
+        assert_eq!(
+            series(&metrics.render(), ENTITIES),
+            ["finisterra_entities{map=\"a\\\"b\\\\c\\nd\"} 1"]
+        );
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        for seconds in [0.25, 0.5, 2.] {
+            metrics.observe(TICK_PHASE_SECONDS, &[("phase", "npcs")], seconds);
+        }
+
+        let text = metrics.render();
+        let lines = series(&text, TICK_PHASE_SECONDS);
+        assert_eq!(lines.len(), 12 + 3);
+        assert_eq!(
+            lines[0],
+            "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"0.0005\"} 0"
+        );
+        assert_eq!(
+            lines[8..],
+            [
+                "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"0.1\"} 0",
+                "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"0.25\"} 1",
+                "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"0.5\"} 2",
+                "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"1\"} 2",
+                "finisterra_tick_phase_seconds_bucket{phase=\"npcs\",le=\"+Inf\"} 3",
+                "finisterra_tick_phase_seconds_sum{phase=\"npcs\"} 2.75",
+                "finisterra_tick_phase_seconds_count{phase=\"npcs\"} 3",
+            ]
+        );
+        assert!(series(&text, DATABASE_QUERY_SECONDS).is_empty());
+    }
+
+    #[test]
+    fn replace_drops_the_series_that_are_gone() {
+        let metrics = Metrics::default();
+        metrics.set(PLAYERS_ONLINE, &[], 1.);
+        let entities = |maps: &[&str]| {
+            maps.iter()
+                .map(|map| (vec![("map", map.to_string())], 10.))
+                .collect::<Vec<_>>()
+        };
+        metrics.replace(ENTITIES, entities(&["1", "2"]));
+        metrics.replace(ENTITIES, entities(&["2", "3"]));
+
+        let text = metrics.render();
+        assert_eq!(
+            series(&text, ENTITIES),
+            [
+                "finisterra_entities{map=\"2\"} 10",
+                "finisterra_entities{map=\"3\"} 10",
+            ]
+        );
+        assert_eq!(
+            series(&text, PLAYERS_ONLINE),
+            ["finisterra_players_online 1"]
+        );
+    }
+}
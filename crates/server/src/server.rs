@@ -1,18 +1,33 @@
-use std::{collections::HashMap, env, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    env,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use anyhow::Result;
-use shared::protocol::{client::ClientPacket, server::ServerPacket, ProtocolMessage};
+use shared::protocol::{
+    client::ClientPacket,
+    logging::{self, log_packet, Direction},
+    server::ServerPacket,
+    ProtocolMessage,
+};
 use tokio::sync::{
     mpsc::{channel, Receiver, UnboundedReceiver},
-    Mutex,
+    oneshot, Mutex,
 };
 use tracing::{error, info};
 use wtransport::{config::IpBindConfig, Endpoint, Identity, SendStream, ServerConfig};
 
+use crate::metrics::{self, metrics};
+
 enum ConnectionEvent {
     Accepted {
         connection_id: u32,
         stream: SendStream,
+        close: oneshot::Sender<()>,
+        address: IpAddr,
     },
     Disconnected {
         connection_id: u32,
@@ -25,6 +40,11 @@ pub struct Server {
     outcoming_messages_receiver: UnboundedReceiver<(u32, ServerPacket)>,
 
     streams: Arc<Mutex<HashMap<u32, SendStream>>>,
+    /// closes the connection from the server side
+    closers: HashMap<u32, oneshot::Sender<()>>,
+    addresses: HashMap<u32, IpAddr>,
+    /// entity of the connections in the world, only used to label the packet logs
+    entities: Arc<RwLock<HashMap<u32, u32>>>,
 }
 
 impl Server {
@@ -50,33 +70,68 @@ impl Server {
 
         let (incoming_messages_sender, incoming_messages_receiver) = channel(3000);
         let (connection_events_sender, connection_events_receiver) = channel(100);
+        let entities = Arc::new(RwLock::new(HashMap::new()));
 
         tokio::spawn({
+            let entities = entities.clone();
+
             async move {
                 for connection_id in 0.. {
                     let incoming_session = endpoint.accept().await;
                     tokio::spawn({
                         let connection_events_sender = connection_events_sender.clone();
                         let incoming_messages_sender = incoming_messages_sender.clone();
+                        let entities = entities.clone();
 
                         async move {
                             let session_request = incoming_session.await.unwrap();
                             let connection = session_request.accept().await.unwrap();
                             let (stream, mut recv) = connection.accept_bi().await.unwrap();
                             info!("connection accepted {connection_id}!");
+                            metrics().increment(metrics::CONNECTIONS, &[("event", "accepted")], 1);
 
+                            let (close, mut closed) = oneshot::channel();
                             connection_events_sender
                                 .send(ConnectionEvent::Accepted {
                                     connection_id,
                                     stream,
+                                    close,
+                                    address: connection.remote_address().ip(),
                                 })
                                 .await
                                 .expect("poisoned");
 
                             let mut buffer = vec![0; 65536].into_boxed_slice();
-                            while let Ok(Some(bytes_read)) = recv.read(&mut buffer).await {
+                            loop {
+                                let bytes_read = tokio::select! {
+                                    read = recv.read(&mut buffer) => match read {
+                                        Ok(Some(bytes_read)) => bytes_read,
+                                        _ => break,
+                                    },
+                                    _ = &mut closed => {
+                                        info!("connection {connection_id} closed by the server");
+                                        break;
+                                    }
+                                };
                                 if let Some(message) = ClientPacket::decode(&buffer[..bytes_read]) {
-                                    info!("connection {connection_id} <= {message:#?}");
+                                    if let Some(level) = logging::sample(message.name()) {
+                                        log_packet(
+                                            level,
+                                            Direction::Incoming,
+                                            Some(connection_id),
+                                            entity(&entities, connection_id),
+                                            message.name(),
+                                            bytes_read,
+                                            &message.redacted(),
+                                        );
+                                    }
+                                    let packet = [("packet", message.name())];
+                                    metrics().increment(metrics::PACKETS_RECEIVED, &packet, 1);
+                                    metrics().increment(
+                                        metrics::BYTES_RECEIVED,
+                                        &packet,
+                                        bytes_read as u64,
+                                    );
                                     incoming_messages_sender
                                         .send((connection_id, message))
                                         .await
@@ -88,6 +143,7 @@ impl Server {
                             }
 
                             info!("connection dropped {connection_id}!");
+                            metrics().increment(metrics::CONNECTIONS, &[("event", "dropped")], 1);
                             connection_events_sender
                                 .send(ConnectionEvent::Disconnected { connection_id })
                                 .await
@@ -105,6 +161,9 @@ impl Server {
             incoming_messages_receiver,
             outcoming_messages_receiver,
             streams,
+            closers: HashMap::new(),
+            addresses: HashMap::new(),
+            entities,
         })
     }
 
@@ -118,12 +177,22 @@ impl Server {
                 ConnectionEvent::Accepted {
                     connection_id,
                     stream,
+                    close,
+                    address,
                 } => {
                     streams.insert(connection_id, stream);
+                    self.closers.insert(connection_id, close);
+                    self.addresses.insert(connection_id, address);
                     connections.push(connection_id);
                 }
                 ConnectionEvent::Disconnected { connection_id } => {
                     streams.remove(&connection_id);
+                    self.closers.remove(&connection_id);
+                    self.addresses.remove(&connection_id);
+                    self.entities
+                        .write()
+                        .expect("poisoned")
+                        .remove(&connection_id);
                     disconnections.push(connection_id);
                 }
             }
@@ -131,11 +200,35 @@ impl Server {
         (connections, disconnections)
     }
 
+    pub fn address(&self, connection_id: u32) -> Option<IpAddr> {
+        self.addresses.get(&connection_id).copied()
+    }
+
+    /// The connection entered the world with that entity
+    pub fn bind_entity(&mut self, connection_id: u32, entity_id: u32) {
+        self.entities
+            .write()
+            .expect("poisoned")
+            .insert(connection_id, entity_id);
+    }
+
+    /// Drops the connection, it's reported back as a disconnection
+    pub fn disconnect(&mut self, connection_id: u32) {
+        if let Some(close) = self.closers.remove(&connection_id) {
+            let _ = close.send(());
+        }
+    }
+
     pub async fn read_incoming_messages(&mut self) -> Vec<(u32, ClientPacket)> {
         let mut messages = vec![];
         while let Ok(incoming_message) = self.incoming_messages_receiver.try_recv() {
             messages.push(incoming_message);
         }
+        metrics().set(
+            metrics::QUEUE_DEPTH,
+            &[("queue", "incoming")],
+            messages.len() as f64,
+        );
 
         messages
     }
@@ -145,14 +238,37 @@ impl Server {
         while let Ok(message) = self.outcoming_messages_receiver.try_recv() {
             outcoming_messages.push(message);
         }
+        metrics().set(
+            metrics::QUEUE_DEPTH,
+            &[("queue", "outcoming")],
+            outcoming_messages.len() as f64,
+        );
         tokio::spawn({
             let streams = self.streams.clone();
+            let entities = self.entities.clone();
 
             async move {
                 for (connection_id, message) in outcoming_messages {
                     if let Some(stream) = streams.lock().await.get_mut(&connection_id) {
-                        info!("connection {connection_id} => {message:#?}");
+                        let name = message.name();
+                        let packet = [("packet", name)];
+                        // encoding consumes the packet, it's formatted before only if it's logged
+                        let logged =
+                            logging::sample(name).map(|level| (level, format!("{message:?}")));
                         if let Some(bytes) = message.encode() {
+                            if let Some((level, payload)) = logged {
+                                log_packet(
+                                    level,
+                                    Direction::Outgoing,
+                                    Some(connection_id),
+                                    entity(&entities, connection_id),
+                                    name,
+                                    bytes.len(),
+                                    &format_args!("{payload}"),
+                                );
+                            }
+                            metrics().increment(metrics::PACKETS_SENT, &packet, 1);
+                            metrics().increment(metrics::BYTES_SENT, &packet, bytes.len() as u64);
                             if stream.write_all(&bytes).await.is_err() {
                                 error!("failed to send message to client");
                             }
@@ -167,3 +283,11 @@ impl Server {
         });
     }
 }
+
+fn entity(entities: &RwLock<HashMap<u32, u32>>, connection_id: u32) -> Option<u32> {
+    entities
+        .read()
+        .expect("poisoned")
+        .get(&connection_id)
+        .copied()
+}
@@ -1,13 +1,16 @@
 use shared::{
-    protocol::server::DialogKind,
-    world::{Map, WorldPosition},
+    argentum::{npc::NPC, object::Object, spell::Spell},
+    protocol::{crafting::Recipe, server::DialogKind},
+    world::{Map, MapInfo, WorldPosition},
 };
 use std::{
     collections::VecDeque,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
-use nohash_hasher::IntMap;
+use database::Database;
+use nohash_hasher::{IntMap, IntSet};
 use shared::{
     character::Character,
     protocol::{
@@ -18,17 +21,85 @@ use shared::{
 };
 use tokio::sync::mpsc::UnboundedSender;
 
-use self::{maps::load_maps, networking::Target};
+use crate::accounts::Session;
 
+use self::{
+    chat::ChatCooldowns,
+    commerce::Shop,
+    maps::{load_map_infos, load_maps},
+    networking::Target,
+    npcs::{load_npcs, npc_character},
+    objects::{load_objects, load_recipes},
+    persistence::Persistence,
+    pets::Pet,
+    spells::load_spells,
+    trade::TradeSide,
+    weather::Climate,
+};
+
+mod admin;
+mod bank;
+mod chat;
+mod commands;
+mod commerce;
+mod crafting;
+mod inventory;
 mod maps;
+mod moderation;
 mod movement;
+mod npcs;
+mod objects;
+mod party;
+mod persistence;
+mod pets;
+mod spells;
+mod trade;
+mod travel;
+mod triggers;
+mod weather;
+
+const MAPS_PATH: &str = "assets/finisterra/maps/";
+const MAP_INFOS_PATH: &str = "assets/finisterra/init/maps.ron";
+const OBJECTS_PATH: &str = "assets/finisterra/init/objects.ron";
+const NPCS_PATH: &str = "assets/finisterra/init/npcs.ron";
+const SPELLS_PATH: &str = "assets/finisterra/init/spells.ron";
 
 pub struct World {
     outcoming_messages_sender: UnboundedSender<(u32, ServerPacket)>,
+    persistence: Persistence,
 
     maps: IntMap<u16, Map>,
+    map_infos: IntMap<u16, MapInfo>,
+    objects: IntMap<usize, Object>,
+    recipes: Vec<Recipe>,
+    npcs: IntMap<usize, NPC>,
+    spells: IntMap<usize, Spell>,
     entities: IntMap<u32, Entity>,
     next_entity_id: u32,
+
+    /// users trading with a merchant, by user entity id
+    commerce: IntMap<u32, u32>,
+    /// users with the bank open, by user entity id
+    bank: IntMap<u32, u32>,
+    trades: IntMap<u32, TradeSide>,
+    chat_cooldowns: IntMap<u32, ChatCooldowns>,
+    /// party members to their leader, the leader included
+    parties: IntMap<u32, u32>,
+    /// pending party invitations, invited user to leader
+    party_invites: IntMap<u32, u32>,
+    /// pending trade requests, requester to target
+    trade_requests: IntMap<u32, u32>,
+    /// users standing on anti-camping tiles and since when
+    camping: IntMap<u32, Instant>,
+    /// users hidden from the other users
+    invisible: IntSet<u32>,
+    /// muted users and until when, indefinitely if not present
+    mutes: IntMap<u32, Option<Instant>>,
+    /// jailed users and until when, indefinitely if not present
+    jails: IntMap<u32, Option<Instant>>,
+    /// users to be disconnected by the server
+    kicked: Vec<u32>,
+    climate: Climate,
 }
 
 pub enum Entity {
@@ -37,20 +108,66 @@ pub enum Entity {
         last_move: Instant,
         last_move_receive: Instant,
         pending_moves: VecDeque<MoveRequest>,
+        session: Session,
+    },
+    Npc {
+        npc: NPC,
+        position: WorldPosition,
+        health: u32,
+        shop: Option<Shop>,
+        pet: Option<Pet>,
     },
-    // NPC,
+}
+
+impl Entity {
+    pub fn position(&self) -> WorldPosition {
+        match self {
+            Entity::Character { character, .. } => character.position,
+            Entity::Npc { position, .. } => *position,
+        }
+    }
 }
 
 impl World {
-    pub fn initialize(outcoming_messages_sender: UnboundedSender<(u32, ServerPacket)>) -> Self {
-        let maps = load_maps("assets/finisterra/maps/");
+    pub fn initialize(
+        outcoming_messages_sender: UnboundedSender<(u32, ServerPacket)>,
+        database: Arc<Database>,
+    ) -> Self {
+        let maps = load_maps(MAPS_PATH);
+        let map_infos = load_map_infos(MAP_INFOS_PATH);
+        let objects = load_objects(OBJECTS_PATH);
+        let recipes = load_recipes(&objects);
+        let npcs = load_npcs(NPCS_PATH);
+        let spells = load_spells(SPELLS_PATH);
         let entities = IntMap::default();
-        Self {
+        let climate = Climate::initialize(&map_infos);
+        let mut world = Self {
             outcoming_messages_sender,
+            persistence: Persistence::initialize(database),
             entities,
             next_entity_id: 0,
             maps,
-        }
+            map_infos,
+            objects,
+            recipes,
+            npcs,
+            spells,
+            commerce: IntMap::default(),
+            bank: IntMap::default(),
+            trades: IntMap::default(),
+            chat_cooldowns: IntMap::default(),
+            parties: IntMap::default(),
+            party_invites: IntMap::default(),
+            trade_requests: IntMap::default(),
+            camping: IntMap::default(),
+            invisible: IntSet::default(),
+            mutes: IntMap::default(),
+            jails: IntMap::default(),
+            kicked: vec![],
+            climate,
+        };
+        world.spawn_npcs();
+        world
     }
 
     pub async fn process_incoming_message(&mut self, entity_id: u32, message: ClientPacket) {
@@ -86,47 +203,65 @@ impl World {
                     }
                 }
                 client::Action::Talk { mut text } => {
-                    if let Some(Entity::Character { character, .. }) =
-                        self.entities.get_mut(&entity_id)
+                    let kind = if text.starts_with('*') {
+                        text.remove(0);
+                        DialogKind::Shout
+                    } else if text.starts_with('.') {
+                        text.remove(0);
+                        DialogKind::Role
+                    } else {
+                        DialogKind::Normal
+                    };
+                    self.say(entity_id, text, kind);
+                }
+                client::Action::DoubleClick { position } => {
+                    if !self.use_crafting_station(entity_id, position)
+                        && !self.open_shop(entity_id, position)
+                        && !self.open_bank(entity_id, position)
                     {
-                        let position = character.position;
-                        let kind = if text.starts_with('*') {
-                            text.remove(0);
-                            DialogKind::Shout
-                        } else if text.starts_with('.') {
-                            text.remove(0);
-                            DialogKind::Role
-                        } else {
-                            DialogKind::Normal
-                        };
-                        self.send(
-                            ServerPacket::CharacterUpdate(CharacterUpdate::DialogAdd {
-                                entity_id,
-                                text,
-                                kind,
-                            }),
-                            Target::Area {
-                                _position: position,
-                            },
-                        );
+                        if let Some(target) = self.entity_at(&position) {
+                            if matches!(self.entities.get(&target), Some(Entity::Npc { .. })) {
+                                self.process_pet(
+                                    entity_id,
+                                    client::Pet::Tame { entity_id: target },
+                                );
+                            } else {
+                                self.process_trade(
+                                    entity_id,
+                                    client::Trade::Request { entity_id: target },
+                                );
+                            }
+                        }
                     }
                 }
+                client::Action::LeftClick { position } => {
+                    self.select_pets_target(entity_id, position)
+                }
+                client::Action::CastSpell { slot, position } => {
+                    self.cast_spell(entity_id, slot, position)
+                }
                 _ => {}
             },
-            ClientPacket::Bank(_) => todo!(),
-            ClientPacket::Commerce(_) => todo!(),
-            ClientPacket::Pet(_) => todo!(),
-            ClientPacket::Request(_) => todo!(),
+            ClientPacket::Craft(craft) => self.process_craft(entity_id, craft),
+            ClientPacket::Bank(bank) => self.process_bank(entity_id, bank),
+            ClientPacket::Trade(trade) => self.process_trade(entity_id, trade),
+            ClientPacket::Commerce(commerce) => self.process_commerce(entity_id, commerce),
+            ClientPacket::Pet(pet) => self.process_pet(entity_id, pet),
+            ClientPacket::Chat(chat) => self.process_chat(entity_id, chat),
+            ClientPacket::Party(party) => self.process_party(entity_id, party),
+            ClientPacket::Command(line) => self.process_command(entity_id, &line),
+            ClientPacket::Request(request) => self.process_request(entity_id, request),
             ClientPacket::Account(_) => unreachable!(),
         }
     }
 
-    pub fn create_character(&mut self, character: &Character) -> u32 {
+    pub fn create_character(&mut self, character: &Character, session: Session) -> u32 {
         let entity = Entity::Character {
             character: character.clone(),
             last_move: Instant::now() - Duration::from_millis(200),
             last_move_receive: Instant::now() - Duration::from_millis(200),
             pending_moves: VecDeque::new(),
+            session,
         };
         let id = self.next_entity_id;
         self.entities.insert(id, entity);
@@ -140,23 +275,36 @@ impl World {
         id
     }
 
-    pub async fn notify_new_character(&mut self, id: u32, character: &Character) {
+    /// Announces the character in its map and sends the user the entities already there, the
+    /// clock and the weather
+    pub fn notify_new_character(&self, id: u32, character: &Character) {
+        self.send_environment(id, character.position.map);
         // notify near entities about new character
         let character_create = ServerPacket::CharacterUpdate(CharacterUpdate::Create {
             entity_id: id,
-            character: character.clone(),
+            character: Box::new(character.clone()),
         });
         self.send(character_create, Target::AreaButUser { entity_id: id });
         // notify user about near entities
         for (area_entity_id, entity) in &self.entities {
-            if area_entity_id == &id {
+            if area_entity_id == &id
+                || entity.position().map != character.position.map
+                || self.invisible.contains(area_entity_id)
+            {
                 continue;
             }
             match entity {
                 Entity::Character { character, .. } => {
                     let character_create = ServerPacket::CharacterUpdate(CharacterUpdate::Create {
                         entity_id: *area_entity_id,
-                        character: character.clone(),
+                        character: Box::new(character.clone()),
+                    });
+                    self.send(character_create, Target::User { entity_id: id });
+                }
+                Entity::Npc { npc, position, .. } => {
+                    let character_create = ServerPacket::CharacterUpdate(CharacterUpdate::Create {
+                        entity_id: *area_entity_id,
+                        character: Box::new(npc_character(npc, *position)),
                     });
                     self.send(character_create, Target::User { entity_id: id });
                 }
@@ -165,6 +313,10 @@ impl World {
     }
 
     pub async fn remove_character(&mut self, entity_id: &u32) {
+        self.cancel_trade(*entity_id, "Trade cancelled, the other user left");
+        self.remove_pets(*entity_id);
+        self.leave_party(*entity_id);
+        self.chat_cooldowns.remove(entity_id);
         if let Some(Entity::Character { character, .. }) = self.entities.get(entity_id) {
             let WorldPosition { map, x, y } = character.position;
             if let Some(map) = self.maps.get_mut(&map) {
@@ -181,23 +333,60 @@ impl World {
             },
         );
         self.entities.remove(entity_id);
+        self.commerce.remove(entity_id);
+        self.bank.remove(entity_id);
+        self.camping.remove(entity_id);
+        self.invisible.remove(entity_id);
+        self.mutes.remove(entity_id);
+        self.jails.remove(entity_id);
+    }
+
+    /// Users kicked since the last call, the server drops their connections
+    pub fn take_kicked(&mut self) -> Vec<u32> {
+        std::mem::take(&mut self.kicked)
+    }
+
+    pub fn object_at(&self, position: &WorldPosition) -> Option<&Object> {
+        let tile = self
+            .maps
+            .get(&position.map)?
+            .get_tile(position.x, position.y)?;
+        self.objects.get(&(tile.obj.as_ref()?.index as usize))
     }
 
     pub async fn tick(&mut self) {
         self.process_pending_moves();
+        self.update_shops();
+        self.update_banks();
+        self.update_pets();
+        self.update_camping();
+        self.update_jails();
+        self.update_environment();
     }
 }
 
 mod networking {
-    use shared::{protocol::server::ServerPacket, world::WorldPosition};
+    use shared::{
+        protocol::server::{CharacterUpdate, ServerPacket},
+        world::WorldPosition,
+    };
 
-    use super::World;
+    use super::{Entity, World};
 
     pub enum Target {
-        User { entity_id: u32 },
-        Area { _position: WorldPosition },
-        AreaButUser { entity_id: u32 },
-        // TODO
+        User {
+            entity_id: u32,
+        },
+        /// Users in the same map as `position`
+        Area {
+            position: WorldPosition,
+        },
+        /// Users in the same map as the user, except the user itself
+        AreaButUser {
+            entity_id: u32,
+        },
+        /// Every user in the world
+        All,
     }
 
     impl World {
@@ -208,26 +397,68 @@ mod networking {
                         .send((entity_id, packet))
                         .expect("poisoned");
                 }
-                Target::Area { .. } => {
-                    for entity_id in self.entities.keys() {
-                        //todo send to near users in entity_id area
+                Target::Area { position } => {
+                    let hidden = self.hidden_entity(&packet);
+                    for entity_id in self.users_in_map(position.map) {
+                        if hidden.is_some_and(|hidden| hidden != entity_id) {
+                            continue;
+                        }
                         self.outcoming_messages_sender
-                            .send((*entity_id, packet.clone()))
+                            .send((entity_id, packet.clone()))
                             .expect("poisoned");
                     }
                 }
                 Target::AreaButUser { entity_id } => {
-                    for area_entity_id in self.entities.keys() {
-                        if area_entity_id == &entity_id {
+                    let Some(entity) = self.entities.get(&entity_id) else {
+                        return;
+                    };
+                    let hidden = self.hidden_entity(&packet);
+                    for area_entity_id in self.users_in_map(entity.position().map) {
+                        if area_entity_id == entity_id
+                            || hidden.is_some_and(|hidden| hidden != area_entity_id)
+                        {
                             continue;
                         }
-                        //todo send to near users in entity_id area
                         self.outcoming_messages_sender
-                            .send((*area_entity_id, packet.clone()))
+                            .send((area_entity_id, packet.clone()))
                             .expect("poisoned");
                     }
                 }
+                Target::All => {
+                    for (entity_id, entity) in &self.entities {
+                        if let Entity::Character { .. } = entity {
+                            self.outcoming_messages_sender
+                                .send((*entity_id, packet.clone()))
+                                .expect("poisoned");
+                        }
+                    }
+                }
             }
         }
+
+        /// Invisible entity the packet would reveal to the other users
+        fn hidden_entity(&self, packet: &ServerPacket) -> Option<u32> {
+            let ServerPacket::CharacterUpdate(
+                CharacterUpdate::Create { entity_id, .. }
+                | CharacterUpdate::Move { entity_id, .. }
+                | CharacterUpdate::Heading { entity_id, .. }
+                | CharacterUpdate::DialogAdd { entity_id, .. },
+            ) = packet
+            else {
+                return None;
+            };
+            self.invisible.get(entity_id).copied()
+        }
+
+        fn users_in_map(&self, map: u16) -> impl Iterator<Item = u32> + '_ {
+            self.entities
+                .iter()
+                .filter_map(move |(entity_id, entity)| match entity {
+                    Entity::Character { character, .. } if character.position.map == map => {
+                        Some(*entity_id)
+                    }
+                    _ => None,
+                })
+        }
     }
 }
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, fs::File, hash::Hash};
+
+use anyhow::Result;
+use nohash_hasher::IntMap;
+use serde::de::DeserializeOwned;
+
+use crate::metrics::{self, metrics};
+
+use super::{
+    objects::load_recipes, Entity, World, MAP_INFOS_PATH, NPCS_PATH, OBJECTS_PATH, SPELLS_PATH,
+};
+
+impl World {
+    /// Refreshes the gauges of users and entities per map
+    pub fn record_metrics(&self) {
+        let mut entities = HashMap::<(u16, &str), usize>::new();
+        for entity in self.entities.values() {
+            let kind = match entity {
+                Entity::Character { .. } => "user",
+                Entity::Npc { pet: Some(_), .. } => "pet",
+                Entity::Npc { .. } => "npc",
+            };
+            *entities.entry((entity.position().map, kind)).or_default() += 1;
+        }
+
+        metrics().set(metrics::PLAYERS_ONLINE, &[], self.users().count() as f64);
+        metrics().replace(
+            metrics::ENTITIES,
+            entities.into_iter().map(|((map, kind), count)| {
+                let labels = vec![("map", map.to_string()), ("kind", kind.to_string())];
+                (labels, count as f64)
+            }),
+        );
+    }
+
+    /// One line per entity, sorted by id
+    pub fn entities_report(&self) -> String {
+        let mut entities = self.entities.iter().collect::<Vec<_>>();
+        entities.sort_by_key(|(entity_id, _)| **entity_id);
+        entities
+            .into_iter()
+            .map(|(entity_id, entity)| {
+                let position = entity.position();
+                let (kind, name) = match entity {
+                    Entity::Character { character, .. } => ("user", &character.name),
+                    Entity::Npc { npc, .. } => ("npc", &npc.name),
+                };
+                format!(
+                    "{entity_id} {kind} {name} at {}-{}-{}",
+                    position.map, position.x, position.y
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn inspect(&self, name: &str) -> Option<String> {
+        let entity_id = self.user_by_name(name)?;
+        let Some(Entity::Character {
+            character, session, ..
+        }) = self.entities.get(&entity_id)
+        else {
+            return None;
+        };
+        let position = character.position;
+        Some(format!(
+            "entity {entity_id}, account {} ({:?}) from {}\n\
+             level {}, exp {}/{}, gold {}, vault gold {}\n\
+             health {}/{}, mana {}/{}, stamina {}/{}\n\
+             position {}-{}-{}, guild {}\n\
+             muted {}, jailed {}, invisible {}",
+            session.account_name,
+            session.privilege,
+            session
+                .ip
+                .map(|ip| ip.to_string())
+                .unwrap_or("unknown".to_string()),
+            character.level,
+            character.exp.current,
+            character.exp.max,
+            character.gold,
+            character.vault.gold,
+            character.stats.health.current,
+            character.stats.health.max,
+            character.stats.mana.current,
+            character.stats.mana.max,
+            character.stats.stamina.current,
+            character.stats.stamina.max,
+            position.map,
+            position.x,
+            position.y,
+            character.guild.as_deref().unwrap_or("none"),
+            self.mutes.contains_key(&entity_id),
+            self.jails.contains_key(&entity_id),
+            self.invisible.contains(&entity_id),
+        ))
+    }
+
+    /// System message for every user in the world
+    pub fn broadcast(&self, text: &str) {
+        for (entity_id, _) in self.users() {
+            self.send_system(entity_id, text);
+        }
+    }
+
+    pub fn save_all(&self) {
+        let entity_ids = self
+            .users()
+            .map(|(entity_id, _)| entity_id)
+            .collect::<Vec<_>>();
+        self.save_items_together(&entity_ids);
+    }
+
+    /// Waits until the pending saves are stored
+    pub async fn flush(&self) {
+        self.persistence.flush().await;
+    }
+
+    /// Reloads the data catalogues, maps are kept since they hold the entities positions.
+    /// Nothing changes if any catalogue fails to load
+    pub fn reload_catalogues(&mut self) -> Result<String> {
+        let objects = read_catalogue(OBJECTS_PATH)?;
+        let npcs = read_catalogue(NPCS_PATH)?;
+        let spells = read_catalogue(SPELLS_PATH)?;
+        let map_infos = read_catalogue(MAP_INFOS_PATH)?;
+
+        let mut report = vec![];
+        if let Some(objects) = objects {
+            self.recipes = load_recipes(&objects);
+            report.push(format!(
+                "{} objects, {} recipes",
+                objects.len(),
+                self.recipes.len()
+            ));
+            self.objects = objects;
+        }
+        if let Some(npcs) = npcs {
+            report.push(format!("{} npcs", npcs.len()));
+            self.npcs = npcs;
+        }
+        if let Some(spells) = spells {
+            report.push(format!("{} spells", spells.len()));
+            self.spells = spells;
+        }
+        if let Some(map_infos) = map_infos {
+            report.push(format!("{} map infos", map_infos.len()));
+            self.map_infos = map_infos;
+        }
+        Ok(format!("reloaded {}", report.join(", ")))
+    }
+}
+
+/// Catalogue in the ron file, `None` if the file is not present
+fn read_catalogue<K, V>(path: &str) -> Result<Option<IntMap<K, V>>>
+where
+    K: DeserializeOwned + Eq + Hash + nohash_hasher::IsEnabled,
+    V: DeserializeOwned,
+{
+    let Ok(file) = File::open(path) else {
+        return Ok(None);
+    };
+    let reader = std::io::BufReader::new(file);
+    let catalogue: HashMap<K, V> =
+        ron::de::from_reader(reader).map_err(|e| anyhow::anyhow!("{path} is not valid: {e}"))?;
+
+    Ok(Some(catalogue.into_iter().collect()))
+}
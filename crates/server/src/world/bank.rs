@@ -0,0 +1,247 @@
+use shared::{
+    argentum::npc::NpcKind,
+    protocol::{
+        client,
+        server::{self, ServerPacket, UserUpdate},
+    },
+    world::WorldPosition,
+};
+
+use super::{networking::Target, npcs::in_interaction_distance, Entity, World};
+
+impl World {
+    pub fn process_bank(&mut self, entity_id: u32, bank: client::Bank) {
+        if let client::Bank::Close = bank {
+            self.bank.remove(&entity_id);
+            return;
+        }
+        if !self.bank.contains_key(&entity_id) {
+            self.send(
+                ServerPacket::Bank(server::Bank::Failed {
+                    reason: "You need to talk to a banker".to_string(),
+                }),
+                Target::User { entity_id },
+            );
+            return;
+        }
+
+        let result = match bank {
+            client::Bank::Show => {
+                self.send_vault(entity_id);
+                Ok(())
+            }
+            client::Bank::Deposit { amount } => self.deposit_gold(entity_id, amount),
+            client::Bank::Extract { amount } => self.extract_gold(entity_id, amount),
+            client::Bank::DepositItem { slot, amount, to } => {
+                self.deposit_item(entity_id, slot, amount, to)
+            }
+            client::Bank::ExtractItem { slot, amount, to } => {
+                self.extract_item(entity_id, slot, amount, to)
+            }
+            client::Bank::Close => Ok(()),
+        };
+
+        if let Err(reason) = result {
+            self.send(
+                ServerPacket::Bank(server::Bank::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    /// Opens the vault when double clicking a banker
+    pub fn open_bank(&mut self, entity_id: u32, position: WorldPosition) -> bool {
+        let Some(banker_id) = self.entity_at(&position) else {
+            return false;
+        };
+        let Some(Entity::Npc { npc, position, .. }) = self.entities.get(&banker_id) else {
+            return false;
+        };
+        if npc.kind != NpcKind::Banker {
+            return false;
+        }
+        let banker_position = *position;
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return false;
+        };
+        if !in_interaction_distance(&character.position, &banker_position) {
+            self.send(
+                ServerPacket::Bank(server::Bank::Failed {
+                    reason: "You are too far away".to_string(),
+                }),
+                Target::User { entity_id },
+            );
+            return true;
+        }
+
+        self.bank.insert(entity_id, banker_id);
+        self.send_vault(entity_id);
+        true
+    }
+
+    /// Closes the vault of users that walked away from the banker
+    pub fn update_banks(&mut self) {
+        let distant = self
+            .bank
+            .iter()
+            .filter(|(entity_id, banker_id)| {
+                let user = match self.entities.get(entity_id) {
+                    Some(Entity::Character { character, .. }) => character.position,
+                    _ => return true,
+                };
+                match self.entities.get(banker_id) {
+                    Some(Entity::Npc { position, .. }) => !in_interaction_distance(&user, position),
+                    _ => true,
+                }
+            })
+            .map(|(entity_id, _)| *entity_id)
+            .collect::<Vec<_>>();
+        for entity_id in distant {
+            self.bank.remove(&entity_id);
+            self.send(
+                ServerPacket::Bank(server::Bank::Close),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    fn deposit_gold(&mut self, entity_id: u32, amount: u64) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        if amount == 0 || character.gold < amount {
+            return Err("Not enough gold");
+        }
+        character.gold -= amount;
+        character.vault.gold += amount;
+
+        self.send_gold(entity_id);
+        self.save_items(entity_id);
+        Ok(())
+    }
+
+    fn extract_gold(&mut self, entity_id: u32, amount: u64) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        if amount == 0 || character.vault.gold < amount {
+            return Err("Not enough gold in the vault");
+        }
+        character.vault.gold -= amount;
+        character.gold += amount;
+
+        self.send_gold(entity_id);
+        self.save_items(entity_id);
+        Ok(())
+    }
+
+    fn deposit_item(
+        &mut self,
+        entity_id: u32,
+        slot: u8,
+        amount: u16,
+        to: Option<u8>,
+    ) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let Some(item) = character.inventory.get(slot as usize).copied() else {
+            return Ok(());
+        };
+        let amount = std::cmp::min(item.amount, amount.max(1) as u32);
+        if !character.vault.items.can_add(item.item_id, amount) {
+            return Err("Not enough space in the vault");
+        }
+        character.inventory.take(slot as usize, amount);
+        let vault = &mut character.vault.items;
+        let vault_slots = match to {
+            Some(to) => vault.add_to(to as usize, item.item_id, amount),
+            None => vault.add(item.item_id, amount),
+        }
+        .unwrap_or_default();
+
+        self.send_inventory_slots(entity_id, &[slot as usize]);
+        self.send_vault_slots(entity_id, &vault_slots);
+        self.save_items(entity_id);
+        Ok(())
+    }
+
+    fn extract_item(
+        &mut self,
+        entity_id: u32,
+        slot: u8,
+        amount: u16,
+        to: Option<u8>,
+    ) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let Some(item) = character.vault.items.get(slot as usize).copied() else {
+            return Ok(());
+        };
+        let amount = std::cmp::min(item.amount, amount.max(1) as u32);
+        if !character.inventory.can_add(item.item_id, amount) {
+            return Err("Not enough space in the inventory");
+        }
+        character.vault.items.take(slot as usize, amount);
+        let inventory = &mut character.inventory;
+        let inventory_slots = match to {
+            Some(to) => inventory.add_to(to as usize, item.item_id, amount),
+            None => inventory.add(item.item_id, amount),
+        }
+        .unwrap_or_default();
+
+        self.send_vault_slots(entity_id, &[slot as usize]);
+        self.send_inventory_slots(entity_id, &inventory_slots);
+        self.save_items(entity_id);
+        Ok(())
+    }
+
+    fn send_vault(&self, entity_id: u32) {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return;
+        };
+        self.send(
+            ServerPacket::Bank(server::Bank::Open {
+                vault: character.vault.clone(),
+            }),
+            Target::User { entity_id },
+        );
+    }
+
+    fn send_vault_slots(&self, entity_id: u32, slots: &[usize]) {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return;
+        };
+        for slot in slots {
+            let item = character.vault.items.get(*slot).copied();
+            self.send(
+                ServerPacket::UserUpdate(UserUpdate::BankSlot {
+                    slot: *slot as u8,
+                    item,
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    fn send_gold(&self, entity_id: u32) {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return;
+        };
+        self.send(
+            ServerPacket::UserUpdate(UserUpdate::Gold {
+                gold: character.gold,
+            }),
+            Target::User { entity_id },
+        );
+        self.send(
+            ServerPacket::UserUpdate(UserUpdate::BankGold {
+                gold: character.vault.gold,
+            }),
+            Target::User { entity_id },
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use std::time::{Duration, Instant};
+
+use shared::{
+    character::Character,
+    protocol::{
+        chat::Channel,
+        client,
+        server::{self, CharacterUpdate, DialogKind, ServerPacket},
+    },
+};
+
+use crate::accounts::Privilege;
+
+use super::{networking::Target, Entity, World};
+
+const MAX_MESSAGE_LENGTH: usize = 200;
+const GLOBAL_COOLDOWN: Duration = Duration::from_secs(10);
+const TRADE_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Last message of the user in the channels with cooldown
+#[derive(Default)]
+pub struct ChatCooldowns {
+    global: Option<Instant>,
+    trade: Option<Instant>,
+}
+
+impl World {
+    pub fn process_chat(&mut self, entity_id: u32, chat: client::Chat) {
+        let result = match chat {
+            client::Chat::Send { channel, text } => self.send_chat(entity_id, channel, text),
+            client::Chat::Whisper { to, text } => self.whisper(entity_id, &to, text),
+        };
+
+        if let Err(reason) = result {
+            self.send_chat_failed(entity_id, reason);
+        }
+    }
+
+    /// Overhead dialog for the users around, it's also logged in their consoles
+    pub fn say(&mut self, entity_id: u32, text: String, kind: DialogKind) {
+        if let Err(reason) = self.check_muted(entity_id) {
+            self.send_chat_failed(entity_id, reason);
+            return;
+        }
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return;
+        };
+        let Ok(text) = clean(text) else {
+            return;
+        };
+        let position = character.position;
+        let from = character.name.clone();
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::DialogAdd {
+                entity_id,
+                text: text.clone(),
+                kind,
+            }),
+            Target::Area { position },
+        );
+        // the name in the console would give away invisible users, only they see their messages
+        let target = if self.invisible.contains(&entity_id) {
+            Target::User { entity_id }
+        } else {
+            Target::Area { position }
+        };
+        self.send(
+            ServerPacket::Chat(server::Chat::Message {
+                channel: Channel::Say,
+                from,
+                text,
+            }),
+            target,
+        );
+    }
+
+    /// Console message from the server, only the user sees it
+    pub fn send_system(&self, entity_id: u32, text: &str) {
+        self.send(
+            ServerPacket::Chat(server::Chat::Message {
+                channel: Channel::System,
+                from: String::new(),
+                text: text.to_string(),
+            }),
+            Target::User { entity_id },
+        );
+    }
+
+    /// Online user with that name, names are case insensitive
+    pub fn user_by_name(&self, name: &str) -> Option<u32> {
+        self.users()
+            .find(|(_, character)| character.name.eq_ignore_ascii_case(name))
+            .map(|(entity_id, _)| entity_id)
+    }
+
+    /// Online user with that name for a user with the privilege, invisible users are only found
+    /// by the ones that can see them
+    pub fn visible_user_by_name(&self, privilege: Privilege, name: &str) -> Option<u32> {
+        self.user_by_name(name)
+            .filter(|entity_id| sees_invisible(privilege) || !self.invisible.contains(entity_id))
+    }
+
+    pub fn privilege(&self, entity_id: u32) -> Privilege {
+        match self.entities.get(&entity_id) {
+            Some(Entity::Character { session, .. }) => session.privilege,
+            _ => Privilege::Player,
+        }
+    }
+
+    pub fn character_name(&self, entity_id: u32) -> String {
+        match self.entities.get(&entity_id) {
+            Some(Entity::Character { character, .. }) => character.name.clone(),
+            Some(Entity::Npc { npc, .. }) => npc.name.clone(),
+            None => String::new(),
+        }
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = (u32, &Character)> {
+        self.entities
+            .iter()
+            .filter_map(|(entity_id, entity)| match entity {
+                Entity::Character { character, .. } => Some((*entity_id, character)),
+                _ => None,
+            })
+    }
+
+    fn send_chat(
+        &mut self,
+        entity_id: u32,
+        channel: Channel,
+        text: String,
+    ) -> Result<(), &'static str> {
+        let text = clean(text)?;
+        self.check_muted(entity_id)?;
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let from = character.name.clone();
+
+        let recipients = match channel {
+            Channel::Say => {
+                self.say(entity_id, text, DialogKind::Normal);
+                return Ok(());
+            }
+            Channel::Whisper => return Err("Whispers need a recipient"),
+            Channel::System => return Err("Only the server can write in the system channel"),
+            Channel::Party => self
+                .party_members(entity_id)
+                .ok_or("You are not in a party")?,
+            Channel::Guild => {
+                let guild = character
+                    .guild
+                    .as_ref()
+                    .ok_or("You don't belong to a guild")?;
+                self.users()
+                    .filter(|(_, character)| character.guild.as_ref() == Some(guild))
+                    .map(|(entity_id, _)| entity_id)
+                    .collect()
+            }
+            Channel::Global | Channel::Trade => {
+                self.use_cooldown(entity_id, channel)?;
+                self.users().map(|(entity_id, _)| entity_id).collect()
+            }
+        };
+
+        for recipient in recipients {
+            self.send(
+                ServerPacket::Chat(server::Chat::Message {
+                    channel,
+                    from: from.clone(),
+                    text: text.clone(),
+                }),
+                Target::User {
+                    entity_id: recipient,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn whisper(&mut self, entity_id: u32, to: &str, text: String) -> Result<(), &'static str> {
+        let text = clean(text)?;
+        self.check_muted(entity_id)?;
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let Some(recipient) = self.visible_user_by_name(self.privilege(entity_id), to) else {
+            self.send_chat_failed(entity_id, &format!("{to} is not online"));
+            return Ok(());
+        };
+        if recipient == entity_id {
+            return Err("You can't whisper to yourself");
+        }
+        let from = character.name.clone();
+        let to = match self.entities.get(&recipient) {
+            Some(Entity::Character { character, .. }) => character.name.clone(),
+            _ => to.to_string(),
+        };
+
+        self.send(
+            ServerPacket::Chat(server::Chat::Message {
+                channel: Channel::Whisper,
+                from,
+                text: text.clone(),
+            }),
+            Target::User {
+                entity_id: recipient,
+            },
+        );
+        self.send(
+            ServerPacket::Chat(server::Chat::Whispered { to, text }),
+            Target::User { entity_id },
+        );
+        Ok(())
+    }
+
+    fn check_muted(&mut self, entity_id: u32) -> Result<(), &'static str> {
+        match self.mutes.get(&entity_id) {
+            Some(None) => Err("You are muted"),
+            Some(Some(until)) if Instant::now() < *until => Err("You are muted"),
+            Some(Some(_)) => {
+                self.mutes.remove(&entity_id);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn use_cooldown(&mut self, entity_id: u32, channel: Channel) -> Result<(), &'static str> {
+        let cooldowns = self.chat_cooldowns.entry(entity_id).or_default();
+        let (last, cooldown) = match channel {
+            Channel::Global => (&mut cooldowns.global, GLOBAL_COOLDOWN),
+            Channel::Trade => (&mut cooldowns.trade, TRADE_COOLDOWN),
+            _ => return Ok(()),
+        };
+        let now = Instant::now();
+        if last.is_some_and(|last| now < last + cooldown) {
+            return Err("You have to wait before writing in that channel again");
+        }
+        *last = Some(now);
+        Ok(())
+    }
+
+    fn send_chat_failed(&self, entity_id: u32, reason: &str) {
+        self.send(
+            ServerPacket::Chat(server::Chat::Failed {
+                reason: reason.to_string(),
+            }),
+            Target::User { entity_id },
+        );
+    }
+}
+
+/// Game masters see and find invisible users
+pub fn sees_invisible(privilege: Privilege) -> bool {
+    privilege >= Privilege::GameMaster
+}
+
+fn clean(text: String) -> Result<String, &'static str> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("The message is empty");
+    }
+    if text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err("The message is too long");
+    }
+    Ok(text.to_string())
+}
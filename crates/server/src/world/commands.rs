@@ -0,0 +1,747 @@
+use std::time::Duration;
+
+use database::model::SanctionKind;
+use shared::{
+    character::Character,
+    protocol::{
+        client::{self, Request},
+        server::{CharacterUpdate, Message, ServerPacket},
+    },
+    world::WorldPosition,
+};
+
+use crate::accounts::Privilege;
+
+use super::{
+    chat::sees_invisible,
+    networking::Target,
+    weather::{has_weather, COMMAND_WEATHER_DURATION},
+    Entity, World,
+};
+
+/// Reason stored in the audit log when the moderator doesn't give one
+const NO_REASON: &str = "No reason given";
+
+/// Max tiles away from the game master to place spawned npcs
+const SPAWN_RADIUS: u16 = 3;
+
+/// Usage and required privilege of the commands, listed by `/help`
+const COMMANDS: &[(&str, Privilege)] = &[
+    ("/help", Privilege::Player),
+    ("/online", Privilege::Player),
+    ("/who", Privilege::Player),
+    ("/stats", Privilege::Player),
+    ("/skills", Privilege::Player),
+    ("/attributes", Privilege::Player),
+    ("/w <name> <text>", Privilege::Player),
+    ("/party invite <name> | accept | leave", Privilege::Player),
+    ("/teleport <map> <x> <y> | <name>", Privilege::Counsellor),
+    ("/summon <name>", Privilege::Counsellor),
+    ("/invisible", Privilege::Counsellor),
+    ("/warn <name> <reason>", Privilege::Counsellor),
+    ("/kick <name> [reason]", Privilege::GameMaster),
+    (
+        "/mute <name> <minutes|perm> [reason]",
+        Privilege::GameMaster,
+    ),
+    ("/unmute <name>", Privilege::GameMaster),
+    (
+        "/jail <name> <minutes|perm> [reason]",
+        Privilege::GameMaster,
+    ),
+    ("/release <name>", Privilege::GameMaster),
+    ("/ban <name> <hours|perm> [reason]", Privilege::GameMaster),
+    ("/spawn <npc>", Privilege::GameMaster),
+    ("/weather <0-255>", Privilege::GameMaster),
+    ("/banip <name> <hours|perm> [reason]", Privilege::Admin),
+    ("/unban <name>", Privilege::Admin),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Help,
+    Online,
+    Who,
+    Stats,
+    Skills,
+    Attributes,
+    Whisper {
+        to: String,
+        text: String,
+    },
+    Party(client::Party),
+    Teleport(Destination),
+    Summon {
+        name: String,
+    },
+    Invisible,
+    Kick {
+        name: String,
+        reason: String,
+    },
+    /// Permanent when there is no duration
+    Sanction {
+        kind: SanctionKind,
+        name: String,
+        duration: Option<Duration>,
+        reason: String,
+        by_ip: bool,
+    },
+    /// Works with the name of an online user or an account
+    Revoke {
+        kind: SanctionKind,
+        name: String,
+    },
+    Spawn {
+        npc: usize,
+    },
+    /// Weather of the map of the game master, 0 clears it
+    Weather {
+        intensity: u8,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Destination {
+    Position(WorldPosition),
+    User(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, &'static str> {
+        let line = line
+            .trim()
+            .strip_prefix('/')
+            .ok_or("Commands start with /")?;
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let args = rest.split_whitespace().collect::<Vec<_>>();
+
+        let command = match (name.to_lowercase().as_str(), &args[..]) {
+            ("help", _) => Command::Help,
+            ("online", _) => Command::Online,
+            ("who", _) => Command::Who,
+            ("stats", _) => Command::Stats,
+            ("skills", _) => Command::Skills,
+            ("attributes", _) => Command::Attributes,
+            ("w" | "whisper", _) => {
+                let (to, text) = rest.split_once(' ').ok_or("Usage: /w <name> <text>")?;
+                Command::Whisper {
+                    to: to.to_string(),
+                    text: text.to_string(),
+                }
+            }
+            ("party", ["invite", name]) => Command::Party(client::Party::Invite {
+                name: name.to_string(),
+            }),
+            ("party", ["accept"]) => Command::Party(client::Party::Accept),
+            ("party", ["leave"]) => Command::Party(client::Party::Leave),
+            ("party", _) => return Err("Usage: /party invite <name> | accept | leave"),
+            ("teleport", [name]) => Command::Teleport(Destination::User(name.to_string())),
+            ("teleport", [map, x, y]) => Command::Teleport(Destination::Position(WorldPosition {
+                map: number(map)?,
+                x: number(x)?,
+                y: number(y)?,
+            })),
+            ("teleport", _) => return Err("Usage: /teleport <map> <x> <y> | <name>"),
+            ("summon", [name]) => Command::Summon {
+                name: name.to_string(),
+            },
+            ("summon", _) => return Err("Usage: /summon <name>"),
+            ("invisible", _) => Command::Invisible,
+            ("warn", [name, reason @ ..]) if !reason.is_empty() => Command::Sanction {
+                kind: SanctionKind::Warning,
+                name: name.to_string(),
+                duration: None,
+                reason: reason.join(" "),
+                by_ip: false,
+            },
+            ("warn", _) => return Err("Usage: /warn <name> <reason>"),
+            ("kick", [name, reason @ ..]) => Command::Kick {
+                name: name.to_string(),
+                reason: reason_of(reason),
+            },
+            ("kick", _) => return Err("Usage: /kick <name> [reason]"),
+            ("mute", [name, minutes, reason @ ..]) => Command::Sanction {
+                kind: SanctionKind::Mute,
+                name: name.to_string(),
+                duration: duration(minutes, 60)?,
+                reason: reason_of(reason),
+                by_ip: false,
+            },
+            ("mute", _) => return Err("Usage: /mute <name> <minutes|perm> [reason]"),
+            ("jail", [name, minutes, reason @ ..]) => Command::Sanction {
+                kind: SanctionKind::Jail,
+                name: name.to_string(),
+                duration: duration(minutes, 60)?,
+                reason: reason_of(reason),
+                by_ip: false,
+            },
+            ("jail", _) => return Err("Usage: /jail <name> <minutes|perm> [reason]"),
+            (command @ ("ban" | "banip"), [name, hours, reason @ ..]) => Command::Sanction {
+                kind: SanctionKind::Ban,
+                name: name.to_string(),
+                duration: duration(hours, 60 * 60)?,
+                reason: reason_of(reason),
+                by_ip: command == "banip",
+            },
+            ("ban" | "banip", _) => return Err("Usage: /ban <name> <hours|perm> [reason]"),
+            ("unmute", [name]) => Command::Revoke {
+                kind: SanctionKind::Mute,
+                name: name.to_string(),
+            },
+            ("release", [name]) => Command::Revoke {
+                kind: SanctionKind::Jail,
+                name: name.to_string(),
+            },
+            ("unban", [name]) => Command::Revoke {
+                kind: SanctionKind::Ban,
+                name: name.to_string(),
+            },
+            ("unmute", _) => return Err("Usage: /unmute <name>"),
+            ("release", _) => return Err("Usage: /release <name>"),
+            ("unban", _) => return Err("Usage: /unban <name>"),
+            ("spawn", [npc]) => Command::Spawn { npc: number(npc)? },
+            ("spawn", _) => return Err("Usage: /spawn <npc>"),
+            ("weather", [intensity]) => Command::Weather {
+                intensity: number(intensity).map_err(|_| "Usage: /weather <0-255>")?,
+            },
+            ("weather", _) => return Err("Usage: /weather <0-255>"),
+            _ => return Err("Unknown command, type /help to see the available commands"),
+        };
+        Ok(command)
+    }
+
+    pub fn privilege(&self) -> Privilege {
+        match self {
+            Command::Help
+            | Command::Online
+            | Command::Who
+            | Command::Stats
+            | Command::Skills
+            | Command::Attributes
+            | Command::Whisper { .. }
+            | Command::Party(_) => Privilege::Player,
+            Command::Teleport(_)
+            | Command::Summon { .. }
+            | Command::Invisible
+            | Command::Sanction {
+                kind: SanctionKind::Warning,
+                ..
+            } => Privilege::Counsellor,
+            Command::Sanction { by_ip: true, .. }
+            | Command::Revoke {
+                kind: SanctionKind::Ban,
+                ..
+            } => Privilege::Admin,
+            Command::Kick { .. }
+            | Command::Sanction { .. }
+            | Command::Revoke { .. }
+            | Command::Spawn { .. }
+            | Command::Weather { .. } => Privilege::GameMaster,
+        }
+    }
+}
+
+impl World {
+    pub fn process_command(&mut self, entity_id: u32, line: &str) {
+        let result = Command::parse(line).and_then(|command| self.run_command(entity_id, command));
+        if let Err(reason) = result {
+            self.send_command_failed(entity_id, reason);
+        }
+    }
+
+    pub fn process_request(&mut self, entity_id: u32, request: Request) {
+        let command = match request {
+            Request::Help => Command::Help,
+            Request::Online => Command::Online,
+            Request::Stats => Command::Stats,
+            Request::Skills => Command::Skills,
+            Request::Attributes => Command::Attributes,
+            Request::Quit => {
+                self.kicked.push(entity_id);
+                return;
+            }
+            Request::SpellInfo => {
+                self.send_command_failed(entity_id, "Spell info is not available yet");
+                return;
+            }
+        };
+        if let Err(reason) = self.run_command(entity_id, command) {
+            self.send_command_failed(entity_id, reason);
+        }
+    }
+
+    fn run_command(&mut self, entity_id: u32, command: Command) -> Result<(), &'static str> {
+        let Some(Entity::Character {
+            character, session, ..
+        }) = self.entities.get(&entity_id)
+        else {
+            return Ok(());
+        };
+        if session.privilege < command.privilege() {
+            return Err("You are not allowed to use that command");
+        }
+        let privilege = session.privilege;
+        let position = character.position;
+        let moderator = character.name.clone();
+
+        match command {
+            Command::Help => {
+                let commands = COMMANDS
+                    .iter()
+                    .filter(|(_, required)| *required <= privilege)
+                    .map(|(usage, _)| *usage)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.send_console(entity_id, format!("Commands: {commands}"));
+            }
+            Command::Online => {
+                let names = self.visible_user_names(privilege, |_| true);
+                self.send_console(
+                    entity_id,
+                    format!("Online users ({}): {}", names.len(), names.join(", ")),
+                );
+            }
+            Command::Who => {
+                let names =
+                    self.visible_user_names(privilege, |other| other.position.map == position.map);
+                self.send_console(
+                    entity_id,
+                    format!("Users in this map ({}): {}", names.len(), names.join(", ")),
+                );
+            }
+            Command::Stats => {
+                let text = format!(
+                    "Level {} ({}/{} exp), health {}/{}, mana {}/{}, stamina {}/{}, gold {}",
+                    character.level,
+                    character.exp.current,
+                    character.exp.max,
+                    character.stats.health.current,
+                    character.stats.health.max,
+                    character.stats.mana.current,
+                    character.stats.mana.max,
+                    character.stats.stamina.current,
+                    character.stats.stamina.max,
+                    character.gold
+                );
+                self.send_console(entity_id, text);
+            }
+            Command::Skills => {
+                let skills = &character.skills;
+                let text = format!(
+                    "Weapons {}, projectiles {}, tactics {}, defense {}, stab {}, wrestling {}, \
+                     magic {}, resistence {}, woodcutting {}, smithy {}, carpentry {}, \
+                     alchemy {}, tailor {}, steal {}, meditate {}, hide {}, survival {}, \
+                     trading {}, leadership {}, tame {}",
+                    skills.weapons,
+                    skills.projectiles,
+                    skills.tactics,
+                    skills.defense,
+                    skills.stab,
+                    skills.wrestling,
+                    skills.magic,
+                    skills.resistence,
+                    skills.woodcutting,
+                    skills.smithy,
+                    skills.carpentry,
+                    skills.alchemy,
+                    skills.tailor,
+                    skills.steal,
+                    skills.meditate,
+                    skills.hide,
+                    skills.survival,
+                    skills.trading,
+                    skills.leadership,
+                    skills.tame
+                );
+                self.send_console(entity_id, text);
+            }
+            Command::Attributes => {
+                let attributes = &character.attributes;
+                let text = format!(
+                    "Strength {}, agility {}, intelligence {}, charisma {}, constitution {}",
+                    attributes.strength,
+                    attributes.agility,
+                    attributes.intelligence,
+                    attributes.charisma,
+                    attributes.constitution
+                );
+                self.send_console(entity_id, text);
+            }
+            Command::Whisper { to, text } => {
+                self.process_chat(entity_id, client::Chat::Whisper { to, text })
+            }
+            Command::Party(party) => self.process_party(entity_id, party),
+            Command::Teleport(destination) => {
+                let destination = match destination {
+                    Destination::Position(position) => position,
+                    Destination::User(name) => {
+                        let target = self
+                            .visible_user_by_name(privilege, &name)
+                            .ok_or("That user is not online")?;
+                        self.entities
+                            .get(&target)
+                            .map(Entity::position)
+                            .ok_or("That user is not online")?
+                    }
+                };
+                self.teleport(entity_id, destination)?;
+            }
+            Command::Summon { name } => {
+                let target = self
+                    .visible_user_by_name(privilege, &name)
+                    .ok_or("That user is not online")?;
+                if target == entity_id {
+                    return Err("You can't summon yourself");
+                }
+                self.teleport(target, position)?;
+                self.send_system(target, "You were summoned by a game master");
+            }
+            Command::Invisible => {
+                if self.invisible.remove(&entity_id) {
+                    let character = Box::new(character.clone());
+                    self.send(
+                        ServerPacket::CharacterUpdate(CharacterUpdate::Create {
+                            entity_id,
+                            character,
+                        }),
+                        Target::AreaButUser { entity_id },
+                    );
+                    self.send_console(entity_id, "You are visible again".to_string());
+                } else {
+                    self.send(
+                        ServerPacket::CharacterUpdate(CharacterUpdate::Remove { entity_id }),
+                        Target::AreaButUser { entity_id },
+                    );
+                    self.invisible.insert(entity_id);
+                    self.send_console(entity_id, "You are invisible".to_string());
+                }
+            }
+            Command::Kick { name, reason } => {
+                let target = self.moderated_user(privilege, &name)?;
+                self.kick(&moderator, target, &reason);
+                self.send_console(entity_id, format!("{name} was kicked"));
+            }
+            Command::Sanction {
+                kind,
+                name,
+                duration,
+                reason,
+                by_ip,
+            } => {
+                let target = self.moderated_user(privilege, &name)?;
+                self.sanction(&moderator, target, kind, duration, &reason, by_ip)?;
+                self.send_console(entity_id, format!("{name} got a {}", kind.as_str()));
+            }
+            Command::Revoke { kind, name } => {
+                let account_name = match self.user_by_name(&name).map(|id| self.entities.get(&id)) {
+                    Some(Some(Entity::Character { session, .. })) => session.account_name.clone(),
+                    _ => name.clone(),
+                };
+                self.revoke_sanctions(&moderator, kind, &account_name, "Revoked by command");
+                self.send_console(
+                    entity_id,
+                    format!("The {} of {account_name} was revoked", kind.as_str()),
+                );
+            }
+            Command::Spawn { npc } => {
+                let npc = self
+                    .npcs
+                    .get(&npc)
+                    .cloned()
+                    .ok_or("That npc doesn't exist")?;
+                let position = self
+                    .free_position_near(&position, SPAWN_RADIUS)
+                    .ok_or("There is no room around you")?;
+                self.spawn_npc(npc, position);
+            }
+            Command::Weather { intensity } => {
+                if !self.map_infos.get(&position.map).is_some_and(has_weather) {
+                    return Err("This map has no weather");
+                }
+                self.set_weather(position.map, intensity, COMMAND_WEATHER_DURATION);
+            }
+        }
+        Ok(())
+    }
+
+    /// Names of the online users matching the filter, game masters see the invisible ones too
+    fn visible_user_names(
+        &self,
+        privilege: Privilege,
+        filter: impl Fn(&Character) -> bool,
+    ) -> Vec<String> {
+        let mut names = self
+            .users()
+            .filter(|(entity_id, character)| {
+                filter(character)
+                    && (sees_invisible(privilege) || !self.invisible.contains(entity_id))
+            })
+            .map(|(_, character)| character.name.clone())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Online user a moderator can act on, only users with lower privileges
+    fn moderated_user(&self, privilege: Privilege, name: &str) -> Result<u32, &'static str> {
+        let target = self
+            .visible_user_by_name(privilege, name)
+            .ok_or("That user is not online")?;
+        match self.entities.get(&target) {
+            Some(Entity::Character { session, .. }) if session.privilege < privilege => Ok(target),
+            _ => Err("You can't do that to that user"),
+        }
+    }
+
+    fn send_console(&self, entity_id: u32, text: String) {
+        self.send(
+            ServerPacket::Message(Message::Console { text }),
+            Target::User { entity_id },
+        );
+    }
+
+    fn send_command_failed(&self, entity_id: u32, reason: &str) {
+        self.send(
+            ServerPacket::Message(Message::Failed {
+                reason: reason.to_string(),
+            }),
+            Target::User { entity_id },
+        );
+    }
+}
+
+fn number<T: std::str::FromStr>(arg: &str) -> Result<T, &'static str> {
+    arg.parse().map_err(|_| "Expected a number")
+}
+
+/// `perm` means no expiration
+fn duration(arg: &str, unit_secs: u64) -> Result<Option<Duration>, &'static str> {
+    if arg.eq_ignore_ascii_case("perm") {
+        return Ok(None);
+    }
+    let amount: u64 = number(arg)?;
+    if amount == 0 {
+        return Err("The duration must be positive, use perm for a permanent sanction");
+    }
+    let secs = amount.checked_mul(unit_secs).ok_or("Duration too long")?;
+    Ok(Some(Duration::from_secs(secs)))
+}
+
+fn reason_of(words: &[&str]) -> String {
+    if words.is_empty() {
+        NO_REASON.to_string()
+    } else {
+        words.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use database::model::SanctionKind;
+    use shared::{protocol::client, world::WorldPosition};
+
+    use super::{Command, Destination, NO_REASON};
+    use crate::accounts::Privilege;
+
+    fn parse(line: &str) -> Command {
+        Command::parse(line).expect("the command to parse")
+    }
+
+    fn sanction(
+        kind: SanctionKind,
+        duration: Option<Duration>,
+        reason: &str,
+        by_ip: bool,
+    ) -> Command {
+        Command::Sanction {
+            kind,
+            name: "Alice".to_string(),
+            duration,
+            reason: reason.to_string(),
+            by_ip,
+        }
+    }
+
+    #[test]
+    fn player_commands() {
+        assert_eq!(parse("/help"), Command::Help);
+        assert_eq!(parse("  /ONLINE "), Command::Online);
+        assert_eq!(parse("/who"), Command::Who);
+        assert_eq!(parse("/stats"), Command::Stats);
+        assert_eq!(parse("/skills"), Command::Skills);
+        assert_eq!(parse("/attributes"), Command::Attributes);
+        assert_eq!(
+            parse("/w Alice see you  later"),
+            Command::Whisper {
+                to: "Alice".to_string(),
+                text: "see you  later".to_string()
+            }
+        );
+        assert_eq!(parse("/whisper Alice hi"), parse("/w Alice hi"));
+        assert_eq!(
+            parse("/party invite Alice"),
+            Command::Party(client::Party::Invite {
+                name: "Alice".to_string()
+            })
+        );
+        assert_eq!(
+            parse("/party accept"),
+            Command::Party(client::Party::Accept)
+        );
+        assert_eq!(parse("/party leave"), Command::Party(client::Party::Leave));
+    }
+
+    #[test]
+    fn moderation_commands() {
+        assert_eq!(
+            parse("/teleport 1 50 60"),
+            Command::Teleport(Destination::Position(WorldPosition {
+                map: 1,
+                x: 50,
+                y: 60
+            }))
+        );
+        assert_eq!(
+            parse("/teleport Alice"),
+            Command::Teleport(Destination::User("Alice".to_string()))
+        );
+        assert_eq!(
+            parse("/summon Alice"),
+            Command::Summon {
+                name: "Alice".to_string()
+            }
+        );
+        assert_eq!(parse("/invisible"), Command::Invisible);
+        assert_eq!(
+            parse("/warn Alice spamming the chat"),
+            sanction(SanctionKind::Warning, None, "spamming the chat", false)
+        );
+        assert_eq!(
+            parse("/kick Alice"),
+            Command::Kick {
+                name: "Alice".to_string(),
+                reason: NO_REASON.to_string()
+            }
+        );
+        assert_eq!(parse("/spawn 12"), Command::Spawn { npc: 12 });
+        assert_eq!(parse("/weather 255"), Command::Weather { intensity: 255 });
+        assert_eq!(parse("/weather 0"), Command::Weather { intensity: 0 });
+    }
+
+    #[test]
+    fn sanctions_last_minutes_hours_or_for_good() {
+        let minutes = |amount: u64| Some(Duration::from_secs(amount * 60));
+        let hours = |amount: u64| Some(Duration::from_secs(amount * 60 * 60));
+        assert_eq!(
+            parse("/mute Alice 10 insults"),
+            sanction(SanctionKind::Mute, minutes(10), "insults", false)
+        );
+        assert_eq!(
+            parse("/jail Alice 30"),
+            sanction(SanctionKind::Jail, minutes(30), NO_REASON, false)
+        );
+        assert_eq!(
+            parse("/ban Alice 24 cheating"),
+            sanction(SanctionKind::Ban, hours(24), "cheating", false)
+        );
+        assert_eq!(
+            parse("/banip Alice PERM cheating"),
+            sanction(SanctionKind::Ban, None, "cheating", true)
+        );
+        assert_eq!(
+            parse("/mute Alice perm"),
+            sanction(SanctionKind::Mute, None, NO_REASON, false)
+        );
+        for (line, kind) in [
+            ("/unmute Alice", SanctionKind::Mute),
+            ("/release Alice", SanctionKind::Jail),
+            ("/unban Alice", SanctionKind::Ban),
+        ] {
+            assert_eq!(
+                parse(line),
+                Command::Revoke {
+                    kind,
+                    name: "Alice".to_string()
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        let error = |line: &str| Command::parse(line).expect_err(line);
+        assert_eq!(error("help"), "Commands start with /");
+        assert_eq!(
+            error("/dance"),
+            "Unknown command, type /help to see the available commands"
+        );
+        assert_eq!(error("/w Alice"), "Usage: /w <name> <text>");
+        assert_eq!(
+            error("/party kick Alice"),
+            "Usage: /party invite <name> | accept | leave"
+        );
+        assert_eq!(
+            error("/teleport 1 50"),
+            "Usage: /teleport <map> <x> <y> | <name>"
+        );
+        assert_eq!(error("/teleport 1 50 x"), "Expected a number");
+        assert_eq!(error("/summon"), "Usage: /summon <name>");
+        assert_eq!(error("/warn Alice"), "Usage: /warn <name> <reason>");
+        assert_eq!(error("/kick"), "Usage: /kick <name> [reason]");
+        assert_eq!(
+            error("/mute Alice"),
+            "Usage: /mute <name> <minutes|perm> [reason]"
+        );
+        assert_eq!(
+            error("/jail Alice"),
+            "Usage: /jail <name> <minutes|perm> [reason]"
+        );
+        assert_eq!(
+            error("/banip Alice"),
+            "Usage: /ban <name> <hours|perm> [reason]"
+        );
+        assert_eq!(error("/ban Alice soon"), "Expected a number");
+        assert_eq!(
+            error("/ban Alice 0"),
+            "The duration must be positive, use perm for a permanent sanction"
+        );
+        assert_eq!(
+            error(&format!("/ban Alice {}", u64::MAX)),
+            "Duration too long"
+        );
+        assert_eq!(error("/unban"), "Usage: /unban <name>");
+        assert_eq!(error("/spawn goblin"), "Expected a number");
+        assert_eq!(error("/weather 256"), "Usage: /weather <0-255>");
+        assert_eq!(error("/weather"), "Usage: /weather <0-255>");
+    }
+
+    #[test]
+    fn privileges_of_the_commands() {
+        for (line, privilege) in [
+            ("/help", Privilege::Player),
+            ("/online", Privilege::Player),
+            ("/w Alice hi", Privilege::Player),
+            ("/party leave", Privilege::Player),
+            ("/teleport Alice", Privilege::Counsellor),
+            ("/summon Alice", Privilege::Counsellor),
+            ("/invisible", Privilege::Counsellor),
+            ("/warn Alice spam", Privilege::Counsellor),
+            ("/kick Alice", Privilege::GameMaster),
+            ("/mute Alice 5", Privilege::GameMaster),
+            ("/jail Alice 5", Privilege::GameMaster),
+            ("/ban Alice 5", Privilege::GameMaster),
+            ("/unmute Alice", Privilege::GameMaster),
+            ("/release Alice", Privilege::GameMaster),
+            ("/spawn 1", Privilege::GameMaster),
+            ("/weather 100", Privilege::GameMaster),
+            ("/banip Alice 5", Privilege::Admin),
+            ("/unban Alice", Privilege::Admin),
+        ] {
+            assert_eq!(parse(line).privilege(), privilege, "{line}");
+        }
+    }
+}
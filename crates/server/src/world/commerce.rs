@@ -0,0 +1,396 @@
+use std::time::{Duration, Instant};
+
+use shared::{
+    argentum::npc::Commerce,
+    protocol::{
+        client,
+        commerce::{buy_price, sell_price, ShopItem},
+        server::{self, ServerPacket, UserUpdate},
+    },
+    world::WorldPosition,
+};
+
+use super::{networking::Target, npcs::in_interaction_distance, Entity, World};
+
+const RESTOCK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+pub struct Shop {
+    stock: Vec<Stock>,
+    last_restock: Instant,
+}
+
+struct Stock {
+    item_id: u32,
+    amount: u32,
+    /// amount restored on every restock, items sold by users are not restocked
+    restock: u32,
+}
+
+impl Shop {
+    pub fn new(commerce: &Commerce) -> Self {
+        let stock = commerce
+            .items
+            .iter()
+            .map(|item| Stock {
+                item_id: item.id as u32,
+                amount: item.amount as u32,
+                restock: item.amount as u32,
+            })
+            .collect();
+        Self {
+            stock,
+            last_restock: Instant::now(),
+        }
+    }
+
+    /// Purchases are addressed by slot, so the sold out items that users sold are only dropped
+    /// while no one is browsing the shop
+    fn restock(&mut self, browsing: bool) {
+        for stock in self.stock.iter_mut() {
+            stock.amount = std::cmp::max(stock.amount, stock.restock);
+        }
+        if !browsing {
+            self.stock
+                .retain(|stock| stock.amount > 0 || stock.restock > 0);
+        }
+        self.last_restock = Instant::now();
+    }
+
+    fn add(&mut self, item_id: u32, amount: u32) {
+        match self.stock.iter_mut().find(|stock| stock.item_id == item_id) {
+            Some(stock) => stock.amount = stock.amount.saturating_add(amount),
+            None => self.stock.push(Stock {
+                item_id,
+                amount,
+                restock: 0,
+            }),
+        }
+    }
+}
+
+impl World {
+    pub fn process_commerce(&mut self, entity_id: u32, commerce: client::Commerce) {
+        let result = match commerce {
+            client::Commerce::Buy { slot, amount } => self.buy(entity_id, slot, amount),
+            client::Commerce::Sell { slot, amount } => self.sell(entity_id, slot, amount),
+            client::Commerce::Close => {
+                self.commerce.remove(&entity_id);
+                Ok(())
+            }
+        };
+
+        if let Err(reason) = result {
+            self.send(
+                ServerPacket::Commerce(server::Commerce::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    /// Starts trading when double clicking a merchant
+    pub fn open_shop(&mut self, entity_id: u32, position: WorldPosition) -> bool {
+        let Some(npc_id) = self.entity_at(&position) else {
+            return false;
+        };
+        let Some(Entity::Npc {
+            npc,
+            position,
+            shop: Some(_),
+            ..
+        }) = self.entities.get(&npc_id)
+        else {
+            return false;
+        };
+        let name = npc.name.clone();
+        let npc_position = *position;
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return false;
+        };
+        if !in_interaction_distance(&character.position, &npc_position) {
+            self.send(
+                ServerPacket::Commerce(server::Commerce::Failed {
+                    reason: "You are too far away".to_string(),
+                }),
+                Target::User { entity_id },
+            );
+            return true;
+        }
+
+        self.commerce.insert(entity_id, npc_id);
+        self.send(
+            ServerPacket::Commerce(server::Commerce::Open {
+                entity_id: npc_id,
+                name,
+                items: self.shop_items(entity_id, npc_id),
+            }),
+            Target::User { entity_id },
+        );
+        true
+    }
+
+    /// Restocks shops and closes the ones whose user walked away
+    pub fn update_shops(&mut self) {
+        let now = Instant::now();
+        let mut restocked = vec![];
+        for (id, entity) in self.entities.iter_mut() {
+            if let Entity::Npc {
+                shop: Some(shop), ..
+            } = entity
+            {
+                if now - shop.last_restock >= RESTOCK_INTERVAL {
+                    let browsing = self.commerce.values().any(|npc_id| npc_id == id);
+                    shop.restock(browsing);
+                    restocked.push(*id);
+                }
+            }
+        }
+        for npc_id in restocked {
+            self.send_stock(npc_id);
+        }
+
+        let distant = self
+            .commerce
+            .iter()
+            .filter(|(entity_id, npc_id)| {
+                let user = match self.entities.get(entity_id) {
+                    Some(Entity::Character { character, .. }) => character.position,
+                    _ => return true,
+                };
+                match self.entities.get(npc_id) {
+                    Some(Entity::Npc { position, .. }) => !in_interaction_distance(&user, position),
+                    _ => true,
+                }
+            })
+            .map(|(entity_id, _)| *entity_id)
+            .collect::<Vec<_>>();
+        for entity_id in distant {
+            self.commerce.remove(&entity_id);
+            self.send(
+                ServerPacket::Commerce(server::Commerce::Close),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    fn buy(&mut self, entity_id: u32, slot: u8, amount: u16) -> Result<(), &'static str> {
+        let Some(npc_id) = self.commerce.get(&entity_id).copied() else {
+            return Err("You aren't trading with anyone");
+        };
+        let Some(Entity::Npc {
+            shop: Some(shop), ..
+        }) = self.entities.get(&npc_id)
+        else {
+            return Ok(());
+        };
+        let Some(stock) = shop.stock.get(slot as usize) else {
+            return Ok(());
+        };
+        let item_id = stock.item_id;
+        let amount = std::cmp::min(amount.max(1) as u32, stock.amount);
+        if amount == 0 {
+            return Err("The merchant ran out of that item");
+        }
+        let Some(object) = self.objects.get(&(item_id as usize)) else {
+            return Err("The merchant can't sell that item");
+        };
+        let value = object.value;
+
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let price = buy_price(
+            value,
+            character.skills.trading,
+            character.attributes.charisma,
+        ) * amount as u64;
+        if character.gold < price {
+            return Err("Not enough gold");
+        }
+        let Some(changed) = character.inventory.add(item_id, amount) else {
+            return Err("Not enough space in the inventory");
+        };
+        character.gold -= price;
+        let gold = character.gold;
+
+        if let Some(Entity::Npc {
+            shop: Some(shop), ..
+        }) = self.entities.get_mut(&npc_id)
+        {
+            shop.stock[slot as usize].amount -= amount;
+        }
+
+        self.send_inventory_slots(entity_id, &changed);
+        self.send(
+            ServerPacket::UserUpdate(UserUpdate::Gold { gold }),
+            Target::User { entity_id },
+        );
+        self.send_stock(npc_id);
+        self.save_items(entity_id);
+
+        Ok(())
+    }
+
+    fn sell(&mut self, entity_id: u32, slot: u8, amount: u16) -> Result<(), &'static str> {
+        let Some(npc_id) = self.commerce.get(&entity_id).copied() else {
+            return Err("You aren't trading with anyone");
+        };
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let Some(item) = character.inventory.get(slot as usize).copied() else {
+            return Ok(());
+        };
+        let value = self
+            .objects
+            .get(&(item.item_id as usize))
+            .map(|object| object.value)
+            .unwrap_or_default();
+        let price = sell_price(
+            value,
+            character.skills.trading,
+            character.attributes.charisma,
+        );
+        if price == 0 {
+            return Err("The merchant isn't interested in that item");
+        }
+
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let Some(sold) = character
+            .inventory
+            .take(slot as usize, amount.max(1) as u32)
+        else {
+            return Ok(());
+        };
+        character.gold += price * sold.amount as u64;
+        let gold = character.gold;
+
+        if let Some(Entity::Npc {
+            shop: Some(shop), ..
+        }) = self.entities.get_mut(&npc_id)
+        {
+            shop.add(sold.item_id, sold.amount);
+        }
+
+        self.send_inventory_slots(entity_id, &[slot as usize]);
+        self.send(
+            ServerPacket::UserUpdate(UserUpdate::Gold { gold }),
+            Target::User { entity_id },
+        );
+        self.send_stock(npc_id);
+        self.save_items(entity_id);
+
+        Ok(())
+    }
+
+    /// Stock of a shop priced for the given user
+    fn shop_items(&self, entity_id: u32, npc_id: u32) -> Vec<ShopItem> {
+        let (
+            Some(Entity::Character { character, .. }),
+            Some(Entity::Npc {
+                shop: Some(shop), ..
+            }),
+        ) = (self.entities.get(&entity_id), self.entities.get(&npc_id))
+        else {
+            return vec![];
+        };
+
+        shop.stock
+            .iter()
+            .map(|stock| {
+                let value = self
+                    .objects
+                    .get(&(stock.item_id as usize))
+                    .map(|object| object.value)
+                    .unwrap_or_default();
+                ShopItem {
+                    item_id: stock.item_id,
+                    amount: stock.amount,
+                    price: buy_price(
+                        value,
+                        character.skills.trading,
+                        character.attributes.charisma,
+                    ),
+                }
+            })
+            .collect()
+    }
+
+    /// Sends the updated stock to every user trading with the merchant
+    fn send_stock(&self, npc_id: u32) {
+        for (entity_id, _) in self.commerce.iter().filter(|(_, id)| **id == npc_id) {
+            self.send(
+                ServerPacket::Commerce(server::Commerce::Stock {
+                    items: self.shop_items(*entity_id, npc_id),
+                }),
+                Target::User {
+                    entity_id: *entity_id,
+                },
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::argentum::npc::{Commerce, CommerceItem};
+
+    use super::Shop;
+
+    fn shop() -> Shop {
+        Shop::new(&Commerce {
+            items: vec![
+                CommerceItem { id: 10, amount: 5 },
+                CommerceItem { id: 20, amount: 1 },
+            ],
+        })
+    }
+
+    fn stock(shop: &Shop) -> Vec<(u32, u32)> {
+        shop.stock
+            .iter()
+            .map(|stock| (stock.item_id, stock.amount))
+            .collect()
+    }
+
+    #[test]
+    fn sold_items_are_added_to_the_stock() {
+        let mut shop = shop();
+        shop.add(10, 3);
+        shop.add(30, 2);
+        assert_eq!(stock(&shop), [(10, 8), (20, 1), (30, 2)]);
+
+        shop.add(30, u32::MAX);
+        assert_eq!(stock(&shop)[2], (30, u32::MAX));
+    }
+
+    #[test]
+    fn restock_refills_the_merchant_items_only() {
+        let mut shop = shop();
+        shop.stock[0].amount = 0;
+        shop.stock[1].amount = 7;
+        shop.add(30, 2);
+
+        shop.restock(false);
+        assert_eq!(stock(&shop), [(10, 5), (20, 7), (30, 2)]);
+    }
+
+    #[test]
+    fn sold_out_items_of_users_are_dropped_when_no_one_browses() {
+        let mut shop = shop();
+        shop.add(30, 2);
+        shop.add(40, 1);
+        shop.stock[0].amount = 0;
+        shop.stock[2].amount = 0;
+
+        // the slots stay in place for the users browsing the shop
+        shop.restock(true);
+        assert_eq!(stock(&shop), [(10, 5), (20, 1), (30, 0), (40, 1)]);
+
+        shop.restock(false);
+        assert_eq!(stock(&shop), [(10, 5), (20, 1), (40, 1)]);
+    }
+}
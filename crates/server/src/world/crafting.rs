@@ -0,0 +1,244 @@
+use shared::{
+    argentum::object::{Object, ObjectData, ORE_PER_INGOT},
+    protocol::{
+        client::Craft,
+        crafting::{CraftingKind, Recipe},
+        server::{Crafting, ServerPacket},
+    },
+    world::WorldPosition,
+};
+
+use super::{networking::Target, Entity, World};
+
+/// Items crafted with a single request, the amount comes from the client
+const MAX_CRAFT_AMOUNT: u32 = 1_000;
+
+impl World {
+    pub fn process_craft(&mut self, entity_id: u32, craft: Craft) {
+        let result = match craft {
+            Craft::Recipes { kind } => self.show_recipes(entity_id, kind),
+            Craft::Item { item_id, amount } => self.craft_item(entity_id, item_id, amount),
+            Craft::Smelt { slot } => self.smelt(entity_id, slot),
+        };
+
+        if let Err(reason) = result {
+            self.send(
+                ServerPacket::Crafting(Crafting::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    /// Opens the recipes window when double clicking a crafting station
+    pub fn use_crafting_station(&self, entity_id: u32, position: WorldPosition) -> bool {
+        let Some(object) = self.object_at(&position) else {
+            return false;
+        };
+        let kind = match object.data {
+            ObjectData::Anvil => CraftingKind::Smithy,
+            ObjectData::Workbench => CraftingKind::Carpentry,
+            _ => return false,
+        };
+
+        if let Err(reason) = self.show_recipes(entity_id, kind) {
+            self.send(
+                ServerPacket::Crafting(Crafting::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+        true
+    }
+
+    fn show_recipes(&self, entity_id: u32, kind: CraftingKind) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        if !self.near_station(&character.position, |object| object.is_station_for(kind)) {
+            return Err(station_error(kind));
+        }
+
+        let recipes = self
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.kind == kind)
+            .cloned()
+            .collect();
+        self.send(
+            ServerPacket::Crafting(Crafting::Recipes { kind, recipes }),
+            Target::User { entity_id },
+        );
+
+        Ok(())
+    }
+
+    fn craft_item(
+        &mut self,
+        entity_id: u32,
+        item_id: u32,
+        amount: u16,
+    ) -> Result<(), &'static str> {
+        let Some(recipe) = self
+            .recipes
+            .iter()
+            .find(|recipe| recipe.item_id == item_id)
+            .cloned()
+        else {
+            return Err("That item can't be crafted");
+        };
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        if !self.near_station(&character.position, |object| {
+            object.is_station_for(recipe.kind)
+        }) {
+            return Err(station_error(recipe.kind));
+        }
+        let skill = match recipe.kind {
+            CraftingKind::Smithy => character.skills.smithy,
+            CraftingKind::Carpentry => character.skills.carpentry,
+        };
+        if skill < recipe.skill {
+            return Err("Not enough skill to craft that item");
+        }
+
+        let amount = amount.max(1) as u32;
+        if amount > MAX_CRAFT_AMOUNT {
+            return Err("Can't craft that many items at once");
+        }
+        let Some(ingredients) = ingredients(&recipe, amount) else {
+            return Err("Can't craft that many items at once");
+        };
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let inventory = &mut character.inventory;
+        if ingredients
+            .iter()
+            .any(|(item_id, required)| inventory.count(*item_id) < *required)
+        {
+            return Err("Not enough materials");
+        }
+        if !inventory.can_add(recipe.item_id, amount) {
+            return Err("Not enough space in the inventory");
+        }
+
+        let mut changed = vec![];
+        for (item_id, required) in ingredients {
+            if let Some(slots) = inventory.remove(item_id, required) {
+                changed.extend(slots);
+            }
+        }
+        if let Some(slots) = inventory.add(recipe.item_id, amount) {
+            changed.extend(slots);
+        }
+
+        changed.sort_unstable();
+        changed.dedup();
+        self.send_inventory_slots(entity_id, &changed);
+        self.send(
+            ServerPacket::Crafting(Crafting::Crafted {
+                item_id: recipe.item_id,
+                amount: amount as u16,
+            }),
+            Target::User { entity_id },
+        );
+        self.save_items(entity_id);
+
+        Ok(())
+    }
+
+    fn smelt(&mut self, entity_id: u32, slot: u8) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        if !self.near_station(&character.position, |object| {
+            matches!(object.data, ObjectData::Forge)
+        }) {
+            return Err("You need to be next to a forge");
+        }
+        let Some(ore) = character.inventory.get(slot as usize).copied() else {
+            return Ok(());
+        };
+        let Some((ingot, required_skill)) = self
+            .objects
+            .get(&(ore.item_id as usize))
+            .and_then(|object| object.smelting())
+        else {
+            return Err("Only ores can be smelted");
+        };
+        if (character.skills.smithy as usize) < required_skill {
+            return Err("Not enough skill to smelt that ore");
+        }
+
+        let ingots = ore.amount / ORE_PER_INGOT as u32;
+        if ingots == 0 {
+            return Err("Not enough ore to smelt an ingot");
+        }
+
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        let inventory = &mut character.inventory;
+        if !inventory.can_add(ingot as u32, ingots) {
+            return Err("Not enough space in the inventory");
+        }
+
+        let mut changed = vec![slot as usize];
+        inventory.take(slot as usize, ingots * ORE_PER_INGOT as u32);
+        if let Some(slots) = inventory.add(ingot as u32, ingots) {
+            changed.extend(slots);
+        }
+
+        changed.sort_unstable();
+        changed.dedup();
+        self.send_inventory_slots(entity_id, &changed);
+        self.send(
+            ServerPacket::Crafting(Crafting::Crafted {
+                item_id: ingot as u32,
+                amount: ingots as u16,
+            }),
+            Target::User { entity_id },
+        );
+        self.save_items(entity_id);
+
+        Ok(())
+    }
+
+    fn near_station(&self, position: &WorldPosition, is_station: impl Fn(&Object) -> bool) -> bool {
+        for x in position.x.saturating_sub(1)..=position.x + 1 {
+            for y in position.y.saturating_sub(1)..=position.y + 1 {
+                let station = WorldPosition {
+                    map: position.map,
+                    x,
+                    y,
+                };
+                if let Some(object) = self.object_at(&station) {
+                    if is_station(object) {
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Items and amounts used to craft `amount` items, `None` when the amounts overflow
+fn ingredients(recipe: &Recipe, amount: u32) -> Option<Vec<(u32, u32)>> {
+    recipe
+        .ingredients
+        .iter()
+        .map(|ingredient| Some((ingredient.item_id, ingredient.amount.checked_mul(amount)?)))
+        .collect()
+}
+
+fn station_error(kind: CraftingKind) -> &'static str {
+    match kind {
+        CraftingKind::Smithy => "You need to be next to an anvil",
+        CraftingKind::Carpentry => "You need to be next to a workbench",
+    }
+}
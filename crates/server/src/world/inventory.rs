@@ -0,0 +1,21 @@
+use shared::protocol::server::{ServerPacket, UserUpdate};
+
+use super::{networking::Target, Entity, World};
+
+impl World {
+    pub fn send_inventory_slots(&self, entity_id: u32, slots: &[usize]) {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return;
+        };
+        for slot in slots {
+            let item = character.inventory.get(*slot).copied();
+            self.send(
+                ServerPacket::UserUpdate(UserUpdate::InventorySlot {
+                    slot: *slot as u8,
+                    item,
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::{collections::HashMap, fs::File};
+
 use nohash_hasher::IntMap;
-use shared::world::Map;
+use shared::world::{Map, MapInfo};
 
 pub fn load_maps(folder: &str) -> IntMap<u16, Map> {
     let mut maps = IntMap::default();
@@ -18,3 +20,16 @@ pub fn load_maps(folder: &str) -> IntMap<u16, Map> {
     }
     maps
 }
+
+/// Map properties like `secure` or `restrict_mode`, maps without info use the defaults
+pub fn load_map_infos(path: &str) -> IntMap<u16, MapInfo> {
+    let Ok(file) = File::open(path) else {
+        tracing::warn!("maps info file {path} not present, using default map properties");
+        return IntMap::default();
+    };
+    let reader = std::io::BufReader::new(file);
+    let infos: HashMap<u16, MapInfo> =
+        ron::de::from_reader(reader).expect("maps info to be correct");
+
+    infos.into_iter().collect()
+}
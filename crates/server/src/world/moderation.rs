@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use database::model::{CreateSanction, Sanction, SanctionKind};
+use shared::world::WorldPosition;
+
+use super::{Entity, World};
+
+/// Where jailed users serve their time
+const JAIL_POSITION: WorldPosition = WorldPosition {
+    map: 66,
+    x: 75,
+    y: 47,
+};
+
+/// Where users are left once their time in jail is over
+const RELEASE_POSITION: WorldPosition = WorldPosition {
+    map: 1,
+    x: 50,
+    y: 50,
+};
+
+impl World {
+    /// Applies the sanctions of the account when the user enters the world
+    pub fn apply_sanctions(&mut self, entity_id: u32, sanctions: &[Sanction]) {
+        let mut warnings = 0;
+        for sanction in sanctions {
+            // an expiration too far to represent doesn't end in practice
+            let until = sanction
+                .remaining()
+                .and_then(|remaining| Instant::now().checked_add(remaining));
+            match sanction.kind {
+                SanctionKind::Ban => self.kicked.push(entity_id),
+                SanctionKind::Mute => {
+                    self.mutes.insert(entity_id, until);
+                }
+                SanctionKind::Jail => self.jail(entity_id, until),
+                SanctionKind::Warning => warnings += 1,
+            }
+        }
+        if warnings > 0 {
+            self.send_system(
+                entity_id,
+                &format!("Your account has {warnings} active warnings"),
+            );
+        }
+    }
+
+    /// Stores a sanction against the account of an online user and applies it right away,
+    /// bans by ip also reject the other accounts connecting from there
+    pub fn sanction(
+        &mut self,
+        moderator: &str,
+        target: u32,
+        kind: SanctionKind,
+        duration: Option<Duration>,
+        reason: &str,
+        by_ip: bool,
+    ) -> Result<(), &'static str> {
+        let Some(Entity::Character { session, .. }) = self.entities.get(&target) else {
+            return Err("That user is not online");
+        };
+        let ip = match (by_ip, session.ip) {
+            (false, _) => None,
+            (true, Some(ip)) => Some(ip.to_string()),
+            (true, None) => return Err("The ip of that user is unknown"),
+        };
+        let until = duration
+            .map(|duration| {
+                Instant::now()
+                    .checked_add(duration)
+                    .ok_or("Duration too long")
+            })
+            .transpose()?;
+        self.persistence.add_sanction(CreateSanction {
+            kind,
+            account_name: Some(session.account_name.clone()),
+            ip,
+            reason: reason.to_string(),
+            moderator: moderator.to_string(),
+            duration,
+        });
+
+        let text = match kind {
+            SanctionKind::Ban => {
+                self.kicked.push(target);
+                format!("You were banned: {reason}")
+            }
+            SanctionKind::Mute => {
+                self.mutes.insert(target, until);
+                format!("You were muted: {reason}")
+            }
+            SanctionKind::Jail => {
+                self.jail(target, until);
+                format!("You were jailed: {reason}")
+            }
+            SanctionKind::Warning => format!("You were warned: {reason}"),
+        };
+        self.send_system(target, &text);
+        Ok(())
+    }
+
+    /// Revokes the sanctions of the account, the online users of the account are released
+    /// The account name is matched ignoring case, here and in the database
+    pub fn revoke_sanctions(
+        &mut self,
+        moderator: &str,
+        kind: SanctionKind,
+        account_name: &str,
+        reason: &str,
+    ) {
+        self.persistence
+            .revoke_sanctions(kind, account_name, moderator, reason);
+
+        let users = self
+            .entities
+            .iter()
+            .filter_map(|(entity_id, entity)| match entity {
+                Entity::Character { session, .. }
+                    if session.account_name.eq_ignore_ascii_case(account_name) =>
+                {
+                    Some(*entity_id)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        for entity_id in users {
+            match kind {
+                SanctionKind::Mute => {
+                    if self.mutes.remove(&entity_id).is_some() {
+                        self.send_system(entity_id, "You are no longer muted");
+                    }
+                }
+                SanctionKind::Jail => {
+                    if self.jails.remove(&entity_id).is_some() {
+                        self.release(entity_id);
+                    }
+                }
+                SanctionKind::Ban | SanctionKind::Warning => {}
+            }
+        }
+    }
+
+    /// Disconnects the user, it's recorded in the audit log
+    pub fn kick(&mut self, moderator: &str, target: u32, reason: &str) {
+        self.audit(moderator, "kick", target, reason);
+        self.send_system(target, &format!("You were kicked: {reason}"));
+        self.kicked.push(target);
+    }
+
+    /// Records a moderation action that is not a sanction, like a kick
+    pub fn audit(&self, moderator: &str, action: &str, target: u32, reason: &str) {
+        let target = match self.entities.get(&target) {
+            Some(Entity::Character { session, .. }) => session.account_name.clone(),
+            _ => return,
+        };
+        self.persistence.audit(moderator, action, &target, reason);
+    }
+
+    pub fn is_jailed(&self, entity_id: u32) -> bool {
+        self.jails.contains_key(&entity_id)
+    }
+
+    /// Releases the users whose time in jail is over
+    pub fn update_jails(&mut self) {
+        let now = Instant::now();
+        let released = self
+            .jails
+            .iter()
+            .filter(|(_, until)| until.is_some_and(|until| now >= until))
+            .map(|(entity_id, _)| *entity_id)
+            .collect::<Vec<_>>();
+
+        for entity_id in released {
+            self.jails.remove(&entity_id);
+            self.release(entity_id);
+        }
+    }
+
+    fn jail(&mut self, entity_id: u32, until: Option<Instant>) {
+        self.jails.insert(entity_id, until);
+        if let Err(reason) = self.teleport(entity_id, JAIL_POSITION) {
+            tracing::warn!("couldn't move {entity_id} to the jail: {reason}");
+        }
+    }
+
+    fn release(&mut self, entity_id: u32) {
+        if let Err(reason) = self.teleport(entity_id, RELEASE_POSITION) {
+            tracing::warn!("couldn't release {entity_id} from the jail: {reason}");
+        }
+        self.send_system(entity_id, "You are free to go");
+    }
+}
@@ -16,100 +16,110 @@ impl World {
     }
 
     fn process_move(&mut self, entity_id: u32) {
-        if let Some(Entity::Character {
+        let Some(Entity::Character {
             character,
             ref mut last_move,
             ref mut pending_moves,
             ..
         }) = self.entities.get_mut(&entity_id)
-        {
-            if pending_moves.is_empty() {
-                return;
+        else {
+            return;
+        };
+        if pending_moves.is_empty() {
+            return;
+        }
+        let now = Instant::now();
+        if now < *last_move + Duration::from_millis(200) {
+            return;
+        }
+
+        let Some(map) = self.maps.get(&character.position.map) else {
+            return;
+        };
+        // we are ready to process a move request
+        let move_request = pending_moves.pop_front().unwrap();
+        *last_move = now;
+
+        let old_position = character.position;
+        let next_position = map.next_position(&old_position, move_request.direction);
+        let exit = map.tile(next_position.x, next_position.y).exit;
+        let result = if next_position == old_position {
+            MoveOutput::Heading {
+                direction: move_request.direction,
             }
-            let now = Instant::now();
-            if now < *last_move + Duration::from_millis(200) {
-                return;
+        } else if let Some(exit) = exit {
+            match self.exit_destination(entity_id, &exit) {
+                Ok(position) => MoveOutput::Translate { position },
+                Err(reason) => {
+                    self.send_system(entity_id, reason);
+                    MoveOutput::Heading {
+                        direction: move_request.direction,
+                    }
+                }
             }
+        } else {
+            MoveOutput::Move {
+                position: next_position,
+            }
+        };
 
-            let Some(map) = self.maps.get(&character.position.map) else {
+        match result {
+            MoveOutput::Heading { direction } => {
+                self.send(
+                    ServerPacket::CharacterUpdate(CharacterUpdate::MoveResponse {
+                        request_id: move_request.id,
+                        position: old_position,
+                    }),
+                    Target::User { entity_id },
+                );
+                self.send(
+                    ServerPacket::CharacterUpdate(CharacterUpdate::Heading {
+                        entity_id,
+                        direction,
+                    }),
+                    Target::AreaButUser { entity_id },
+                );
                 return;
-            };
-            // we are ready to process a move request
-            let move_request = pending_moves.pop_front().unwrap();
-            *last_move = now;
-
-            let old_position = character.position;
-            let next_position = map.next_position(&character.position, move_request.direction);
-            let result = if next_position == old_position {
-                MoveOutput::Heading {
-                    direction: move_request.direction,
-                }
-            } else if let Some(position) = map.tile(next_position.x, next_position.y).exit {
-                MoveOutput::Translate { position }
-            } else {
-                MoveOutput::Move {
-                    position: next_position,
-                }
-            };
-            if let Some(map) = self.maps.get_mut(&old_position.map) {
-                map.tile_mut(old_position.x, old_position.y).user = None;
             }
-            if let Some(map) = self.maps.get_mut(&next_position.map) {
-                map.tile_mut(next_position.x, next_position.y).user = Some(entity_id);
+            MoveOutput::Move { position } => {
+                self.set_character_position(entity_id, position);
+                self.send(
+                    ServerPacket::CharacterUpdate(CharacterUpdate::MoveResponse {
+                        request_id: move_request.id,
+                        position,
+                    }),
+                    Target::User { entity_id },
+                );
+                self.send(
+                    ServerPacket::CharacterUpdate(CharacterUpdate::Move {
+                        entity_id,
+                        position,
+                    }),
+                    Target::AreaButUser { entity_id },
+                );
+                self.track_camping(entity_id, &position);
+            }
+            MoveOutput::Translate { position } => {
+                self.change_map(entity_id, position);
+                self.track_camping(entity_id, &position);
             }
+        };
 
-            match result {
-                MoveOutput::Heading { direction } => {
-                    let position = character.position;
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::MoveResponse {
-                            request_id: move_request.id,
-                            position,
-                        }),
-                        Target::User { entity_id },
-                    );
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::Heading {
-                            entity_id,
-                            direction,
-                        }),
-                        Target::AreaButUser { entity_id },
-                    )
-                }
-                MoveOutput::Move { position } => {
-                    character.position = position;
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::MoveResponse {
-                            request_id: move_request.id,
-                            position,
-                        }),
-                        Target::User { entity_id },
-                    );
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::Move {
-                            entity_id,
-                            position,
-                        }),
-                        Target::AreaButUser { entity_id },
-                    );
-                }
-                MoveOutput::Translate { position } => {
-                    character.position = position;
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::Translate {
-                            entity_id,
-                            position,
-                        }),
-                        Target::User { entity_id },
-                    );
-                    self.send(
-                        ServerPacket::CharacterUpdate(CharacterUpdate::Remove { entity_id }),
-                        Target::Area {
-                            _position: old_position,
-                        },
-                    );
-                }
-            };
+        self.cancel_trade(entity_id, "Trade cancelled because someone moved");
+    }
+
+    /// Updates the character position and the tiles it takes in both maps
+    pub fn set_character_position(&mut self, entity_id: u32, position: WorldPosition) {
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return;
+        };
+        let old_position = character.position;
+        character.position = position;
+        if let Some(map) = self.maps.get_mut(&old_position.map) {
+            map.tile_mut(old_position.x, old_position.y).user = None;
+        }
+        if let Some(map) = self.maps.get_mut(&position.map) {
+            map.tile_mut(position.x, position.y).user = Some(entity_id);
         }
     }
 }
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, fs::File};
+
+use nohash_hasher::IntMap;
+use shared::{
+    argentum::npc::NPC,
+    character::Character,
+    protocol::server::{CharacterUpdate, ServerPacket},
+    world::{
+        pathfinding::{find_path_to_adjacent, Walker},
+        WorldPosition,
+    },
+};
+
+use super::{commerce::Shop, networking::Target, Entity, World};
+
+/// Max tiles between a user and a npc to keep interacting with it
+const INTERACTION_DISTANCE: u16 = 3;
+
+pub fn load_npcs(path: &str) -> IntMap<usize, NPC> {
+    let Ok(file) = File::open(path) else {
+        tracing::warn!("npcs file {path} not present, starting without npcs");
+        return IntMap::default();
+    };
+    let reader = std::io::BufReader::new(file);
+    let npcs: HashMap<usize, NPC> = ron::de::from_reader(reader).expect("npcs to be correct");
+
+    npcs.into_iter().collect()
+}
+
+impl World {
+    /// Spawns the npcs placed in the maps, they take the tile like users do
+    pub fn spawn_npcs(&mut self) {
+        let mut spawns = vec![];
+        for (map_number, map) in &self.maps {
+            for (x, column) in map.tiles.iter().enumerate() {
+                for (y, tile) in column.iter().enumerate() {
+                    if let Some(index) = tile.npc {
+                        let position = WorldPosition {
+                            map: *map_number,
+                            x: x as u16 + 1,
+                            y: y as u16 + 1,
+                        };
+                        spawns.push((index as usize, position));
+                    }
+                }
+            }
+        }
+
+        for (index, position) in spawns {
+            let Some(npc) = self.npcs.get(&index).cloned() else {
+                continue;
+            };
+            self.spawn_npc(npc, position);
+        }
+    }
+
+    pub fn spawn_npc(&mut self, npc: NPC, position: WorldPosition) -> u32 {
+        let id = self.next_entity_id;
+        self.next_entity_id += 1;
+
+        let shop = npc.commerce.as_ref().map(Shop::new);
+        let health = npc.health.max as u32;
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Create {
+                entity_id: id,
+                character: Box::new(npc_character(&npc, position)),
+            }),
+            Target::Area { position },
+        );
+        self.entities.insert(
+            id,
+            Entity::Npc {
+                npc,
+                position,
+                health,
+                shop,
+                pet: None,
+            },
+        );
+        if let Some(map) = self.maps.get_mut(&position.map) {
+            map.tile_mut(position.x, position.y).user = Some(id);
+        }
+
+        id
+    }
+
+    pub fn despawn_npc(&mut self, npc_id: u32) {
+        let Some(Entity::Npc { position, .. }) = self.entities.remove(&npc_id) else {
+            return;
+        };
+        if let Some(map) = self.maps.get_mut(&position.map) {
+            map.tile_mut(position.x, position.y).user = None;
+        }
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Remove { entity_id: npc_id }),
+            Target::Area { position },
+        );
+    }
+
+    /// Moves a npc to an adjacent free tile and notifies the users
+    pub fn move_npc(&mut self, npc_id: u32, to: WorldPosition) {
+        let Some(Entity::Npc { position, .. }) = self.entities.get_mut(&npc_id) else {
+            return;
+        };
+        let from = *position;
+        *position = to;
+        if let Some(map) = self.maps.get_mut(&from.map) {
+            map.tile_mut(from.x, from.y).user = None;
+            map.tile_mut(to.x, to.y).user = Some(npc_id);
+        }
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Move {
+                entity_id: npc_id,
+                position: to,
+            }),
+            Target::Area { position: to },
+        );
+    }
+
+    /// Moves the npc one step in the shortest path to a tile next to `to`
+    pub fn chase(&mut self, npc_id: u32, to: &WorldPosition) {
+        let Some(Entity::Npc { position, .. }) = self.entities.get(&npc_id) else {
+            return;
+        };
+        let Some(map) = self.maps.get(&position.map) else {
+            return;
+        };
+        let Some(direction) = find_path_to_adjacent(map, position, to, Walker::Npc)
+            .and_then(|path| path.first().copied())
+        else {
+            return;
+        };
+        let next = map.next_position(position, direction);
+        if next != *position {
+            self.move_npc(npc_id, next);
+        }
+    }
+
+    /// Tiles where an entity can be placed, triggers that forbid npcs are avoided too
+    pub fn is_free(&self, position: &WorldPosition) -> bool {
+        self.maps
+            .get(&position.map)
+            .and_then(|map| map.get_tile(position.x, position.y))
+            .is_some_and(|tile| {
+                tile.blocked == 0
+                    && tile.user.is_none()
+                    && tile.exit.is_none()
+                    && !tile.trigger_kind().blocks_npcs()
+            })
+    }
+
+    /// Closest free tile around `position`, searching up to `radius` tiles away
+    pub fn free_position_near(
+        &self,
+        position: &WorldPosition,
+        radius: u16,
+    ) -> Option<WorldPosition> {
+        for distance in 0..=radius {
+            for x in position.x.saturating_sub(distance)..=position.x + distance {
+                for y in position.y.saturating_sub(distance)..=position.y + distance {
+                    if x.abs_diff(position.x) != distance && y.abs_diff(position.y) != distance {
+                        continue;
+                    }
+                    let candidate = WorldPosition {
+                        map: position.map,
+                        x,
+                        y,
+                    };
+                    if self.is_free(&candidate) {
+                        return Some(candidate);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Entity standing on the given position, either a user or a npc
+    pub fn entity_at(&self, position: &WorldPosition) -> Option<u32> {
+        self.maps
+            .get(&position.map)?
+            .get_tile(position.x, position.y)?
+            .user
+    }
+}
+
+/// Clients render npcs as characters
+pub fn npc_character(npc: &NPC, position: WorldPosition) -> Character {
+    Character {
+        name: npc.name.clone(),
+        description: npc.description.clone(),
+        position,
+        ..Default::default()
+    }
+}
+
+pub fn in_interaction_distance(user: &WorldPosition, npc: &WorldPosition) -> bool {
+    user.map == npc.map
+        && user.x.abs_diff(npc.x) <= INTERACTION_DISTANCE
+        && user.y.abs_diff(npc.y) <= INTERACTION_DISTANCE
+}
@@ -0,0 +1,25 @@
+use std::{collections::HashMap, fs::File};
+
+use nohash_hasher::IntMap;
+use shared::{argentum::object::Object, protocol::crafting::Recipe};
+
+pub fn load_objects(path: &str) -> IntMap<usize, Object> {
+    let Ok(file) = File::open(path) else {
+        tracing::warn!("objects file {path} not present, starting without objects");
+        return IntMap::default();
+    };
+    let reader = std::io::BufReader::new(file);
+    let objects: HashMap<usize, Object> =
+        ron::de::from_reader(reader).expect("objects to be correct");
+
+    objects.into_iter().collect()
+}
+
+pub fn load_recipes(objects: &IntMap<usize, Object>) -> Vec<Recipe> {
+    let mut recipes = objects
+        .values()
+        .filter_map(|object| object.recipe())
+        .collect::<Vec<_>>();
+    recipes.sort_by_key(|recipe| (recipe.skill, recipe.item_id));
+    recipes
+}
@@ -0,0 +1,124 @@
+use shared::protocol::client;
+
+use super::{Entity, World};
+
+/// Max members of a party, the leader included
+const MAX_PARTY_MEMBERS: usize = 5;
+
+impl World {
+    pub fn process_party(&mut self, entity_id: u32, party: client::Party) {
+        let result = match party {
+            client::Party::Invite { name } => self.invite_to_party(entity_id, &name),
+            client::Party::Accept => self.accept_party(entity_id),
+            client::Party::Leave => {
+                self.leave_party(entity_id);
+                Ok(())
+            }
+        };
+
+        if let Err(reason) = result {
+            self.send_system(entity_id, reason);
+        }
+    }
+
+    /// Members of the party of the user, the user included
+    pub fn party_members(&self, entity_id: u32) -> Option<Vec<u32>> {
+        let leader = self.parties.get(&entity_id)?;
+        Some(
+            self.parties
+                .iter()
+                .filter(|(_, member_leader)| *member_leader == leader)
+                .map(|(member, _)| *member)
+                .collect(),
+        )
+    }
+
+    /// Leaving the party as leader disbands it, used on disconnection too
+    pub fn leave_party(&mut self, entity_id: u32) {
+        self.party_invites
+            .retain(|invited, leader| *invited != entity_id && *leader != entity_id);
+        let Some(members) = self.party_members(entity_id) else {
+            return;
+        };
+        let leader = self.parties.get(&entity_id).copied().unwrap_or(entity_id);
+
+        if leader == entity_id || members.len() <= 2 {
+            for member in members {
+                self.parties.remove(&member);
+                self.send_system(member, "The party was disbanded");
+            }
+            return;
+        }
+
+        self.parties.remove(&entity_id);
+        self.send_system(entity_id, "You left the party");
+        let name = self.character_name(entity_id);
+        for member in members.into_iter().filter(|member| *member != entity_id) {
+            self.send_system(member, &format!("{name} left the party"));
+        }
+    }
+
+    fn invite_to_party(&mut self, entity_id: u32, name: &str) -> Result<(), &'static str> {
+        let Some(target) = self.visible_user_by_name(self.privilege(entity_id), name) else {
+            self.send_system(entity_id, &format!("{name} is not online"));
+            return Ok(());
+        };
+        if target == entity_id {
+            return Err("You can't invite yourself");
+        }
+        if self.parties.contains_key(&target) {
+            return Err("That user is already in a party");
+        }
+        if let Some(leader) = self.parties.get(&entity_id) {
+            if *leader != entity_id {
+                return Err("Only the leader can invite to the party");
+            }
+        }
+        if self
+            .party_members(entity_id)
+            .is_some_and(|members| members.len() >= MAX_PARTY_MEMBERS)
+        {
+            return Err("The party is full");
+        }
+
+        self.party_invites.insert(target, entity_id);
+        let leader_name = self.character_name(entity_id);
+        let target_name = self.character_name(target);
+        self.send_system(target, &format!("{leader_name} invited you to a party"));
+        self.send_system(
+            entity_id,
+            &format!("You invited {target_name} to the party"),
+        );
+        Ok(())
+    }
+
+    fn accept_party(&mut self, entity_id: u32) -> Result<(), &'static str> {
+        let Some(leader) = self.party_invites.remove(&entity_id) else {
+            return Err("Nobody invited you to a party");
+        };
+        if !matches!(self.entities.get(&leader), Some(Entity::Character { .. })) {
+            return Err("The leader of that party is not online");
+        }
+        if self
+            .parties
+            .get(&leader)
+            .is_some_and(|lead| *lead != leader)
+        {
+            return Err("That party no longer exists");
+        }
+        if self
+            .party_members(leader)
+            .is_some_and(|members| members.len() >= MAX_PARTY_MEMBERS)
+        {
+            return Err("The party is full");
+        }
+
+        self.parties.insert(leader, leader);
+        self.parties.insert(entity_id, leader);
+        let name = self.character_name(entity_id);
+        for member in self.party_members(entity_id).unwrap_or_default() {
+            self.send_system(member, &format!("{name} joined the party"));
+        }
+        Ok(())
+    }
+}
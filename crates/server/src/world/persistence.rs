@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use database::{
+    model::{CharacterItems, CreateSanction, SanctionKind},
+    Database,
+};
+use shared::protocol::ProtocolMessage;
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedSender},
+    oneshot,
+};
+
+use crate::metrics::{self, metrics};
+
+use super::{Entity, World};
+
+/// Saves are processed in order by a single task so an older snapshot never overwrites a newer one
+pub struct Persistence {
+    sender: UnboundedSender<Job>,
+}
+
+enum Job {
+    Items(Vec<CharacterItems>),
+    Sanction(CreateSanction),
+    Revoke {
+        kind: SanctionKind,
+        account_name: String,
+        moderator: String,
+        reason: String,
+    },
+    Audit {
+        moderator: String,
+        action: String,
+        target: String,
+        reason: String,
+    },
+    /// Answered once the jobs sent before are done
+    Flush(oneshot::Sender<()>),
+}
+
+impl Persistence {
+    pub fn initialize(database: Arc<Database>) -> Self {
+        let (sender, mut receiver) = unbounded_channel::<Job>();
+        tokio::spawn(async move {
+            while let Some(job) = receiver.recv().await {
+                metrics().add(metrics::QUEUE_DEPTH, &[("queue", "persistence")], -1.0);
+                match job {
+                    Job::Items(characters) => {
+                        if let Err(e) = metrics::query(
+                            "update_characters_items",
+                            database.update_characters_items(&characters),
+                        )
+                        .await
+                        {
+                            let names = characters
+                                .iter()
+                                .map(|items| items.name.as_str())
+                                .collect::<Vec<_>>();
+                            tracing::error!("couldn't save items of {names:?}: {e}");
+                        }
+                    }
+                    Job::Sanction(sanction) => {
+                        if let Err(e) =
+                            metrics::query("add_sanction", database.add_sanction(&sanction)).await
+                        {
+                            tracing::error!("couldn't store sanction {sanction:?}: {e}");
+                        }
+                    }
+                    Job::Revoke {
+                        kind,
+                        account_name,
+                        moderator,
+                        reason,
+                    } => {
+                        if let Err(e) = metrics::query(
+                            "revoke_sanctions",
+                            database.revoke_sanctions(kind, &account_name, &moderator, &reason),
+                        )
+                        .await
+                        {
+                            tracing::error!("couldn't revoke {kind:?} of {account_name}: {e}");
+                        }
+                    }
+                    Job::Audit {
+                        moderator,
+                        action,
+                        target,
+                        reason,
+                    } => {
+                        if let Err(e) = metrics::query(
+                            "audit",
+                            database.audit(&moderator, &action, &target, &reason),
+                        )
+                        .await
+                        {
+                            tracing::error!("couldn't audit {action} of {target}: {e}");
+                        }
+                    }
+                    Job::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    /// Drops every job, for the tests of the world that don't have a database
+    #[cfg(test)]
+    pub fn discard() -> Self {
+        let (sender, mut receiver) = unbounded_channel::<Job>();
+        std::thread::spawn(move || while receiver.blocking_recv().is_some() {});
+        Self { sender }
+    }
+
+    fn queue(&self, job: Job) {
+        metrics().add(metrics::QUEUE_DEPTH, &[("queue", "persistence")], 1.0);
+        self.sender.send(job).expect("poisoned");
+    }
+
+    pub fn add_sanction(&self, sanction: CreateSanction) {
+        self.queue(Job::Sanction(sanction));
+    }
+
+    pub fn revoke_sanctions(
+        &self,
+        kind: SanctionKind,
+        account_name: &str,
+        moderator: &str,
+        reason: &str,
+    ) {
+        self.queue(Job::Revoke {
+            kind,
+            account_name: account_name.to_string(),
+            moderator: moderator.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Waits until everything sent before is stored
+    pub async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+        self.queue(Job::Flush(done));
+        let _ = flushed.await;
+    }
+
+    /// Records a moderation action in the audit log
+    pub fn audit(&self, moderator: &str, action: &str, target: &str, reason: &str) {
+        self.queue(Job::Audit {
+            moderator: moderator.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+}
+
+impl World {
+    /// Persists inventory, vault and gold of a character in a single transaction
+    pub fn save_items(&self, entity_id: u32) {
+        self.save_items_together(&[entity_id]);
+    }
+
+    /// Like `save_items` but all the characters are saved in the same transaction
+    pub fn save_items_together(&self, entity_ids: &[u32]) {
+        let characters = entity_ids
+            .iter()
+            .filter_map(|entity_id| match self.entities.get(entity_id) {
+                Some(Entity::Character { character, .. }) => Some(CharacterItems {
+                    name: character.name.clone(),
+                    gold: character.gold as i64,
+                    inventory: character.inventory.clone().encode().unwrap_or_default(),
+                    vault: character.vault.clone().encode().unwrap_or_default(),
+                }),
+                _ => None,
+            })
+            .collect();
+        self.persistence.queue(Job::Items(characters));
+    }
+}
@@ -0,0 +1,337 @@
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use shared::{
+    argentum::npc::NpcKind,
+    protocol::{
+        client,
+        server::{self, ServerPacket},
+    },
+    world::WorldPosition,
+};
+
+use super::{networking::Target, npcs::in_interaction_distance, Entity, World};
+
+/// Max amount of pets a user can have at the same time
+const MAX_PETS: usize = 3;
+const PET_MOVE_INTERVAL: Duration = Duration::from_millis(400);
+const PET_ATTACK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PetMode {
+    Follow,
+    Stand,
+}
+
+/// Owner data of a tamed or summoned npc, summoned ones expire
+pub struct Pet {
+    pub owner: u32,
+    mode: PetMode,
+    expires: Option<Instant>,
+    target: Option<u32>,
+    last_action: Instant,
+}
+
+impl Pet {
+    fn new(owner: u32, expires: Option<Instant>) -> Self {
+        Self {
+            owner,
+            mode: PetMode::Follow,
+            expires,
+            target: None,
+            last_action: Instant::now(),
+        }
+    }
+}
+
+impl World {
+    pub fn process_pet(&mut self, entity_id: u32, pet: client::Pet) {
+        let result = match pet {
+            client::Pet::Stand => {
+                self.set_pets_mode(entity_id, PetMode::Stand);
+                Ok(())
+            }
+            client::Pet::Follow => {
+                self.set_pets_mode(entity_id, PetMode::Follow);
+                Ok(())
+            }
+            client::Pet::Leave => {
+                self.release_pets(entity_id);
+                Ok(())
+            }
+            client::Pet::Tame { entity_id: npc_id } => self.tame(entity_id, npc_id),
+        };
+
+        if let Err(reason) = result {
+            self.send(
+                ServerPacket::Pet(server::Pet::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    /// Spawns a pet for the user next to `position`, it disappears after `duration`
+    pub fn summon_pet(
+        &mut self,
+        entity_id: u32,
+        npc_index: usize,
+        position: WorldPosition,
+        duration: Duration,
+    ) -> Result<(), &'static str> {
+        if self.pets_of(entity_id).len() >= MAX_PETS {
+            return Err("You can't have more pets");
+        }
+        let Some(npc) = self.npcs.get(&npc_index).cloned() else {
+            return Err("That creature doesn't exist");
+        };
+        let Some(position) = self.free_position_near(&position, 2) else {
+            return Err("There is no room for the creature");
+        };
+
+        let name = npc.name.clone();
+        let npc_id = self.spawn_npc(npc, position);
+        if let Some(Entity::Npc { pet, .. }) = self.entities.get_mut(&npc_id) {
+            *pet = Some(Pet::new(entity_id, Some(Instant::now() + duration)));
+        }
+        self.send(
+            ServerPacket::Pet(server::Pet::Summoned {
+                entity_id: npc_id,
+                name,
+            }),
+            Target::User { entity_id },
+        );
+
+        Ok(())
+    }
+
+    /// Selects the npc at `position` as the target of the user pets
+    pub fn select_pets_target(&mut self, entity_id: u32, position: WorldPosition) {
+        let Some(target) = self.entity_at(&position) else {
+            return;
+        };
+        if !matches!(
+            self.entities.get(&target),
+            Some(Entity::Npc { npc, pet: None, .. }) if npc.attackable
+        ) {
+            return;
+        }
+        if !self.can_fight(&position) {
+            self.send(
+                ServerPacket::Pet(server::Pet::Failed {
+                    reason: "You can't fight here".to_string(),
+                }),
+                Target::User { entity_id },
+            );
+            return;
+        }
+        for pet_id in self.pets_of(entity_id) {
+            if let Some(Entity::Npc { pet: Some(pet), .. }) = self.entities.get_mut(&pet_id) {
+                pet.target = Some(target);
+            }
+        }
+    }
+
+    /// Pets don't outlive the session of their owner, tamed ones included, used on disconnection
+    pub fn remove_pets(&mut self, entity_id: u32) {
+        for pet_id in self.pets_of(entity_id) {
+            self.despawn_npc(pet_id);
+        }
+    }
+
+    pub fn update_pets(&mut self) {
+        let now = Instant::now();
+        let pets = self
+            .entities
+            .iter()
+            .filter_map(|(id, entity)| match entity {
+                Entity::Npc { pet: Some(_), .. } => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        for pet_id in pets {
+            let Some(Entity::Npc {
+                position,
+                pet: Some(pet),
+                ..
+            }) = self.entities.get(&pet_id)
+            else {
+                continue;
+            };
+            if pet.expires.is_some_and(|expires| now >= expires) {
+                self.despawn_pet(pet_id);
+                continue;
+            }
+            let position = *position;
+            let (owner, mode, target, last_action) =
+                (pet.owner, pet.mode, pet.target, pet.last_action);
+
+            let target_position = match target.and_then(|target| self.entities.get(&target)) {
+                Some(Entity::Npc { position, .. }) => Some(*position),
+                _ => None,
+            };
+            if let Some(target_position) = target_position {
+                if is_adjacent(&position, &target_position) {
+                    if now >= last_action + PET_ATTACK_INTERVAL {
+                        self.pet_attack(pet_id, target.unwrap_or_default());
+                    }
+                } else if now >= last_action + PET_MOVE_INTERVAL {
+                    self.step_towards(pet_id, &target_position);
+                }
+                continue;
+            }
+            if target.is_some() {
+                self.set_pet_target(pet_id, None);
+            }
+
+            if mode == PetMode::Follow && now >= last_action + PET_MOVE_INTERVAL {
+                let owner_position = match self.entities.get(&owner) {
+                    Some(Entity::Character { character, .. }) => character.position,
+                    _ => continue,
+                };
+                if owner_position.map == position.map && !is_adjacent(&position, &owner_position) {
+                    self.step_towards(pet_id, &owner_position);
+                }
+            }
+        }
+    }
+
+    fn tame(&mut self, entity_id: u32, npc_id: u32) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let Some(Entity::Npc {
+            npc,
+            position,
+            shop,
+            pet,
+            ..
+        }) = self.entities.get(&npc_id)
+        else {
+            return Ok(());
+        };
+        if npc.kind != NpcKind::Common || !npc.attackable || shop.is_some() {
+            return Err("That creature can't be tamed");
+        }
+        if pet.is_some() {
+            return Err("That creature already has an owner");
+        }
+        if !in_interaction_distance(&character.position, position) {
+            return Err("You are too far away");
+        }
+        let chance = character.skills.tame as f32 / (npc.health.max.max(1) as f32 / 2.).max(1.);
+        let name = npc.name.clone();
+        if self.pets_of(entity_id).len() >= MAX_PETS {
+            return Err("You can't have more pets");
+        }
+        if !rand::thread_rng().gen_bool(chance.clamp(0., 0.9) as f64) {
+            return Err("You failed to tame the creature");
+        }
+
+        if let Some(Entity::Npc { pet, .. }) = self.entities.get_mut(&npc_id) {
+            *pet = Some(Pet::new(entity_id, None));
+        }
+        self.send(
+            ServerPacket::Pet(server::Pet::Tamed {
+                entity_id: npc_id,
+                name,
+            }),
+            Target::User { entity_id },
+        );
+
+        Ok(())
+    }
+
+    fn set_pets_mode(&mut self, entity_id: u32, mode: PetMode) {
+        for pet_id in self.pets_of(entity_id) {
+            if let Some(Entity::Npc { pet: Some(pet), .. }) = self.entities.get_mut(&pet_id) {
+                pet.mode = mode;
+                pet.target = None;
+            }
+        }
+    }
+
+    fn release_pets(&mut self, entity_id: u32) {
+        for pet_id in self.pets_of(entity_id) {
+            self.despawn_pet(pet_id);
+        }
+    }
+
+    /// Summoned pets disappear and tamed ones lose their owner
+    fn despawn_pet(&mut self, pet_id: u32) {
+        let Some(Entity::Npc { npc, pet, .. }) = self.entities.get_mut(&pet_id) else {
+            return;
+        };
+        let Some(Pet { owner, expires, .. }) = pet.take() else {
+            return;
+        };
+        let name = npc.name.clone();
+        if expires.is_some() {
+            self.despawn_npc(pet_id);
+        }
+        self.send(
+            ServerPacket::Pet(server::Pet::Gone {
+                entity_id: pet_id,
+                name,
+            }),
+            Target::User { entity_id: owner },
+        );
+    }
+
+    fn pets_of(&self, entity_id: u32) -> Vec<u32> {
+        self.entities
+            .iter()
+            .filter_map(|(id, entity)| match entity {
+                Entity::Npc { pet: Some(pet), .. } if pet.owner == entity_id => Some(*id),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn set_pet_target(&mut self, pet_id: u32, target: Option<u32>) {
+        if let Some(Entity::Npc { pet: Some(pet), .. }) = self.entities.get_mut(&pet_id) {
+            pet.target = target;
+        }
+    }
+
+    fn touch_pet(&mut self, pet_id: u32) {
+        if let Some(Entity::Npc { pet: Some(pet), .. }) = self.entities.get_mut(&pet_id) {
+            pet.last_action = Instant::now();
+        }
+    }
+
+    fn step_towards(&mut self, pet_id: u32, to: &WorldPosition) {
+        self.touch_pet(pet_id);
+        self.chase(pet_id, to);
+    }
+
+    fn pet_attack(&mut self, pet_id: u32, target: u32) {
+        self.touch_pet(pet_id);
+        let Some(Entity::Npc { npc, position, .. }) = self.entities.get(&pet_id) else {
+            return;
+        };
+        let target_position = match self.entities.get(&target) {
+            Some(Entity::Npc { position, .. }) => *position,
+            _ => return,
+        };
+        if !self.can_fight(position) || !self.can_fight(&target_position) {
+            self.set_pet_target(pet_id, None);
+            return;
+        }
+        let damage = rand::thread_rng().gen_range(npc.hit.min..=npc.hit.max.max(npc.hit.min));
+        let Some(Entity::Npc { health, .. }) = self.entities.get_mut(&target) else {
+            return;
+        };
+        *health = health.saturating_sub(damage as u32);
+        if *health == 0 {
+            self.despawn_npc(target);
+            self.set_pet_target(pet_id, None);
+        }
+    }
+}
+
+fn is_adjacent(a: &WorldPosition, b: &WorldPosition) -> bool {
+    a.map == b.map && a.x.abs_diff(b.x) + a.y.abs_diff(b.y) <= 1
+}
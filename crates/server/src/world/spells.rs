@@ -0,0 +1,121 @@
+use std::{collections::HashMap, fs::File, time::Duration};
+
+use nohash_hasher::IntMap;
+use shared::{
+    argentum::spell::{Spell, SpellKind},
+    protocol::server::{Event, ServerPacket, UserUpdate},
+    world::WorldPosition,
+};
+
+use super::{networking::Target, Entity, World};
+
+/// How long a summoned creature stays in the world
+const INVOCATION_DURATION: Duration = Duration::from_secs(60);
+/// Spells reach the tiles the caster sees, half the view around them
+const SPELL_RANGE: (u16, u16) = (8, 8);
+
+pub fn load_spells(path: &str) -> IntMap<usize, Spell> {
+    let Ok(file) = File::open(path) else {
+        tracing::warn!("spells file {path} not present, starting without spells");
+        return IntMap::default();
+    };
+    let reader = std::io::BufReader::new(file);
+    let spells: HashMap<usize, Spell> = ron::de::from_reader(reader).expect("spells to be correct");
+
+    spells.into_iter().collect()
+}
+
+impl World {
+    pub fn cast_spell(&mut self, entity_id: u32, slot: u8, position: WorldPosition) {
+        if let Err(reason) = self.try_cast_spell(entity_id, slot, position) {
+            self.send_system(entity_id, reason);
+        }
+    }
+
+    fn try_cast_spell(
+        &mut self,
+        entity_id: u32,
+        slot: u8,
+        position: WorldPosition,
+    ) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let Some(spell) = character
+            .spellbook
+            .get(slot as usize)
+            .and_then(|spell_id| self.spells.get(&(spell_id as usize)))
+        else {
+            return Err("You don't know that spell");
+        };
+        if (character.stats.mana.current as usize) < spell.required_mana {
+            return Err("Not enough mana");
+        }
+        if (character.skills.magic as usize) < spell.required_skill {
+            return Err("Your magic skill is too low");
+        }
+        if !in_spell_range(&character.position, &position) {
+            return Err("The target is too far");
+        }
+        let required_mana = spell.required_mana as u16;
+        let (fx, loops) = (spell.fx as u16, spell.loops as u16);
+
+        match spell.kind.clone() {
+            SpellKind::Invoke { npc } => {
+                self.summon_pet(entity_id, npc, position, INVOCATION_DURATION)?
+            }
+            _ => return Err("That spell can't be cast yet"),
+        }
+
+        let Some(Entity::Character { character, .. }) = self.entities.get_mut(&entity_id) else {
+            return Ok(());
+        };
+        character.stats.mana.current -= required_mana;
+        let mana = character.stats.mana.current;
+        self.send(
+            ServerPacket::UserUpdate(UserUpdate::Mana { mana }),
+            Target::User { entity_id },
+        );
+        if fx != 0 {
+            self.send(
+                ServerPacket::Event(Event::FX {
+                    position,
+                    fx,
+                    loops,
+                }),
+                Target::Area { position },
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn in_spell_range(caster: &WorldPosition, target: &WorldPosition) -> bool {
+    caster.map == target.map
+        && caster.x.abs_diff(target.x) <= SPELL_RANGE.0
+        && caster.y.abs_diff(target.y) <= SPELL_RANGE.1
+}
+
+#[cfg(test)]
+mod tests {
+    use shared::world::WorldPosition;
+
+    use super::in_spell_range;
+
+    #[test]
+    fn spells_reach_the_tiles_in_view() {
+        let caster = WorldPosition {
+            map: 1,
+            x: 50,
+            y: 50,
+        };
+        let at = |map, x, y| WorldPosition { map, x, y };
+        assert!(in_spell_range(&caster, &caster));
+        assert!(in_spell_range(&caster, &at(1, 42, 58)));
+        assert!(in_spell_range(&caster, &at(1, 58, 42)));
+        assert!(!in_spell_range(&caster, &at(1, 59, 50)));
+        assert!(!in_spell_range(&caster, &at(1, 50, 41)));
+        assert!(!in_spell_range(&caster, &at(2, 50, 50)));
+    }
+}
@@ -0,0 +1,551 @@
+use shared::{
+    character::Inventory,
+    protocol::{
+        client,
+        server::{self, ServerPacket, UserUpdate},
+        trade::{OfferItem, TradeOffer, TradeStage},
+    },
+};
+
+use super::{networking::Target, npcs::in_interaction_distance, Entity, World};
+
+pub struct TradeSide {
+    partner: u32,
+    offer: TradeOffer,
+    stage: TradeStage,
+}
+
+impl TradeSide {
+    fn new(partner: u32) -> Self {
+        Self {
+            partner,
+            offer: TradeOffer::default(),
+            stage: TradeStage::Offering,
+        }
+    }
+}
+
+impl World {
+    pub fn process_trade(&mut self, entity_id: u32, trade: client::Trade) {
+        let result = match trade {
+            client::Trade::Request { entity_id: target } => self.request_trade(entity_id, target),
+            client::Trade::Offer { slot, amount } => self.offer_item(entity_id, slot, amount),
+            client::Trade::Gold { amount } => self.offer_gold(entity_id, amount),
+            client::Trade::Ready => self.trade_ready(entity_id),
+            client::Trade::Confirm => self.trade_confirm(entity_id),
+            client::Trade::Cancel => {
+                self.cancel_trade(entity_id, "Trade cancelled");
+                Ok(())
+            }
+        };
+
+        if let Err(reason) = result {
+            self.send(
+                ServerPacket::Trade(server::Trade::Failed {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    /// Asks `target` to trade, if `target` already asked the trade starts
+    pub fn request_trade(&mut self, entity_id: u32, target: u32) -> Result<(), &'static str> {
+        if entity_id == target {
+            return Ok(());
+        }
+        let (
+            Some(Entity::Character { character, .. }),
+            Some(Entity::Character {
+                character: target_character,
+                ..
+            }),
+        ) = (self.entities.get(&entity_id), self.entities.get(&target))
+        else {
+            return Ok(());
+        };
+        if !in_interaction_distance(&character.position, &target_character.position) {
+            return Err("You are too far away");
+        }
+        if self.trades.contains_key(&entity_id) {
+            return Err("You are already trading");
+        }
+        if self.trades.contains_key(&target) {
+            return Err("That user is already trading");
+        }
+        let name = character.name.clone();
+        let target_name = target_character.name.clone();
+
+        if self.trade_requests.get(&target) == Some(&entity_id) {
+            self.trade_requests.remove(&target);
+            self.trade_requests.remove(&entity_id);
+            self.trades.insert(entity_id, TradeSide::new(target));
+            self.trades.insert(target, TradeSide::new(entity_id));
+            self.send(
+                ServerPacket::Trade(server::Trade::Started {
+                    entity_id: target,
+                    name: target_name,
+                }),
+                Target::User { entity_id },
+            );
+            self.send(
+                ServerPacket::Trade(server::Trade::Started { entity_id, name }),
+                Target::User { entity_id: target },
+            );
+            self.send_trade(entity_id);
+        } else {
+            self.trade_requests.insert(entity_id, target);
+            self.send(
+                ServerPacket::Trade(server::Trade::Requested { entity_id, name }),
+                Target::User { entity_id: target },
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Cancels the trade of the user and forgets its requests, used on movement and disconnection
+    pub fn cancel_trade(&mut self, entity_id: u32, reason: &str) {
+        self.trade_requests
+            .retain(|requester, target| *requester != entity_id && *target != entity_id);
+        let Some(side) = self.trades.remove(&entity_id) else {
+            return;
+        };
+        self.trades.remove(&side.partner);
+
+        for entity_id in [entity_id, side.partner] {
+            self.send(
+                ServerPacket::Trade(server::Trade::Cancelled {
+                    reason: reason.to_string(),
+                }),
+                Target::User { entity_id },
+            );
+        }
+    }
+
+    fn offer_item(&mut self, entity_id: u32, slot: u8, amount: u32) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let Some(item) = character.inventory.get(slot as usize).copied() else {
+            return Err("There is nothing in that slot");
+        };
+        if item.amount < amount {
+            return Err("You don't have that many");
+        }
+        let Some(side) = self.trades.get_mut(&entity_id) else {
+            return Err("You aren't trading with anyone");
+        };
+
+        side.offer.items.retain(|offered| offered.slot != slot);
+        if amount > 0 {
+            side.offer.items.push(OfferItem {
+                slot,
+                item_id: item.item_id,
+                amount,
+            });
+        }
+        self.reset_trade_stages(entity_id);
+        self.send_trade(entity_id);
+
+        Ok(())
+    }
+
+    fn offer_gold(&mut self, entity_id: u32, amount: u64) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        if character.gold < amount {
+            return Err("Not enough gold");
+        }
+        let Some(side) = self.trades.get_mut(&entity_id) else {
+            return Err("You aren't trading with anyone");
+        };
+
+        side.offer.gold = amount;
+        self.reset_trade_stages(entity_id);
+        self.send_trade(entity_id);
+
+        Ok(())
+    }
+
+    fn trade_ready(&mut self, entity_id: u32) -> Result<(), &'static str> {
+        let Some(side) = self.trades.get_mut(&entity_id) else {
+            return Err("You aren't trading with anyone");
+        };
+        if side.stage == TradeStage::Offering {
+            side.stage = TradeStage::Ready;
+        }
+        self.send_trade(entity_id);
+
+        Ok(())
+    }
+
+    fn trade_confirm(&mut self, entity_id: u32) -> Result<(), &'static str> {
+        let Some(side) = self.trades.get(&entity_id) else {
+            return Err("You aren't trading with anyone");
+        };
+        let partner = side.partner;
+        let partner_stage = self
+            .trades
+            .get(&partner)
+            .map(|side| side.stage)
+            .unwrap_or_default();
+        if side.stage == TradeStage::Offering || partner_stage == TradeStage::Offering {
+            return Err("Both users have to be ready before confirming");
+        }
+
+        if let Some(side) = self.trades.get_mut(&entity_id) {
+            side.stage = TradeStage::Confirmed;
+        }
+        if partner_stage == TradeStage::Confirmed {
+            if let Err(reason) = self.complete_trade(entity_id, partner) {
+                self.cancel_trade(entity_id, reason);
+            }
+        } else {
+            self.send_trade(entity_id);
+        }
+
+        Ok(())
+    }
+
+    /// Swaps both offers, nothing changes unless both sides can be applied
+    fn complete_trade(&mut self, entity_id: u32, partner: u32) -> Result<(), &'static str> {
+        let (Some(side), Some(partner_side)) =
+            (self.trades.get(&entity_id), self.trades.get(&partner))
+        else {
+            return Ok(());
+        };
+        let (
+            Some(Entity::Character { character, .. }),
+            Some(Entity::Character {
+                character: partner_character,
+                ..
+            }),
+        ) = (self.entities.get(&entity_id), self.entities.get(&partner))
+        else {
+            return Ok(());
+        };
+
+        let mut inventory = character.inventory.clone();
+        let mut partner_inventory = partner_character.inventory.clone();
+        if character.gold < side.offer.gold || partner_character.gold < partner_side.offer.gold {
+            return Err("The offer is no longer valid");
+        }
+        let gold = character.gold - side.offer.gold + partner_side.offer.gold;
+        let partner_gold = partner_character.gold - partner_side.offer.gold + side.offer.gold;
+
+        let (Some(mut changed), Some(mut partner_changed)) = (
+            take_offer(&mut inventory, &side.offer),
+            take_offer(&mut partner_inventory, &partner_side.offer),
+        ) else {
+            return Err("The offer is no longer valid");
+        };
+        for item in partner_side.offer.items.iter() {
+            changed.extend(
+                inventory
+                    .add(item.item_id, item.amount)
+                    .ok_or("Not enough space in the inventory")?,
+            );
+        }
+        for item in side.offer.items.iter() {
+            partner_changed.extend(
+                partner_inventory
+                    .add(item.item_id, item.amount)
+                    .ok_or("Not enough space in the inventory")?,
+            );
+        }
+
+        for (id, inventory, gold) in [
+            (entity_id, inventory, gold),
+            (partner, partner_inventory, partner_gold),
+        ] {
+            if let Some(Entity::Character { character, .. }) = self.entities.get_mut(&id) {
+                character.inventory = inventory;
+                character.gold = gold;
+            }
+        }
+        self.trades.remove(&entity_id);
+        self.trades.remove(&partner);
+        self.save_items_together(&[entity_id, partner]);
+
+        for (id, mut slots, gold) in [
+            (entity_id, changed, gold),
+            (partner, partner_changed, partner_gold),
+        ] {
+            slots.sort_unstable();
+            slots.dedup();
+            self.send_inventory_slots(id, &slots);
+            self.send(
+                ServerPacket::UserUpdate(UserUpdate::Gold { gold }),
+                Target::User { entity_id: id },
+            );
+            self.send(
+                ServerPacket::Trade(server::Trade::Completed),
+                Target::User { entity_id: id },
+            );
+        }
+
+        Ok(())
+    }
+
+    fn reset_trade_stages(&mut self, entity_id: u32) {
+        let Some(side) = self.trades.get_mut(&entity_id) else {
+            return;
+        };
+        side.stage = TradeStage::Offering;
+        let partner = side.partner;
+        if let Some(side) = self.trades.get_mut(&partner) {
+            side.stage = TradeStage::Offering;
+        }
+    }
+
+    /// Sends the state of the trade to both users
+    fn send_trade(&self, entity_id: u32) {
+        let Some(side) = self.trades.get(&entity_id) else {
+            return;
+        };
+        let Some(partner_side) = self.trades.get(&side.partner) else {
+            return;
+        };
+        for (id, own, other) in [
+            (entity_id, side, partner_side),
+            (side.partner, partner_side, side),
+        ] {
+            self.send(
+                ServerPacket::Trade(server::Trade::Update {
+                    own: own.offer.clone(),
+                    other: other.offer.clone(),
+                    own_stage: own.stage,
+                    other_stage: other.stage,
+                }),
+                Target::User { entity_id: id },
+            );
+        }
+    }
+}
+
+/// Removes the offered items from their slots, `None` if any of them is not there anymore
+fn take_offer(inventory: &mut Inventory, offer: &TradeOffer) -> Option<Vec<usize>> {
+    let mut changed = vec![];
+    for offered in offer.items.iter() {
+        let slot = offered.slot as usize;
+        match inventory.get(slot) {
+            Some(item) if item.item_id == offered.item_id && item.amount >= offered.amount => {
+                inventory.take(slot, offered.amount);
+                changed.push(slot);
+            }
+            _ => return None,
+        }
+    }
+    Some(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::VecDeque, time::Instant};
+
+    use nohash_hasher::{IntMap, IntSet};
+    use shared::{
+        character::{Character, Inventory, Item},
+        protocol::{
+            server::{self, ServerPacket},
+            trade::TradeStage,
+        },
+        world::WorldPosition,
+    };
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    use crate::{
+        accounts::Session,
+        world::{persistence::Persistence, weather::Climate, Entity, World},
+    };
+
+    const SELLER: u32 = 1;
+    const BUYER: u32 = 2;
+
+    fn user(name: &str, x: u16, item: Item, gold: u64) -> Entity {
+        let mut inventory = Inventory::default();
+        inventory.set(0, Some(item));
+        Entity::Character {
+            character: Character {
+                name: name.to_string(),
+                gold,
+                position: WorldPosition { map: 1, x, y: 50 },
+                inventory,
+                ..Default::default()
+            },
+            last_move: Instant::now(),
+            last_move_receive: Instant::now(),
+            pending_moves: VecDeque::new(),
+            session: Session {
+                account_name: name.to_string(),
+                privilege: Default::default(),
+                ip: None,
+            },
+        }
+    }
+
+    /// Two users next to each other trading, the seller with 5 of item 10 and the buyer with
+    /// one of item 20
+    fn trading() -> (World, UnboundedReceiver<(u32, ServerPacket)>) {
+        let (sender, receiver) = unbounded_channel();
+        let mut world = World {
+            outcoming_messages_sender: sender,
+            persistence: Persistence::discard(),
+            maps: IntMap::default(),
+            map_infos: IntMap::default(),
+            objects: IntMap::default(),
+            recipes: vec![],
+            npcs: IntMap::default(),
+            spells: IntMap::default(),
+            entities: IntMap::default(),
+            next_entity_id: 3,
+            commerce: IntMap::default(),
+            bank: IntMap::default(),
+            trades: IntMap::default(),
+            chat_cooldowns: IntMap::default(),
+            parties: IntMap::default(),
+            party_invites: IntMap::default(),
+            trade_requests: IntMap::default(),
+            camping: IntMap::default(),
+            invisible: IntSet::default(),
+            mutes: IntMap::default(),
+            jails: IntMap::default(),
+            kicked: vec![],
+            climate: Climate::initialize(&IntMap::default()),
+        };
+        let item = |item_id, amount| Item { item_id, amount };
+        world
+            .entities
+            .insert(SELLER, user("seller", 50, item(10, 5), 100));
+        world
+            .entities
+            .insert(BUYER, user("buyer", 51, item(20, 1), 50));
+
+        world.request_trade(SELLER, BUYER).unwrap();
+        world.request_trade(BUYER, SELLER).unwrap();
+        assert!(world.trades.contains_key(&SELLER) && world.trades.contains_key(&BUYER));
+        (world, receiver)
+    }
+
+    fn character(world: &World, entity_id: u32) -> &Character {
+        match world.entities.get(&entity_id) {
+            Some(Entity::Character { character, .. }) => character,
+            _ => panic!("no user {entity_id}"),
+        }
+    }
+
+    fn stage(world: &World, entity_id: u32) -> TradeStage {
+        world.trades[&entity_id].stage
+    }
+
+    fn trade_packets(
+        receiver: &mut UnboundedReceiver<(u32, ServerPacket)>,
+    ) -> Vec<(u32, server::Trade)> {
+        let mut packets = vec![];
+        while let Ok((entity_id, packet)) = receiver.try_recv() {
+            if let ServerPacket::Trade(trade) = packet {
+                packets.push((entity_id, trade));
+            }
+        }
+        packets
+    }
+
+    #[test]
+    fn ready_and_confirmed_offers_are_swapped() {
+        let (mut world, mut receiver) = trading();
+        world.offer_item(SELLER, 0, 3).unwrap();
+        world.offer_gold(BUYER, 30).unwrap();
+        world.offer_item(BUYER, 0, 1).unwrap();
+
+        assert_eq!(
+            world.trade_confirm(SELLER),
+            Err("Both users have to be ready before confirming")
+        );
+        world.trade_ready(SELLER).unwrap();
+        assert_eq!(
+            world.trade_confirm(SELLER),
+            Err("Both users have to be ready before confirming")
+        );
+        world.trade_ready(BUYER).unwrap();
+        world.trade_confirm(SELLER).unwrap();
+        assert_eq!(stage(&world, SELLER), TradeStage::Confirmed);
+        assert_eq!(stage(&world, BUYER), TradeStage::Ready);
+        trade_packets(&mut receiver);
+
+        world.trade_confirm(BUYER).unwrap();
+        assert!(world.trades.is_empty());
+
+        let seller = character(&world, SELLER);
+        assert_eq!(seller.gold, 130);
+        assert_eq!(seller.inventory.count(10), 2);
+        assert_eq!(seller.inventory.count(20), 1);
+        let buyer = character(&world, BUYER);
+        assert_eq!(buyer.gold, 20);
+        assert_eq!(buyer.inventory.count(10), 3);
+        assert_eq!(buyer.inventory.count(20), 0);
+
+        assert_eq!(
+            trade_packets(&mut receiver),
+            [
+                (BUYER, server::Trade::Completed),
+                (SELLER, server::Trade::Completed)
+            ]
+        );
+    }
+
+    #[test]
+    fn changing_an_offer_asks_both_users_again() {
+        let (mut world, _receiver) = trading();
+        world.offer_item(SELLER, 0, 3).unwrap();
+        world.trade_ready(SELLER).unwrap();
+        world.trade_ready(BUYER).unwrap();
+        world.trade_confirm(SELLER).unwrap();
+
+        world.offer_item(SELLER, 0, 5).unwrap();
+        assert_eq!(stage(&world, SELLER), TradeStage::Offering);
+        assert_eq!(stage(&world, BUYER), TradeStage::Offering);
+        assert_eq!(
+            world.trade_confirm(BUYER),
+            Err("Both users have to be ready before confirming")
+        );
+
+        world.trade_ready(SELLER).unwrap();
+        world.trade_ready(BUYER).unwrap();
+        world.trade_confirm(BUYER).unwrap();
+        world.trade_confirm(SELLER).unwrap();
+        assert_eq!(character(&world, BUYER).inventory.count(10), 5);
+    }
+
+    #[test]
+    fn items_gone_before_completing_cancel_the_trade() {
+        let (mut world, mut receiver) = trading();
+        world.offer_item(SELLER, 0, 3).unwrap();
+        world.offer_gold(BUYER, 30).unwrap();
+        world.trade_ready(SELLER).unwrap();
+        world.trade_ready(BUYER).unwrap();
+        world.trade_confirm(SELLER).unwrap();
+
+        // dropped on the floor without touching the offer
+        if let Some(Entity::Character { character, .. }) = world.entities.get_mut(&SELLER) {
+            character.inventory.take(0, 4);
+        }
+        trade_packets(&mut receiver);
+        world.trade_confirm(BUYER).unwrap();
+
+        assert!(world.trades.is_empty());
+        assert_eq!(character(&world, SELLER).gold, 100);
+        assert_eq!(character(&world, SELLER).inventory.count(10), 1);
+        assert_eq!(character(&world, BUYER).gold, 50);
+        assert_eq!(character(&world, BUYER).inventory.count(10), 0);
+
+        let cancelled = server::Trade::Cancelled {
+            reason: "The offer is no longer valid".to_string(),
+        };
+        assert_eq!(
+            trade_packets(&mut receiver),
+            [(BUYER, cancelled.clone()), (SELLER, cancelled)]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use shared::{
+    character::Character,
+    protocol::server::{CharacterUpdate, ServerPacket},
+    world::WorldPosition,
+};
+
+use super::{networking::Target, Entity, World};
+
+/// Max level allowed to enter maps restricted to newbies
+const NEWBIE_MAX_LEVEL: u16 = 12;
+
+/// Max tiles away from the exit target to look for a free tile
+const ARRIVAL_RADIUS: u16 = 5;
+
+impl World {
+    /// Where the user lands when taking an exit, the closest free tile to the exit target
+    pub fn exit_destination(
+        &self,
+        entity_id: u32,
+        exit: &WorldPosition,
+    ) -> Result<WorldPosition, &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Err("You can't go there");
+        };
+        if self.is_jailed(entity_id) {
+            return Err("You can't leave the jail");
+        }
+        if !self.maps.contains_key(&exit.map) {
+            return Err("That way leads nowhere");
+        }
+        if let Some(info) = self.map_infos.get(&exit.map) {
+            can_enter(&info.restrict_mode, character)?;
+        }
+
+        self.free_position_near(exit, ARRIVAL_RADIUS)
+            .ok_or("The way is blocked")
+    }
+
+    /// Moves the user to another map, the users of both maps are notified
+    pub fn change_map(&mut self, entity_id: u32, position: WorldPosition) {
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Remove { entity_id }),
+            Target::AreaButUser { entity_id },
+        );
+        self.set_character_position(entity_id, position);
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Translate {
+                entity_id,
+                position,
+            }),
+            Target::User { entity_id },
+        );
+
+        if let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) {
+            self.notify_new_character(entity_id, character);
+        }
+    }
+
+    /// Places the user on the closest free tile to `position`, in any map
+    pub fn teleport(
+        &mut self,
+        entity_id: u32,
+        position: WorldPosition,
+    ) -> Result<(), &'static str> {
+        let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+            return Ok(());
+        };
+        let map = character.position.map;
+        if !self.maps.contains_key(&position.map) {
+            return Err("That map doesn't exist");
+        }
+        let position = self
+            .free_position_near(&position, ARRIVAL_RADIUS)
+            .ok_or("There is no room there")?;
+
+        self.cancel_trade(entity_id, "Trade cancelled, the other user left");
+        if let Some(Entity::Character { pending_moves, .. }) = self.entities.get_mut(&entity_id) {
+            pending_moves.clear();
+        }
+        if position.map == map {
+            self.relocate_user(entity_id, position);
+        } else {
+            self.change_map(entity_id, position);
+        }
+        self.track_camping(entity_id, &position);
+        Ok(())
+    }
+}
+
+/// Checks the `restrict_mode` of the map, faction restrictions are not enforced yet
+fn can_enter(restrict_mode: &str, character: &Character) -> Result<(), &'static str> {
+    match restrict_mode.to_uppercase().as_str() {
+        "NEWBIE" if character.level > NEWBIE_MAX_LEVEL => Err("Only newbies can enter that map"),
+        _ => Ok(()),
+    }
+}
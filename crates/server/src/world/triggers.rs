@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use shared::{
+    protocol::server::{CharacterUpdate, ServerPacket},
+    world::{TileTrigger, WorldPosition},
+};
+
+use super::{networking::Target, Entity, World};
+
+/// Time a user can stay on an anti-camping tile before being moved away
+const CAMPING_LIMIT: Duration = Duration::from_secs(20);
+
+impl World {
+    pub fn trigger_at(&self, position: &WorldPosition) -> TileTrigger {
+        self.maps
+            .get(&position.map)
+            .map(|map| map.trigger(position.x, position.y))
+            .unwrap_or_default()
+    }
+
+    /// Combat is not allowed in secure maps nor in safe zone and no combat tiles
+    pub fn can_fight(&self, position: &WorldPosition) -> bool {
+        let secure = self
+            .map_infos
+            .get(&position.map)
+            .is_some_and(|info| info.secure);
+        !secure && self.trigger_at(position).allows_combat()
+    }
+
+    /// Starts or stops the anti-camping timer of a user that moved
+    pub fn track_camping(&mut self, entity_id: u32, position: &WorldPosition) {
+        if self.trigger_at(position) == TileTrigger::AntiCamping {
+            self.camping.entry(entity_id).or_insert_with(Instant::now);
+        } else {
+            self.camping.remove(&entity_id);
+        }
+    }
+
+    /// Moves away the users that stayed too long on anti-camping tiles
+    pub fn update_camping(&mut self) {
+        let now = Instant::now();
+        let campers = self
+            .camping
+            .iter()
+            .filter(|(_, since)| now >= **since + CAMPING_LIMIT)
+            .map(|(entity_id, _)| *entity_id)
+            .collect::<Vec<_>>();
+
+        for entity_id in campers {
+            self.camping.remove(&entity_id);
+            let Some(Entity::Character { character, .. }) = self.entities.get(&entity_id) else {
+                continue;
+            };
+            let Some(position) = self.free_position_near(&character.position, 5) else {
+                continue;
+            };
+            self.relocate_user(entity_id, position);
+        }
+    }
+
+    /// Moves the user within its map
+    pub fn relocate_user(&mut self, entity_id: u32, position: WorldPosition) {
+        if let Some(Entity::Character { pending_moves, .. }) = self.entities.get_mut(&entity_id) {
+            pending_moves.clear();
+        }
+        self.set_character_position(entity_id, position);
+
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Translate {
+                entity_id,
+                position,
+            }),
+            Target::User { entity_id },
+        );
+        self.send(
+            ServerPacket::CharacterUpdate(CharacterUpdate::Move {
+                entity_id,
+                position,
+            }),
+            Target::AreaButUser { entity_id },
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::{Duration, Instant};
+
+use nohash_hasher::IntMap;
+use rand::Rng;
+use shared::{
+    protocol::server::{Environment, ServerPacket},
+    world::{MapInfo, WorldPosition},
+};
+
+use super::{networking::Target, World};
+
+/// A day of the world lasts an hour
+const DAY_LENGTH: Duration = Duration::from_secs(60 * 60);
+/// The world starts in the morning, at 8
+const START_TIME: Duration = Duration::from_secs(DAY_LENGTH.as_secs() * 8 / 24);
+/// The clients keep their own clock, resynced to correct their drift
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Minutes of clear weather between two weather events
+const CLEAR_MINUTES: (u64, u64) = (10, 30);
+/// Minutes a weather event lasts
+const EVENT_MINUTES: (u64, u64) = (3, 10);
+/// Weather events are at least this heavy
+const MIN_INTENSITY: u8 = 80;
+
+/// Time the game masters' weather lasts before the maps go back to their events
+pub const COMMAND_WEATHER_DURATION: Duration = Duration::from_secs(10 * 60);
+
+struct Weather {
+    intensity: u8,
+    next_change: Instant,
+}
+
+/// World clock and the weather of the maps that have rain, snow or fog
+pub struct Climate {
+    start: Instant,
+    last_sync: Instant,
+    weather: IntMap<u16, Weather>,
+}
+
+impl Climate {
+    pub fn initialize(map_infos: &IntMap<u16, MapInfo>) -> Self {
+        let now = Instant::now();
+        let weather = map_infos
+            .iter()
+            .filter(|(_, info)| has_weather(info))
+            .map(|(map, _)| {
+                let weather = Weather {
+                    intensity: 0,
+                    next_change: now + minutes(CLEAR_MINUTES),
+                };
+                (*map, weather)
+            })
+            .collect();
+        Self {
+            start: now,
+            last_sync: now,
+            weather,
+        }
+    }
+
+    fn clock(&self, now: Instant) -> Environment {
+        let time =
+            (now.duration_since(self.start) + START_TIME).as_millis() % DAY_LENGTH.as_millis();
+        Environment::Clock {
+            time: time as u32,
+            day: DAY_LENGTH.as_millis() as u32,
+        }
+    }
+
+    fn intensity(&self, map: u16) -> u8 {
+        self.weather
+            .get(&map)
+            .map_or(0, |weather| weather.intensity)
+    }
+
+    /// Whether the clients clock is due a resync, the next one is due an interval later
+    fn sync_clock(&mut self, now: Instant) -> bool {
+        if now < self.last_sync + CLOCK_SYNC_INTERVAL {
+            return false;
+        }
+        self.last_sync = now;
+        true
+    }
+
+    /// Maps whose weather is due a change, with the intensity and duration of the next weather,
+    /// clear maps start an event and the ones with an event clear up
+    fn changes(&self, now: Instant) -> Vec<(u16, u8, Duration)> {
+        let rng = &mut rand::thread_rng();
+        self.weather
+            .iter()
+            .filter(|(_, weather)| now >= weather.next_change)
+            .map(|(map, weather)| {
+                if weather.intensity == 0 {
+                    let intensity = rng.gen_range(MIN_INTENSITY..=u8::MAX);
+                    (*map, intensity, minutes(EVENT_MINUTES))
+                } else {
+                    (*map, 0, minutes(CLEAR_MINUTES))
+                }
+            })
+            .collect()
+    }
+
+    fn set(&mut self, map: u16, intensity: u8, next_change: Instant) {
+        self.weather.insert(
+            map,
+            Weather {
+                intensity,
+                next_change,
+            },
+        );
+    }
+}
+
+/// Maps without rain, snow nor fog are always clear
+pub fn has_weather(info: &MapInfo) -> bool {
+    info.rain || info.snow || info.fog
+}
+
+fn minutes((min, max): (u64, u64)) -> Duration {
+    Duration::from_secs(rand::thread_rng().gen_range(min..=max) * 60)
+}
+
+impl World {
+    /// Sends a user that entered a map the world clock and the weather of the map
+    pub fn send_environment(&self, entity_id: u32, map: u16) {
+        let clock = ServerPacket::Environment(self.climate.clock(Instant::now()));
+        self.send(clock, Target::User { entity_id });
+        let intensity = self.climate.intensity(map);
+        let weather = ServerPacket::Environment(Environment::Weather { intensity });
+        self.send(weather, Target::User { entity_id });
+    }
+
+    /// Starts and ends the weather events of the maps and resyncs the clients clock
+    pub fn update_environment(&mut self) {
+        let now = Instant::now();
+        if self.climate.sync_clock(now) {
+            let clock = ServerPacket::Environment(self.climate.clock(now));
+            self.send(clock, Target::All);
+        }
+
+        for (map, intensity, duration) in self.climate.changes(now) {
+            self.set_weather(map, intensity, duration);
+        }
+    }
+
+    /// Changes the weather of a map for a while and tells the users in the map
+    pub fn set_weather(&mut self, map: u16, intensity: u8, duration: Duration) {
+        self.climate.set(map, intensity, Instant::now() + duration);
+        self.send(
+            ServerPacket::Environment(Environment::Weather { intensity }),
+            Target::Area {
+                position: WorldPosition {
+                    map,
+                    ..Default::default()
+                },
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use nohash_hasher::IntMap;
+    use shared::{protocol::server::Environment, world::MapInfo};
+
+    use super::{
+        has_weather, Climate, CLEAR_MINUTES, CLOCK_SYNC_INTERVAL, DAY_LENGTH, EVENT_MINUTES,
+        MIN_INTENSITY,
+    };
+
+    fn info(rain: bool, snow: bool, fog: bool) -> MapInfo {
+        MapInfo {
+            rain,
+            snow,
+            fog,
+            ..Default::default()
+        }
+    }
+
+    fn climate() -> Climate {
+        let map_infos: IntMap<u16, MapInfo> = [
+            (1, info(false, false, false)),
+            (2, info(true, false, false)),
+            (3, info(false, true, false)),
+        ]
+        .into_iter()
+        .collect();
+        Climate::initialize(&map_infos)
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn only_maps_with_rain_snow_or_fog_have_weather() {
+        assert!(!has_weather(&info(false, false, false)));
+        assert!(has_weather(&info(true, false, false)));
+        assert!(has_weather(&info(false, true, false)));
+        assert!(has_weather(&info(false, false, true)));
+
+        let climate = climate();
+        let mut maps = climate.weather.keys().copied().collect::<Vec<_>>();
+        maps.sort();
+        assert_eq!(maps, [2, 3]);
+        assert!(climate
+            .weather
+            .values()
+            .all(|weather| weather.intensity == 0));
+        assert_eq!(climate.intensity(1), 0);
+    }
+
+    #[test]
+    fn clock_starts_in_the_morning_and_wraps_every_day() {
+        let climate = climate();
+        let day = DAY_LENGTH.as_millis() as u32;
+        let time = |elapsed: Duration| match climate.clock(climate.start + elapsed) {
+            Environment::Clock { time, day: length } => {
+                assert_eq!(length, day);
+                time
+            }
+            _ => panic!("expected the clock"),
+        };
+
+        assert_eq!(time(Duration::ZERO), day / 3);
+        assert_eq!(time(DAY_LENGTH / 2), day / 3 + day / 2);
+        assert_eq!(time(DAY_LENGTH), day / 3);
+        assert_eq!(time(DAY_LENGTH * 2 / 3), 0);
+    }
+
+    #[test]
+    fn clock_resyncs_every_interval() {
+        let mut climate = climate();
+        let start = climate.start;
+
+        assert!(!climate.sync_clock(start + CLOCK_SYNC_INTERVAL / 2));
+        assert!(climate.sync_clock(start + CLOCK_SYNC_INTERVAL));
+        assert!(!climate.sync_clock(start + CLOCK_SYNC_INTERVAL * 3 / 2));
+        assert!(climate.sync_clock(start + CLOCK_SYNC_INTERVAL * 2));
+    }
+
+    #[test]
+    fn weather_alternates_between_events_and_clear_skies() {
+        let mut climate = climate();
+        let start = climate.start;
+
+        assert!(climate.changes(start).is_empty());
+        assert!(climate
+            .changes(start + minutes(CLEAR_MINUTES.0) - Duration::from_secs(1))
+            .is_empty());
+        let now = start + minutes(CLEAR_MINUTES.1);
+        let mut changes = climate.changes(now);
+        changes.sort();
+        assert_eq!(changes.len(), 2);
+        for (index, (map, intensity, duration)) in changes.into_iter().enumerate() {
+            assert_eq!(map, index as u16 + 2);
+            assert!(intensity >= MIN_INTENSITY);
+            assert!((minutes(EVENT_MINUTES.0)..=minutes(EVENT_MINUTES.1)).contains(&duration));
+            climate.set(map, intensity, now + duration);
+        }
+        assert!(climate.intensity(2) >= MIN_INTENSITY);
+
+        let now = now + minutes(EVENT_MINUTES.1);
+        let changes = climate.changes(now);
+        assert_eq!(changes.len(), 2);
+        for (_, intensity, duration) in changes {
+            assert_eq!(intensity, 0);
+            assert!((minutes(CLEAR_MINUTES.0)..=minutes(CLEAR_MINUTES.1)).contains(&duration));
+        }
+    }
+
+    #[test]
+    fn set_weather_holds_until_its_change() {
+        let mut climate = climate();
+        let now = Instant::now();
+
+        climate.set(1, 200, now + minutes(10));
+        assert_eq!(climate.intensity(1), 200);
+        assert!(climate
+            .changes(now + minutes(5))
+            .iter()
+            .all(|(map, ..)| *map != 1));
+        assert!(climate
+            .changes(now + minutes(10))
+            .iter()
+            .any(|(map, intensity, _)| *map == 1 && *intensity == 0));
+    }
+}
@@ -1,4 +1,15 @@
-use crate::argentum::{class::Class, Range};
+use crate::{
+    argentum::{class::Class, Range},
+    protocol::crafting::{CraftingKind, Ingredient, Recipe},
+};
+
+pub const BRONZE_INGOT: usize = 386;
+pub const SILVER_INGOT: usize = 387;
+pub const GOLD_INGOT: usize = 388;
+pub const WOOD: usize = 58;
+
+/// Amount of ore consumed to smelt a single ingot
+pub const ORE_PER_INGOT: usize = 5;
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct Object {
@@ -124,6 +135,7 @@ pub enum ObjectData {
     },
     Anvil,
     Forge,
+    Workbench,
     Gem,
     Flower,
     Boat {
@@ -149,3 +161,62 @@ pub struct SmithyItem {
     id: usize,
     item_index: usize,
 }
+
+impl Object {
+    /// Builds the recipe to craft this object if it has smithy or carpentry data
+    pub fn recipe(&self) -> Option<Recipe> {
+        let (kind, skill, ingredients) = match (&self.smithy, &self.carpentry) {
+            (Some(smithy), _) => (
+                CraftingKind::Smithy,
+                smithy.skills,
+                vec![
+                    (BRONZE_INGOT, smithy.bronze_ingots),
+                    (SILVER_INGOT, smithy.silver_ingots),
+                    (GOLD_INGOT, smithy.gold_ingots),
+                ],
+            ),
+            (None, Some(carpentry)) => (
+                CraftingKind::Carpentry,
+                carpentry.skills,
+                vec![(WOOD, carpentry.wood)],
+            ),
+            (None, None) => return None,
+        };
+
+        let ingredients = ingredients
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(item_id, amount)| Ingredient {
+                item_id: item_id as u32,
+                amount: amount as u32,
+            })
+            .collect();
+
+        Some(Recipe {
+            item_id: self.id as u32,
+            name: self.name.clone(),
+            kind,
+            skill: skill.min(u8::MAX as usize) as u8,
+            ingredients,
+        })
+    }
+
+    /// Returns the ingot produced and the smithy skill required if this object can be smelted
+    pub fn smelting(&self) -> Option<(usize, usize)> {
+        match self.data {
+            ObjectData::Metals {
+                skills,
+                ingot_index,
+            } => Some((ingot_index, skills)),
+            _ => None,
+        }
+    }
+
+    pub fn is_station_for(&self, kind: CraftingKind) -> bool {
+        matches!(
+            (&self.data, kind),
+            (ObjectData::Anvil, CraftingKind::Smithy)
+                | (ObjectData::Workbench, CraftingKind::Carpentry)
+        )
+    }
+}
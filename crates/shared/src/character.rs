@@ -26,6 +26,7 @@ pub struct Character {
     pub exp: Stat<u64>,
     pub gold: u64,
     pub position: WorldPosition,
+    pub guild: Option<String>,
 
     pub class: Class,
     pub race: Race,
@@ -36,6 +37,8 @@ pub struct Character {
     pub skills: Skills,
     pub stats: Stats,
     pub inventory: Inventory,
+    pub vault: Vault,
+    pub spellbook: Spellbook,
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
@@ -140,21 +143,206 @@ pub struct Stat<T> {
     pub max: T,
 }
 
+/// Items and gold kept in the bank, uses the same slots as the inventory
 #[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
 pub struct Vault {
-    items: Vec<Item>,
-    gold: u64,
+    pub items: Inventory,
+    pub gold: u64,
 }
 
+/// Spell ids by slot
 #[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
-pub struct Inventory {
-    items: Vec<Item>,
+pub struct Spellbook {
+    pub spells: Vec<Option<u32>>,
+}
+
+impl Spellbook {
+    pub fn get(&self, slot: usize) -> Option<u32> {
+        self.spells.get(slot).copied().flatten()
+    }
 }
 
+/// Slot based item container, empty slots are kept as `None` so slot numbers are stable
 #[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
+pub struct Inventory {
+    items: Vec<Option<Item>>,
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Default, Clone, Copy)]
 pub struct Item {
-    item_id: u32,
-    amount: u32,
+    pub item_id: u32,
+    pub amount: u32,
+}
+
+impl Inventory {
+    pub const SLOTS: usize = 36;
+    pub const MAX_STACK: u32 = 10_000;
+
+    pub fn get(&self, slot: usize) -> Option<&Item> {
+        self.items.get(slot).and_then(|item| item.as_ref())
+    }
+
+    pub fn set(&mut self, slot: usize, item: Option<Item>) {
+        if slot >= Self::SLOTS {
+            return;
+        }
+        if self.items.len() <= slot {
+            self.items.resize(slot + 1, None);
+        }
+        self.items[slot] = item.filter(|item| item.amount > 0);
+    }
+
+    pub fn slots(&self) -> impl Iterator<Item = (usize, &Item)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, item)| item.as_ref().map(|item| (slot, item)))
+    }
+
+    pub fn count(&self, item_id: u32) -> u32 {
+        self.slots()
+            .filter(|(_, item)| item.item_id == item_id)
+            .map(|(_, item)| item.amount)
+            .sum()
+    }
+
+    /// Checks if `amount` of `item_id` fits, either stacking or using free slots
+    pub fn can_add(&self, item_id: u32, amount: u32) -> bool {
+        let mut space = 0;
+        for slot in 0..Self::SLOTS {
+            match self.get(slot) {
+                Some(item) if item.item_id == item_id => space += Self::MAX_STACK - item.amount,
+                Some(_) => {}
+                None => space += Self::MAX_STACK,
+            }
+            if space >= amount {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Adds items stacking first, returns the modified slots or `None` if it doesn't fit
+    pub fn add(&mut self, item_id: u32, amount: u32) -> Option<Vec<usize>> {
+        if !self.can_add(item_id, amount) {
+            return None;
+        }
+
+        let mut changed = vec![];
+        let mut remaining = amount;
+        for slot in 0..Self::SLOTS {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(item) = self.items.get_mut(slot).and_then(|item| item.as_mut()) {
+                if item.item_id == item_id && item.amount < Self::MAX_STACK {
+                    let added = std::cmp::min(remaining, Self::MAX_STACK - item.amount);
+                    item.amount += added;
+                    remaining -= added;
+                    changed.push(slot);
+                }
+            }
+        }
+        for slot in 0..Self::SLOTS {
+            if remaining == 0 {
+                break;
+            }
+            if self.get(slot).is_none() {
+                let added = std::cmp::min(remaining, Self::MAX_STACK);
+                self.set(
+                    slot,
+                    Some(Item {
+                        item_id,
+                        amount: added,
+                    }),
+                );
+                remaining -= added;
+                changed.push(slot);
+            }
+        }
+
+        Some(changed)
+    }
+
+    /// Adds items to a slot, what doesn't fit in it goes where `add` puts it. A slot holding
+    /// another item is skipped entirely
+    pub fn add_to(&mut self, slot: usize, item_id: u32, amount: u32) -> Option<Vec<usize>> {
+        if !self.can_add(item_id, amount) {
+            return None;
+        }
+        let stacked = match self.get(slot) {
+            Some(item) if item.item_id == item_id => item.amount,
+            None if slot < Self::SLOTS => 0,
+            _ => return self.add(item_id, amount),
+        };
+
+        let added = std::cmp::min(amount, Self::MAX_STACK - stacked);
+        self.set(
+            slot,
+            Some(Item {
+                item_id,
+                amount: stacked + added,
+            }),
+        );
+        let mut changed = vec![slot];
+        if added < amount {
+            changed.extend(self.add(item_id, amount - added)?);
+        }
+
+        Some(changed)
+    }
+
+    /// Removes items from any slot, returns the modified slots or `None` if there aren't enough
+    pub fn remove(&mut self, item_id: u32, amount: u32) -> Option<Vec<usize>> {
+        if self.count(item_id) < amount {
+            return None;
+        }
+
+        let mut changed = vec![];
+        let mut remaining = amount;
+        for slot in (0..self.items.len()).rev() {
+            if remaining == 0 {
+                break;
+            }
+            if let Some(item) = self.get(slot).copied() {
+                if item.item_id == item_id {
+                    let removed = std::cmp::min(remaining, item.amount);
+                    self.set(
+                        slot,
+                        Some(Item {
+                            item_id,
+                            amount: item.amount - removed,
+                        }),
+                    );
+                    remaining -= removed;
+                    changed.push(slot);
+                }
+            }
+        }
+
+        Some(changed)
+    }
+
+    /// Removes up to `amount` items from a specific slot and returns what was taken
+    pub fn take(&mut self, slot: usize, amount: u32) -> Option<Item> {
+        let item = *self.get(slot)?;
+        let taken = std::cmp::min(item.amount, amount);
+        if taken == 0 {
+            return None;
+        }
+        self.set(
+            slot,
+            Some(Item {
+                item_id: item.item_id,
+                amount: item.amount - taken,
+            }),
+        );
+
+        Some(Item {
+            item_id: item.item_id,
+            amount: taken,
+        })
+    }
 }
 
 impl Class {
@@ -274,3 +462,101 @@ impl Display for Gender {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Inventory, Item};
+
+    const SWORD: u32 = 1;
+    const ARROW: u32 = 2;
+
+    fn item(item_id: u32, amount: u32) -> Option<Item> {
+        Some(Item { item_id, amount })
+    }
+
+    #[test]
+    fn add_stacks_before_using_free_slots() {
+        let mut inventory = Inventory::default();
+        inventory.set(3, item(ARROW, 10));
+
+        assert_eq!(inventory.add(ARROW, 5), Some(vec![3]));
+        assert_eq!(inventory.get(3).copied(), item(ARROW, 15));
+        assert_eq!(inventory.add(SWORD, 1), Some(vec![0]));
+        assert_eq!(inventory.get(0).copied(), item(SWORD, 1));
+    }
+
+    #[test]
+    fn add_splits_by_max_stack() {
+        let mut inventory = Inventory::default();
+        inventory.set(0, item(ARROW, Inventory::MAX_STACK - 1));
+
+        let changed = inventory.add(ARROW, Inventory::MAX_STACK + 2);
+        assert_eq!(changed, Some(vec![0, 1, 2]));
+        assert_eq!(inventory.get(0).copied(), item(ARROW, Inventory::MAX_STACK));
+        assert_eq!(inventory.get(1).copied(), item(ARROW, Inventory::MAX_STACK));
+        assert_eq!(inventory.get(2).copied(), item(ARROW, 1));
+        assert_eq!(inventory.count(ARROW), 2 * Inventory::MAX_STACK + 1);
+    }
+
+    #[test]
+    fn can_add_counts_stacks_and_free_slots() {
+        let mut inventory = Inventory::default();
+        for slot in 0..Inventory::SLOTS - 1 {
+            inventory.set(slot, item(SWORD, 1));
+        }
+        inventory.set(0, item(ARROW, Inventory::MAX_STACK - 5));
+
+        assert!(inventory.can_add(ARROW, Inventory::MAX_STACK + 5));
+        assert!(!inventory.can_add(ARROW, Inventory::MAX_STACK + 6));
+        assert!(inventory.can_add(SWORD, Inventory::MAX_STACK));
+
+        let before = inventory.clone();
+        assert_eq!(inventory.add(ARROW, Inventory::MAX_STACK + 6), None);
+        assert_eq!(inventory, before);
+    }
+
+    #[test]
+    fn add_to_prefers_the_slot() {
+        let mut inventory = Inventory::default();
+        inventory.set(0, item(ARROW, 5));
+        inventory.set(5, item(ARROW, Inventory::MAX_STACK - 1));
+        inventory.set(6, item(SWORD, 1));
+
+        assert_eq!(inventory.add_to(9, ARROW, 3), Some(vec![9]));
+        assert_eq!(inventory.get(9).copied(), item(ARROW, 3));
+        assert_eq!(inventory.add_to(5, ARROW, 3), Some(vec![5, 0]));
+        assert_eq!(inventory.get(5).copied(), item(ARROW, Inventory::MAX_STACK));
+        assert_eq!(inventory.get(0).copied(), item(ARROW, 7));
+        // the slot holds another item, it stacks like `add`
+        assert_eq!(inventory.add_to(6, ARROW, 1), Some(vec![0]));
+        assert_eq!(inventory.get(6).copied(), item(SWORD, 1));
+    }
+
+    #[test]
+    fn remove_takes_from_any_slot() {
+        let mut inventory = Inventory::default();
+        inventory.set(0, item(ARROW, 5));
+        inventory.set(1, item(SWORD, 1));
+        inventory.set(2, item(ARROW, 5));
+
+        assert_eq!(inventory.remove(ARROW, 11), None);
+        assert_eq!(inventory.count(ARROW), 10);
+
+        assert_eq!(inventory.remove(ARROW, 7), Some(vec![2, 0]));
+        assert_eq!(inventory.get(2), None);
+        assert_eq!(inventory.get(0).copied(), item(ARROW, 3));
+        assert_eq!(inventory.get(1).copied(), item(SWORD, 1));
+    }
+
+    #[test]
+    fn take_empties_the_slot() {
+        let mut inventory = Inventory::default();
+        inventory.set(4, item(ARROW, 5));
+
+        assert_eq!(inventory.take(4, 2), item(ARROW, 2));
+        assert_eq!(inventory.take(4, 10), item(ARROW, 3));
+        assert_eq!(inventory.get(4), None);
+        assert_eq!(inventory.take(4, 1), None);
+        assert_eq!(inventory.take(Inventory::SLOTS + 1, 1), None);
+    }
+}
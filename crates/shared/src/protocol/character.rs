@@ -1,6 +1,6 @@
 use crate::{
     bincode::CONFIG,
-    character::{Inventory, Skills},
+    character::{Inventory, Skills, Spellbook, Vault},
     protocol::ProtocolMessage,
 };
 
@@ -27,3 +27,27 @@ impl ProtocolMessage for Skills {
         bincode::encode_to_vec(self, CONFIG).ok()
     }
 }
+
+impl ProtocolMessage for Vault {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::decode_from_slice(bytes, CONFIG)
+            .ok()
+            .map(|(result, _)| result)
+    }
+
+    fn encode(self) -> Option<Vec<u8>> {
+        bincode::encode_to_vec(self, CONFIG).ok()
+    }
+}
+
+impl ProtocolMessage for Spellbook {
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::decode_from_slice(bytes, CONFIG)
+            .ok()
+            .map(|(result, _)| result)
+    }
+
+    fn encode(self) -> Option<Vec<u8>> {
+        bincode::encode_to_vec(self, CONFIG).ok()
+    }
+}
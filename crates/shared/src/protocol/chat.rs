@@ -0,0 +1,13 @@
+use bincode::{Decode, Encode};
+
+/// Where a console message goes, `System` is only sent by the server
+#[derive(Encode, Decode, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Channel {
+    Say,
+    Whisper,
+    Party,
+    Guild,
+    Global,
+    Trade,
+    System,
+}
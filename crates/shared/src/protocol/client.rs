@@ -1,3 +1,5 @@
+use std::fmt;
+
 use bincode::{Decode, Encode};
 
 use crate::world::WorldPosition;
@@ -5,7 +7,7 @@ use crate::{bincode::CONFIG, protocol::ProtocolMessage};
 
 use crate::character::{Class, Gender, Race};
 
-use super::movement::MoveRequest;
+use super::{chat::Channel, crafting::CraftingKind, movement::MoveRequest};
 
 #[derive(Encode, Decode, PartialEq, Debug)]
 pub enum ClientPacket {
@@ -14,6 +16,12 @@ pub enum ClientPacket {
     Bank(Bank),
     Commerce(Commerce),
     Pet(Pet),
+    Craft(Craft),
+    Trade(Trade),
+    Chat(Chat),
+    Party(Party),
+    /// Slash command typed in the console, parsed by the server
+    Command(String),
     Request(Request),
 }
 
@@ -92,23 +100,92 @@ pub enum Action {
 
 #[derive(Encode, Decode, PartialEq, Debug)]
 pub enum Commerce {
-    Buy,
-    Sell,
+    /// Buys from the shop slot of the merchant the user is trading with
+    Buy {
+        slot: u8,
+        amount: u16,
+    },
+    /// Sells from an inventory slot
+    Sell {
+        slot: u8,
+        amount: u16,
+    },
+    Close,
 }
 
 #[derive(Encode, Decode, PartialEq, Debug)]
 pub enum Bank {
     Show,
-    Deposit,
-    Extract,
-    DepositItem,
-    ExtractItem,
+    /// Gold
+    Deposit {
+        amount: u64,
+    },
+    Extract {
+        amount: u64,
+    },
+    /// Moves items from an inventory slot to the vault, into the `to` slot when it's empty or
+    /// holds the same item
+    DepositItem {
+        slot: u8,
+        amount: u16,
+        to: Option<u8>,
+    },
+    /// Moves items from a vault slot to the inventory, into the `to` slot like `DepositItem`
+    ExtractItem {
+        slot: u8,
+        amount: u16,
+        to: Option<u8>,
+    },
+    Close,
+}
+
+/// Overhead talk stays in `Action::Talk`, this goes to the console of the recipients
+#[derive(Encode, Decode, PartialEq, Debug)]
+pub enum Chat {
+    Send { channel: Channel, text: String },
+    Whisper { to: String, text: String },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug)]
+pub enum Party {
+    Invite { name: String },
+    Accept,
+    Leave,
 }
+
+/// Commands apply to every pet of the user
 #[derive(Encode, Decode, PartialEq, Debug)]
 pub enum Pet {
     Stand,
     Follow,
     Leave,
+    Tame { entity_id: u32 },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug)]
+pub enum Craft {
+    Recipes { kind: CraftingKind },
+    Item { item_id: u32, amount: u16 },
+    Smelt { slot: u8 },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug)]
+pub enum Trade {
+    /// Asks a nearby user to trade, accepts if that user already asked
+    Request {
+        entity_id: u32,
+    },
+    /// Sets the amount offered from an inventory slot, zero removes it from the offer
+    Offer {
+        slot: u8,
+        amount: u32,
+    },
+    Gold {
+        amount: u64,
+    },
+    Ready,
+    Confirm,
+    Cancel,
 }
 
 #[derive(Encode, Decode, PartialEq, Debug)]
@@ -122,6 +199,54 @@ pub enum Request {
     Quit,
 }
 
+impl ClientPacket {
+    /// Name of the variant, used to label metrics and logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientPacket::Account(_) => "account",
+            ClientPacket::UserAction(_) => "user_action",
+            ClientPacket::Bank(_) => "bank",
+            ClientPacket::Commerce(_) => "commerce",
+            ClientPacket::Pet(_) => "pet",
+            ClientPacket::Craft(_) => "craft",
+            ClientPacket::Trade(_) => "trade",
+            ClientPacket::Chat(_) => "chat",
+            ClientPacket::Party(_) => "party",
+            ClientPacket::Command(_) => "command",
+            ClientPacket::Request(_) => "request",
+        }
+    }
+}
+
+impl ClientPacket {
+    /// Debug output without passwords and pins, for logging
+    pub fn redacted(&self) -> Redacted<'_> {
+        Redacted(self)
+    }
+}
+
+pub struct Redacted<'a>(&'a ClientPacket);
+
+impl fmt::Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            ClientPacket::Account(Account::CreateAccount { name, email, .. }) => f
+                .debug_struct("CreateAccount")
+                .field("name", name)
+                .field("email", email)
+                .field("password", &"<redacted>")
+                .field("pin", &"<redacted>")
+                .finish(),
+            ClientPacket::Account(Account::LoginAccount { name, .. }) => f
+                .debug_struct("LoginAccount")
+                .field("name", name)
+                .field("password", &"<redacted>")
+                .finish(),
+            packet => packet.fmt(f),
+        }
+    }
+}
+
 impl ProtocolMessage for ClientPacket {
     fn decode(bytes: &[u8]) -> Option<Self> {
         bincode::decode_from_slice(bytes, CONFIG)
@@ -133,3 +258,36 @@ impl ProtocolMessage for ClientPacket {
         bincode::encode_to_vec(self, CONFIG).ok()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_hides_passwords_and_pins() {
+        let packets = [
+            ClientPacket::Account(Account::CreateAccount {
+                name: "someone".to_string(),
+                email: "someone@example.com".to_string(),
+                password: "hunter2".to_string(),
+                pin: 9876,
+            }),
+            ClientPacket::Account(Account::LoginAccount {
+                name: "someone".to_string(),
+                password: "hunter2".to_string(),
+            }),
+        ];
+        for packet in packets {
+            let output = format!("{:?}", packet.redacted());
+            assert!(output.contains("someone"), "{output}");
+            assert!(!output.contains("hunter2"), "{output}");
+            assert!(!output.contains("9876"), "{output}");
+        }
+    }
+
+    #[test]
+    fn redacted_keeps_other_packets() {
+        let packet = ClientPacket::Command("/online".to_string());
+        assert_eq!(format!("{:?}", packet.redacted()), format!("{packet:?}"));
+    }
+}
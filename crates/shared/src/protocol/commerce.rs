@@ -0,0 +1,60 @@
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone, Copy)]
+pub struct ShopItem {
+    pub item_id: u32,
+    pub amount: u32,
+    pub price: u64,
+}
+
+/// Discount between 0 and 0.3 given by trading skill and charisma
+fn discount(trading: u8, charisma: u16) -> f64 {
+    let trading = std::cmp::min(trading, 100) as f64 / 100.;
+    let charisma = std::cmp::min(charisma, 40) as f64 / 40.;
+    trading * 0.2 + charisma * 0.1
+}
+
+/// Price a merchant asks for an object of the given `value`
+pub fn buy_price(value: usize, trading: u8, charisma: u16) -> u64 {
+    let price = value as f64 * (1. - discount(trading, charisma));
+    std::cmp::max(1, price.ceil() as u64)
+}
+
+/// Price a merchant pays for an object of the given `value`, always lower than `buy_price`
+pub fn sell_price(value: usize, trading: u8, charisma: u16) -> u64 {
+    let price = value as f64 / 3. * (1. + discount(trading, charisma));
+    price.floor() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buy_price, sell_price};
+
+    #[test]
+    fn prices_without_discount() {
+        assert_eq!(buy_price(100, 0, 0), 100);
+        assert_eq!(sell_price(100, 0, 0), 33);
+    }
+
+    #[test]
+    fn trading_and_charisma_discount_up_to_a_cap() {
+        assert_eq!(buy_price(100, 50, 0), 90);
+        assert_eq!(buy_price(100, 0, 20), 95);
+        assert_eq!(buy_price(100, 100, 40), 70);
+        assert_eq!(buy_price(100, 255, 1000), 70);
+        assert_eq!(sell_price(100, 100, 40), 43);
+        assert_eq!(sell_price(100, 255, 1000), 43);
+    }
+
+    #[test]
+    fn merchants_never_buy_for_more_than_they_sell() {
+        for value in [0, 1, 2, 3, 10, 99, 1000, 123_456] {
+            for (trading, charisma) in [(0, 0), (50, 20), (100, 40)] {
+                assert!(buy_price(value, trading, charisma) >= 1);
+                assert!(sell_price(value, trading, charisma) < buy_price(value, trading, charisma));
+            }
+        }
+        assert_eq!(sell_price(0, 100, 40), 0);
+        assert_eq!(sell_price(2, 0, 0), 0);
+    }
+}
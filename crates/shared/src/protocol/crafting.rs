@@ -0,0 +1,22 @@
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone, Copy)]
+pub enum CraftingKind {
+    Smithy,
+    Carpentry,
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone, Copy)]
+pub struct Ingredient {
+    pub item_id: u32,
+    pub amount: u32,
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub struct Recipe {
+    pub item_id: u32,
+    pub name: String,
+    pub kind: CraftingKind,
+    pub skill: u8,
+    pub ingredients: Vec<Ingredient>,
+}
@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+};
+
+use tracing::Level;
+
+/// Target of the packet events, enable them with `RUST_LOG=packets=debug`
+pub const TARGET: &str = "packets";
+
+/// Level and sampling of the packets, read from `PACKET_LOG` once.
+///
+/// It's a comma separated list of rules, a rule without variant is the default for the rest:
+/// `PACKET_LOG=debug,user_action=trace/20,chat=off` logs every packet at debug, one of every 20
+/// user actions at trace and no chat packets.
+struct PacketLog {
+    default: Rule,
+    variants: HashMap<String, Rule>,
+}
+
+struct Rule {
+    /// `None` when the variant is not logged
+    level: Option<Level>,
+    /// Only one of every `every` packets is logged
+    every: u64,
+    seen: AtomicU64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Direction {
+    Incoming,
+    Outgoing,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Incoming => "in",
+            Direction::Outgoing => "out",
+        }
+    }
+}
+
+/// Level to log the packet at, `None` when it's filtered out or not sampled.
+/// It's cheap so it's checked before formatting the packet
+pub fn sample(packet: &'static str) -> Option<Level> {
+    packet_log().sample(packet).filter(|level| enabled(*level))
+}
+
+/// Logs a packet as a structured event in the packets target, `payload` has to be redacted
+/// already if it contains secrets
+pub fn log_packet(
+    level: Level,
+    direction: Direction,
+    connection: Option<u32>,
+    entity: Option<u32>,
+    packet: &'static str,
+    bytes: usize,
+    payload: &dyn Debug,
+) {
+    macro_rules! packet_event {
+        ($level:expr) => {
+            tracing::event!(
+                target: TARGET,
+                $level,
+                direction = direction.as_str(),
+                connection,
+                entity,
+                packet,
+                bytes,
+                payload = ?payload,
+            )
+        };
+    }
+    // the level of an event has to be a constant
+    if level == Level::ERROR {
+        packet_event!(Level::ERROR)
+    } else if level == Level::WARN {
+        packet_event!(Level::WARN)
+    } else if level == Level::INFO {
+        packet_event!(Level::INFO)
+    } else if level == Level::DEBUG {
+        packet_event!(Level::DEBUG)
+    } else {
+        packet_event!(Level::TRACE)
+    }
+}
+
+fn enabled(level: Level) -> bool {
+    if level == Level::ERROR {
+        tracing::enabled!(target: TARGET, Level::ERROR)
+    } else if level == Level::WARN {
+        tracing::enabled!(target: TARGET, Level::WARN)
+    } else if level == Level::INFO {
+        tracing::enabled!(target: TARGET, Level::INFO)
+    } else if level == Level::DEBUG {
+        tracing::enabled!(target: TARGET, Level::DEBUG)
+    } else {
+        tracing::enabled!(target: TARGET, Level::TRACE)
+    }
+}
+
+fn packet_log() -> &'static PacketLog {
+    static PACKET_LOG: OnceLock<PacketLog> = OnceLock::new();
+    PACKET_LOG.get_or_init(|| {
+        PacketLog::parse(&env::var("PACKET_LOG").unwrap_or_default()).unwrap_or_else(|error| {
+            tracing::warn!("{error}, logging every packet at debug");
+            PacketLog::default()
+        })
+    })
+}
+
+impl Default for PacketLog {
+    fn default() -> Self {
+        Self {
+            default: Rule::new(Some(Level::DEBUG), 1),
+            variants: HashMap::new(),
+        }
+    }
+}
+
+impl PacketLog {
+    /// Fails on the first invalid rule
+    fn parse(config: &str) -> Result<Self, String> {
+        let mut packet_log = PacketLog::default();
+        for rule in config
+            .split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+        {
+            let (variant, rule_text) = match rule.split_once('=') {
+                Some((variant, rule)) => (Some(variant.trim()), rule.trim()),
+                None => (None, rule),
+            };
+            let parsed =
+                Rule::parse(rule_text).ok_or_else(|| format!("invalid PACKET_LOG rule {rule}"))?;
+            match variant {
+                Some(variant) => {
+                    packet_log.variants.insert(variant.to_lowercase(), parsed);
+                }
+                None => packet_log.default = parsed,
+            }
+        }
+        Ok(packet_log)
+    }
+
+    /// Level to log the packet at, `None` when it's filtered out or not sampled
+    fn sample(&self, packet: &str) -> Option<Level> {
+        let rule = self.variants.get(packet).unwrap_or(&self.default);
+        let level = rule.level?;
+        let seen = rule.seen.fetch_add(1, Ordering::Relaxed);
+        seen.is_multiple_of(rule.every).then_some(level)
+    }
+}
+
+impl Rule {
+    fn new(level: Option<Level>, every: u64) -> Self {
+        Self {
+            level,
+            every,
+            seen: AtomicU64::new(0),
+        }
+    }
+
+    /// `level` or `level/every`, like `trace/20`
+    fn parse(rule: &str) -> Option<Self> {
+        let (level, every) = match rule.split_once('/') {
+            Some((level, every)) => (level, every.parse().ok().filter(|every| *every > 0)?),
+            None => (rule, 1),
+        };
+        let level = match level.to_lowercase().as_str() {
+            "off" => None,
+            level => Some(level.parse().ok()?),
+        };
+        Some(Self::new(level, every))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_default_and_variant_rules() {
+        let packet_log = PacketLog::parse("info, user_action = trace/2,CHAT=off").unwrap();
+        assert_eq!(packet_log.default.level, Some(Level::INFO));
+        assert_eq!(packet_log.variants["user_action"].level, Some(Level::TRACE));
+        assert_eq!(packet_log.variants["user_action"].every, 2);
+        assert_eq!(packet_log.variants["chat"].level, None);
+
+        let empty = PacketLog::parse("").unwrap();
+        assert_eq!(empty.default.level, Some(Level::DEBUG));
+        assert!(empty.variants.is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_rules() {
+        for config in ["loud", "chat=trace/0", "chat=trace/often", "debug,chat="] {
+            assert!(PacketLog::parse(config).is_err(), "{config}");
+        }
+    }
+
+    #[test]
+    fn sample_one_of_every() {
+        let packet_log = PacketLog::parse("warn,user_action=trace/3,chat=off").unwrap();
+        let sampled = (0..6)
+            .map(|_| packet_log.sample("user_action").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(sampled, [true, false, false, true, false, false]);
+        assert_eq!(packet_log.sample("user_action"), Some(Level::TRACE));
+        assert_eq!(packet_log.sample("chat"), None);
+        assert_eq!(packet_log.sample("bank"), Some(Level::WARN));
+    }
+}
@@ -1,7 +1,12 @@
 pub mod character;
+pub mod chat;
 pub mod client;
+pub mod commerce;
+pub mod crafting;
+pub mod logging;
 pub mod movement;
 pub mod server;
+pub mod trade;
 
 pub trait ProtocolMessage {
     fn encode(self) -> Option<Vec<u8>>;
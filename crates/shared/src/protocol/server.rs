@@ -1,8 +1,15 @@
 use crate::bincode::CONFIG;
-use crate::character::{Character, CharacterPreview};
+use crate::character::{Character, CharacterPreview, Item, Vault};
 use crate::protocol::ProtocolMessage;
 use crate::world::{Direction, WorldPosition};
 
+use super::{
+    chat::Channel,
+    commerce::ShopItem,
+    crafting::{CraftingKind, Recipe},
+    trade::{TradeOffer, TradeStage},
+};
+
 use bincode::{Decode, Encode};
 
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
@@ -15,6 +22,13 @@ pub enum ServerPacket {
     Event(Event),
     Object(Object),
     Message(Message),
+    Crafting(Crafting),
+    Commerce(Commerce),
+    Bank(Bank),
+    Trade(Trade),
+    Pet(Pet),
+    Chat(Chat),
+    Environment(Environment),
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
@@ -36,6 +50,9 @@ pub enum Account {
         characters: Vec<CharacterPreview>,
     },
     LoginFailed,
+    Banned {
+        reason: String,
+    },
 
     CreateCharacterOk {
         entity_id: u32,
@@ -59,7 +76,7 @@ pub enum Account {
 pub enum CharacterUpdate {
     Create {
         entity_id: u32,
-        character: Character,
+        character: Box<Character>,
     },
     Remove {
         entity_id: u32,
@@ -105,27 +122,43 @@ pub enum DialogKind {
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
 pub enum UserUpdate {
     Sta,
-    Mana,
+    Mana { mana: u16 },
     Health,
     Hunger,
     Thirst,
-    Gold,
+    Gold { gold: u64 },
     Exp,
     Position,
     Stats,
-    InventorySlot,
+    InventorySlot { slot: u8, item: Option<Item> },
     SpellsSlot,
-    BankSlot,
+    BankSlot { slot: u8, item: Option<Item> },
+    BankGold { gold: u64 },
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
 pub enum Event {
     LevelUp,
-    Attack,
-    ShieldBlock,
-    Hit,
-    Kill,
-    FX,
+    /// The entity swung its weapon
+    Attack {
+        entity_id: u32,
+    },
+    ShieldBlock {
+        entity_id: u32,
+    },
+    /// The entity was hit
+    Hit {
+        entity_id: u32,
+    },
+    Kill {
+        entity_id: u32,
+    },
+    /// Effect of a spell on a tile, played `loops` times
+    FX {
+        position: WorldPosition,
+        fx: u16,
+        loops: u16,
+    },
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
@@ -143,13 +176,144 @@ pub enum Response {
     Online,
 }
 
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Crafting {
+    Recipes {
+        kind: CraftingKind,
+        recipes: Vec<Recipe>,
+    },
+    Crafted {
+        item_id: u32,
+        amount: u16,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Commerce {
+    Open {
+        entity_id: u32,
+        name: String,
+        items: Vec<ShopItem>,
+    },
+    /// Stock changed while the shop is open
+    Stock {
+        items: Vec<ShopItem>,
+    },
+    Close,
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Bank {
+    Open { vault: Vault },
+    Close,
+    Failed { reason: String },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Trade {
+    Requested {
+        entity_id: u32,
+        name: String,
+    },
+    Started {
+        entity_id: u32,
+        name: String,
+    },
+    Update {
+        own: TradeOffer,
+        other: TradeOffer,
+        own_stage: TradeStage,
+        other_stage: TradeStage,
+    },
+    Completed,
+    Cancelled {
+        reason: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Pet {
+    Tamed { entity_id: u32, name: String },
+    Summoned { entity_id: u32, name: String },
+    Gone { entity_id: u32, name: String },
+    Failed { reason: String },
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Chat {
+    /// `from` is empty for system messages
+    Message {
+        channel: Channel,
+        from: String,
+        text: String,
+    },
+    /// Copy of a whisper for the sender
+    Whispered {
+        to: String,
+        text: String,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+/// World clock and weather, both owned by the server
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub enum Environment {
+    /// Milliseconds since the start of the day, and the length of a day in milliseconds
+    Clock { time: u32, day: u32 },
+    /// Weather of the map of the user, from 0 when clear to 255
+    Weather { intensity: u8 },
+}
+
 #[derive(Encode, Decode, PartialEq, Debug, Clone)]
 pub enum Message {
-    See { entity_id: u32 },
+    See {
+        entity_id: u32,
+    },
     SeeNothing,
+    /// Result of a command, shown in the console
+    Console {
+        text: String,
+    },
+    Failed {
+        reason: String,
+    },
     // TODO
 }
 
+impl ServerPacket {
+    /// Name of the variant, used to label metrics and logs
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerPacket::Intervals => "intervals",
+            ServerPacket::Connection(_) => "connection",
+            ServerPacket::Account(_) => "account",
+            ServerPacket::CharacterUpdate(_) => "character_update",
+            ServerPacket::UserUpdate(_) => "user_update",
+            ServerPacket::Event(_) => "event",
+            ServerPacket::Object(_) => "object",
+            ServerPacket::Message(_) => "message",
+            ServerPacket::Crafting(_) => "crafting",
+            ServerPacket::Commerce(_) => "commerce",
+            ServerPacket::Bank(_) => "bank",
+            ServerPacket::Trade(_) => "trade",
+            ServerPacket::Pet(_) => "pet",
+            ServerPacket::Chat(_) => "chat",
+            ServerPacket::Environment(_) => "environment",
+        }
+    }
+}
+
 impl ProtocolMessage for ServerPacket {
     fn decode(bytes: &[u8]) -> Option<Self> {
         bincode::decode_from_slice(bytes, CONFIG)
@@ -0,0 +1,24 @@
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone, Copy)]
+pub struct OfferItem {
+    /// inventory slot of the user offering the item
+    pub slot: u8,
+    pub item_id: u32,
+    pub amount: u32,
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
+pub struct TradeOffer {
+    pub items: Vec<OfferItem>,
+    pub gold: u64,
+}
+
+/// Both users have to be ready before confirming, changing an offer goes back to `Offering`
+#[derive(Encode, Decode, PartialEq, Debug, Default, Clone, Copy)]
+pub enum TradeStage {
+    #[default]
+    Offering,
+    Ready,
+    Confirmed,
+}
@@ -1,5 +1,7 @@
 use bincode::{Decode, Encode};
 
+pub mod pathfinding;
+
 #[derive(
     Default, Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, Encode, Decode,
 )]
@@ -54,6 +56,45 @@ pub type GrhIndex = u32;
 
 pub type Trigger = u16;
 
+/// Behaviour of a tile, decoded from its raw `Trigger` value
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileTrigger {
+    #[default]
+    None,
+    UnderRoof,
+    NoCombat,
+    InvalidNpcPosition,
+    SafeZone,
+    AntiCamping,
+}
+
+impl From<Trigger> for TileTrigger {
+    fn from(value: Trigger) -> Self {
+        match value {
+            1 => TileTrigger::UnderRoof,
+            2 => TileTrigger::NoCombat,
+            3 => TileTrigger::InvalidNpcPosition,
+            4 => TileTrigger::SafeZone,
+            5 => TileTrigger::AntiCamping,
+            _ => TileTrigger::None,
+        }
+    }
+}
+
+impl TileTrigger {
+    pub fn allows_combat(&self) -> bool {
+        !matches!(self, TileTrigger::SafeZone | TileTrigger::NoCombat)
+    }
+
+    /// Npcs can't be placed nor walk over these tiles
+    pub fn blocks_npcs(&self) -> bool {
+        matches!(
+            self,
+            TileTrigger::InvalidNpcPosition | TileTrigger::AntiCamping
+        )
+    }
+}
+
 #[derive(
     Default, Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize, Encode, Decode,
 )]
@@ -141,6 +182,12 @@ pub struct Tile {
     pub user: Option<u32>,
 }
 
+impl Tile {
+    pub fn trigger_kind(&self) -> TileTrigger {
+        TileTrigger::from(self.trigger)
+    }
+}
+
 #[derive(
     Default, Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize, Encode, Decode,
 )]
@@ -156,6 +203,21 @@ impl Map {
         &mut self.tiles[(x - 1) as usize][(y - 1) as usize]
     }
 
+    /// Like `tile` but returns `None` for positions outside of the map
+    pub fn get_tile(&self, x: u16, y: u16) -> Option<&Tile> {
+        if x == 0 || y == 0 {
+            return None;
+        }
+        self.tiles.get((x - 1) as usize)?.get((y - 1) as usize)
+    }
+
+    /// Trigger of the tile, `TileTrigger::None` outside of the map
+    pub fn trigger(&self, x: u16, y: u16) -> TileTrigger {
+        self.get_tile(x, y)
+            .map(Tile::trigger_kind)
+            .unwrap_or_default()
+    }
+
     const CONFIG: bincode::config::Configuration = bincode::config::standard();
     pub fn from_path(path: &str) -> Option<Self> {
         let file = std::fs::File::open(path).ok()?;
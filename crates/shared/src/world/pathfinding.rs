@@ -0,0 +1,270 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use super::{Direction, Map, Tile, WorldPosition};
+
+/// Max tiles explored before giving up, keeps searches to unreachable tiles cheap
+const MAX_EXPLORED: usize = 2500;
+
+/// Tile each explored tile was reached from and the step taken
+type CameFrom = HashMap<(u16, u16), ((u16, u16), Direction)>;
+
+/// Who is walking, npcs can't step over some triggers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walker {
+    User,
+    Npc,
+}
+
+impl Walker {
+    fn can_walk(&self, tile: &Tile) -> bool {
+        if tile.blocked != 0 || tile.user.is_some() {
+            return false;
+        }
+        match self {
+            Walker::User => true,
+            Walker::Npc => tile.exit.is_none() && !tile.trigger_kind().blocks_npcs(),
+        }
+    }
+}
+
+/// Directions to walk from `from` to `to` with A*, `None` if `to` can't be reached
+pub fn find_path(
+    map: &Map,
+    from: &WorldPosition,
+    to: &WorldPosition,
+    walker: Walker,
+) -> Option<Vec<Direction>> {
+    if from.map != to.map {
+        return None;
+    }
+    search(map, from, to, walker, |x, y| x == to.x && y == to.y)
+}
+
+/// Like `find_path` but stops next to `to`, used to reach occupied tiles
+pub fn find_path_to_adjacent(
+    map: &Map,
+    from: &WorldPosition,
+    to: &WorldPosition,
+    walker: Walker,
+) -> Option<Vec<Direction>> {
+    if from.map != to.map {
+        return None;
+    }
+    search(map, from, to, walker, |x, y| {
+        x.abs_diff(to.x) + y.abs_diff(to.y) == 1
+    })
+}
+
+fn search(
+    map: &Map,
+    from: &WorldPosition,
+    to: &WorldPosition,
+    walker: Walker,
+    is_goal: impl Fn(u16, u16) -> bool,
+) -> Option<Vec<Direction>> {
+    let start = (from.x, from.y);
+    let heuristic = |(x, y): (u16, u16)| x.abs_diff(to.x) + y.abs_diff(to.y);
+
+    let mut open = BinaryHeap::new();
+    let mut came_from = CameFrom::new();
+    let mut cost: HashMap<(u16, u16), u16> = HashMap::new();
+    open.push(Reverse((heuristic(start), 0, start)));
+    cost.insert(start, 0);
+
+    let mut explored = 0;
+    while let Some(Reverse((_, current_cost, current))) = open.pop() {
+        if is_goal(current.0, current.1) {
+            return Some(rebuild(&came_from, start, current));
+        }
+        if cost.get(&current).is_some_and(|best| *best < current_cost) {
+            continue;
+        }
+        explored += 1;
+        if explored > MAX_EXPLORED {
+            return None;
+        }
+
+        for (direction, next) in neighbours(current) {
+            let Some(tile) = map.get_tile(next.0, next.1) else {
+                continue;
+            };
+            if !walker.can_walk(tile) {
+                continue;
+            }
+            // stepping on an exit leaves the map, it can only end the path
+            if tile.exit.is_some() && !is_goal(next.0, next.1) {
+                continue;
+            }
+            let next_cost = current_cost + 1;
+            if cost.get(&next).is_some_and(|best| *best <= next_cost) {
+                continue;
+            }
+            cost.insert(next, next_cost);
+            came_from.insert(next, (current, direction));
+            open.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+        }
+    }
+
+    None
+}
+
+/// Tiles reachable in one step, `Map::next_position` doesn't move from the map borders
+fn neighbours((x, y): (u16, u16)) -> Vec<(Direction, (u16, u16))> {
+    if x <= 1 || x >= 99 || y <= 1 || y >= 99 {
+        return vec![];
+    }
+    vec![
+        (Direction::North, (x, y + 1)),
+        (Direction::East, (x + 1, y)),
+        (Direction::South, (x, y - 1)),
+        (Direction::West, (x - 1, y)),
+    ]
+}
+
+fn rebuild(came_from: &CameFrom, start: (u16, u16), mut current: (u16, u16)) -> Vec<Direction> {
+    let mut path = vec![];
+    while current != start {
+        let Some((previous, direction)) = came_from.get(&current) else {
+            break;
+        };
+        path.push(*direction);
+        current = *previous;
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> Map {
+        Map {
+            tiles: vec![vec![Tile::default(); 100]; 100],
+        }
+    }
+
+    fn position(x: u16, y: u16) -> WorldPosition {
+        WorldPosition { map: 1, x, y }
+    }
+
+    /// Follows the path checking every step, returns where it ends
+    fn walk(map: &Map, from: &WorldPosition, path: &[Direction], walker: Walker) -> WorldPosition {
+        path.iter().fold(*from, |position, direction| {
+            let next = map.next_position(&position, *direction);
+            assert_ne!(next, position);
+            assert!(walker.can_walk(map.tile(next.x, next.y)));
+            next
+        })
+    }
+
+    #[test]
+    fn straight_path() {
+        let map = map();
+        let path = find_path(&map, &position(10, 10), &position(10, 15), Walker::User).unwrap();
+        assert_eq!(path, vec![Direction::North; 5]);
+        assert_eq!(
+            find_path(&map, &position(10, 10), &position(10, 10), Walker::User),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn routes_around_blocked_tiles() {
+        let mut map = map();
+        for y in 5..=15 {
+            map.tile_mut(12, y).blocked = 1;
+        }
+        let (from, to) = (position(10, 10), position(14, 10));
+        let path = find_path(&map, &from, &to, Walker::User).unwrap();
+        assert_eq!(walk(&map, &from, &path, Walker::User), to);
+        // around the end of the wall and back
+        assert_eq!(path.len(), 4 + 2 * 6);
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let mut map = map();
+        for (x, y) in [(19, 20), (21, 20), (20, 19), (20, 21)] {
+            map.tile_mut(x, y).blocked = 1;
+        }
+        let from = position(10, 10);
+        assert_eq!(
+            find_path(&map, &from, &position(20, 20), Walker::User),
+            None
+        );
+        assert_eq!(
+            find_path(
+                &map,
+                &from,
+                &WorldPosition {
+                    map: 2,
+                    x: 10,
+                    y: 15
+                },
+                Walker::User
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn gives_up_after_exploring_too_many_tiles() {
+        let mut map = map();
+        // the only way through the wall is at the bottom of the map, far from the straight line
+        for y in 3..=99 {
+            map.tile_mut(50, y).blocked = 1;
+        }
+        let (from, to) = (position(10, 50), position(90, 50));
+        assert_eq!(find_path(&map, &from, &to, Walker::User), None);
+
+        let near = position(48, 3);
+        let path = find_path(&map, &near, &to, Walker::User).unwrap();
+        assert_eq!(walk(&map, &near, &path, Walker::User), to);
+    }
+
+    #[test]
+    fn stops_next_to_occupied_tiles() {
+        let mut map = map();
+        map.tile_mut(15, 10).user = Some(1);
+        let (from, to) = (position(10, 10), position(15, 10));
+        assert_eq!(find_path(&map, &from, &to, Walker::User), None);
+        let path = find_path_to_adjacent(&map, &from, &to, Walker::User).unwrap();
+        assert_eq!(path, vec![Direction::East; 4]);
+    }
+
+    #[test]
+    fn exits_only_end_the_path() {
+        let mut map = map();
+        map.tile_mut(11, 10).exit = Some(WorldPosition {
+            map: 2,
+            x: 50,
+            y: 50,
+        });
+        let from = position(10, 10);
+        let path = find_path(&map, &from, &position(12, 10), Walker::User).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(walk(&map, &from, &path, Walker::User), position(12, 10));
+        assert_eq!(
+            find_path(&map, &from, &position(11, 10), Walker::User),
+            Some(vec![Direction::East])
+        );
+    }
+
+    #[test]
+    fn npcs_avoid_blocking_triggers() {
+        let mut map = map();
+        map.tile_mut(11, 10).trigger = 3;
+        let (from, to) = (position(10, 10), position(12, 10));
+        assert_eq!(
+            find_path(&map, &from, &to, Walker::User),
+            Some(vec![Direction::East; 2])
+        );
+        let path = find_path(&map, &from, &to, Walker::Npc).unwrap();
+        assert_eq!(path.len(), 4);
+        assert_eq!(walk(&map, &from, &path, Walker::Npc), to);
+    }
+}